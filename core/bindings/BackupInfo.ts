@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BackupFormat } from "./BackupFormat";
+import type { WorldBackupEntry } from "./WorldBackupEntry";
+
+export interface BackupInfo { name: string, size: bigint, creation_time: bigint, format: BackupFormat, worlds: Array<WorldBackupEntry>, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ConflictingProcess } from "./ConflictingProcess";
+
+export type CrashCause = { type: "PortInUse", port: number, suggested_port: number, conflicting_process: ConflictingProcess | null, } | { type: "StartupTimeout", timeout_secs: bigint, };
\ No newline at end of file
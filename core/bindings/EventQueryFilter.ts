@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { EventLevel } from "./EventLevel";
+import type { InstanceUuid } from "./InstanceUuid";
+import type { TimeRange } from "./TimeRange";
+import type { UserId } from "./UserId";
+
+export interface EventQueryFilter { instance_id: InstanceUuid | null, level: EventLevel | null, time_range: TimeRange | null, caused_by_user_id: UserId | null, limit: number | null, offset: number | null, }
\ No newline at end of file
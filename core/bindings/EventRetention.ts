@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface EventRetention { max_age_days: number, keep_min_per_instance: number, prune_interval_minutes: number, }
\ No newline at end of file
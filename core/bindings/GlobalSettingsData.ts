@@ -1,3 +1,5 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { EventRetention } from "./EventRetention";
+import type { RamGuardrail } from "./RamGuardrail";
 
-export interface GlobalSettingsData { core_name: string, safe_mode: boolean, domain: string | null, playit_enabled: boolean, }
\ No newline at end of file
+export interface GlobalSettingsData { core_name: string, safe_mode: boolean, domain: string | null, playit_enabled: boolean, deletion_grace_period_days: number, ram_guardrail: RamGuardrail | null, persist_progression: boolean, event_retention: EventRetention | null, }
\ No newline at end of file
@@ -1,5 +1,7 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { InstanceState } from "./InstanceState";
+import type { ModerationAction } from "./ModerationAction";
+import type { PaperNotice } from "./PaperNotice";
 import type { Player } from "./Player";
 
-export type InstanceEventInner = { type: "StateTransition", to: InstanceState, } | { type: "InstanceWarning", message: string, } | { type: "InstanceError", message: string, } | { type: "InstanceInput", message: string, } | { type: "InstanceOutput", message: string, } | { type: "SystemMessage", message: string, } | { type: "PlayerChange", player_list: Array<Player>, players_joined: Array<Player>, players_left: Array<Player>, } | { type: "PlayerMessage", player: string, player_message: string, };
\ No newline at end of file
+export type InstanceEventInner = { type: "StateTransition", to: InstanceState, } | { type: "InstanceWarning", message: string, } | { type: "InstanceError", message: string, } | { type: "InstanceInput", message: string, } | { type: "InstanceOutput", message: string, } | { type: "SystemMessage", message: string, } | { type: "PlayerChange", player_list: Array<Player>, players_joined: Array<Player>, players_left: Array<Player>, } | { type: "PlayerMessage", player: string, player_message: string, } | { type: "InstanceRenamed", old_name: string, new_name: string, } | { type: "ServerLagWarning", ms_behind: bigint, ticks_skipped: bigint, } | { type: "PaperNotice", notice: PaperNotice, message: string, } | { type: "PromptAnswered", prompt: string, response: string, } | { type: "OutOfDiskSpace", path: string, } | { type: "BackupCancelled", backup_name: string, } | { type: "BackupCompleted", name: string, size: bigint, duration_ms: bigint, } | { type: "BackupFailed", reason: string, } | { type: "PlayerModerated", player: string, action: ModerationAction, reason: string | null, };
\ No newline at end of file
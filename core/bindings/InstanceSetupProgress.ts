@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SetupStatus } from "./SetupStatus";
+
+export interface InstanceSetupProgress { step: string, fraction: number | null, status: SetupStatus, last_error: string | null, }
\ No newline at end of file
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Game } from "./Game";
+import type { InstanceState } from "./InstanceState";
+import type { InstanceUuid } from "./InstanceUuid";
+
+export interface InstanceSummary { uuid: InstanceUuid, name: string, game_type: Game, version: string, state: InstanceState, player_count: number | null, port: number, creation_time: bigint, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ModLoader } from "./ModLoader";
+
+export interface MismatchedMod { file_name: string, loaders: Array<ModLoader>, }
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type ModIssue = { type: "DuplicateId", mod_id: string, file_names: Array<string>, } | { type: "MissingDependency", file_name: string, mod_id: string, dependency: string, } | { type: "VersionMismatch", file_name: string, mod_id: string, dependency: string, required: Array<string>, found: string, };
\ No newline at end of file
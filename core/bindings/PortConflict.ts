@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { PortUsage } from "./PortUsage";
+
+export interface PortConflict { port: number, usages: Array<PortUsage>, }
\ No newline at end of file
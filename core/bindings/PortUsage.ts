@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { InstanceUuid } from "./InstanceUuid";
+import type { PortKind } from "./PortKind";
+
+export interface PortUsage { instance_uuid: InstanceUuid, instance_name: string, kind: PortKind, is_running: boolean, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { RamGuardrailPolicy } from "./RamGuardrailPolicy";
+
+export interface RamGuardrail { max_ram_fraction: number, policy: RamGuardrailPolicy, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ScheduleDay } from "./ScheduleDay";
+
+export interface RestartSchedule { days: Array<ScheduleDay>, hour: number, minute: number, timezone: string | null, }
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface StartupEstimate { estimated_ms: bigint, low_ms: bigint, high_ms: bigint, samples: number, elapsed_ms: bigint | null, remaining_ms: bigint | null, }
\ No newline at end of file
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CrashCause } from "./CrashCause";
+import type { InstanceState } from "./InstanceState";
+
+export interface SupportBundle { lodestone_version: string, os: string, flavour: string, version: string, state: InstanceState, config: unknown, launch_command: Array<string> | null, java_version: string | null, console_tail: Array<string>, crash_cause: CrashCause | null, }
\ No newline at end of file
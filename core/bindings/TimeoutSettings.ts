@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface TimeoutSettings { idle_timeout: number | null, no_activity_timeout: number | null, start_on_connection: boolean, }
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface VersionInfo { configured_version: string, configured_build: string | null, detected_version: string | null, detected_build: string | null, }
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface VersionSummary { flavour: string, installed_version: string, installed_build: string | null, latest_build: string | null, latest_version: string | null, build_outdated: boolean, version_outdated: boolean, }
\ No newline at end of file
@@ -1,14 +1,32 @@
 use crate::{
-    error::Error, output_types::ClientEvent,
-    prelude::LODESTONE_EPOCH_MIL, events::EventQuery,
+    auth::user_id::UserId,
+    error::Error,
+    events::{EventInner, EventLevel, EventQuery, EventType},
+    output_types::ClientEvent,
+    prelude::LODESTONE_EPOCH_MIL,
+    types::{InstanceUuid, TimeRange},
 };
 
 use color_eyre::eyre::Context;
+use futures::TryStreamExt;
+use serde::Deserialize;
 use sqlx::sqlite::SqlitePool;
+use sqlx::{QueryBuilder, Row, Sqlite};
 use tracing::error;
+use ts_rs::TS;
 
 // TODO clean up all unwraps
 
+/// The lowest and highest snowflake created within `time_range`, both inclusive
+///
+/// The timestamp is in the bits above the lowest 22 of a snowflake
+fn snowflake_bounds(time_range: &TimeRange) -> (i64, i64) {
+    let epoch = LODESTONE_EPOCH_MIL.with(|p| *p);
+    let start = (time_range.start - epoch) << 22;
+    let end = ((time_range.end + 1 - epoch) << 22) - 1;
+    (start, end)
+}
+
 pub async fn search_events(
     pool: &SqlitePool,
     event_query: EventQuery,
@@ -19,8 +37,7 @@ pub async fn search_events(
         .await
         .context("Failed to aquire connection to db")?;
     let parsed_client_events = if let Some(time_range) = &event_query.time_range {
-        let start = (time_range.start - LODESTONE_EPOCH_MIL.with(|p| *p)) << 22;
-        let end = (time_range.end + 1 - LODESTONE_EPOCH_MIL.with(|p| *p)) << 22;
+        let (start, end) = snowflake_bounds(time_range);
         let rows = sqlx::query!(
             r#"
 SELECT
@@ -69,16 +86,266 @@ FROM ClientEvents"#
     Ok(filtered)
 }
 
+/// Events returned by `query_events` when the filter sets no limit
+const DEFAULT_EVENT_PAGE_SIZE: u32 = 100;
+/// Most events `query_events` returns at once
+const MAX_EVENT_PAGE_SIZE: u32 = 1000;
+
+/// Narrows `query_events` down with conditions on the indexed columns, a page at a time
+#[derive(Deserialize, Clone, Debug, Default, TS)]
+#[ts(export)]
+pub struct EventQueryFilter {
+    pub instance_id: Option<InstanceUuid>,
+    pub level: Option<EventLevel>,
+    /// Unix timestamps in milliseconds, both inclusive
+    pub time_range: Option<TimeRange>,
+    pub caused_by_user_id: Option<UserId>,
+    /// Defaults to 100, at most 1000
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+/// Reads the stored events matching `filter`, the newest first
+pub async fn query_events(
+    pool: &SqlitePool,
+    filter: EventQueryFilter,
+) -> Result<Vec<ClientEvent>, Error> {
+    let mut connection = pool
+        .acquire()
+        .await
+        .context("Failed to aquire connection to db")?;
+    let mut query: QueryBuilder<Sqlite> =
+        QueryBuilder::new("SELECT event_value FROM ClientEvents WHERE 1 = 1");
+    if let Some(instance_id) = filter.instance_id {
+        query.push(" AND instance_id = ").push_bind(instance_id);
+    }
+    if let Some(level) = filter.level {
+        query.push(" AND level = ").push_bind(level);
+    }
+    if let Some(time_range) = &filter.time_range {
+        let (start, end) = snowflake_bounds(time_range);
+        query
+            .push(" AND snowflake >= ")
+            .push_bind(start)
+            .push(" AND snowflake <= ")
+            .push_bind(end);
+    }
+    if let Some(caused_by_user_id) = filter.caused_by_user_id {
+        query
+            .push(" AND caused_by_user_id = ")
+            .push_bind(caused_by_user_id);
+    }
+    let limit = filter
+        .limit
+        .unwrap_or(DEFAULT_EVENT_PAGE_SIZE)
+        .min(MAX_EVENT_PAGE_SIZE);
+    query
+        .push(" ORDER BY snowflake DESC LIMIT ")
+        .push_bind(limit as i64)
+        .push(" OFFSET ")
+        .push_bind(filter.offset.unwrap_or(0) as i64);
+    let rows = query
+        .build()
+        .fetch_all(&mut connection)
+        .await
+        .context("Failed to fetch events")?;
+    let mut ret = Vec::with_capacity(rows.len());
+    for row in rows {
+        let event_value: String = row
+            .try_get("event_value")
+            .context("Failed to read event from db")?;
+        match serde_json::from_str(&event_value) {
+            Ok(client_event) => ret.push(client_event),
+            Err(_) => error!("Failed to parse client event: {}", event_value),
+        }
+    }
+    Ok(ret)
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, TS)]
+#[ts(export)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+const CSV_HEADER: &str =
+    "snowflake,timestamp,level,event_type,instance_uuid,caused_by,details,event\n";
+
+/// Writes events one at a time in the requested format
+struct EventExporter {
+    format: ExportFormat,
+    buf: Vec<u8>,
+    count: usize,
+}
+
+impl EventExporter {
+    fn new(format: ExportFormat) -> Self {
+        let buf = match format {
+            ExportFormat::Csv => CSV_HEADER.as_bytes().to_vec(),
+            ExportFormat::Json => b"[".to_vec(),
+        };
+        Self {
+            format,
+            buf,
+            count: 0,
+        }
+    }
+
+    fn push(&mut self, client_event: &ClientEvent) -> Result<(), Error> {
+        match self.format {
+            ExportFormat::Csv => {
+                let instance_uuid = match &client_event.event_inner {
+                    EventInner::InstanceEvent(instance_event) => {
+                        instance_event.instance_uuid.to_string()
+                    }
+                    EventInner::MacroEvent(macro_event) => macro_event
+                        .instance_uuid
+                        .as_ref()
+                        .map(|uuid| uuid.to_string())
+                        .unwrap_or_default(),
+                    _ => String::new(),
+                };
+                let fields = [
+                    client_event.snowflake.to_string(),
+                    client_event.snowflake.timestamp_millis().to_string(),
+                    format!("{:?}", client_event.level),
+                    format!("{:?}", EventType::from(&client_event.event_inner)),
+                    instance_uuid,
+                    serde_json::to_string(&client_event.caused_by)
+                        .context("Failed to serialize event")?,
+                    client_event.details.clone(),
+                    serde_json::to_string(&client_event.event_inner)
+                        .context("Failed to serialize event")?,
+                ];
+                let line = fields
+                    .iter()
+                    .map(|field| escape_csv_field(field))
+                    .collect::<Vec<_>>()
+                    .join(",");
+                self.buf.extend_from_slice(line.as_bytes());
+                self.buf.push(b'\n');
+            }
+            ExportFormat::Json => {
+                if self.count > 0 {
+                    self.buf.push(b',');
+                }
+                serde_json::to_writer(&mut self.buf, client_event)
+                    .context("Failed to serialize event")?;
+            }
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.format == ExportFormat::Json {
+            self.buf.push(b']');
+        }
+        self.buf
+    }
+}
+
+fn escape_csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Serializes the stored events matching `event_query` for analysis in external tools
+///
+/// Rows are serialized as they are read from the database instead of collecting every parsed
+/// event first, but the whole output is still held in memory until it is returned
+pub async fn export_events(
+    pool: &SqlitePool,
+    event_query: EventQuery,
+    format: ExportFormat,
+) -> Result<Vec<u8>, Error> {
+    let mut connection = pool
+        .acquire()
+        .await
+        .context("Failed to aquire connection to db")?;
+    let mut query: QueryBuilder<Sqlite> =
+        QueryBuilder::new("SELECT event_value FROM ClientEvents WHERE 1 = 1");
+    if let Some(time_range) = &event_query.time_range {
+        let (start, end) = snowflake_bounds(time_range);
+        query
+            .push(" AND snowflake >= ")
+            .push_bind(start)
+            .push(" AND snowflake <= ")
+            .push_bind(end);
+    }
+    let mut exporter = EventExporter::new(format);
+    let mut rows = query.build().fetch(&mut connection);
+    while let Some(row) = rows.try_next().await.context("Failed to fetch events")? {
+        let event_value: String = row
+            .try_get("event_value")
+            .context("Failed to read event from db")?;
+        match serde_json::from_str::<ClientEvent>(&event_value) {
+            Ok(client_event) if event_query.filter(&client_event) => {
+                exporter.push(&client_event)?
+            }
+            Ok(_) => {}
+            Err(_) => error!("Failed to parse client event: {}", event_value),
+        }
+    }
+    Ok(exporter.finish())
+}
+
+/// Serializes the stored events of an instance, oldest first, to archive its activity log
+///
+/// Only the rows of the instance are read, and like `export_events` they are serialized as
+/// they are read
+pub async fn export_instance_events(
+    pool: &SqlitePool,
+    instance_id: &InstanceUuid,
+    format: ExportFormat,
+) -> Result<Vec<u8>, Error> {
+    let mut connection = pool
+        .acquire()
+        .await
+        .context("Failed to aquire connection to db")?;
+    let mut exporter = EventExporter::new(format);
+    let mut rows = sqlx::query(
+        r#"
+SELECT
+event_value
+FROM ClientEvents
+WHERE instance_id = ?1
+ORDER BY snowflake"#,
+    )
+    .bind(instance_id.clone())
+    .fetch(&mut connection);
+    while let Some(row) = rows.try_next().await.context("Failed to fetch events")? {
+        let event_value: String = row
+            .try_get("event_value")
+            .context("Failed to read event from db")?;
+        match serde_json::from_str::<ClientEvent>(&event_value) {
+            Ok(client_event) => exporter.push(&client_event)?,
+            Err(_) => error!("Failed to parse client event: {}", event_value),
+        }
+    }
+    Ok(exporter.finish())
+}
+
 #[cfg(test)]
 #[allow(unused_imports)]
 mod tests {
     use std::{path::PathBuf, str::FromStr};
 
-    use sqlx::{sqlite::SqliteConnectOptions, Pool, Sqlite};
+    use sqlx::{
+        sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+        Pool, Sqlite,
+    };
 
     use crate::{
-        db::write::init_client_events_table,
-        events::{CausedBy, EventInner, EventLevel, FSEvent, FSOperation, FSTarget},
+        db::write::{init_client_events_table, write_client_events},
+        events::{
+            CausedBy, EventInner, EventLevel, FSEvent, FSOperation, FSTarget, InstanceEvent,
+            InstanceEventInner,
+        },
         types::Snowflake,
     };
 
@@ -128,6 +395,215 @@ mod tests {
         // let row_1 = row_1_result.unwrap();
     }
 
+    #[tokio::test]
+    async fn test_query_events() {
+        // a single connection, as every connection to an in-memory database gets its own
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        init_client_events_table(&pool).await.unwrap();
+
+        let instance_event = |message: &str, level: EventLevel, caused_by: CausedBy| ClientEvent {
+            event_inner: EventInner::InstanceEvent(InstanceEvent {
+                instance_uuid: InstanceUuid::from("INSTANCE_test".to_string()),
+                instance_name: "test".to_string(),
+                instance_event_inner: InstanceEventInner::SystemMessage {
+                    message: message.to_string(),
+                },
+            }),
+            details: message.to_string(),
+            snowflake: Snowflake::new(),
+            level,
+            caused_by,
+        };
+        let user = CausedBy::User {
+            user_id: UserId::from("USER_test".to_string()),
+            user_name: "test".to_string(),
+        };
+        let events = vec![
+            instance_event("first", EventLevel::Info, CausedBy::System),
+            instance_event("second", EventLevel::Error, user.clone()),
+            instance_event("third", EventLevel::Info, user),
+            ClientEvent {
+                event_inner: EventInner::FSEvent(FSEvent {
+                    operation: FSOperation::Read,
+                    target: FSTarget::File(PathBuf::from("/test")),
+                }),
+                details: "fourth".to_string(),
+                snowflake: Snowflake::new(),
+                level: EventLevel::Info,
+                caused_by: CausedBy::System,
+            },
+        ];
+        write_client_events(&pool, &events).await.unwrap();
+
+        let details = |events: Vec<ClientEvent>| {
+            events
+                .into_iter()
+                .map(|event| event.details)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            details(
+                query_events(&pool, EventQueryFilter::default())
+                    .await
+                    .unwrap()
+            ),
+            vec!["fourth", "third", "second", "first"]
+        );
+        assert_eq!(
+            details(
+                query_events(
+                    &pool,
+                    EventQueryFilter {
+                        instance_id: Some(InstanceUuid::from("INSTANCE_test".to_string())),
+                        level: Some(EventLevel::Info),
+                        ..Default::default()
+                    }
+                )
+                .await
+                .unwrap()
+            ),
+            vec!["third", "first"]
+        );
+        assert_eq!(
+            details(
+                query_events(
+                    &pool,
+                    EventQueryFilter {
+                        caused_by_user_id: Some(UserId::from("USER_test".to_string())),
+                        limit: Some(1),
+                        offset: Some(1),
+                        ..Default::default()
+                    }
+                )
+                .await
+                .unwrap()
+            ),
+            vec!["second"]
+        );
+        let first = events[0].snowflake.timestamp_millis();
+        assert!(query_events(
+            &pool,
+            EventQueryFilter {
+                time_range: Some(TimeRange {
+                    start: first - 2000,
+                    end: first - 1000,
+                }),
+                ..Default::default()
+            }
+        )
+        .await
+        .unwrap()
+        .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_export_instance_events() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        init_client_events_table(&pool).await.unwrap();
+
+        let instance_event = |instance: &str, details: &str| ClientEvent {
+            event_inner: EventInner::InstanceEvent(InstanceEvent {
+                instance_uuid: InstanceUuid::from(instance.to_string()),
+                instance_name: instance.to_string(),
+                instance_event_inner: InstanceEventInner::SystemMessage {
+                    message: details.to_string(),
+                },
+            }),
+            details: details.to_string(),
+            snowflake: Snowflake::new(),
+            level: EventLevel::Info,
+            caused_by: CausedBy::System,
+        };
+        write_client_events(
+            &pool,
+            &[
+                instance_event("INSTANCE_a", "first, with a comma"),
+                instance_event("INSTANCE_b", "other instance"),
+                instance_event("INSTANCE_a", "second\non two lines"),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let instance_id = InstanceUuid::from("INSTANCE_a".to_string());
+        let exported: Vec<ClientEvent> = serde_json::from_slice(
+            &export_instance_events(&pool, &instance_id, ExportFormat::Json)
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            exported
+                .into_iter()
+                .map(|event| event.details)
+                .collect::<Vec<_>>(),
+            vec!["first, with a comma", "second\non two lines"]
+        );
+
+        let csv = String::from_utf8(
+            export_instance_events(&pool, &instance_id, ExportFormat::Csv)
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(csv.starts_with(CSV_HEADER));
+        assert!(csv.contains(",\"first, with a comma\","));
+        assert!(csv.contains(",\"second\non two lines\","));
+        assert!(!csv.contains("other instance"));
+    }
+
+    #[test]
+    fn test_event_exporter() {
+        let client_event = ClientEvent {
+            event_inner: EventInner::FSEvent(FSEvent {
+                operation: FSOperation::Read,
+                target: FSTarget::File(PathBuf::from("/test")),
+            }),
+            details: "Read \"a\", then b".to_string(),
+            snowflake: Snowflake::new(),
+            level: EventLevel::Info,
+            caused_by: CausedBy::System,
+        };
+
+        let mut exporter = EventExporter::new(ExportFormat::Csv);
+        exporter.push(&client_event).unwrap();
+        let csv = String::from_utf8(exporter.finish()).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next().unwrap(), CSV_HEADER.trim_end());
+        let row = lines.next().unwrap();
+        assert!(row.contains(",Info,FSEvent,,"));
+        assert!(row.contains(r#","Read ""a"", then b","#));
+        assert!(lines.next().is_none());
+
+        let mut exporter = EventExporter::new(ExportFormat::Json);
+        exporter.push(&client_event).unwrap();
+        exporter.push(&client_event).unwrap();
+        let exported: Vec<ClientEvent> = serde_json::from_slice(&exporter.finish()).unwrap();
+        assert_eq!(exported.len(), 2);
+        assert_eq!(exported[0].details, client_event.details);
+    }
+
+    #[test]
+    fn test_snowflake_bounds() {
+        let epoch = LODESTONE_EPOCH_MIL.with(|p| *p);
+        let (start, end) = snowflake_bounds(&TimeRange {
+            start: epoch + 1000,
+            end: epoch + 2000,
+        });
+        assert_eq!(start, 1000 << 22);
+        // every snowflake of the last millisecond is included, none of the one after
+        assert_eq!(end >> 22, 2000);
+        assert_eq!((end + 1) >> 22, 2001);
+    }
+
     // TODO should properly implement tests, with dummy values
     // #[tokio::test]
     // async fn test_read() {
@@ -1,80 +1,315 @@
 use crate::{
-    error::Error,
-    events::{Event, EventInner, ProgressionEventInner},
+    error::{Error, ErrorKind},
+    events::{Event, EventInner, ProgressionEvent, ProgressionEventInner},
+    global_settings::GlobalSettings,
     output_types::ClientEvent,
+    prelude::LODESTONE_EPOCH_MIL,
+    types::Snowflake,
 };
 
-use color_eyre::eyre::Context;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use color_eyre::eyre::{eyre, Context};
+use serde::{Deserialize, Serialize};
 use sqlx::sqlite::SqlitePool;
 use tokio::sync::broadcast::{error::RecvError, Receiver};
-use tracing::{error, warn};
+use tokio::sync::Mutex;
+use tokio::time::MissedTickBehavior;
+use tracing::{error, info, warn};
+use ts_rs::TS;
 
 use super::types::ClientEventRow;
 
 // TODO clean up all unwraps
 
-pub async fn write_event_to_db_task(mut event_receiver: Receiver<Event>, sqlite_pool: SqlitePool) {
+/// Events buffered before they are written to the database in a single transaction
+const EVENT_BATCH_SIZE: usize = 64;
+/// Longest an event stays buffered when events come in slower than a batch fills up
+const EVENT_FLUSH_INTERVAL: Duration = Duration::from_millis(500);
+/// Batches in a row that fail to be written before the task backs off, e.g. as the disk is full
+const FAILED_WRITES_BEFORE_BACKOFF: u32 = 3;
+/// Pause after `FAILED_WRITES_BEFORE_BACKOFF` failed batches, doubled for every further one
+const EVENT_WRITE_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_EVENT_WRITE_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Follows the progressions in flight, so that only their start and end are stored rather than
+/// every update
+#[derive(Default)]
+struct ProgressionTracker {
+    /// Total and progress so far of the progressions that started but haven't finished
+    running: HashMap<Snowflake, (Option<f64>, f64)>,
+}
+
+impl ProgressionTracker {
+    /// Whether the event is where a progression starts or ends
+    ///
+    /// Some progressions never send an end, so the update that brings the progress up to the
+    /// total counts as the end as well
+    fn is_terminal(&mut self, progression_event: &ProgressionEvent) -> bool {
+        let event_id = progression_event.event_id();
+        match progression_event.progression_event_inner() {
+            ProgressionEventInner::ProgressionStart { total, .. } => {
+                self.running.insert(event_id, (*total, 0.0));
+                true
+            }
+            ProgressionEventInner::ProgressionUpdate { progress, .. } => {
+                let finished = match self.running.get_mut(&event_id) {
+                    Some((total, done)) => {
+                        *done += progress;
+                        total.map_or(false, |total| *done >= total)
+                    }
+                    None => false,
+                };
+                if finished {
+                    self.running.remove(&event_id);
+                }
+                finished
+            }
+            ProgressionEventInner::ProgressionEnd { .. } => {
+                self.running.remove(&event_id);
+                true
+            }
+        }
+    }
+}
+
+pub async fn write_event_to_db_task(
+    mut event_receiver: Receiver<Event>,
+    sqlite_pool: SqlitePool,
+    global_settings: Arc<Mutex<GlobalSettings>>,
+) {
     let init_result = init_client_events_table(&sqlite_pool).await;
     if let Err(error) = init_result.as_ref() {
         warn!("Failed to initialize client events table: {}", error);
         return;
     }
 
+    let mut batch: Vec<ClientEvent> = Vec::with_capacity(EVENT_BATCH_SIZE);
+    let mut progression_tracker = ProgressionTracker::default();
+    let mut failed_writes = 0;
+    let mut flush_interval = tokio::time::interval(EVENT_FLUSH_INTERVAL);
+    flush_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
     loop {
-        let result = event_receiver.recv().await;
-        if let Err(error) = result.as_ref() {
-            match error {
-                RecvError::Lagged(_) => {
+        let closed = tokio::select! {
+            result = event_receiver.recv() => match result {
+                Ok(event) => {
+                    let client_event: ClientEvent = event.into();
+                    if let EventInner::ProgressionEvent(pe) = &client_event.event_inner {
+                        if !progression_tracker.is_terminal(pe)
+                            && !global_settings.lock().await.persist_progression()
+                        {
+                            continue;
+                        }
+                    }
+                    batch.push(client_event);
+                    if batch.len() < EVENT_BATCH_SIZE {
+                        continue;
+                    }
+                    false
+                }
+                Err(RecvError::Lagged(_)) => {
                     warn!("Event buffer lagged");
                     continue;
                 }
-                RecvError::Closed => {
+                Err(RecvError::Closed) => {
                     warn!("Event buffer closed");
-                    break;
+                    true
+                }
+            },
+            _ = flush_interval.tick() => false,
+        };
+        // written on close as well, so the events buffered until then aren't lost
+        if !batch.is_empty() {
+            if write_batch(&sqlite_pool, &batch).await {
+                failed_writes = 0;
+            } else {
+                failed_writes += 1;
+                if failed_writes >= FAILED_WRITES_BEFORE_BACKOFF && !closed {
+                    let backoff = EVENT_WRITE_BACKOFF
+                        .saturating_mul(
+                            2_u32.saturating_pow(failed_writes - FAILED_WRITES_BEFORE_BACKOFF),
+                        )
+                        .min(MAX_EVENT_WRITE_BACKOFF);
+                    warn!(
+                        "Failed to write events {} times in a row, retrying in {:?}",
+                        failed_writes, backoff
+                    );
+                    tokio::time::sleep(backoff).await;
                 }
             }
+            batch.clear();
         }
-
-        let client_event: ClientEvent = result.unwrap().into();
-        if let EventInner::ProgressionEvent(pe) = &client_event.event_inner {
-            if let ProgressionEventInner::ProgressionUpdate { .. } = pe.progression_event_inner() {
-                continue;
-            }
-        }
-        let insertion_result = write_client_event(&sqlite_pool, client_event).await;
-        if let Err(e) = insertion_result.as_ref() {
-            error!("Error inserting into database: {}", e);
+        if closed {
             break;
         }
     }
 }
 
-async fn write_client_event(pool: &SqlitePool, client_event: ClientEvent) -> Result<i64, Error> {
-    let mut connection = pool
-        .acquire()
+/// Writes a batch of events, one at a time if the batch as a whole fails so that a single bad
+/// event doesn't take the others down with it
+///
+/// Every write takes a connection from the pool anew, so a connection that broke isn't reused.
+/// Returns whether any event was written
+async fn write_batch(pool: &SqlitePool, batch: &[ClientEvent]) -> bool {
+    let e = match write_client_events(pool, batch).await {
+        Ok(()) => return true,
+        Err(e) => e,
+    };
+    if batch.len() == 1 {
+        error!("Error inserting into database: {}", e);
+        return false;
+    }
+    warn!(
+        "Error inserting {} events into database, writing them one by one: {}",
+        batch.len(),
+        e
+    );
+    let mut written = false;
+    for client_event in batch {
+        match write_client_events(pool, std::slice::from_ref(client_event)).await {
+            Ok(()) => written = true,
+            Err(e) => error!(
+                "Error inserting event {} into database: {}",
+                client_event.snowflake.to_string(),
+                e
+            ),
+        }
+    }
+    written
+}
+
+/// Inserts the events in a single transaction, so either all of them are written or none are
+pub(crate) async fn write_client_events(
+    pool: &SqlitePool,
+    client_events: &[ClientEvent],
+) -> Result<(), Error> {
+    let mut transaction = pool
+        .begin()
         .await
-        .context("Failed to aquire db connection")?;
+        .context("Failed to begin db transaction")?;
 
-    let row = ClientEventRow::from(&client_event);
-    let id = sqlx::query!(
-        r#"
+    for client_event in client_events {
+        let row = ClientEventRow::from(client_event);
+        sqlx::query!(
+            r#"
 INSERT INTO ClientEvents
 (event_value, details, snowflake, level, caused_by_user_id, instance_id)
 VALUES
 (?1, ?2, ?3, ?4, ?5, ?6)
         "#,
-        row.event_value,
-        row.details,
-        row.snowflake,
-        row.level,
-        row.caused_by_user_id,
-        row.instance_id,
+            row.event_value,
+            row.details,
+            row.snowflake,
+            row.level,
+            row.caused_by_user_id,
+            row.instance_id,
+        )
+        .execute(&mut transaction)
+        .await
+        .context("Failed to write to DB")?;
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit db transaction")?;
+    Ok(())
+}
+
+/// How long events are kept in the database before they are pruned
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct EventRetention {
+    pub max_age_days: u32,
+    /// The most recent events of each instance kept regardless of their age
+    pub keep_min_per_instance: u32,
+    pub prune_interval_minutes: u32,
+}
+
+impl Default for EventRetention {
+    fn default() -> Self {
+        Self {
+            max_age_days: 90,
+            keep_min_per_instance: 1000,
+            prune_interval_minutes: 60,
+        }
+    }
+}
+
+impl EventRetention {
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.prune_interval_minutes == 0 {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("The event prune interval must be at least a minute"),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Deletes the events older than `older_than`, besides the `keep_min` most recent events of each
+/// instance, and returns how many were deleted
+///
+/// Events that don't belong to an instance are kept as if they all belonged to one
+pub async fn prune_events(
+    pool: &SqlitePool,
+    older_than: Duration,
+    keep_min: usize,
+) -> Result<u64, Error> {
+    let cutoff_millis = chrono::Utc::now().timestamp_millis() - older_than.as_millis() as i64;
+    let cutoff = (cutoff_millis - LODESTONE_EPOCH_MIL.with(|p| *p)).max(0) << 22;
+    let result = sqlx::query(
+        r#"
+DELETE FROM ClientEvents
+WHERE snowflake < ?1
+AND id NOT IN (
+    SELECT id FROM (
+        SELECT id, ROW_NUMBER() OVER (PARTITION BY instance_id ORDER BY snowflake DESC) AS recency
+        FROM ClientEvents
     )
-    .execute(&mut connection)
+    WHERE recency <= ?2
+)"#,
+    )
+    .bind(cutoff)
+    .bind(keep_min as i64)
+    .execute(pool)
     .await
-    .context("Failed to write to DB")?
-    .last_insert_rowid();
-    Ok(id)
+    .context("Failed to prune events")?;
+    Ok(result.rows_affected())
+}
+
+/// Periodically prunes the events past the retention configured in the global settings
+pub async fn prune_events_task(
+    sqlite_pool: SqlitePool,
+    global_settings: Arc<Mutex<GlobalSettings>>,
+) {
+    loop {
+        // read every time, so that changes to the retention apply without a restart
+        let event_retention = global_settings.lock().await.event_retention();
+        let prune_interval = event_retention
+            .map(|event_retention| event_retention.prune_interval_minutes)
+            .unwrap_or(EventRetention::default().prune_interval_minutes);
+        tokio::time::sleep(Duration::from_secs(prune_interval as u64 * 60)).await;
+        let event_retention = match event_retention {
+            Some(event_retention) => event_retention,
+            None => continue,
+        };
+        match prune_events(
+            &sqlite_pool,
+            Duration::from_secs(event_retention.max_age_days as u64 * 86400),
+            event_retention.keep_min_per_instance as usize,
+        )
+        .await
+        {
+            Ok(pruned) => info!(
+                "Pruned {} events older than {} days",
+                pruned, event_retention.max_age_days
+            ),
+            Err(e) => error!("Failed to prune events: {}", e),
+        }
+    }
 }
 
 pub async fn init_client_events_table(pool: &SqlitePool) -> Result<(), Error> {
@@ -109,11 +344,19 @@ pub async fn init_client_events_table(pool: &SqlitePool) -> Result<(), Error> {
 mod tests {
     use std::{path::PathBuf, str::FromStr};
 
-    use sqlx::{sqlite::SqliteConnectOptions, Pool};
+    use sqlx::{
+        sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+        Pool,
+    };
 
     use crate::{
-        events::{CausedBy, EventLevel, FSEvent, FSOperation, FSTarget},
-        types::Snowflake,
+        event_broadcaster::EventBroadcaster,
+        events::{
+            new_fs_event, CausedBy, EventLevel, FSEvent, FSOperation, FSTarget, InstanceEvent,
+            InstanceEventInner,
+        },
+        global_settings::GlobalSettingsData,
+        types::{InstanceUuid, Snowflake},
     };
 
     use super::*;
@@ -144,7 +387,7 @@ mod tests {
             level: EventLevel::Info,
             caused_by: CausedBy::System,
         };
-        let write_result = write_client_event(&pool, dummy_event.clone()).await;
+        let write_result = write_client_events(&pool, &[dummy_event.clone()]).await;
         assert!(write_result.is_ok());
 
         let row_result = sqlx::query!(
@@ -166,4 +409,148 @@ mod tests {
         assert_eq!(row.caused_by_user_id, None);
         assert_eq!(row.instance_id, None);
     }
+
+    #[tokio::test]
+    async fn test_prune_events() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        init_client_events_table(&pool).await.unwrap();
+
+        let days_ago = |days: i64| -> Snowflake {
+            let millis = chrono::Utc::now().timestamp_millis() - days * 86400 * 1000;
+            let snowflake = (millis - LODESTONE_EPOCH_MIL.with(|p| *p)) << 22;
+            serde_json::from_str(&format!("\"{}\"", snowflake)).unwrap()
+        };
+        let event = |instance: Option<&str>, snowflake: Snowflake| ClientEvent {
+            event_inner: match instance {
+                Some(instance) => EventInner::InstanceEvent(InstanceEvent {
+                    instance_uuid: InstanceUuid::from(instance.to_string()),
+                    instance_name: instance.to_string(),
+                    instance_event_inner: InstanceEventInner::SystemMessage {
+                        message: "test".to_string(),
+                    },
+                }),
+                None => EventInner::FSEvent(FSEvent {
+                    operation: FSOperation::Read,
+                    target: FSTarget::File(PathBuf::from("/test")),
+                }),
+            },
+            details: "test".to_string(),
+            snowflake,
+            level: EventLevel::Info,
+            caused_by: CausedBy::System,
+        };
+        write_client_events(
+            &pool,
+            &[
+                event(Some("INSTANCE_a"), days_ago(30)),
+                event(Some("INSTANCE_a"), days_ago(20)),
+                event(Some("INSTANCE_a"), days_ago(10)),
+                event(Some("INSTANCE_a"), days_ago(0)),
+                event(Some("INSTANCE_b"), days_ago(30)),
+                event(None, days_ago(30)),
+            ],
+        )
+        .await
+        .unwrap();
+
+        // the 30 and 20 days old events of a go, b and the event without an instance are kept
+        // as their most recent events
+        let pruned = prune_events(&pool, Duration::from_secs(5 * 86400), 2)
+            .await
+            .unwrap();
+        assert_eq!(pruned, 2);
+        let remaining: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM ClientEvents")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(remaining, 4);
+    }
+
+    #[tokio::test]
+    async fn test_write_task_survives_failed_writes() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        init_client_events_table(&pool).await.unwrap();
+        // makes every insert of an event about a path containing "fail" fail
+        sqlx::query(
+            r#"
+CREATE TRIGGER fail_insert BEFORE INSERT ON ClientEvents
+WHEN NEW.event_value LIKE '%fail%'
+BEGIN
+    SELECT RAISE(ABORT, 'injected failure');
+END"#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let (event_broadcaster, _) = EventBroadcaster::new(10);
+        let global_settings = Arc::new(Mutex::new(GlobalSettings::new(
+            temp_dir.path().join("global_settings.json"),
+            event_broadcaster,
+            GlobalSettingsData::default(),
+        )));
+        let (tx, rx) = tokio::sync::broadcast::channel(16);
+        let task = tokio::spawn(write_event_to_db_task(rx, pool.clone(), global_settings));
+        let fs_event = |path: &str| {
+            new_fs_event(
+                FSOperation::Read,
+                FSTarget::File(PathBuf::from(path)),
+                CausedBy::System,
+            )
+        };
+
+        // written along with a failing event in the same batch
+        tx.send(fs_event("/fail")).unwrap();
+        tx.send(fs_event("/first")).unwrap();
+        tokio::time::sleep(EVENT_FLUSH_INTERVAL * 2).await;
+        // written in a batch after the failed one
+        tx.send(fs_event("/second")).unwrap();
+        drop(tx);
+        task.await.unwrap();
+
+        let written: Vec<String> =
+            sqlx::query_scalar("SELECT event_value FROM ClientEvents ORDER BY snowflake")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(written.len(), 2);
+        assert!(written[0].contains("/first"));
+        assert!(written[1].contains("/second"));
+    }
+
+    #[test]
+    fn test_progression_tracker() {
+        let progression_event = |event: Event| match event.event_inner {
+            EventInner::ProgressionEvent(progression_event) => progression_event,
+            _ => unreachable!(),
+        };
+        let mut tracker = ProgressionTracker::default();
+
+        let (start, event_id) =
+            Event::new_progression_event_start("Downloading", Some(10.0), None, CausedBy::System);
+        assert!(tracker.is_terminal(&progression_event(start)));
+        let update = || Event::new_progression_event_update(&event_id, "Downloading", 5.0);
+        assert!(!tracker.is_terminal(&progression_event(update())));
+        // brings the progress up to the total
+        assert!(tracker.is_terminal(&progression_event(update())));
+        assert!(!tracker.is_terminal(&progression_event(update())));
+        assert!(
+            tracker.is_terminal(&progression_event(Event::new_progression_event_end(
+                event_id,
+                true,
+                Some("Done"),
+                None
+            )))
+        );
+        assert!(tracker.running.is_empty());
+    }
 }
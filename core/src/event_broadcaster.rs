@@ -1,10 +1,12 @@
 use std::collections::HashSet;
 
+use futures::Stream;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::broadcast::{Receiver, Sender};
 use tracing::error;
 
 use crate::{
-    events::{Event, EventInner, InstanceEvent, InstanceEventInner},
+    events::{Event, EventInner, InstanceEvent, InstanceEventInner, InstanceEventKind},
     traits::{t_player::Player, t_server::State},
     types::InstanceUuid,
 };
@@ -27,6 +29,34 @@ pub struct PlayerChange {
     players_left: HashSet<Player>,
 }
 
+/// Narrows down which events a subscriber receives, `None` matches everything
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    pub instance_uuid: Option<InstanceUuid>,
+    pub instance_event_kinds: Option<Vec<InstanceEventKind>>,
+}
+
+impl EventFilter {
+    pub fn matches(&self, event: &Event) -> bool {
+        let instance_event = match &event.event_inner {
+            EventInner::InstanceEvent(instance_event) => instance_event,
+            _ => return self.instance_uuid.is_none() && self.instance_event_kinds.is_none(),
+        };
+        if let Some(instance_uuid) = &self.instance_uuid {
+            if instance_event.instance_uuid != instance_uuid {
+                return false;
+            }
+        }
+        if let Some(instance_event_kinds) = &self.instance_event_kinds {
+            if !instance_event_kinds.contains(&instance_event.instance_event_inner.as_ref().into())
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
 impl EventBroadcaster {
     pub fn new(capacity: usize) -> (Self, Receiver<Event>) {
         let (event_tx, rx) = tokio::sync::broadcast::channel(capacity);
@@ -43,6 +73,21 @@ impl EventBroadcaster {
         self.event_tx.subscribe()
     }
 
+    /// Streams the events that match the filter, skipping any the subscriber fell behind on
+    ///
+    /// The stream ends once every sender is dropped
+    pub fn subscribe_filtered(&self, filter: EventFilter) -> impl Stream<Item = Event> {
+        futures::stream::unfold((self.subscribe(), filter), |(mut rx, filter)| async move {
+            loop {
+                match rx.recv().await {
+                    Ok(event) if filter.matches(&event) => return Some((event, (rx, filter))),
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+
     /// Returns the next event that matches the given instance uuid.
     ///
     /// Will block forever if instance_uuid is not found.
@@ -144,3 +189,68 @@ impl AsRef<Sender<Event>> for EventBroadcaster {
         &self.event_tx
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::events::CausedBy;
+    use crate::types::Snowflake;
+
+    fn instance_event(
+        instance_uuid: &InstanceUuid,
+        instance_event_inner: InstanceEventInner,
+    ) -> Event {
+        Event {
+            event_inner: EventInner::InstanceEvent(InstanceEvent {
+                instance_uuid: instance_uuid.clone(),
+                instance_name: "test".to_string(),
+                instance_event_inner,
+            }),
+            details: "".to_string(),
+            snowflake: Snowflake::default(),
+            caused_by: CausedBy::System,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_filtered() {
+        let (event_broadcaster, _rx) = EventBroadcaster::new(10);
+        let instance_uuid = InstanceUuid::default();
+        let other_uuid = InstanceUuid::default();
+        let stream = event_broadcaster.subscribe_filtered(EventFilter {
+            instance_uuid: Some(instance_uuid.clone()),
+            instance_event_kinds: Some(vec![InstanceEventKind::SystemMessage]),
+        });
+        tokio::pin!(stream);
+
+        event_broadcaster.send(instance_event(
+            &instance_uuid,
+            InstanceEventInner::InstanceOutput {
+                message: "output".to_string(),
+            },
+        ));
+        event_broadcaster.send(instance_event(
+            &other_uuid,
+            InstanceEventInner::SystemMessage {
+                message: "other".to_string(),
+            },
+        ));
+        event_broadcaster.send(instance_event(
+            &instance_uuid,
+            InstanceEventInner::SystemMessage {
+                message: "match".to_string(),
+            },
+        ));
+
+        let event = stream.next().await.unwrap();
+        match event.event_inner {
+            EventInner::InstanceEvent(InstanceEvent {
+                instance_event_inner: InstanceEventInner::SystemMessage { message },
+                ..
+            }) => assert_eq!(message, "match"),
+            _ => panic!("Unexpected event {:?}", event),
+        }
+    }
+}
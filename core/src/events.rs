@@ -7,6 +7,7 @@ use ts_rs::TS;
 
 use crate::{
     auth::{permission::UserPermission, user_id::UserId},
+    implementations::minecraft::player::ModerationAction,
     macro_executor::MacroPID,
     output_types::ClientEvent,
     traits::{t_macro::ExitStatus, t_player::Player, t_server::State, InstanceInfo},
@@ -86,6 +87,40 @@ impl EventQuery {
     }
 }
 
+/// Startup output of Paper that changed the instance's files or needs the operator to act
+///
+/// The plugin notices come from Bukkit, so Spigot prints them as well
+#[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq, Eq)]
+#[ts(export)]
+#[serde(tag = "type")]
+pub enum PaperNotice {
+    /// Paper rewrote the instance's config files into its current layout
+    ConfigMigrated,
+    /// A plugin that predates `api-version`, loaded through Bukkit's slower legacy support
+    LegacyPlugin { plugin: String },
+    /// A plugin that failed to load or enable, usually because it is outdated
+    PluginFailed { plugin: String },
+}
+
+impl PaperNotice {
+    /// A message for the operator, the raw line is easy to miss among the rest of the startup
+    pub fn describe(&self) -> String {
+        match self {
+            PaperNotice::ConfigMigrated => {
+                "Paper migrated your config files, review them before changing settings".to_string()
+            }
+            PaperNotice::LegacyPlugin { plugin } => format!(
+                "Plugin {} is a legacy plugin and may be outdated, look for an update",
+                plugin
+            ),
+            PaperNotice::PluginFailed { plugin } => format!(
+                "Plugin {} failed to load, it may be outdated or built for another version",
+                plugin
+            ),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq)]
 #[ts(export)]
 #[serde(tag = "type")]
@@ -120,6 +155,51 @@ pub enum InstanceEventInner {
         player: String,
         player_message: String,
     },
+    InstanceRenamed {
+        old_name: String,
+        new_name: String,
+    },
+    /// The server reported it can't keep up with its tick rate, aggregated over a window so a lag
+    /// storm results in a single event
+    ServerLagWarning {
+        ms_behind: u64,
+        ticks_skipped: u64,
+    },
+    /// Paper changed the instance's files or printed a warning during startup that needs action
+    PaperNotice {
+        notice: PaperNotice,
+        message: String,
+    },
+    /// A prompt the server was waiting on was answered with its configured response
+    PromptAnswered {
+        prompt: String,
+        response: String,
+    },
+    /// A write failed because the disk is full, the partially written file was removed
+    OutOfDiskSpace {
+        path: String,
+    },
+    /// A backup was cancelled while it was being archived
+    BackupCancelled {
+        backup_name: String,
+    },
+    /// A backup was archived and stored
+    BackupCompleted {
+        name: String,
+        /// Size of the archive in bytes
+        size: u64,
+        duration_ms: u64,
+    },
+    /// A backup failed, including when it was cancelled
+    BackupFailed {
+        reason: String,
+    },
+    /// A player, or an IP address for the IP actions, was kicked, banned or pardoned
+    PlayerModerated {
+        player: String,
+        action: ModerationAction,
+        reason: Option<String>,
+    },
 }
 
 impl AsRef<InstanceEventInner> for InstanceEventInner {
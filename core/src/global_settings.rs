@@ -1,11 +1,15 @@
 use std::path::PathBuf;
+use std::time::Duration;
 
 use color_eyre::eyre::Context;
 use serde::{Deserialize, Serialize};
 use tokio::io::AsyncWriteExt;
 use ts_rs::TS;
 
-use crate::{error::Error, event_broadcaster::EventBroadcaster};
+use crate::{
+    db::write::EventRetention, error::Error, event_broadcaster::EventBroadcaster,
+    resource_guardrail::RamGuardrail,
+};
 
 #[derive(Serialize, Deserialize, Clone, TS)]
 #[ts(export)]
@@ -14,6 +18,31 @@ pub struct GlobalSettingsData {
     pub safe_mode: bool,
     pub domain: Option<String>,
     pub playit_enabled: bool,
+    /// How long deleted instances are kept around before they are permanently removed
+    #[serde(default = "default_deletion_grace_period_days")]
+    pub deletion_grace_period_days: u32,
+    /// Limits the RAM committed to running instances, `None` to allow any amount
+    #[serde(default = "default_ram_guardrail")]
+    pub ram_guardrail: Option<RamGuardrail>,
+    /// Stores every progression update in the event database rather than only where progressions
+    /// start and end, for debugging
+    #[serde(default)]
+    pub persist_progression: bool,
+    /// When stored events are pruned, `None` to keep them forever
+    #[serde(default = "default_event_retention")]
+    pub event_retention: Option<EventRetention>,
+}
+
+fn default_deletion_grace_period_days() -> u32 {
+    7
+}
+
+fn default_ram_guardrail() -> Option<RamGuardrail> {
+    Some(RamGuardrail::default())
+}
+
+fn default_event_retention() -> Option<EventRetention> {
+    Some(EventRetention::default())
 }
 
 impl Default for GlobalSettingsData {
@@ -23,6 +52,10 @@ impl Default for GlobalSettingsData {
             safe_mode: true,
             domain: None,
             playit_enabled: true,
+            deletion_grace_period_days: default_deletion_grace_period_days(),
+            ram_guardrail: default_ram_guardrail(),
+            persist_progression: false,
+            event_retention: default_event_retention(),
         }
     }
 }
@@ -165,6 +198,85 @@ impl GlobalSettings {
         self.global_settings_data.playit_enabled
     }
 
+    pub async fn set_deletion_grace_period_days(&mut self, days: u32) -> Result<(), Error> {
+        let old_days = self.global_settings_data.deletion_grace_period_days;
+        self.global_settings_data.deletion_grace_period_days = days;
+        match self.write_to_file().await {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                self.global_settings_data.deletion_grace_period_days = old_days;
+                Err(e)
+            }
+        }
+    }
+
+    pub fn deletion_grace_period(&self) -> Duration {
+        Duration::from_secs(self.global_settings_data.deletion_grace_period_days as u64 * 86400)
+    }
+
+    pub async fn set_ram_guardrail(
+        &mut self,
+        ram_guardrail: Option<RamGuardrail>,
+    ) -> Result<(), Error> {
+        if let Some(ram_guardrail) = &ram_guardrail {
+            ram_guardrail.validate()?;
+        }
+        let old_ram_guardrail = self.global_settings_data.ram_guardrail;
+        self.global_settings_data.ram_guardrail = ram_guardrail;
+        match self.write_to_file().await {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                self.global_settings_data.ram_guardrail = old_ram_guardrail;
+                Err(e)
+            }
+        }
+    }
+
+    pub fn ram_guardrail(&self) -> Option<RamGuardrail> {
+        self.global_settings_data.ram_guardrail
+    }
+
+    pub async fn set_persist_progression(
+        &mut self,
+        persist_progression: bool,
+    ) -> Result<(), Error> {
+        let old_persist_progression = self.global_settings_data.persist_progression;
+        self.global_settings_data.persist_progression = persist_progression;
+        match self.write_to_file().await {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                self.global_settings_data.persist_progression = old_persist_progression;
+                Err(e)
+            }
+        }
+    }
+
+    pub fn persist_progression(&self) -> bool {
+        self.global_settings_data.persist_progression
+    }
+
+    pub async fn set_event_retention(
+        &mut self,
+        event_retention: Option<EventRetention>,
+    ) -> Result<(), Error> {
+        if let Some(event_retention) = &event_retention {
+            event_retention.validate()?;
+        }
+        let old_event_retention = self.global_settings_data.event_retention;
+        self.global_settings_data.event_retention = event_retention;
+        match self.write_to_file().await {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                self.global_settings_data.event_retention = old_event_retention;
+                Err(e)
+            }
+        }
+    }
+
+    pub fn event_retention(&self) -> Option<EventRetention> {
+        self.global_settings_data.event_retention
+    }
+
 }
 
 impl AsRef<GlobalSettingsData> for GlobalSettings {
@@ -1,6 +1,10 @@
+use crate::auth::user::UserAction;
+use crate::error::Error;
+use crate::port_manager::{detect_port_conflicts, PortConflict};
 use crate::traits::t_configurable::TConfigurable;
 use crate::{port_manager::PortStatus, AppState};
 use axum::{extract::Path, routing::get, Json, Router};
+use axum_auth::AuthBearer;
 /// Check the status of a port
 /// Note: this function is not cheap
 pub async fn get_port_status(
@@ -24,9 +28,30 @@ pub async fn is_name_in_use(
     Json(false)
 }
 
+/// List ports configured for more than one server socket across instances
+pub async fn get_port_conflicts(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<Vec<PortConflict>>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    Ok(Json(
+        detect_port_conflicts(&state.instances)
+            .await
+            .into_iter()
+            .filter(|conflict| {
+                conflict.usages.iter().any(|usage| {
+                    requester
+                        .can_perform_action(&UserAction::ViewInstance(usage.instance_uuid.clone()))
+                })
+            })
+            .collect(),
+    ))
+}
+
 pub fn get_checks_routes(state: AppState) -> Router {
     Router::new()
         .route("/check/port/:port", get(get_port_status))
         .route("/check/name/:name", get(is_name_in_use))
+        .route("/check/port_conflicts", get(get_port_conflicts))
         .with_state(state)
 }
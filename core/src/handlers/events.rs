@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 use axum::{
     extract::{ws::WebSocket, Path, Query, WebSocketUpgrade},
+    http,
     response::Response,
     routing::get,
     Json, Router,
@@ -10,14 +11,18 @@ use axum_auth::AuthBearer;
 
 use color_eyre::eyre::eyre;
 use futures::{SinkExt, StreamExt};
+use headers::HeaderName;
 use ringbuffer::{AllocRingBuffer, RingBufferExt};
 use tracing::{debug, error};
 
 use crate::output_types::ClientEvent;
 use crate::types::InstanceUuid;
 use crate::{
-    auth::{user::UsersManager, user_id::UserId},
-    db::read::search_events,
+    auth::{
+        user::{UserAction, UsersManager},
+        user_id::UserId,
+    },
+    db::read::{export_events, export_instance_events, search_events, ExportFormat},
     error::{Error, ErrorKind},
     events::EventQuery,
 };
@@ -99,6 +104,89 @@ pub async fn get_event_search(
     search_events(&state.sqlite_pool, query).await.map(Json)
 }
 
+#[derive(Deserialize, Clone, Debug)]
+pub struct EventExportQuery {
+    filter: String,
+    format: ExportFormat,
+}
+
+pub async fn get_event_export(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    AuthBearer(token): AuthBearer,
+    query: Query<EventExportQuery>,
+) -> Result<([(HeaderName, String); 2], Vec<u8>), Error> {
+    let event_query: EventQuery = serde_json::from_str(&query.filter).map_err(|e| {
+        error!("Error deserializing event query: {}", e);
+        Error {
+            kind: ErrorKind::BadRequest,
+            source: e.into(),
+        }
+    })?;
+    let requester = state
+        .users_manager
+        .read()
+        .await
+        .try_auth(&token)
+        .ok_or_else(|| Error {
+            kind: ErrorKind::Unauthorized,
+            source: eyre!("Token error"),
+        })?;
+    if !requester.is_owner && !requester.is_admin {
+        return Err(Error {
+            kind: ErrorKind::PermissionDenied,
+            source: eyre!("Only admins can export events"),
+        });
+    }
+    let (content_type, extension) = match query.format {
+        ExportFormat::Csv => ("text/csv", "csv"),
+        ExportFormat::Json => ("application/json", "json"),
+    };
+    let body = export_events(&state.sqlite_pool, event_query, query.format).await?;
+    Ok((
+        [
+            (http::header::CONTENT_TYPE, content_type.to_string()),
+            (
+                http::header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"events.{extension}\""),
+            ),
+        ],
+        body,
+    ))
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct InstanceEventExportQuery {
+    format: ExportFormat,
+}
+
+pub async fn get_instance_event_export(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    AuthBearer(token): AuthBearer,
+    Path(uuid): Path<InstanceUuid>,
+    query: Query<InstanceEventExportQuery>,
+) -> Result<([(HeaderName, String); 2], Vec<u8>), Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::ViewInstance(uuid.clone()))?;
+    let (content_type, extension) = match query.format {
+        ExportFormat::Csv => ("text/csv", "csv"),
+        ExportFormat::Json => ("application/json", "json"),
+    };
+    let body = export_instance_events(&state.sqlite_pool, &uuid, query.format).await?;
+    Ok((
+        [
+            (http::header::CONTENT_TYPE, content_type.to_string()),
+            (
+                http::header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"{}_events.{extension}\"",
+                    uuid.no_prefix()
+                ),
+            ),
+        ],
+        body,
+    ))
+}
+
 pub async fn get_console_buffer(
     axum::extract::State(state): axum::extract::State<AppState>,
     AuthBearer(token): AuthBearer,
@@ -292,6 +380,11 @@ pub fn get_events_routes(state: AppState) -> Router {
         .route("/events/:uuid/stream", get(event_stream))
         .route("/events/:uuid/buffer", get(get_event_buffer))
         .route("/events/search", get(get_event_search))
+        .route("/events/export", get(get_event_export))
+        .route(
+            "/instance/:uuid/events/export",
+            get(get_instance_event_export),
+        )
         .route("/instance/:uuid/console/stream", get(console_stream))
         .route("/instance/:uuid/console/buffer", get(get_console_buffer))
         .with_state(state)
@@ -5,7 +5,10 @@ use axum::{
 use axum_auth::AuthBearer;
 use color_eyre::eyre::eyre;
 
-use crate::{error::ErrorKind, AppState, Error, GlobalSettingsData};
+use crate::{
+    db::write::EventRetention, error::ErrorKind, resource_guardrail::RamGuardrail, AppState, Error,
+    GlobalSettingsData,
+};
 
 pub async fn get_core_settings(
     axum::extract::State(state): axum::extract::State<AppState>,
@@ -134,6 +137,98 @@ pub async fn change_core_playit_enabled(
     Ok(())
 }
 
+pub async fn change_deletion_grace_period_days(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    AuthBearer(token): AuthBearer,
+    Json(days): Json<u32>,
+) -> Result<(), Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+
+    if !requester.is_owner {
+        return Err(Error {
+            kind: ErrorKind::PermissionDenied,
+            source: eyre!("Not authorized to change the deletion grace period."),
+        });
+    }
+
+    state
+        .global_settings
+        .lock()
+        .await
+        .set_deletion_grace_period_days(days)
+        .await?;
+    Ok(())
+}
+
+pub async fn change_ram_guardrail(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    AuthBearer(token): AuthBearer,
+    Json(ram_guardrail): Json<Option<RamGuardrail>>,
+) -> Result<(), Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+
+    if !requester.is_owner {
+        return Err(Error {
+            kind: ErrorKind::PermissionDenied,
+            source: eyre!("Not authorized to change the RAM guardrail."),
+        });
+    }
+
+    state
+        .global_settings
+        .lock()
+        .await
+        .set_ram_guardrail(ram_guardrail)
+        .await?;
+    Ok(())
+}
+
+pub async fn change_persist_progression(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    AuthBearer(token): AuthBearer,
+    Json(persist_progression): Json<bool>,
+) -> Result<(), Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+
+    if !requester.is_owner {
+        return Err(Error {
+            kind: ErrorKind::PermissionDenied,
+            source: eyre!("Not authorized to change whether progressions are persisted."),
+        });
+    }
+
+    state
+        .global_settings
+        .lock()
+        .await
+        .set_persist_progression(persist_progression)
+        .await?;
+    Ok(())
+}
+
+pub async fn change_event_retention(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    AuthBearer(token): AuthBearer,
+    Json(event_retention): Json<Option<EventRetention>>,
+) -> Result<(), Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+
+    if !requester.is_owner {
+        return Err(Error {
+            kind: ErrorKind::PermissionDenied,
+            source: eyre!("Not authorized to change the event retention."),
+        });
+    }
+
+    state
+        .global_settings
+        .lock()
+        .await
+        .set_event_retention(event_retention)
+        .await?;
+    Ok(())
+}
+
 pub fn get_global_settings_routes(state: AppState) -> Router {
     Router::new()
         .route("/global_settings", get(get_core_settings))
@@ -141,5 +236,18 @@ pub fn get_global_settings_routes(state: AppState) -> Router {
         .route("/global_settings/safe_mode", put(change_core_safe_mode))
         .route("/global_settings/domain", put(change_domain))
         .route("/global_settings/playit_enabled", put(change_core_playit_enabled))
+        .route(
+            "/global_settings/deletion_grace_period_days",
+            put(change_deletion_grace_period_days),
+        )
+        .route("/global_settings/ram_guardrail", put(change_ram_guardrail))
+        .route(
+            "/global_settings/persist_progression",
+            put(change_persist_progression),
+        )
+        .route(
+            "/global_settings/event_retention",
+            put(change_event_retention),
+        )
         .with_state(state)
 }
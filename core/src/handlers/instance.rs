@@ -1,6 +1,11 @@
+use std::time::Duration;
+
 use axum::routing::{delete, get, post};
 use axum::Router;
-use axum::{extract::Path, Json};
+use axum::{
+    extract::{Path, Query},
+    Json,
+};
 use axum_auth::AuthBearer;
 
 use color_eyre::eyre::{eyre, Context};
@@ -15,10 +20,15 @@ use crate::implementations::generic;
 use crate::traits::t_configurable::GameType;
 
 use crate::implementations::minecraft::MinecraftInstance;
-use crate::prelude::{path_to_instances, GameInstance};
+use crate::prelude::{path_to_instances, path_to_trash, GameInstance};
+use crate::resource_guardrail::check_ram_guardrail;
+use crate::setup_progress::InstanceSetupProgress;
 use crate::traits::t_configurable::manifest::SetupValue;
-use crate::traits::{t_configurable::TConfigurable, t_server::TServer, InstanceInfo, TInstance};
+use crate::traits::{
+    t_configurable::TConfigurable, t_server::TServer, InstanceInfo, InstanceSummary, TInstance,
+};
 
+use crate::trash::{list_deleted, move_to_trash, purge_deleted, restore_deleted, DeletedInstance};
 use crate::types::{DotLodestoneConfig, InstanceUuid};
 use crate::{implementations::minecraft, traits::t_server::State, AppState};
 
@@ -42,6 +52,68 @@ pub async fn get_instance_list(
     Ok(Json(list_of_configs))
 }
 
+const DEFAULT_INSTANCE_PAGE_SIZE: usize = 50;
+const MAX_INSTANCE_PAGE_SIZE: usize = 500;
+
+#[derive(Deserialize)]
+pub struct InstanceSummaryQuery {
+    /// Only instances whose name contains this, ignoring case
+    name: Option<String>,
+    state: Option<State>,
+    /// Zero based
+    #[serde(default)]
+    page: usize,
+    page_size: Option<usize>,
+}
+
+impl InstanceSummaryQuery {
+    fn matches(&self, summary: &InstanceSummary) -> bool {
+        self.name.as_ref().map_or(true, |name| {
+            summary.name.to_lowercase().contains(&name.to_lowercase())
+        }) && self.state.map_or(true, |state| summary.state == state)
+    }
+}
+
+/// Lists the instances the requester can view oldest first, a page at a time
+pub async fn get_instance_summaries(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Query(query): Query<InstanceSummaryQuery>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<Vec<InstanceSummary>>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    let page_size = query.page_size.unwrap_or(DEFAULT_INSTANCE_PAGE_SIZE);
+    if page_size == 0 || page_size > MAX_INSTANCE_PAGE_SIZE {
+        return Err(Error {
+            kind: ErrorKind::BadRequest,
+            source: eyre!("Page size must be between 1 and {}", MAX_INSTANCE_PAGE_SIZE),
+        });
+    }
+    // cloned out so that no shard of the map stays locked while the summaries are gathered
+    let instances: Vec<GameInstance> = state
+        .instances
+        .iter()
+        .map(|instance| instance.value().clone())
+        .collect();
+    let mut summaries = Vec::new();
+    for instance in instances {
+        if !requester.can_perform_action(&UserAction::ViewInstance(instance.uuid().await)) {
+            continue;
+        }
+        let summary = instance.get_instance_summary().await;
+        if query.matches(&summary) {
+            summaries.push(summary);
+        }
+    }
+    summaries.sort_by(|a, b| a.creation_time.cmp(&b.creation_time));
+    Ok(Json(
+        summaries
+            .into_iter()
+            .skip(query.page.saturating_mul(page_size))
+            .take(page_size)
+            .collect(),
+    ))
+}
+
 pub async fn get_instance_info(
     Path(uuid): Path<InstanceUuid>,
     axum::extract::State(state): axum::extract::State<AppState>,
@@ -58,6 +130,28 @@ pub async fn get_instance_info(
     Ok(Json(instance.get_instance_info().await))
 }
 
+pub async fn get_setup_progress(
+    Path(uuid): Path<InstanceUuid>,
+    axum::extract::State(state): axum::extract::State<AppState>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<InstanceSetupProgress>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    // the creator is only granted access to the instance once the setup is done
+    if !requester.can_perform_action(&UserAction::CreateInstance) {
+        requester.try_action(&UserAction::ViewInstance(uuid.clone()))?;
+    }
+    state
+        .setup_progress
+        .lock()
+        .await
+        .get(&uuid)
+        .map(Json)
+        .ok_or_else(|| Error {
+            kind: ErrorKind::NotFound,
+            source: eyre!("No setup found for this instance"),
+        })
+}
+
 pub async fn create_minecraft_instance(
     axum::extract::State(state): axum::extract::State<AppState>,
     AuthBearer(token): AuthBearer,
@@ -84,6 +178,17 @@ pub async fn create_minecraft_instance(
 
     let setup_config = MinecraftInstance::construct_setup_config(manifest_value, flavour).await?;
 
+    let ram_guardrail = state.global_settings.lock().await.ram_guardrail();
+    check_ram_guardrail(
+        &state.instances,
+        &mut *state.system.lock().await,
+        ram_guardrail,
+        None,
+        &setup_config.name,
+        setup_config.max_ram.unwrap_or(4096),
+    )
+    .await?;
+
     let setup_path = path_to_instances().join(format!(
         "{}-{}",
         setup_config.name,
@@ -291,9 +396,17 @@ pub async fn create_generic_instance(
     Ok(Json(()))
 }
 
+#[derive(Deserialize)]
+pub struct DeleteInstanceQuery {
+    /// Remove the instance right away instead of moving it to the trash
+    #[serde(default)]
+    permanent: bool,
+}
+
 pub async fn delete_instance(
     axum::extract::State(state): axum::extract::State<AppState>,
     Path(uuid): Path<InstanceUuid>,
+    Query(query): Query<DeleteInstanceQuery>,
     AuthBearer(token): AuthBearer,
 ) -> Result<Json<()>, Error> {
     let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
@@ -302,6 +415,9 @@ pub async fn delete_instance(
         user_id: requester.uid.clone(),
         user_name: requester.username.clone(),
     };
+    if !query.permanent {
+        return trash_instance(&state, uuid, caused_by).await;
+    }
     if let Some((_, instance)) = state.instances.remove(&uuid) {
         if !(instance.state().await == State::Stopped) {
             state.instances.insert(uuid.clone(), instance);
@@ -339,9 +455,9 @@ pub async fn delete_instance(
                 .await
                 .deallocate(instance.port().await);
             let instance_path = instance.path().await;
-            // if instance is generic
-            if let GameInstance::GenericInstance(i) = instance {
-                i.destruct().await;
+            match instance {
+                GameInstance::GenericInstance(i) => i.destruct().await,
+                GameInstance::MinecraftInstance(i) => i.shutdown().await,
             };
             let res = crate::util::fs::remove_dir_all(instance_path).await;
             match &res {
@@ -374,15 +490,116 @@ pub async fn delete_instance(
     }
 }
 
+/// Stops the instance and moves it to the trash, where it can be restored from until purged
+async fn trash_instance(
+    state: &AppState,
+    uuid: InstanceUuid,
+    caused_by: CausedBy,
+) -> Result<Json<()>, Error> {
+    // only removed once it's in the trash, so a failed move doesn't lose track of it
+    let instance = state
+        .instances
+        .get(&uuid)
+        .map(|instance| instance.clone())
+        .ok_or_else(|| Error {
+            kind: ErrorKind::NotFound,
+            source: eyre!("Instance not found"),
+        })?;
+    let (progression_event_start, event_id) = Event::new_progression_event_start(
+        format!("Deleting instance {}", instance.name().await),
+        Some(10.0),
+        None,
+        caused_by,
+    );
+    state.event_broadcaster.send(progression_event_start);
+    let port = instance.port().await;
+    let res = match instance {
+        GameInstance::MinecraftInstance(i) => i.mark_for_deletion().await,
+        GameInstance::GenericInstance(i) => {
+            let instance_path = i.path().await;
+            i.destruct().await;
+            move_to_trash(&instance_path, path_to_trash()).await
+        }
+    };
+    match res {
+        Ok(_) => {
+            state.instances.remove(&uuid);
+            state.port_manager.lock().await.deallocate(port);
+            state.event_broadcaster.send(Event::new_progression_event_end(
+                event_id,
+                true,
+                Some("Instance moved to trash"),
+                Some(ProgressionEndValue::InstanceDelete {
+                    instance_uuid: uuid,
+                }),
+            ));
+            Ok(Json(()))
+        }
+        Err(e) => {
+            state.event_broadcaster.send(Event::new_progression_event_end(
+                event_id,
+                false,
+                Some(&format!("Failed to move instance to trash : {e}")),
+                None,
+            ));
+            Err(e)
+        }
+    }
+}
+
+pub async fn get_deleted_instances(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<Vec<DeletedInstance>>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::DeleteInstance)?;
+    Ok(Json(list_deleted(path_to_trash()).await?))
+}
+
+pub async fn restore_deleted_instance(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::DeleteInstance)?;
+    restore_deleted(&state, &uuid).await?;
+    Ok(Json(()))
+}
+
+#[derive(Deserialize)]
+pub struct PurgeDeletedQuery {
+    /// Defaults to the deletion grace period in the global settings
+    older_than_days: Option<u32>,
+}
+
+pub async fn purge_deleted_instances(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Query(query): Query<PurgeDeletedQuery>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<Vec<InstanceUuid>>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::DeleteInstance)?;
+    let older_than = match query.older_than_days {
+        Some(days) => Duration::from_secs(days as u64 * 86400),
+        None => state.global_settings.lock().await.deletion_grace_period(),
+    };
+    Ok(Json(purge_deleted(path_to_trash(), older_than).await?))
+}
+
 pub fn get_instance_routes(state: AppState) -> Router {
     Router::new()
         .route("/instance/list", get(get_instance_list))
+        .route("/instance/summaries", get(get_instance_summaries))
         .route(
             "/instance/create/:game_type",
             post(create_minecraft_instance),
         )
         .route("/instance/create_generic", post(create_generic_instance))
         .route("/instance/:uuid", delete(delete_instance))
+        .route("/instance/trash", get(get_deleted_instances).delete(purge_deleted_instances))
+        .route("/instance/trash/:uuid/restore", post(restore_deleted_instance))
         .route("/instance/:uuid/info", get(get_instance_info))
+        .route("/instance/:uuid/setup_progress", get(get_setup_progress))
         .with_state(state)
 }
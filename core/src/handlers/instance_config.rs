@@ -1,14 +1,33 @@
+use std::{collections::BTreeMap, path::PathBuf, time::Duration};
+
 use axum::{
-    extract::Path,
+    extract::{DefaultBodyLimit, Multipart, Path, Query},
     routing::{get, put},
     Json, Router,
 };
 use axum_auth::AuthBearer;
-use color_eyre::eyre::eyre;
+use color_eyre::eyre::{eyre, Context};
+use serde::Deserialize;
+use tokio::io::AsyncWriteExt;
 
 use crate::{
     auth::user::UserAction,
     error::{Error, ErrorKind},
+    events::CausedBy,
+    implementations::minecraft::{
+        backup::{BackupDestination, BackupInfo, BackupOnStopMode},
+        bukkit_config::{BukkitConfigFile, BukkitSettingValue},
+        configurable::{
+            PropertiesSnapshot, PropertiesUpdate, PropertyDiff, UpdateInfo, VersionSummary,
+            WorldDifficulty,
+        },
+        datapack::SavedDatapack,
+        mod_loader::ModLoaderMismatchPolicy,
+        restart_schedule::RestartSchedule,
+        AlreadyRunningPolicy, CrashLoopGuard, JavaAgent, JvmFlagsPreset, PromptResponse,
+        RconConfig, ServerLocale, StartupTimeout, TimeoutSettings, WatchdogConfig,
+    },
+    prelude::GameInstance,
     traits::t_configurable::{
         manifest::{ConfigurableManifest, ConfigurableValue},
         TConfigurable,
@@ -105,9 +124,17 @@ pub async fn set_instance_description(
     Ok(Json(()))
 }
 
+#[derive(Deserialize)]
+pub struct ChangeVersionQuery {
+    /// Change the version even if it would downgrade the world
+    #[serde(default)]
+    force: bool,
+}
+
 pub async fn change_version(
     axum::extract::State(state): axum::extract::State<AppState>,
     Path((uuid, new_version)): Path<(InstanceUuid, String)>,
+    Query(query): Query<ChangeVersionQuery>,
     AuthBearer(token): AuthBearer,
 ) -> Result<Json<()>, Error> {
     let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
@@ -119,24 +146,1392 @@ pub async fn change_version(
             kind: ErrorKind::NotFound,
             source: eyre!("Instance not found"),
         })?
-        .change_version(new_version)
+        .change_version(new_version, query.force)
         .await?;
     Ok(Json(()))
 }
 
-pub fn get_instance_config_routes(state: AppState) -> Router {
-    Router::new()
-        .route(
-            "/instance/:uuid/configurable_manifest",
-            get(get_instance_configurable_manifest),
-        )
-        .route("/instance/:uuid/version/:new_version", put(change_version))
-        .route("/instance/:uuid/settings", get(get_instance_settings))
-        .route(
-            "/instance/:uuid/settings/:section_id/:setting_id",
-            put(set_instance_setting),
-        )
-        .route("/instance/:uuid/name", put(set_instance_name))
-        .route("/instance/:uuid/description", put(set_instance_description))
+pub async fn get_difficulty(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<WorldDifficulty>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance.get_difficulty().await.map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Difficulty is unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn set_difficulty(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(difficulty): Json<String>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => {
+            instance.set_difficulty(&difficulty).await.map(Json)
+        }
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Difficulty is unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn get_timeout_settings(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<TimeoutSettings>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => Ok(Json(instance.timeout_settings().await)),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Timeout settings are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn set_timeout_settings(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(timeout_settings): Json<TimeoutSettings>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance
+            .set_timeout_settings(timeout_settings)
+            .await
+            .map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Timeout settings are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn get_java_agents(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<Vec<JavaAgent>>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => Ok(Json(instance.java_agents().await)),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Java agents are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn set_java_agents(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(java_agents): Json<Vec<JavaAgent>>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => {
+            instance.set_java_agents(java_agents).await.map(Json)
+        }
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Java agents are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn get_jvm_flags_preset(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<JvmFlagsPreset>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => Ok(Json(instance.jvm_flags_preset().await)),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("JVM flags are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn set_jvm_flags_preset(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(jvm_flags_preset): Json<JvmFlagsPreset>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance
+            .set_jvm_flags_preset(jvm_flags_preset)
+            .await
+            .map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("JVM flags are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn get_commands_on_start(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<Vec<String>>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => Ok(Json(instance.commands_on_start().await)),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Commands on start are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn set_commands_on_start(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(commands_on_start): Json<Vec<String>>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance
+            .set_commands_on_start(commands_on_start)
+            .await
+            .map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Commands on start are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn get_prompt_responses(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<Vec<PromptResponse>>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => Ok(Json(instance.prompt_responses().await)),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Prompt responses are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn set_prompt_responses(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(prompt_responses): Json<Vec<PromptResponse>>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance
+            .set_prompt_responses(prompt_responses)
+            .await
+            .map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Prompt responses are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn get_restart_schedule(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<Option<RestartSchedule>>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => Ok(Json(instance.restart_schedule().await)),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Restart schedules are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn set_restart_schedule(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(restart_schedule): Json<Option<RestartSchedule>>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance
+            .set_restart_schedule(restart_schedule)
+            .await
+            .map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Restart schedules are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn set_max_log_line_length(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(max_log_line_length): Json<usize>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance
+            .set_max_log_line_length(max_log_line_length)
+            .await
+            .map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Maximum log line length is unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn set_mirror_console_output(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(mirror_console_output): Json<bool>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance
+            .set_mirror_console_output(mirror_console_output)
+            .await
+            .map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Mirroring console output is unsupported for this instance"),
+        }),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SetProcessPriorityBody {
+    cpu_affinity: Option<Vec<usize>>,
+    nice: Option<i32>,
+}
+
+pub async fn set_process_priority(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(body): Json<SetProcessPriorityBody>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance
+            .set_process_priority(body.cpu_affinity, body.nice)
+            .await
+            .map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Process priorities are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn set_server_locale(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(server_locale): Json<ServerLocale>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => {
+            instance.set_server_locale(server_locale).await.map(Json)
+        }
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Server locales are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn set_backups_enabled(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(backups_enabled): Json<bool>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance
+            .set_backups_enabled(backups_enabled)
+            .await
+            .map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Periodic backups are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn list_backups(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<Vec<BackupInfo>>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance.list_backups().await.map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Backups are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn backup_now(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance.backup_now().await.map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Backups are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn cancel_running_backup(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let caused_by = CausedBy::User {
+        user_id: requester.uid.clone(),
+        user_name: requester.username.clone(),
+    };
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => {
+            instance.cancel_running_backup(caused_by).await.map(Json)
+        }
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Backups are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn restore_backup(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path((uuid, backup_name)): Path<(InstanceUuid, String)>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => {
+            instance.restore_backup(&backup_name).await.map(Json)
+        }
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Backups are unsupported for this instance"),
+        }),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RestoreBackupWorldBody {
+    world_name: String,
+}
+
+pub async fn restore_backup_world(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path((uuid, backup_name)): Path<(InstanceUuid, String)>,
+    AuthBearer(token): AuthBearer,
+    Json(body): Json<RestoreBackupWorldBody>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance
+            .restore_backup_world(&backup_name, &body.world_name)
+            .await
+            .map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Backups are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn set_mod_loader_mismatch_policy(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(mod_loader_mismatch_policy): Json<ModLoaderMismatchPolicy>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance
+            .set_mod_loader_mismatch_policy(mod_loader_mismatch_policy)
+            .await
+            .map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Mods are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn set_backup_include(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(backup_include): Json<Vec<String>>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => {
+            instance.set_backup_include(backup_include).await.map(Json)
+        }
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Backups are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn set_backup_on_stop_mode(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(backup_on_stop_mode): Json<Option<BackupOnStopMode>>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance
+            .set_backup_on_stop_mode(backup_on_stop_mode)
+            .await
+            .map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Backups on stop are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn get_properties(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<PropertiesSnapshot>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => Ok(Json(instance.properties_snapshot().await)),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Server properties are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn get_properties_diff(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<Vec<PropertyDiff>>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => Ok(Json(instance.properties_diff().await)),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Server properties are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn update_properties(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(update): Json<PropertiesUpdate>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance
+            .update_properties(&update.token, update.changes)
+            .await
+            .map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Server properties are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn get_available_update(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<Option<UpdateInfo>>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::ViewInstance(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance.available_update().await.map(Json),
+        GameInstance::GenericInstance(_) => Ok(Json(None)),
+    }
+}
+
+pub async fn get_flavour_version_summary(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<VersionSummary>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::ViewInstance(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => {
+            instance.get_flavour_version_summary().await.map(Json)
+        }
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Version summary is unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn update_build(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance.update_build().await.map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Updating the build is unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn set_manage_rcon(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(manage_rcon): Json<bool>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => {
+            instance.set_manage_rcon(manage_rcon).await.map(Json)
+        }
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("RCON is unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn rotate_rcon_password(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => {
+            instance.rotate_rcon_password().await.map(Json)
+        }
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("RCON is unsupported for this instance"),
+        }),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SetResourcePackBody {
+    url: String,
+    required: bool,
+}
+
+pub async fn set_resource_pack(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(body): Json<SetResourcePackBody>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance
+            .set_resource_pack(&body.url, body.required)
+            .await
+            .map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Resource packs are unsupported for this instance"),
+        }),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DatapackQuery {
+    /// Folder of the world to add the datapack to, the main world if omitted
+    world: Option<String>,
+}
+
+pub async fn upload_datapack(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    Query(query): Query<DatapackQuery>,
+    AuthBearer(token): AuthBearer,
+    mut multipart: Multipart,
+) -> Result<Json<SavedDatapack>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::WriteResource(uuid.clone()))?;
+    let mut field = multipart
+        .next_field()
+        .await
+        .context("Failed to read the uploaded datapack")?
+        .ok_or_else(|| Error {
+            kind: ErrorKind::BadRequest,
+            source: eyre!("No datapack was uploaded"),
+        })?;
+    let file_name = field
+        .file_name()
+        .ok_or_else(|| Error {
+            kind: ErrorKind::BadRequest,
+            source: eyre!("Missing file name"),
+        })?
+        .to_string();
+    // streamed to disk since the body limit is disabled for uploads
+    let staging_dir = tempfile::tempdir().context("Failed to create temp dir")?;
+    let archive = staging_dir.path().join("datapack.zip");
+    let mut file = crate::util::fs::create(&archive).await?;
+    while let Some(chunk) = field
+        .chunk()
+        .await
+        .context("Failed to read the uploaded datapack")?
+    {
+        file.write_all(&chunk)
+            .await
+            .context("Failed to save the uploaded datapack")?;
+    }
+    file.flush()
+        .await
+        .context("Failed to save the uploaded datapack")?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance
+            .install_datapack(&file_name, &archive, query.world.as_deref())
+            .await
+            .map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Datapacks are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn get_bukkit_settings(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path((uuid, file)): Path<(InstanceUuid, BukkitConfigFile)>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<BTreeMap<String, BukkitSettingValue>>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance.bukkit_settings(file).await.map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Bukkit configs are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn update_bukkit_settings(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path((uuid, file)): Path<(InstanceUuid, BukkitConfigFile)>,
+    AuthBearer(token): AuthBearer,
+    Json(changes): Json<BTreeMap<String, BukkitSettingValue>>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance
+            .update_bukkit_settings(file, changes)
+            .await
+            .map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Bukkit configs are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn set_crash_restart_cooldown(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(crash_restart_cooldown): Json<Option<Duration>>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance
+            .set_crash_restart_cooldown(crash_restart_cooldown)
+            .await
+            .map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Restarting on crash is unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn set_watchdog(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(watchdog): Json<Option<WatchdogConfig>>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => {
+            instance.set_watchdog(watchdog).await.map(Json)
+        }
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("The watchdog is unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn set_world_dir_override(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(world_dir_override): Json<Option<PathBuf>>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    // the directory can be anywhere on the machine
+    requester.try_action(&UserAction::WriteGlobalFile)?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance
+            .set_world_dir_override(world_dir_override)
+            .await
+            .map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Directory overrides are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn set_resources_dir_override(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(resources_dir_override): Json<Option<PathBuf>>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    // the directory can be anywhere on the machine
+    requester.try_action(&UserAction::WriteGlobalFile)?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance
+            .set_resources_dir_override(resources_dir_override)
+            .await
+            .map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Directory overrides are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn set_backup_destination(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(backup_destination): Json<Option<BackupDestination>>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    // the directory can be anywhere on the machine
+    requester.try_action(&UserAction::WriteGlobalFile)?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance
+            .set_backup_destination(backup_destination)
+            .await
+            .map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Backups are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn set_autosave_interval(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(autosave_interval): Json<Option<Duration>>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance
+            .set_autosave_interval(autosave_interval)
+            .await
+            .map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Autosaving is unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn set_prune_backups_on_low_space(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(prune_backups_on_low_space): Json<bool>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance
+            .set_prune_backups_on_low_space(prune_backups_on_low_space)
+            .await
+            .map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Backups are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn set_backup_io_limit(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(backup_io_limit): Json<Option<u64>>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance
+            .set_backup_io_limit(backup_io_limit)
+            .await
+            .map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Backups are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn set_already_running_policy(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(already_running_policy): Json<AlreadyRunningPolicy>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance
+            .set_already_running_policy(already_running_policy)
+            .await
+            .map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Automatic starts are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn set_backup_retention(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(backup_retention): Json<Option<u32>>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance
+            .set_backup_retention(backup_retention)
+            .await
+            .map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Backups are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn set_crash_loop_guard(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(crash_loop_guard): Json<Option<CrashLoopGuard>>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance
+            .set_crash_loop_guard(crash_loop_guard)
+            .await
+            .map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Restarting on crash is unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn set_rcon_config(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(rcon_config): Json<RconConfig>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => {
+            instance.set_rcon_config(rcon_config).await.map(Json)
+        }
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("RCON is unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn set_startup_timeout(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(startup_timeout): Json<Option<StartupTimeout>>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance
+            .set_startup_timeout(startup_timeout)
+            .await
+            .map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("The startup timeout is unsupported for this instance"),
+        }),
+    }
+}
+
+pub fn get_instance_config_routes(state: AppState) -> Router {
+    Router::new()
+        .route(
+            "/instance/:uuid/configurable_manifest",
+            get(get_instance_configurable_manifest),
+        )
+        .route("/instance/:uuid/version/:new_version", put(change_version))
+        .route("/instance/:uuid/settings", get(get_instance_settings))
+        .route(
+            "/instance/:uuid/settings/:section_id/:setting_id",
+            put(set_instance_setting),
+        )
+        .route("/instance/:uuid/name", put(set_instance_name))
+        .route("/instance/:uuid/description", put(set_instance_description))
+        .route("/instance/:uuid/difficulty", get(get_difficulty).put(set_difficulty))
+        .route(
+            "/instance/:uuid/timeout_settings",
+            get(get_timeout_settings).put(set_timeout_settings),
+        )
+        .route("/instance/:uuid/resource_pack", put(set_resource_pack))
+        .route(
+            "/instance/:uuid/datapacks",
+            put(upload_datapack).layer(DefaultBodyLimit::disable()),
+        )
+        .route(
+            "/instance/:uuid/java_agents",
+            get(get_java_agents).put(set_java_agents),
+        )
+        .route(
+            "/instance/:uuid/jvm_flags_preset",
+            get(get_jvm_flags_preset).put(set_jvm_flags_preset),
+        )
+        .route("/instance/:uuid/server_locale", put(set_server_locale))
+        .route(
+            "/instance/:uuid/process_priority",
+            put(set_process_priority),
+        )
+        .route(
+            "/instance/:uuid/max_log_line_length",
+            put(set_max_log_line_length),
+        )
+        .route(
+            "/instance/:uuid/mirror_console_output",
+            put(set_mirror_console_output),
+        )
+        .route(
+            "/instance/:uuid/commands_on_start",
+            get(get_commands_on_start).put(set_commands_on_start),
+        )
+        .route(
+            "/instance/:uuid/prompt_responses",
+            get(get_prompt_responses).put(set_prompt_responses),
+        )
+        .route(
+            "/instance/:uuid/restart_schedule",
+            get(get_restart_schedule).put(set_restart_schedule),
+        )
+        .route("/instance/:uuid/backups_enabled", put(set_backups_enabled))
+        .route(
+            "/instance/:uuid/backups",
+            get(list_backups).post(backup_now),
+        )
+        .route("/instance/:uuid/cancel_backup", put(cancel_running_backup))
+        .route(
+            "/instance/:uuid/mod_loader_mismatch_policy",
+            put(set_mod_loader_mismatch_policy),
+        )
+        .route(
+            "/instance/:uuid/backups/:backup_name/restore",
+            put(restore_backup),
+        )
+        .route(
+            "/instance/:uuid/backups/:backup_name/restore_world",
+            put(restore_backup_world),
+        )
+        .route("/instance/:uuid/backup_include", put(set_backup_include))
+        .route(
+            "/instance/:uuid/backup_on_stop_mode",
+            put(set_backup_on_stop_mode),
+        )
+        .route(
+            "/instance/:uuid/available_update",
+            get(get_available_update),
+        )
+        .route(
+            "/instance/:uuid/version_summary",
+            get(get_flavour_version_summary),
+        )
+        .route("/instance/:uuid/update_build", put(update_build))
+        .route(
+            "/instance/:uuid/properties",
+            get(get_properties).put(update_properties),
+        )
+        .route("/instance/:uuid/properties/diff", get(get_properties_diff))
+        .route("/instance/:uuid/manage_rcon", put(set_manage_rcon))
+        .route(
+            "/instance/:uuid/rotate_rcon_password",
+            put(rotate_rcon_password),
+        )
+        .route(
+            "/instance/:uuid/bukkit_settings/:file",
+            get(get_bukkit_settings).put(update_bukkit_settings),
+        )
+        .route(
+            "/instance/:uuid/crash_restart_cooldown",
+            put(set_crash_restart_cooldown),
+        )
+        .route("/instance/:uuid/watchdog", put(set_watchdog))
+        .route(
+            "/instance/:uuid/world_dir_override",
+            put(set_world_dir_override),
+        )
+        .route(
+            "/instance/:uuid/resources_dir_override",
+            put(set_resources_dir_override),
+        )
+        .route(
+            "/instance/:uuid/backup_destination",
+            put(set_backup_destination),
+        )
+        .route(
+            "/instance/:uuid/autosave_interval",
+            put(set_autosave_interval),
+        )
+        .route(
+            "/instance/:uuid/prune_backups_on_low_space",
+            put(set_prune_backups_on_low_space),
+        )
+        .route("/instance/:uuid/backup_io_limit", put(set_backup_io_limit))
+        .route(
+            "/instance/:uuid/already_running_policy",
+            put(set_already_running_policy),
+        )
+        .route(
+            "/instance/:uuid/backup_retention",
+            put(set_backup_retention),
+        )
+        .route(
+            "/instance/:uuid/crash_loop_guard",
+            put(set_crash_loop_guard),
+        )
+        .route("/instance/:uuid/rcon_config", put(set_rcon_config))
+        .route("/instance/:uuid/startup_timeout", put(set_startup_timeout))
         .with_state(state)
 }
@@ -1,10 +1,20 @@
 use std::collections::HashSet;
 
-use axum::{extract::Path, routing::get, Json, Router};
+use axum::{
+    extract::Path,
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+use axum_auth::AuthBearer;
 use color_eyre::eyre::eyre;
+use serde::Deserialize;
 
 use crate::{
+    auth::user::UserAction,
     error::{Error, ErrorKind},
+    events::CausedBy,
+    implementations::minecraft::player::PlayerRoster,
+    prelude::GameInstance,
     traits::t_player::{Player, TPlayerManagement},
     types::InstanceUuid,
     AppState,
@@ -75,6 +85,302 @@ pub async fn get_player_list(
         .map(Json)
 }
 
+pub async fn get_online_players(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+) -> Result<Json<PlayerRoster>, Error> {
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance.online_players().await.map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Querying online players is unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn get_whitelist(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<Vec<String>>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::ViewInstance(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance.get_whitelist().await.map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Whitelists are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn add_to_whitelist(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(player): Json<String>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => {
+            instance.add_to_whitelist(&player).await.map(Json)
+        }
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Whitelists are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn remove_from_whitelist(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path((uuid, player)): Path<(InstanceUuid, String)>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => {
+            instance.remove_from_whitelist(&player).await.map(Json)
+        }
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Whitelists are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn set_whitelist_enabled(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(enabled): Json<bool>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance
+            .set_whitelist_enabled(
+                enabled,
+                CausedBy::User {
+                    user_id: requester.uid.clone(),
+                    user_name: requester.username.clone(),
+                },
+            )
+            .await
+            .map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Whitelists are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn op_player(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path((uuid, player)): Path<(InstanceUuid, String)>,
+    AuthBearer(token): AuthBearer,
+    Json(level): Json<u8>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => {
+            instance.op_player(&player, level).await.map(Json)
+        }
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Operators are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn deop_player(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path((uuid, player)): Path<(InstanceUuid, String)>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance.deop_player(&player).await.map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Operators are unsupported for this instance"),
+        }),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ModerationBody {
+    pub reason: Option<String>,
+}
+
+pub async fn kick_player(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path((uuid, target)): Path<(InstanceUuid, String)>,
+    AuthBearer(token): AuthBearer,
+    Json(body): Json<ModerationBody>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let caused_by = CausedBy::User {
+        user_id: requester.uid.clone(),
+        user_name: requester.username.clone(),
+    };
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance
+            .kick_player(&target, body.reason.as_deref(), caused_by)
+            .await
+            .map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Kicking players is unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn ban_player(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path((uuid, target)): Path<(InstanceUuid, String)>,
+    AuthBearer(token): AuthBearer,
+    Json(body): Json<ModerationBody>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let caused_by = CausedBy::User {
+        user_id: requester.uid.clone(),
+        user_name: requester.username.clone(),
+    };
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance
+            .ban_player(&target, body.reason.as_deref(), caused_by)
+            .await
+            .map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Banning players is unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn pardon_player(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path((uuid, target)): Path<(InstanceUuid, String)>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let caused_by = CausedBy::User {
+        user_id: requester.uid.clone(),
+        user_name: requester.username.clone(),
+    };
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => {
+            instance.pardon_player(&target, caused_by).await.map(Json)
+        }
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Pardoning players is unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn ban_ip(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path((uuid, target)): Path<(InstanceUuid, String)>,
+    AuthBearer(token): AuthBearer,
+    Json(body): Json<ModerationBody>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let caused_by = CausedBy::User {
+        user_id: requester.uid.clone(),
+        user_name: requester.username.clone(),
+    };
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance
+            .ban_ip(&target, body.reason.as_deref(), caused_by)
+            .await
+            .map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Banning IP addresses is unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn pardon_ip(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path((uuid, target)): Path<(InstanceUuid, String)>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let caused_by = CausedBy::User {
+        user_id: requester.uid.clone(),
+        user_name: requester.username.clone(),
+    };
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => {
+            instance.pardon_ip(&target, caused_by).await.map(Json)
+        }
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Pardoning IP addresses is unsupported for this instance"),
+        }),
+    }
+}
+
 pub fn get_instance_players_routes(state: AppState) -> Router {
     Router::new()
         .route("/instance/:uuid/players/count", get(get_player_count))
@@ -83,5 +389,31 @@ pub fn get_instance_players_routes(state: AppState) -> Router {
             get(get_max_player_count).put(set_max_player_count),
         )
         .route("/instance/:uuid/players", get(get_player_list))
+        .route("/instance/:uuid/players/online", get(get_online_players))
+        .route(
+            "/instance/:uuid/players/whitelist",
+            get(get_whitelist).post(add_to_whitelist),
+        )
+        .route(
+            "/instance/:uuid/players/whitelist_enabled",
+            put(set_whitelist_enabled),
+        )
+        .route(
+            "/instance/:uuid/players/whitelist/:player",
+            delete(remove_from_whitelist),
+        )
+        .route(
+            "/instance/:uuid/players/ops/:player",
+            put(op_player).delete(deop_player),
+        )
+        .route("/instance/:uuid/players/kick/:player", post(kick_player))
+        .route(
+            "/instance/:uuid/players/bans/:player",
+            put(ban_player).delete(pardon_player),
+        )
+        .route(
+            "/instance/:uuid/players/ip_bans/:ip",
+            put(ban_ip).delete(pardon_ip),
+        )
         .with_state(state)
 }
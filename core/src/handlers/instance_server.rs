@@ -1,5 +1,5 @@
 use axum::{
-    extract::Path,
+    extract::{Path, Query},
     routing::{get, post, put},
     Router,
 };
@@ -8,12 +8,21 @@ use axum::Json;
 use axum_auth::AuthBearer;
 
 use color_eyre::eyre::eyre;
+use serde::Deserialize;
 use serde_json::{json, Value};
+use tracing::warn;
 
 use crate::{
     auth::user::UserAction,
     error::{Error, ErrorKind},
     events::CausedBy,
+    implementations::minecraft::{
+        mod_dependency::ModIssue, runtime_content::RuntimeContent,
+        startup_estimate::StartupEstimate, support::SupportBundle, tps::TpsReport, ConnectionInfo,
+        CrashCause, InstanceAge, VersionInfo,
+    },
+    port_manager::detect_port_conflicts,
+    prelude::GameInstance,
     types::InstanceUuid,
 };
 
@@ -33,9 +42,35 @@ pub async fn start_instance(
         user_id: requester.uid.clone(),
         user_name: requester.username.clone(),
     };
+    for conflict in detect_port_conflicts(&state.instances)
+        .await
+        .into_iter()
+        .filter(|conflict| conflict.involves(&uuid))
+    {
+        if let Some(usage) = conflict
+            .usages
+            .iter()
+            .find(|usage| usage.instance_uuid != uuid && usage.is_running)
+        {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!(
+                    "Port {} is also configured for running instance {}",
+                    conflict.port,
+                    usage.instance_name
+                ),
+            });
+        }
+        warn!(
+            "Port {} of instance {} is shared with another instance",
+            conflict.port, uuid
+        );
+    }
+    // cloned so the map isn't borrowed while the start checks the RAM guardrail of the others
     let instance = state
         .instances
         .get(&uuid)
+        .map(|instance| instance.value().clone())
         .ok_or_else(|| Error {
             kind: ErrorKind::NotFound,
             source: eyre!("Instance not found"),
@@ -99,6 +134,80 @@ pub async fn restart_instance(
     Ok(Json(()))
 }
 
+pub async fn start_maintenance(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+    Json(motd): Json<Option<String>>,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester
+        .try_action(&UserAction::StartInstance(uuid.clone()))
+        .and_then(|_x| requester.try_action(&UserAction::AccessSetting(uuid.clone())))?;
+    let caused_by = CausedBy::User {
+        user_id: requester.uid.clone(),
+        user_name: requester.username.clone(),
+    };
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => {
+            instance.start_maintenance(motd, caused_by).await.map(Json)
+        }
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Maintenance mode is unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn end_maintenance(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let caused_by = CausedBy::User {
+        user_id: requester.uid.clone(),
+        user_name: requester.username.clone(),
+    };
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => {
+            instance.end_maintenance(caused_by).await.map(Json)
+        }
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Maintenance mode is unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn cancel_pending_restart(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::StopInstance(uuid.clone()))?;
+    state
+        .instances
+        .get(&uuid)
+        .ok_or_else(|| Error {
+            kind: ErrorKind::NotFound,
+            source: eyre!("Instance not found"),
+        })?
+        .cancel_pending_restart()
+        .await?;
+    Ok(Json(()))
+}
+
 pub async fn kill_instance(
     axum::extract::State(state): axum::extract::State<AppState>,
     Path(uuid): Path<InstanceUuid>,
@@ -122,28 +231,45 @@ pub async fn kill_instance(
     Ok(Json(json!("ok")))
 }
 
+#[derive(Deserialize)]
+pub struct SendCommandQuery {
+    /// Wait for the server to respond to the command
+    #[serde(default)]
+    confirm: bool,
+}
+
+/// Returns whether the server responded to the command if `confirm` is set
 pub async fn send_command(
     axum::extract::State(state): axum::extract::State<AppState>,
     Path(uuid): Path<InstanceUuid>,
+    Query(query): Query<SendCommandQuery>,
     AuthBearer(token): AuthBearer,
     Json(command): Json<String>,
-) -> Result<Json<()>, Error> {
+) -> Result<Json<Option<bool>>, Error> {
     let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
     requester.try_action(&UserAction::AccessConsole(uuid.clone()))?;
     let caused_by = CausedBy::User {
         user_id: requester.uid.clone(),
         user_name: requester.username.clone(),
     };
-    state
+    let instance = state
         .instances
         .get(&uuid)
         .ok_or_else(|| Error {
             kind: ErrorKind::NotFound,
             source: eyre!("Instance not found"),
         })?
-        .send_command(&command, caused_by)
-        .await
-        .map(|_| Json(()))
+        .clone();
+    match instance {
+        GameInstance::MinecraftInstance(instance) if query.confirm => instance
+            .send_command_confirmed(&command, caused_by)
+            .await
+            .map(|responded| Json(Some(responded))),
+        instance => instance
+            .send_command(&command, caused_by)
+            .await
+            .map(|_| Json(None)),
+    }
 }
 
 pub async fn get_instance_state(
@@ -171,13 +297,238 @@ pub async fn get_instance_state(
     )))
 }
 
+pub async fn get_connection_info(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<ConnectionInfo>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::ViewInstance(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => Ok(Json(instance.connection_info().await)),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Connection info is unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn get_version_info(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<VersionInfo>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::ViewInstance(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => Ok(Json(instance.version_info().await)),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Version info is unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn get_runtime_content(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<RuntimeContent>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::ViewInstance(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance.runtime_content().await.map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Listing loaded content is unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn get_mod_issues(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<Vec<ModIssue>>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::ViewInstance(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance.validate_mods().await.map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Checking mods is unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn get_startup_estimate(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<Option<StartupEstimate>>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::ViewInstance(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => Ok(Json(instance.startup_estimate().await)),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Startup estimates are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn get_tps(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<TpsReport>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::ViewInstance(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance.get_tps().await.map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Tick rates are unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn get_instance_age(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<InstanceAge>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::ViewInstance(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => Ok(Json(InstanceAge {
+            created_at: instance.created_at().await,
+            age: instance.age().await.as_secs(),
+        })),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Instance age is unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn get_crash_cause(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<Option<CrashCause>>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::ViewInstance(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => Ok(Json(instance.crash_cause().await)),
+        GameInstance::GenericInstance(_) => Ok(Json(None)),
+    }
+}
+
+pub async fn kill_port_conflict(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<()>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    // the conflicting process isn't necessarily managed by lodestone
+    if !requester.is_owner {
+        return Err(Error {
+            kind: ErrorKind::PermissionDenied,
+            source: eyre!("Only the owner can kill processes outside of an instance"),
+        });
+    }
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance.kill_port_conflict().await.map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Crash remediation is unsupported for this instance"),
+        }),
+    }
+}
+
+pub async fn get_support_bundle(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Path(uuid): Path<InstanceUuid>,
+    AuthBearer(token): AuthBearer,
+) -> Result<Json<SupportBundle>, Error> {
+    let requester = state.users_manager.read().await.try_auth_or_err(&token)?;
+    requester.try_action(&UserAction::AccessSetting(uuid.clone()))?;
+    let instance = state.instances.get(&uuid).ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!("Instance not found"),
+    })?;
+    match instance.value() {
+        GameInstance::MinecraftInstance(instance) => instance.support_bundle().await.map(Json),
+        GameInstance::GenericInstance(_) => Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("Support bundles are unsupported for this instance"),
+        }),
+    }
+}
+
 pub fn get_instance_server_routes(state: AppState) -> Router {
     Router::new()
         .route("/instance/:uuid/start", put(start_instance))
         .route("/instance/:uuid/stop", put(stop_instance))
         .route("/instance/:uuid/restart", put(restart_instance))
+        .route("/instance/:uuid/maintenance/start", put(start_maintenance))
+        .route("/instance/:uuid/maintenance/end", put(end_maintenance))
+        .route("/instance/:uuid/restart/cancel", put(cancel_pending_restart))
         .route("/instance/:uuid/kill", put(kill_instance))
         .route("/instance/:uuid/console", post(send_command))
         .route("/instance/:uuid/state", get(get_instance_state))
+        .route("/instance/:uuid/connection_info", get(get_connection_info))
+        .route("/instance/:uuid/version_info", get(get_version_info))
+        .route("/instance/:uuid/runtime_content", get(get_runtime_content))
+        .route("/instance/:uuid/mod_issues", get(get_mod_issues))
+        .route(
+            "/instance/:uuid/startup_estimate",
+            get(get_startup_estimate),
+        )
+        .route("/instance/:uuid/tps", get(get_tps))
+        .route("/instance/:uuid/age", get(get_instance_age))
+        .route("/instance/:uuid/crash_cause", get(get_crash_cause))
+        .route(
+            "/instance/:uuid/crash_cause/kill_conflict",
+            put(kill_port_conflict),
+        )
+        .route("/instance/:uuid/support_bundle", get(get_support_bundle))
         .with_state(state)
 }
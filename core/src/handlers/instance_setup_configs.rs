@@ -7,6 +7,7 @@ use crate::traits::t_configurable::manifest::SetupManifest;
 use crate::traits::t_configurable::GameType;
 use crate::AppState;
 use axum::extract::Path;
+use axum::extract::Query;
 use axum::routing::get;
 use axum::routing::put;
 use axum::Json;
@@ -22,8 +23,11 @@ use ts_rs::TS;
 pub enum HandlerGameType {
     MinecraftJavaVanilla,
     MinecraftFabric,
+    MinecraftQuilt,
     MinecraftForge,
+    MinecraftNeoForge,
     MinecraftPaper,
+    MinecraftSpigot,
     MinecraftBedrock,
 }
 
@@ -32,8 +36,11 @@ impl From<HandlerGameType> for GameType {
         match value {
             HandlerGameType::MinecraftJavaVanilla => Self::MinecraftJava,
             HandlerGameType::MinecraftFabric => Self::MinecraftJava,
+            HandlerGameType::MinecraftQuilt => Self::MinecraftJava,
             HandlerGameType::MinecraftForge => Self::MinecraftJava,
+            HandlerGameType::MinecraftNeoForge => Self::MinecraftJava,
             HandlerGameType::MinecraftPaper => Self::MinecraftJava,
+            HandlerGameType::MinecraftSpigot => Self::MinecraftJava,
             HandlerGameType::MinecraftBedrock => Self::MinecraftBedrock,
         }
     }
@@ -46,8 +53,11 @@ impl TryFrom<HandlerGameType> for FlavourKind {
         Ok(match value {
             HandlerGameType::MinecraftJavaVanilla => Self::Vanilla,
             HandlerGameType::MinecraftFabric => Self::Fabric,
+            HandlerGameType::MinecraftQuilt => Self::Quilt,
             HandlerGameType::MinecraftForge => Self::Forge,
+            HandlerGameType::MinecraftNeoForge => Self::NeoForge,
             HandlerGameType::MinecraftPaper => Self::Paper,
+            HandlerGameType::MinecraftSpigot => Self::Spigot,
             HandlerGameType::MinecraftBedrock => {
                 return Err(Error {
                     kind: ErrorKind::BadRequest,
@@ -62,8 +72,11 @@ pub async fn get_available_games() -> Json<Vec<HandlerGameType>> {
     Json(vec![
         HandlerGameType::MinecraftJavaVanilla,
         HandlerGameType::MinecraftFabric,
+        HandlerGameType::MinecraftQuilt,
         HandlerGameType::MinecraftForge,
+        HandlerGameType::MinecraftNeoForge,
         HandlerGameType::MinecraftPaper,
+        HandlerGameType::MinecraftSpigot,
     ])
 }
 
@@ -75,6 +88,29 @@ pub async fn get_setup_manifest(
         .map(Json)
 }
 
+#[derive(Deserialize)]
+pub struct VersionSearchQuery {
+    #[serde(default)]
+    pub query: String,
+}
+
+pub async fn search_versions(
+    Path(game_type): Path<HandlerGameType>,
+    Query(query): Query<VersionSearchQuery>,
+) -> Result<Json<Vec<String>>, Error> {
+    minecraft::MinecraftInstance::search_versions(&game_type.try_into()?, &query.query)
+        .await
+        .map(Json)
+}
+
+pub async fn get_build_changelog(
+    Path((game_type, version, build)): Path<(HandlerGameType, String, String)>,
+) -> Result<Json<Option<String>>, Error> {
+    minecraft::util::get_build_changelog(&game_type.try_into()?, &version, &build)
+        .await
+        .map(Json)
+}
+
 #[derive(Deserialize)]
 pub struct GenericSetupManifestBody {
     pub url: String,
@@ -93,6 +129,11 @@ pub fn get_instance_setup_config_routes(appstate: AppState) -> Router {
     Router::new()
         .route("/games", get(get_available_games))
         .route("/setup_manifest/:game_type", get(get_setup_manifest))
+        .route("/setup_manifest/:game_type/versions", get(search_versions))
+        .route(
+            "/setup_manifest/:game_type/:version/:build/changelog",
+            get(get_build_changelog),
+        )
         .route("/generic_setup_manifest", put(get_generic_setup_manifest))
         .with_state(appstate)
 }
@@ -137,7 +137,7 @@ impl TConfigurable for GenericInstance {
         })
     }
 
-    async fn change_version(&self, _version: String) -> Result<(), Error> {
+    async fn change_version(&self, _version: String, _force: bool) -> Result<(), Error> {
         Err(Error {
             kind: ErrorKind::UnsupportedOperation,
             source: eyre!("This instance does not support changing version"),
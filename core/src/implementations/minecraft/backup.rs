@@ -0,0 +1,586 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Component, Path, PathBuf};
+
+use async_trait::async_trait;
+use color_eyre::eyre::{eyre, Context};
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+use ts_rs::TS;
+
+use crate::error::{Error, ErrorKind};
+use crate::util::resolve_path_conflict;
+
+use super::dir_size;
+
+/// Where finished backup archives are written to
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BackupDestination {
+    /// A directory on this machine, such as a mounted network drive
+    Local { path: PathBuf },
+}
+
+impl BackupDestination {
+    pub fn sink(&self) -> Box<dyn BackupSink> {
+        match self {
+            BackupDestination::Local { path } => Box::new(LocalBackupSink::new(path.clone())),
+        }
+    }
+}
+
+/// When the world is backed up as part of stopping the server
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, TS)]
+#[ts(export)]
+pub enum BackupOnStopMode {
+    /// Before the stop command is sent, capturing the world as it was while running
+    BeforeStop,
+    /// Once the server process has exited, which avoids racing with the final save but delays
+    /// the instance reaching `Stopped`
+    AfterExit,
+}
+
+/// Controls the periodic backup task of an instance
+#[derive(Debug)]
+pub enum BackupInstruction {
+    /// Minutes between backups, `None` disables them
+    SetPeriod(Option<u32>),
+    Pause,
+    Resume,
+    /// Backs up the world right away, sending the outcome once the archive is written
+    BackupNow(oneshot::Sender<Result<(), Error>>),
+    /// Ends the task once the backup in progress, if any, is written
+    Shutdown,
+}
+
+/// How a backup is stored
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, TS)]
+#[ts(export)]
+pub enum BackupFormat {
+    Zip,
+    /// A plain copy of the worlds, e.g. made by hand
+    Directory,
+}
+
+/// A backup stored in a destination
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BackupEntry {
+    pub name: String,
+    /// Total size in bytes, of all files for directories
+    pub size: u64,
+    /// Unix timestamp in seconds, taken from the name if it has one
+    pub creation_time: i64,
+    pub format: BackupFormat,
+}
+
+/// A world stored in a backup archive
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct WorldBackupEntry {
+    /// Name of the world's directory, e.g. `world_nether`
+    pub name: String,
+    /// Uncompressed size in bytes
+    pub size: u64,
+}
+
+/// A backup along with the worlds it contains, so that only some of them can be restored
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct BackupInfo {
+    pub name: String,
+    pub size: u64,
+    /// Unix timestamp in seconds
+    pub creation_time: i64,
+    pub format: BackupFormat,
+    pub worlds: Vec<WorldBackupEntry>,
+}
+
+/// Sums up the uncompressed size of each world of a zip archive
+///
+/// Only top-level directories with a `level.dat` are worlds, other directories the backup
+/// includes, like plugin data, aren't listed
+pub fn read_archive_worlds(archive: &Path) -> Result<Vec<WorldBackupEntry>, Error> {
+    let file = std::fs::File::open(archive)
+        .context(format!("Failed to open archive {}", archive.display()))?;
+    let mut zip = zip::ZipArchive::new(file)
+        .context(format!("Failed to read archive {}", archive.display()))?;
+    let mut sizes = BTreeMap::new();
+    let mut worlds = BTreeSet::new();
+    for i in 0..zip.len() {
+        let entry = zip
+            .by_index(i)
+            .context(format!("Failed to read archive {}", archive.display()))?;
+        // files at the top level aren't part of any world
+        if let Some((world, path)) = entry.name().split_once(['/', '\\']) {
+            *sizes.entry(world.to_string()).or_insert(0) += entry.size();
+            if path == "level.dat" {
+                worlds.insert(world.to_string());
+            }
+        }
+    }
+    Ok(sizes
+        .into_iter()
+        .filter(|(name, _)| worlds.contains(name))
+        .map(|(name, size)| WorldBackupEntry { name, size })
+        .collect())
+}
+
+/// Lists the worlds of a backup stored as a directory, each subdirectory with a `level.dat` being
+/// one
+pub fn read_directory_worlds(backup: &Path) -> Result<Vec<WorldBackupEntry>, Error> {
+    let mut worlds = Vec::new();
+    for entry in
+        std::fs::read_dir(backup).context(format!("Failed to read {}", backup.display()))?
+    {
+        let entry = entry.context(format!("Failed to read {}", backup.display()))?;
+        if entry.path().join("level.dat").is_file() {
+            worlds.push(WorldBackupEntry {
+                name: entry.file_name().to_string_lossy().to_string(),
+                size: dir_size(&entry.path()),
+            });
+        }
+    }
+    worlds.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(worlds)
+}
+
+/// Extracts a single top-level directory of a zip archive into `dest`
+pub fn extract_archive_world(archive: &Path, world_name: &str, dest: &Path) -> Result<(), Error> {
+    let file = std::fs::File::open(archive)
+        .context(format!("Failed to open archive {}", archive.display()))?;
+    let mut zip = zip::ZipArchive::new(file)
+        .context(format!("Failed to read archive {}", archive.display()))?;
+    for i in 0..zip.len() {
+        let mut entry = zip
+            .by_index(i)
+            .context(format!("Failed to read archive {}", archive.display()))?;
+        // entries that would escape the destination are skipped
+        let relative_path = match entry.enclosed_name() {
+            Some(relative_path) if relative_path.starts_with(world_name) => {
+                relative_path.to_owned()
+            }
+            _ => continue,
+        };
+        let path = dest.join(relative_path);
+        if entry.is_dir() {
+            std::fs::create_dir_all(&path)
+                .context(format!("Failed to create directory {}", path.display()))?;
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .context(format!("Failed to create directory {}", parent.display()))?;
+        }
+        let mut out = std::fs::File::create(&path)
+            .context(format!("Failed to create file {}", path.display()))?;
+        std::io::copy(&mut entry, &mut out)
+            .context(format!("Failed to extract {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Copies a single world of a backup stored as a directory into `dest`
+pub fn copy_directory_world(backup: &Path, world_name: &str, dest: &Path) -> Result<(), Error> {
+    let world = backup.join(world_name);
+    for entry in walkdir::WalkDir::new(&world) {
+        let entry = entry.context(format!("Failed to read {}", world.display()))?;
+        let relative_path = entry
+            .path()
+            .strip_prefix(backup)
+            .context(format!("{} is not in the backup", entry.path().display()))?;
+        let path = dest.join(relative_path);
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&path)
+                .context(format!("Failed to create directory {}", path.display()))?;
+        } else if entry.file_type().is_file() {
+            std::fs::copy(entry.path(), &path)
+                .context(format!("Failed to copy {}", entry.path().display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Extracts or copies a single world of a backup into `dest`, whichever format it's in
+pub fn extract_backup_world(backup: &Path, world_name: &str, dest: &Path) -> Result<(), Error> {
+    if backup.is_dir() {
+        copy_directory_world(backup, world_name, dest)
+    } else {
+        extract_archive_world(backup, world_name, dest)
+    }
+}
+
+/// Rejects backup names that aren't a single file or directory name, e.g. `..` or `a/b`
+pub fn validate_backup_name(name: &str) -> Result<(), Error> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) if !name.contains(['/', '\\']) => Ok(()),
+        _ => Err(Error {
+            kind: ErrorKind::BadRequest,
+            source: eyre!("Invalid backup name {}", name),
+        }),
+    }
+}
+
+/// Format of the timestamp backup names start with
+const BACKUP_TIMESTAMP_FORMAT: &str = "%Y-%m-%d_%H-%M-%S";
+
+/// When a backup was taken according to its name, `None` for files lodestone didn't create
+fn parse_backup_timestamp(name: &str) -> Option<chrono::NaiveDateTime> {
+    // the timestamp is followed by the reason, e.g. `2024-01-31_12-00-00-periodic.zip`
+    let timestamp = name.get(..19)?;
+    chrono::NaiveDateTime::parse_from_str(timestamp, BACKUP_TIMESTAMP_FORMAT).ok()
+}
+
+/// Names of the backups to remove so only the newest `retention` are kept, oldest first
+///
+/// Entries without a timestamp in their name are never removed
+pub fn backups_beyond_retention(backups: &[BackupEntry], retention: u32) -> Vec<String> {
+    let mut backups: Vec<(chrono::NaiveDateTime, &String)> = backups
+        .iter()
+        .filter_map(|backup| Some((parse_backup_timestamp(&backup.name)?, &backup.name)))
+        .collect();
+    backups.sort();
+    let excess = backups.len().saturating_sub(retention as usize);
+    backups
+        .into_iter()
+        .take(excess)
+        .map(|(_, name)| name.clone())
+        .collect()
+}
+
+/// A storage backend for backup archives
+#[async_trait]
+pub trait BackupSink: Send + Sync {
+    /// Checks that the destination is reachable and writable
+    async fn validate(&self) -> Result<(), Error>;
+    /// Moves a finished archive into the destination, returning where it was stored
+    async fn store(&self, archive: &Path, name: &str) -> Result<PathBuf, Error>;
+    /// Lists the stored backups, oldest first
+    ///
+    /// Zip archives and directories are both listed, other files are skipped
+    async fn list(&self) -> Result<Vec<BackupEntry>, Error>;
+    /// Deletes a backup, returning where it was stored
+    async fn remove(&self, name: &str) -> Result<PathBuf, Error>;
+    /// The worlds stored in a backup
+    async fn list_worlds(&self, name: &str) -> Result<Vec<WorldBackupEntry>, Error>;
+    /// Path to a local copy of a backup, for reading it
+    async fn retrieve(&self, name: &str) -> Result<PathBuf, Error>;
+    /// Free space left in the destination in bytes
+    async fn available_space(&self) -> Result<u64, Error>;
+}
+
+pub struct LocalBackupSink {
+    path: PathBuf,
+}
+
+impl LocalBackupSink {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Path of a backup, rejecting names that would escape the destination
+    fn backup_path(&self, name: &str) -> Result<PathBuf, Error> {
+        validate_backup_name(name)?;
+        Ok(self.path.join(name))
+    }
+}
+
+#[async_trait]
+impl BackupSink for LocalBackupSink {
+    async fn validate(&self) -> Result<(), Error> {
+        crate::util::fs::create_dir_all(&self.path).await?;
+        tempfile::tempfile_in(&self.path).map_err(|e| Error {
+            kind: ErrorKind::BadRequest,
+            source: eyre!(
+                "Backup destination {} is not writable: {}",
+                self.path.display(),
+                e
+            ),
+        })?;
+        Ok(())
+    }
+
+    async fn store(&self, archive: &Path, name: &str) -> Result<PathBuf, Error> {
+        crate::util::fs::create_dir_all(&self.path).await?;
+        let dest = resolve_path_conflict(self.path.join(name), None);
+        // renaming fails across filesystems, e.g. onto a mounted drive
+        if tokio::fs::rename(archive, &dest).await.is_err() {
+            if let Err(e) = tokio::fs::copy(archive, &dest).await.context(format!(
+                "Failed to copy backup {} to {}",
+                archive.display(),
+                dest.display()
+            )) {
+                // don't leave a truncated archive behind, e.g. when the destination filled up
+                let _ = tokio::fs::remove_file(&dest).await;
+                return Err(e.into());
+            }
+            crate::util::fs::remove_file(archive).await?;
+        }
+        Ok(dest)
+    }
+
+    async fn list(&self) -> Result<Vec<BackupEntry>, Error> {
+        if !self.path.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut ret = Vec::new();
+        let mut read_dir = tokio::fs::read_dir(&self.path)
+            .await
+            .context(format!("Failed to read {}", self.path.display()))?;
+        while let Some(entry) = read_dir
+            .next_entry()
+            .await
+            .context(format!("Failed to read {}", self.path.display()))?
+        {
+            let path = entry.path();
+            let metadata = entry
+                .metadata()
+                .await
+                .context(format!("Failed to read {}", path.display()))?;
+            let (format, size) = if metadata.is_dir() {
+                let dir = path.clone();
+                let size = tokio::task::spawn_blocking(move || dir_size(&dir))
+                    .await
+                    .context("Failed to get backup size in a blocking task")?;
+                (BackupFormat::Directory, size)
+            } else if metadata.is_file()
+                && path
+                    .extension()
+                    .map_or(false, |extension| extension == "zip")
+            {
+                (BackupFormat::Zip, metadata.len())
+            } else {
+                continue;
+            };
+            let name = entry.file_name().to_string_lossy().to_string();
+            let creation_time = match parse_backup_timestamp(&name) {
+                Some(timestamp) => timestamp.timestamp(),
+                None => metadata
+                    .modified()
+                    .ok()
+                    .and_then(|modified| modified.duration_since(std::time::UNIX_EPOCH).ok())
+                    .map(|duration| duration.as_secs() as i64)
+                    .unwrap_or_default(),
+            };
+            ret.push(BackupEntry {
+                name,
+                size,
+                creation_time,
+                format,
+            });
+        }
+        ret.sort_by_key(|entry| entry.creation_time);
+        Ok(ret)
+    }
+
+    async fn remove(&self, name: &str) -> Result<PathBuf, Error> {
+        let path = self.backup_path(name)?;
+        if path.is_dir() {
+            crate::util::fs::remove_dir_all(&path).await?;
+        } else {
+            crate::util::fs::remove_file(&path).await?;
+        }
+        Ok(path)
+    }
+
+    async fn list_worlds(&self, name: &str) -> Result<Vec<WorldBackupEntry>, Error> {
+        let path = self.backup_path(name)?;
+        if path.is_dir() {
+            return tokio::task::spawn_blocking(move || read_directory_worlds(&path))
+                .await
+                .context("Failed to read backup in a blocking task")?;
+        }
+        tokio::task::spawn_blocking(move || read_archive_worlds(&path))
+            .await
+            .context("Failed to read archive in a blocking task")?
+    }
+
+    async fn retrieve(&self, name: &str) -> Result<PathBuf, Error> {
+        let path = self.backup_path(name)?;
+        if !path.exists() {
+            return Err(Error {
+                kind: ErrorKind::NotFound,
+                source: eyre!("Backup {} does not exist", name),
+            });
+        }
+        Ok(path)
+    }
+
+    async fn available_space(&self) -> Result<u64, Error> {
+        Ok(fs3::available_space(&self.path).context(format!(
+            "Failed to get available space of {}",
+            self.path.display()
+        ))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_local_backup_sink() {
+        let src = tempfile::tempdir().unwrap();
+        let dest = tempfile::tempdir().unwrap();
+        let sink = BackupDestination::Local {
+            path: dest.path().join("backups"),
+        }
+        .sink();
+        sink.validate().await.unwrap();
+
+        for _ in 0..2 {
+            let archive = src.path().join("archive.zip");
+            tokio::fs::write(&archive, b"backup").await.unwrap();
+            sink.store(&archive, "world.zip").await.unwrap();
+            assert!(!archive.exists());
+        }
+        assert!(dest.path().join("backups/world.zip").is_file());
+        assert!(dest.path().join("backups/world_1.zip").is_file());
+        assert_eq!(sink.list().await.unwrap().len(), 2);
+
+        sink.remove("world.zip").await.unwrap();
+        assert!(sink.remove("../world_1.zip").await.is_err());
+        assert!(sink.remove("..").await.is_err());
+        assert!(sink.remove(".").await.is_err());
+        let backups = sink.list().await.unwrap();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(backups[0].name, "world_1.zip");
+        assert_eq!(backups[0].size, 6);
+    }
+
+    #[tokio::test]
+    async fn test_directory_backup() {
+        let dest = tempfile::tempdir().unwrap();
+        let sink = LocalBackupSink::new(dest.path().to_owned());
+        // directories are backups too, anything else in the destination is not
+        let copied_world = dest.path().join("2024-01-01_00-00-00-copy/world");
+        std::fs::create_dir_all(copied_world.join("region")).unwrap();
+        std::fs::write(copied_world.join("level.dat"), b"level").unwrap();
+        std::fs::write(copied_world.join("region/r.0.0.mca"), b"region").unwrap();
+        std::fs::write(dest.path().join("notes.txt"), b"notes").unwrap();
+        let backups = sink.list().await.unwrap();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(backups[0].name, "2024-01-01_00-00-00-copy");
+        assert_eq!(backups[0].format, BackupFormat::Directory);
+        assert_eq!(backups[0].size, 11);
+        assert_eq!(backups[0].creation_time, 1704067200);
+        assert_eq!(
+            sink.list_worlds("2024-01-01_00-00-00-copy").await.unwrap(),
+            vec![WorldBackupEntry {
+                name: "world".to_string(),
+                size: 11,
+            }]
+        );
+
+        let restored = tempfile::tempdir().unwrap();
+        let backup = sink.retrieve("2024-01-01_00-00-00-copy").await.unwrap();
+        extract_backup_world(&backup, "world", restored.path()).unwrap();
+        assert_eq!(
+            std::fs::read(restored.path().join("world/region/r.0.0.mca")).unwrap(),
+            b"region"
+        );
+
+        sink.remove("2024-01-01_00-00-00-copy").await.unwrap();
+        assert!(sink.list().await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_validate_backup_name() {
+        assert!(validate_backup_name("2024-01-01_00-00-00-manual.zip").is_ok());
+        assert!(validate_backup_name("2024-01-01_00-00-00-copy").is_ok());
+        for name in ["..", ".", "", "../backup.zip", "a/b", "a\\b", "/backup.zip"] {
+            assert!(validate_backup_name(name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_backups_beyond_retention() {
+        let entry = |name: &str| BackupEntry {
+            name: name.to_string(),
+            size: 0,
+            creation_time: 0,
+            format: BackupFormat::Zip,
+        };
+        let backups = vec![
+            entry("2024-01-03_00-00-00-periodic.zip"),
+            entry("2024-01-01_00-00-00-manual.zip"),
+            entry("notes.txt"),
+            entry("2024-01-02_12-30-00-periodic.zip"),
+            entry("2024-13-01_00-00-00-periodic.zip"),
+        ];
+        assert_eq!(
+            backups_beyond_retention(&backups, 1),
+            vec![
+                "2024-01-01_00-00-00-manual.zip".to_string(),
+                "2024-01-02_12-30-00-periodic.zip".to_string(),
+            ]
+        );
+        assert!(backups_beyond_retention(&backups, 3).is_empty());
+        assert_eq!(backups_beyond_retention(&backups, 0).len(), 3);
+    }
+
+    /// Writes a backup with two worlds and a file that isn't part of any world
+    fn write_worlds_backup(path: &Path) {
+        use std::io::Write;
+
+        let mut writer = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+        let options = zip::write::FileOptions::default();
+        writer.add_directory("world", options).unwrap();
+        writer.start_file("world/level.dat", options).unwrap();
+        writer.write_all(b"level").unwrap();
+        writer
+            .start_file("world/region/r.0.0.mca", options)
+            .unwrap();
+        writer.write_all(b"region").unwrap();
+        writer
+            .start_file("world_nether/level.dat", options)
+            .unwrap();
+        writer.write_all(b"nether").unwrap();
+        writer
+            .start_file("plugins/Essentials/config.yml", options)
+            .unwrap();
+        writer.write_all(b"plugin data").unwrap();
+        writer.start_file("readme.txt", options).unwrap();
+        writer.write_all(b"not a world").unwrap();
+        writer.finish().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_list_worlds() {
+        let dest = tempfile::tempdir().unwrap();
+        write_worlds_backup(&dest.path().join("backup.zip"));
+        let sink = LocalBackupSink::new(dest.path().to_owned());
+        assert_eq!(
+            sink.list_worlds("backup.zip").await.unwrap(),
+            vec![
+                WorldBackupEntry {
+                    name: "world".to_string(),
+                    size: 11,
+                },
+                WorldBackupEntry {
+                    name: "world_nether".to_string(),
+                    size: 6,
+                },
+            ]
+        );
+        assert!(sink.list_worlds("../backup.zip").await.is_err());
+        assert!(sink.list_worlds("..").await.is_err());
+        assert!(sink.list_worlds(".").await.is_err());
+        assert!(sink.retrieve("..").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_extract_archive_world() {
+        let dest = tempfile::tempdir().unwrap();
+        write_worlds_backup(&dest.path().join("backup.zip"));
+        let sink = LocalBackupSink::new(dest.path().to_owned());
+        let restored = tempfile::tempdir().unwrap();
+        let archive = sink.retrieve("backup.zip").await.unwrap();
+        extract_archive_world(&archive, "world_nether", restored.path()).unwrap();
+        assert_eq!(
+            std::fs::read(restored.path().join("world_nether/level.dat")).unwrap(),
+            b"nether"
+        );
+        assert!(!restored.path().join("world").exists());
+        assert!(!restored.path().join("readme.txt").exists());
+    }
+}
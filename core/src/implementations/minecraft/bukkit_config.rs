@@ -0,0 +1,588 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::{eyre, Context};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::error::{Error, ErrorKind};
+
+use super::{tmp_config_path, FlavourKind};
+
+/// The YAML configs Bukkit-derived servers read alongside server.properties
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, TS)]
+#[ts(export)]
+pub enum BukkitConfigFile {
+    Bukkit,
+    Spigot,
+    PaperGlobal,
+    PaperWorldDefaults,
+}
+
+impl BukkitConfigFile {
+    /// Path of the file relative to the instance directory
+    pub fn relative_path(&self) -> &'static str {
+        match self {
+            BukkitConfigFile::Bukkit => "bukkit.yml",
+            BukkitConfigFile::Spigot => "spigot.yml",
+            BukkitConfigFile::PaperGlobal => "config/paper-global.yml",
+            BukkitConfigFile::PaperWorldDefaults => "config/paper-world-defaults.yml",
+        }
+    }
+
+    pub fn is_supported_by(&self, flavour: FlavourKind) -> bool {
+        match self {
+            BukkitConfigFile::Bukkit | BukkitConfigFile::Spigot => {
+                matches!(flavour, FlavourKind::Paper | FlavourKind::Spigot)
+            }
+            BukkitConfigFile::PaperGlobal | BukkitConfigFile::PaperWorldDefaults => {
+                matches!(flavour, FlavourKind::Paper)
+            }
+        }
+    }
+
+    fn known_settings(&self) -> &'static [KnownSetting] {
+        match self {
+            BukkitConfigFile::Bukkit => BUKKIT_SETTINGS,
+            BukkitConfigFile::Spigot => SPIGOT_SETTINGS,
+            BukkitConfigFile::PaperGlobal => PAPER_GLOBAL_SETTINGS,
+            BukkitConfigFile::PaperWorldDefaults => PAPER_WORLD_DEFAULTS_SETTINGS,
+        }
+    }
+}
+
+/// The value of a managed key, only scalars are managed
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, TS)]
+#[ts(export)]
+#[serde(untagged)]
+pub enum BukkitSettingValue {
+    Bool(bool),
+    Integer(i64),
+}
+
+#[derive(Clone, Copy, Debug)]
+enum SettingKind {
+    Bool,
+    Integer { min: i64, max: i64 },
+}
+
+#[derive(Clone, Copy, Debug)]
+struct KnownSetting {
+    /// Dot separated path of the key
+    key: &'static str,
+    kind: SettingKind,
+}
+
+impl KnownSetting {
+    const fn bool(key: &'static str) -> Self {
+        KnownSetting {
+            key,
+            kind: SettingKind::Bool,
+        }
+    }
+
+    const fn int(key: &'static str, min: i64, max: i64) -> Self {
+        KnownSetting {
+            key,
+            kind: SettingKind::Integer { min, max },
+        }
+    }
+
+    fn validate(&self, value: &BukkitSettingValue) -> Result<(), Error> {
+        match (self.kind, value) {
+            (SettingKind::Bool, BukkitSettingValue::Bool(_)) => Ok(()),
+            (SettingKind::Integer { min, max }, BukkitSettingValue::Integer(value)) => {
+                if *value < min || *value > max {
+                    Err(Error {
+                        kind: ErrorKind::BadRequest,
+                        source: eyre!("{} must be between {} and {}", self.key, min, max),
+                    })
+                } else {
+                    Ok(())
+                }
+            }
+            (SettingKind::Bool, _) => Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("{} must be a boolean", self.key),
+            }),
+            (SettingKind::Integer { .. }, _) => Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("{} must be an integer", self.key),
+            }),
+        }
+    }
+}
+
+const BUKKIT_SETTINGS: &[KnownSetting] = &[
+    KnownSetting::bool("settings.allow-end"),
+    KnownSetting::int("settings.connection-throttle", -1, i64::MAX),
+    KnownSetting::int("spawn-limits.monsters", -1, i64::MAX),
+    KnownSetting::int("spawn-limits.animals", -1, i64::MAX),
+    KnownSetting::int("spawn-limits.water-animals", -1, i64::MAX),
+    KnownSetting::int("spawn-limits.ambient", -1, i64::MAX),
+    KnownSetting::int("ticks-per.autosave", 0, i64::MAX),
+];
+
+const SPIGOT_SETTINGS: &[KnownSetting] = &[
+    KnownSetting::bool("settings.bungeecord"),
+    KnownSetting::bool("settings.restart-on-crash"),
+    KnownSetting::int("settings.timeout-time", 1, i64::MAX),
+    KnownSetting::int(
+        "world-settings.default.entity-activation-range.animals",
+        0,
+        128,
+    ),
+    KnownSetting::int(
+        "world-settings.default.entity-activation-range.monsters",
+        0,
+        128,
+    ),
+    KnownSetting::int("world-settings.default.mob-spawn-range", 0, 128),
+];
+
+const PAPER_GLOBAL_SETTINGS: &[KnownSetting] = &[
+    KnownSetting::bool("proxies.velocity.enabled"),
+    KnownSetting::bool("proxies.velocity.online-mode"),
+    KnownSetting::bool("proxies.bungee-cord.online-mode"),
+    KnownSetting::int("chunk-system.worker-threads", -1, 256),
+];
+
+const PAPER_WORLD_DEFAULTS_SETTINGS: &[KnownSetting] = &[
+    KnownSetting::bool("anticheat.anti-xray.enabled"),
+    KnownSetting::bool("entities.spawning.per-player-mob-spawns"),
+    KnownSetting::bool("environment.optimize-explosions"),
+    KnownSetting::int("chunks.max-auto-save-chunks-per-tick", 1, i64::MAX),
+];
+
+/// One of the YAML configs, edited line by line so that comments and keys that aren't
+/// managed survive a save
+///
+/// Only block mappings are understood, which is all these files use for the managed keys
+#[derive(Clone, Debug)]
+pub struct BukkitConfig {
+    file: BukkitConfigFile,
+    path: PathBuf,
+    lines: Vec<String>,
+}
+
+/// A `key:` line of the file
+#[derive(Clone, Debug)]
+struct Entry {
+    line: usize,
+    indent: usize,
+    /// Dot separated path of the key
+    path: String,
+    /// The scalar on the same line, `None` for a key that opens a nested mapping
+    value: Option<String>,
+}
+
+/// Splits `key: value # comment` into the key and the value, without the comment
+fn split_key_value(content: &str) -> Option<(&str, &str)> {
+    let (key, rest) = match content.split_once(": ") {
+        Some((key, rest)) => (key, rest),
+        None => (content.strip_suffix(':')?, ""),
+    };
+    let key = key.trim_matches(|c| c == '\'' || c == '"');
+    if key.is_empty() || key.contains(' ') {
+        return None;
+    }
+    let rest = rest.trim();
+    let value = if rest.starts_with('#') {
+        ""
+    } else if rest.starts_with(['\'', '"']) {
+        rest
+    } else {
+        rest.split(" #").next().unwrap_or_default()
+    };
+    Some((key, value.trim()))
+}
+
+fn indent_of(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+impl BukkitConfig {
+    /// Loads the file from the instance directory, a missing file loads as empty since
+    /// the server only generates it on its first start
+    pub async fn load(path_to_instance: &Path, file: BukkitConfigFile) -> Result<Self, Error> {
+        let path = path_to_instance.join(file.relative_path());
+        let content = match tokio::fs::read_to_string(&path).await {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            content => content.context(format!("Failed to read {}", path.display()))?,
+        };
+        let config = BukkitConfig {
+            file,
+            path,
+            lines: content.lines().map(|line| line.to_string()).collect(),
+        };
+        config.entries()?;
+        Ok(config)
+    }
+
+    /// Writes to a temporary file first so that a failed write can't leave a truncated config
+    pub async fn save(&self) -> Result<(), Error> {
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .context(format!("Failed to create {}", parent.display()))?;
+        }
+        let mut content = self.lines.join("\n");
+        content.push('\n');
+        let path_to_tmp = tmp_config_path(&self.path);
+        tokio::fs::write(&path_to_tmp, content)
+            .await
+            .context(format!("Failed to write {}", path_to_tmp.display()))?;
+        tokio::fs::rename(&path_to_tmp, &self.path)
+            .await
+            .context(format!("Failed to write {}", self.path.display()))?;
+        Ok(())
+    }
+
+    /// The managed keys and their current values, keys the file doesn't set yet are left out
+    pub fn settings(&self) -> BTreeMap<String, BukkitSettingValue> {
+        let entries = match self.entries() {
+            Ok(entries) => entries,
+            Err(_) => return BTreeMap::new(),
+        };
+        self.file
+            .known_settings()
+            .iter()
+            .filter_map(|setting| {
+                let raw = entries
+                    .iter()
+                    .find(|entry| entry.path == setting.key)?
+                    .value
+                    .as_deref()?;
+                let value = match setting.kind {
+                    SettingKind::Bool => BukkitSettingValue::Bool(raw.parse().ok()?),
+                    SettingKind::Integer { .. } => BukkitSettingValue::Integer(raw.parse().ok()?),
+                };
+                Some((setting.key.to_string(), value))
+            })
+            .collect()
+    }
+
+    /// Validates every change before applying any of them
+    pub fn update_settings(
+        &mut self,
+        changes: BTreeMap<String, BukkitSettingValue>,
+    ) -> Result<(), Error> {
+        for (key, value) in changes.iter() {
+            self.file
+                .known_settings()
+                .iter()
+                .find(|setting| setting.key == key.as_str())
+                .ok_or_else(|| Error {
+                    kind: ErrorKind::BadRequest,
+                    source: eyre!(
+                        "{} is not a managed key of {}",
+                        key,
+                        self.file.relative_path()
+                    ),
+                })?
+                .validate(value)?;
+        }
+        for (key, value) in changes {
+            let value = match value {
+                BukkitSettingValue::Bool(value) => value.to_string(),
+                BukkitSettingValue::Integer(value) => value.to_string(),
+            };
+            self.set(&key, &value)?;
+        }
+        Ok(())
+    }
+
+    fn entries(&self) -> Result<Vec<Entry>, Error> {
+        let mut entries = Vec::new();
+        let mut parents: Vec<(usize, String)> = Vec::new();
+        // indent of a `|` or `>` scalar, the lines indented past it are its content
+        let mut block_scalar_indent = None;
+        for (index, line) in self.lines.iter().enumerate() {
+            let content = line.trim();
+            let indent = indent_of(line);
+            if let Some(scalar_indent) = block_scalar_indent {
+                if content.is_empty() || indent > scalar_indent {
+                    continue;
+                }
+                block_scalar_indent = None;
+            }
+            if content.is_empty()
+                || content.starts_with('#')
+                || content.starts_with('-')
+                || content == "..."
+            {
+                continue;
+            }
+            let (key, value) = split_key_value(content).ok_or_else(|| {
+                eyre!(
+                    "Failed to parse line {} of {}",
+                    index + 1,
+                    self.path.display()
+                )
+            })?;
+            while matches!(parents.last(), Some((parent_indent, _)) if *parent_indent >= indent) {
+                parents.pop();
+            }
+            let path = parents
+                .iter()
+                .map(|(_, key)| key.as_str())
+                .chain(std::iter::once(key))
+                .collect::<Vec<_>>()
+                .join(".");
+            if value.starts_with(['|', '>']) {
+                block_scalar_indent = Some(indent);
+            }
+            let value = (!value.is_empty()).then(|| value.to_string());
+            if value.is_none() {
+                parents.push((indent, key.to_string()));
+            }
+            entries.push(Entry {
+                line: index,
+                indent,
+                path,
+                value,
+            });
+        }
+        Ok(entries)
+    }
+
+    /// Sets the value at a dot separated path, creating the mappings along it
+    fn set(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        let entries = self.entries()?;
+        if let Some(entry) = entries.iter().find(|entry| entry.path == key) {
+            if entry.value.is_none() {
+                return Err(eyre!(
+                    "Cannot set {} in {}, it is a mapping",
+                    key,
+                    self.path.display()
+                )
+                .into());
+            }
+            let line = &self.lines[entry.line];
+            let key_end = line.find(':').unwrap_or(line.len());
+            let comment = line[key_end..]
+                .find(" #")
+                .map(|index| line[key_end + index..].to_string())
+                .unwrap_or_default();
+            self.lines[entry.line] = format!("{}: {}{}", &line[..key_end], value, comment);
+            return Ok(());
+        }
+        let segments: Vec<&str> = key.split('.').collect();
+        // the deepest mapping along the path that already exists
+        let parent = (1..segments.len()).rev().find_map(|depth| {
+            let path = segments[..depth].join(".");
+            entries
+                .iter()
+                .find(|entry| entry.path == path)
+                .map(|entry| (depth, entry))
+        });
+        let (depth, insert_at, indent) = match parent {
+            Some((_, entry)) if entry.value.is_some() => {
+                return Err(eyre!(
+                    "Cannot set {} in {}, a parent key is not a mapping",
+                    key,
+                    self.path.display()
+                )
+                .into());
+            }
+            Some((depth, entry)) => {
+                let children: Vec<&Entry> = entries
+                    .iter()
+                    .filter(|child| child.path.starts_with(&format!("{}.", entry.path)))
+                    .collect();
+                let indent = children
+                    .first()
+                    .map_or(entry.indent + 2, |child| child.indent);
+                // after the last line of the mapping, leaving the comments that follow it to
+                // the key they describe
+                let mut insert_at = children.last().map_or(entry.line, |child| child.line) + 1;
+                for (index, line) in self.lines.iter().enumerate().skip(insert_at) {
+                    let content = line.trim();
+                    if content.is_empty() || content.starts_with('#') {
+                        continue;
+                    }
+                    if indent_of(line) <= entry.indent {
+                        break;
+                    }
+                    insert_at = index + 1;
+                }
+                (depth, insert_at, indent)
+            }
+            None => (0, self.lines.len(), 0),
+        };
+        let new_lines = segments[depth..]
+            .iter()
+            .enumerate()
+            .map(|(offset, segment)| {
+                let indent = " ".repeat(indent + offset * 2);
+                if depth + offset + 1 == segments.len() {
+                    format!("{}{}: {}", indent, segment, value)
+                } else {
+                    format!("{}{}:", indent, segment)
+                }
+            })
+            .collect::<Vec<_>>();
+        self.lines.splice(insert_at..insert_at, new_lines);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_update_settings_keeps_unknown_keys() {
+        let instance_dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            instance_dir.path().join("spigot.yml"),
+            "settings:\n  bungeecord: false\n  sample-count: 12\nconfig-version: 12\n",
+        )
+        .unwrap();
+        let mut config = BukkitConfig::load(instance_dir.path(), BukkitConfigFile::Spigot)
+            .await
+            .unwrap();
+        assert_eq!(
+            config.settings(),
+            BTreeMap::from([(
+                "settings.bungeecord".to_string(),
+                BukkitSettingValue::Bool(false)
+            )])
+        );
+
+        config
+            .update_settings(BTreeMap::from([
+                (
+                    "settings.bungeecord".to_string(),
+                    BukkitSettingValue::Bool(true),
+                ),
+                (
+                    "world-settings.default.mob-spawn-range".to_string(),
+                    BukkitSettingValue::Integer(6),
+                ),
+            ]))
+            .unwrap();
+        config.save().await.unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(instance_dir.path().join("spigot.yml")).unwrap(),
+            "settings:\n  bungeecord: true\n  sample-count: 12\nconfig-version: 12\n\
+             world-settings:\n  default:\n    mob-spawn-range: 6\n"
+        );
+        let config = BukkitConfig::load(instance_dir.path(), BukkitConfigFile::Spigot)
+            .await
+            .unwrap();
+        assert_eq!(
+            config
+                .settings()
+                .get("world-settings.default.mob-spawn-range"),
+            Some(&BukkitSettingValue::Integer(6))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_settings_keeps_comments() {
+        let instance_dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(instance_dir.path().join("config")).unwrap();
+        std::fs::write(
+            instance_dir.path().join("config/paper-global.yml"),
+            "# This is the global configuration file for Paper.\n\
+             _version: 29\n\
+             chunk-system:\n\
+             \x20 # -1 picks a count from the number of cores\n\
+             \x20 worker-threads: -1 # the default\n\
+             \x20 gen-parallelism: default\n\
+             # proxies forward the real address of players\n\
+             proxies:\n\
+             \x20 bungee-cord:\n\
+             \x20   online-mode: true\n\
+             \x20 motd: |\n\
+             \x20   velocity: not a key\n",
+        )
+        .unwrap();
+        let mut config = BukkitConfig::load(instance_dir.path(), BukkitConfigFile::PaperGlobal)
+            .await
+            .unwrap();
+        assert_eq!(
+            config.settings(),
+            BTreeMap::from([
+                (
+                    "chunk-system.worker-threads".to_string(),
+                    BukkitSettingValue::Integer(-1)
+                ),
+                (
+                    "proxies.bungee-cord.online-mode".to_string(),
+                    BukkitSettingValue::Bool(true)
+                ),
+            ])
+        );
+        config
+            .update_settings(BTreeMap::from([
+                (
+                    "chunk-system.worker-threads".to_string(),
+                    BukkitSettingValue::Integer(4),
+                ),
+                (
+                    "proxies.velocity.enabled".to_string(),
+                    BukkitSettingValue::Bool(true),
+                ),
+            ]))
+            .unwrap();
+        config.save().await.unwrap();
+        assert_eq!(
+            std::fs::read_to_string(instance_dir.path().join("config/paper-global.yml")).unwrap(),
+            "# This is the global configuration file for Paper.\n\
+             _version: 29\n\
+             chunk-system:\n\
+             \x20 # -1 picks a count from the number of cores\n\
+             \x20 worker-threads: 4 # the default\n\
+             \x20 gen-parallelism: default\n\
+             # proxies forward the real address of players\n\
+             proxies:\n\
+             \x20 bungee-cord:\n\
+             \x20   online-mode: true\n\
+             \x20 motd: |\n\
+             \x20   velocity: not a key\n\
+             \x20 velocity:\n\
+             \x20   enabled: true\n"
+        );
+        assert!(!instance_dir
+            .path()
+            .join("config/paper-global.yml.tmp")
+            .exists());
+    }
+
+    #[tokio::test]
+    async fn test_update_settings_rejects_invalid_changes() {
+        let instance_dir = tempfile::tempdir().unwrap();
+        let mut config = BukkitConfig::load(instance_dir.path(), BukkitConfigFile::Bukkit)
+            .await
+            .unwrap();
+        assert!(config
+            .update_settings(BTreeMap::from([(
+                "settings.allow-end".to_string(),
+                BukkitSettingValue::Integer(1)
+            )]))
+            .is_err());
+        assert!(config
+            .update_settings(BTreeMap::from([
+                (
+                    "settings.allow-end".to_string(),
+                    BukkitSettingValue::Bool(false)
+                ),
+                (
+                    "ticks-per.autosave".to_string(),
+                    BukkitSettingValue::Integer(-5)
+                ),
+            ]))
+            .is_err());
+        assert!(config
+            .update_settings(BTreeMap::from([(
+                "settings.unknown".to_string(),
+                BukkitSettingValue::Bool(true)
+            )]))
+            .is_err());
+        // nothing from the rejected batches is applied
+        assert!(config.settings().is_empty());
+    }
+}
@@ -1,22 +1,40 @@
+use std::collections::{BTreeMap, HashMap};
 use std::str::FromStr;
 use std::sync::atomic;
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
 use color_eyre::eyre::{eyre, Context, ContextCompat};
+use futures_util::StreamExt;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use tokio::sync::Mutex;
+use ts_rs::TS;
 
 use crate::error::{Error, ErrorKind};
+use crate::events::CausedBy;
 use crate::prelude::path_to_tmp;
 use crate::traits::t_configurable::manifest::{
     ConfigurableManifest, ConfigurableValue, ConfigurableValueType, SettingManifest,
 };
 use crate::traits::t_configurable::{Game, TConfigurable};
-use crate::traits::t_server::State;
+use crate::traits::t_server::{State, StateAction, TServer};
 
 use crate::types::InstanceUuid;
 use crate::util::download_file;
 
-use super::util::{get_fabric_jar_url, get_paper_jar_url, get_vanilla_jar_url};
-use super::MinecraftInstance;
+use super::backup::BackupInstruction;
+use super::line_parser::parse_rcon_difficulty;
+use super::nbt::{read_world_difficulty_lock, read_world_version};
+use super::util::{
+    compare_version_release, get_build_changelog, get_fabric_jar_url, get_forge_jar_url,
+    get_neoforge_jar_url, get_paper_jar_url, get_vanilla_jar_url, read_jar_data_version,
+};
+use super::{
+    Flavour, FlavourKind, ForgeBuildVersion, MaintenanceRestore, MinecraftInstance,
+    PaperBuildVersion,
+};
 
 #[async_trait]
 impl TConfigurable for MinecraftInstance {
@@ -61,21 +79,7 @@ impl TConfigurable for MinecraftInstance {
     }
 
     async fn set_name(&self, name: String) -> Result<(), Error> {
-        if name.is_empty() {
-            return Err(Error {
-                kind: ErrorKind::BadRequest,
-                source: eyre!("Name cannot be empty"),
-            });
-        }
-        if name.len() > 100 {
-            return Err(Error {
-                kind: ErrorKind::BadRequest,
-                source: eyre!("Name cannot be longer than 100 characters"),
-            });
-        }
-        self.config.lock().await.name = name;
-        self.write_config_to_file().await?;
-        Ok(())
+        self.rename(name).await
     }
 
     async fn set_description(&self, description: String) -> Result<(), Error> {
@@ -97,7 +101,10 @@ impl TConfigurable for MinecraftInstance {
     }
 
     async fn set_auto_start(&self, auto_start: bool) -> Result<(), Error> {
-        self.config.lock().await.auto_start = auto_start;
+        let mut config = self.config.lock().await;
+        config.timeout_settings.validate(auto_start)?;
+        config.auto_start = auto_start;
+        drop(config);
         self.auto_start.store(auto_start, atomic::Ordering::Relaxed);
         self.write_config_to_file().await
     }
@@ -109,67 +116,127 @@ impl TConfigurable for MinecraftInstance {
         self.write_config_to_file().await
     }
 
-    async fn change_version(&self, version: String) -> Result<(), Error> {
-        if *self.state.lock().await != State::Stopped {
-            return Err(Error {
-                kind: ErrorKind::BadRequest,
-                source: eyre!("Cannot change version while server is running"),
-            });
-        }
-        if version == self.config.lock().await.version {
-            return Ok(());
-        }
-        let (url, _) = match self.config.lock().await.flavour {
-            super::Flavour::Vanilla => get_vanilla_jar_url(&version).await.ok_or_else(|| {
-                let error_msg =
-                    format!("Cannot get the vanilla jar version for version {}", version);
-                Error {
-                    kind: ErrorKind::BadRequest,
-                    source: eyre!(error_msg),
-                }
-            })?,
-            super::Flavour::Fabric { .. } => get_fabric_jar_url(&version, &None, &None)
-                .await
-                .ok_or_else(|| {
+    async fn set_backup_period(&self, backup_period: Option<u32>) -> Result<(), Error> {
+        self.config.lock().await.backup_period = backup_period;
+        let _ = self
+            .backup_sender
+            .send(BackupInstruction::SetPeriod(backup_period));
+        self.write_config_to_file().await
+    }
+
+    async fn change_version(&self, version: String, force: bool) -> Result<(), Error> {
+        // keeps the server from being started while its jar and world are being replaced
+        self.transition_maintenance(StateAction::BeginUpdate)
+            .await?;
+        let result: Result<(), Error> = async {
+            if version == self.config.lock().await.version {
+                return Ok(());
+            }
+            let (url, _) = match self.config.lock().await.flavour {
+                super::Flavour::Vanilla => get_vanilla_jar_url(&version).await.ok_or_else(|| {
                     let error_msg =
-                        format!("Cannot get the fabric jar version for version {}", version);
+                        format!("Cannot get the vanilla jar version for version {}", version);
                     Error {
                         kind: ErrorKind::BadRequest,
                         source: eyre!(error_msg),
                     }
                 })?,
-            super::Flavour::Paper { .. } => {
-                get_paper_jar_url(&version, &None).await.ok_or_else(|| {
-                    let error_msg =
-                        format!("Cannot get the paper jar version for version {}", version);
-                    Error {
-                        kind: ErrorKind::BadRequest,
-                        source: eyre!(error_msg),
+                super::Flavour::Fabric { .. } => get_fabric_jar_url(&version, &None, &None)
+                    .await
+                    .ok_or_else(|| {
+                        let error_msg =
+                            format!("Cannot get the fabric jar version for version {}", version);
+                        Error {
+                            kind: ErrorKind::BadRequest,
+                            source: eyre!(error_msg),
+                        }
+                    })?,
+                super::Flavour::Paper { .. } => {
+                    get_paper_jar_url(&version, &None).await.ok_or_else(|| {
+                        let error_msg =
+                            format!("Cannot get the paper jar version for version {}", version);
+                        Error {
+                            kind: ErrorKind::BadRequest,
+                            source: eyre!(error_msg),
+                        }
+                    })?
+                }
+                super::Flavour::Spigot => {
+                    return Err(Error {
+                        kind: ErrorKind::UnsupportedOperation,
+                        source: eyre!("Changing versions is unsupported for spigot servers"),
+                    })
+                }
+                super::Flavour::Quilt { .. } => {
+                    return Err(Error {
+                        kind: ErrorKind::UnsupportedOperation,
+                        source: eyre!("Changing versions is unsupported for quilt servers"),
+                    })
+                }
+                super::Flavour::Forge { .. } => {
+                    return Err(Error {
+                        kind: ErrorKind::UnsupportedOperation,
+                        source: eyre!("Changing versions is unsupported for forge servers"),
+                    })
+                }
+                super::Flavour::NeoForge { .. } => {
+                    return Err(Error {
+                        kind: ErrorKind::UnsupportedOperation,
+                        source: eyre!("Changing versions is unsupported for neoforge servers"),
+                    })
+                }
+            };
+            let lodestone_tmp = path_to_tmp().clone();
+            let temp_dir = tempfile::tempdir_in(lodestone_tmp).context("Failed to create temp dir")?;
+            download_file(
+                &url,
+                temp_dir.path(),
+                Some("server.jar"),
+                &Box::new(|_| {}),
+                true,
+            )
+            .await?;
+            let jar_path = temp_dir.path().join("server.jar");
+
+            let path_to_world = self.path_to_world().await;
+            if let Some(world_version) = read_world_version(&path_to_world).await? {
+                // prefer the exact data versions, fall back to the release order of the version names
+                let is_downgrade = match (
+                    world_version.data_version,
+                    read_jar_data_version(&jar_path).await,
+                ) {
+                    (Some(world_data_version), Some(target_data_version)) => {
+                        target_data_version < world_data_version
                     }
-                })?
-            }
-            super::Flavour::Spigot => todo!(),
-            super::Flavour::Forge { .. } => {
-                return Err(Error {
-                    kind: ErrorKind::UnsupportedOperation,
-                    source: eyre!("Changing versions is unsupported for forge servers"),
-                })
+                    _ => match world_version.name {
+                        Some(ref world_version_name) => {
+                            compare_version_release(&version, world_version_name).await
+                                == Some(std::cmp::Ordering::Less)
+                        }
+                        None => false,
+                    },
+                };
+                if is_downgrade && !force {
+                    return Err(Error {
+                        kind: ErrorKind::BadRequest,
+                        source: eyre!(
+                            "The world was last saved with a newer version ({}) than {}, downgrading may corrupt it. Use force to change the version anyway",
+                            world_version.name.unwrap_or_else(|| "unknown".to_string()),
+                            version
+                        ),
+                    });
+                }
+                self.backup_world(&format!("pre-{version}")).await?;
             }
-        };
-        let lodestone_tmp = path_to_tmp().clone();
-        let temp_dir = tempfile::tempdir_in(lodestone_tmp).context("Failed to create temp dir")?;
-        download_file(
-            &url,
-            temp_dir.path(),
-            Some("server.jar"),
-            &Box::new(|_| {}),
-            true,
-        )
-        .await?;
-        let jar_path = temp_dir.path().join("server.jar");
-        crate::util::fs::rename(jar_path, self.path().await.join("server.jar")).await?;
-        self.config.lock().await.version = version;
-        self.write_config_to_file().await
+
+            crate::util::fs::rename(jar_path, self.path().await.join("server.jar")).await?;
+            self.config.lock().await.version = version;
+            self.write_config_to_file().await
+        }
+        .await;
+        self.transition_maintenance(StateAction::EndMaintenance)
+            .await?;
+        result
     }
 
     async fn configurable_manifest(&self) -> ConfigurableManifest {
@@ -187,6 +254,22 @@ impl TConfigurable for MinecraftInstance {
         setting_id: &str,
         value: ConfigurableValue,
     ) -> Result<(), Error> {
+        if setting_id
+            == ServerPropertySetting::GeneratorSettings(Default::default()).get_identifier()
+        {
+            if self.config.lock().await.has_started {
+                return Err(Error {
+                    kind: ErrorKind::BadRequest,
+                    source: eyre!(
+                        "Generator settings can only be changed before the world is generated"
+                    ),
+                });
+            }
+            validate_generator_settings(value.try_as_string()?)?;
+        }
+        if setting_id == ServerPropertySetting::LevelName(Default::default()).get_identifier() {
+            validate_level_name(value.try_as_string()?)?;
+        }
         let _ = self.read_properties().await;
         self.configurable_manifest
             .lock()
@@ -198,6 +281,834 @@ impl TConfigurable for MinecraftInstance {
     }
 }
 
+/// Checks that `generator-settings` is either empty or valid JSON, as expected by the server
+pub fn validate_generator_settings(generator_settings: &str) -> Result<(), Error> {
+    if generator_settings.trim().is_empty() {
+        return Ok(());
+    }
+    serde_json::from_str::<serde_json::Value>(generator_settings).map_err(|e| Error {
+        kind: ErrorKind::BadRequest,
+        source: eyre!("Generator settings is not valid JSON: {}", e),
+    })?;
+    Ok(())
+}
+
+/// Checks that a `level-name` is a single directory name inside the instance directory
+pub fn validate_level_name(level_name: &str) -> Result<(), Error> {
+    let invalid = |reason: &str| Error {
+        kind: ErrorKind::BadRequest,
+        source: eyre!("Invalid world folder name {:?}: {}", level_name, reason),
+    };
+    if level_name.trim().is_empty() {
+        return Err(invalid("it is empty"));
+    }
+    if level_name == "." || level_name == ".." {
+        return Err(invalid("it refers to a parent or the current directory"));
+    }
+    if level_name
+        .chars()
+        .any(|c| matches!(c, '/' | '\\' | ':') || c.is_control())
+    {
+        return Err(invalid(
+            "it must not contain path separators, ':' or control characters",
+        ));
+    }
+    Ok(())
+}
+
+/// The difficulty of a world and whether it can be changed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct WorldDifficulty {
+    pub difficulty: String,
+    /// Hardcore worlds are locked to hard
+    pub hardcore: bool,
+    pub locked: bool,
+}
+
+impl MinecraftInstance {
+    /// Returns whether the world is hardcore and whether its difficulty is locked
+    ///
+    /// Before the world is generated this goes by the `hardcore` property instead
+    async fn difficulty_lock(&self) -> Result<(bool, bool), Error> {
+        if let Some(lock) = read_world_difficulty_lock(&self.path_to_world().await).await? {
+            return Ok((lock.hardcore, lock.difficulty_locked || lock.hardcore));
+        }
+        let hardcore = self
+            .configurable_manifest
+            .lock()
+            .await
+            .get_unique_setting_key(&ServerPropertySetting::Hardcore(false).get_identifier())
+            .and_then(|v| v.get_value().map(|v| v.try_as_boolean()))
+            .unwrap_or(Ok(false))?;
+        Ok((hardcore, hardcore))
+    }
+
+    async fn difficulty_property(&self) -> Result<String, Error> {
+        self.configurable_manifest
+            .lock()
+            .await
+            .get_unique_setting_key(
+                &ServerPropertySetting::Difficulty(Default::default()).get_identifier(),
+            )
+            .and_then(|v| v.get_value().map(|v| v.try_as_enum().cloned()))
+            .unwrap_or_else(|| Ok(Difficulty::default().to_string()))
+    }
+
+    /// Returns the difficulty, asking the server over RCON if it's running
+    pub async fn get_difficulty(&self) -> Result<WorldDifficulty, Error> {
+        let (hardcore, locked) = self.difficulty_lock().await?;
+        let live_difficulty = if *self.state.lock().await == State::Running {
+            self.send_rcon("difficulty")
+                .await
+                .ok()
+                .and_then(|response| parse_rcon_difficulty(&response))
+        } else {
+            None
+        };
+        let difficulty = match live_difficulty {
+            Some(difficulty) => difficulty,
+            None if hardcore => Difficulty::Hard.to_string(),
+            None => self.difficulty_property().await?,
+        };
+        Ok(WorldDifficulty {
+            difficulty,
+            hardcore,
+            locked,
+        })
+    }
+
+    /// Changes the difficulty, live over RCON if the server is running
+    ///
+    /// Fails on hardcore worlds and worlds with a locked difficulty instead of silently doing
+    /// nothing, as the server would
+    pub async fn set_difficulty(&self, difficulty: &str) -> Result<(), Error> {
+        let difficulty = difficulty.trim().to_lowercase().parse::<Difficulty>()?;
+        let (hardcore, locked) = self.difficulty_lock().await?;
+        if hardcore && difficulty != Difficulty::Hard {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("The world is hardcore, its difficulty is locked to hard"),
+            });
+        }
+        if locked && !hardcore {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("The difficulty of this world is locked"),
+            });
+        }
+        if *self.state.lock().await == State::Running {
+            let difficulty_str = difficulty.to_string();
+            let response = self
+                .send_rcon(&format!("difficulty {}", difficulty_str))
+                .await?;
+            if parse_rcon_difficulty(&response).as_deref() != Some(difficulty_str.as_str()) {
+                return Err(Error {
+                    kind: ErrorKind::Internal,
+                    source: eyre!("Failed to change difficulty: {}", response),
+                });
+            }
+        }
+        // the server applies the property on startup, so keep it in sync either way
+        let _ = self.read_properties().await;
+        self.configurable_manifest.lock().await.set_setting(
+            ServerPropertySetting::get_section_id(),
+            ServerPropertySetting::Difficulty(difficulty).into(),
+        )?;
+        self.write_properties_to_file().await
+    }
+}
+
+/// Resource packs larger than this are rejected by the client
+const MAX_RESOURCE_PACK_SIZE: u64 = 250 * 1024 * 1024;
+
+impl MinecraftInstance {
+    /// Points clients at the resource pack at `url`
+    ///
+    /// The pack is downloaded to check that it is a zip and to compute the SHA1 clients verify
+    /// it against, then `resource-pack`, `resource-pack-sha1` and `require-resource-pack` are set
+    pub async fn set_resource_pack(&self, url: &str, required: bool) -> Result<(), Error> {
+        let parsed_url = url::Url::parse(url).map_err(|e| Error {
+            kind: ErrorKind::BadRequest,
+            source: eyre!("Invalid resource pack URL: {}", e),
+        })?;
+        if !["http", "https"].contains(&parsed_url.scheme()) {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("Resource pack URL must be http or https"),
+            });
+        }
+        let response = reqwest::get(parsed_url)
+            .await
+            .context("Failed to download resource pack")?
+            .error_for_status()
+            .context("Failed to download resource pack")?;
+        let mut hasher = Sha1::new();
+        let mut header = Vec::new();
+        let mut size: u64 = 0;
+        let mut stream = response.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.context("Failed to download resource pack")?;
+            if header.len() < 4 {
+                header.extend(chunk.iter().take(4 - header.len()));
+            }
+            size += chunk.len() as u64;
+            if size > MAX_RESOURCE_PACK_SIZE {
+                return Err(Error {
+                    kind: ErrorKind::BadRequest,
+                    source: eyre!("Resource pack is larger than 250 MiB"),
+                });
+            }
+            hasher.update(&chunk);
+        }
+        // every zip archive starts with a local file header
+        if !header.starts_with(b"PK\x03\x04") {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("Resource pack URL does not point to a zip file"),
+            });
+        }
+        let sha1 = format!("{:x}", hasher.finalize());
+
+        let _ = self.read_properties().await;
+        {
+            let mut configurable_manifest = self.configurable_manifest.lock().await;
+            for setting in [
+                ServerPropertySetting::ResourcePack(url.to_string()),
+                ServerPropertySetting::ResourcePackSha1(sha1),
+                ServerPropertySetting::RequireResourcePack(required),
+            ] {
+                configurable_manifest
+                    .set_setting(ServerPropertySetting::get_section_id(), setting.into())?;
+            }
+        }
+        self.write_properties_to_file().await
+    }
+}
+
+/// The server properties of the manifest as they are written to server.properties
+fn property_values(configurable_manifest: &ConfigurableManifest) -> Vec<(String, String)> {
+    configurable_manifest
+        .get_section(ServerPropertySetting::get_section_id())
+        .unwrap()
+        .all_settings()
+        .iter()
+        .map(|(key, value)| {
+            (
+                key.clone(),
+                value
+                    .get_value()
+                    .expect("Programming error, value is not set")
+                    .to_string(),
+            )
+        })
+        .collect()
+}
+
+/// Renders the server properties section of the manifest as the contents of server.properties
+pub(super) fn properties_to_string(configurable_manifest: &ConfigurableManifest) -> String {
+    let mut setting_str = "".to_string();
+    for (key, value) in property_values(configurable_manifest) {
+        // print the key and value separated by a =
+        setting_str.push_str(&format!("{}={}\n", key, value));
+    }
+    setting_str
+}
+
+/// Identifies the current contents of server.properties, any change to a property changes it
+fn properties_token(configurable_manifest: &ConfigurableManifest) -> String {
+    format!(
+        "{:x}",
+        Sha1::digest(properties_to_string(configurable_manifest).as_bytes())
+    )
+}
+
+impl MinecraftInstance {
+    /// Starts the server with the whitelist enabled so only staff can join, showing `motd` in
+    /// the server list if given
+    ///
+    /// The whitelist and MOTD are remembered until `end_maintenance` puts them back. If the start
+    /// fails the instance stays in maintenance mode, so it isn't opened up to everyone by accident
+    pub async fn start_maintenance(
+        &self,
+        motd: Option<String>,
+        caused_by: CausedBy,
+    ) -> Result<(), Error> {
+        if self.config.lock().await.maintenance_restore.is_some() {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("The instance is already in maintenance mode"),
+            });
+        }
+        if *self.state.lock().await != State::Stopped {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("The server must be stopped to start it in maintenance mode"),
+            });
+        }
+        if motd
+            .as_ref()
+            .map_or(false, |motd| motd.contains(['\n', '\r']))
+        {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("The MOTD must be a single line"),
+            });
+        }
+        let _ = self.read_properties().await;
+        let maintenance_restore = {
+            let mut configurable_manifest = self.configurable_manifest.lock().await;
+            let white_list = configurable_manifest
+                .get_unique_setting_key(&ServerPropertySetting::WhiteList(false).get_identifier())
+                .and_then(|v| v.get_value().map(|v| v.try_as_boolean()))
+                .unwrap_or(Ok(false))?;
+            let previous_motd = configurable_manifest
+                .get_unique_setting_key(
+                    &ServerPropertySetting::Motd(Default::default()).get_identifier(),
+                )
+                .and_then(|v| v.get_value().map(|v| v.try_as_string().cloned()))
+                .unwrap_or_else(|| Ok(String::new()))?;
+            configurable_manifest.set_setting(
+                ServerPropertySetting::get_section_id(),
+                ServerPropertySetting::WhiteList(true).into(),
+            )?;
+            if let Some(motd) = motd {
+                configurable_manifest.set_setting(
+                    ServerPropertySetting::get_section_id(),
+                    ServerPropertySetting::Motd(motd).into(),
+                )?;
+            }
+            self.write_properties_from_manifest(&configurable_manifest)
+                .await?;
+            MaintenanceRestore {
+                white_list,
+                motd: previous_motd,
+            }
+        };
+        self.config.lock().await.maintenance_restore = Some(maintenance_restore);
+        self.write_config_to_file().await?;
+        self.start(caused_by, false).await
+    }
+
+    /// Puts back the whitelist and MOTD from before `start_maintenance`
+    ///
+    /// A running server has its whitelist turned off right away if it was off before, the MOTD
+    /// only changes on the next start
+    pub async fn end_maintenance(&self, caused_by: CausedBy) -> Result<(), Error> {
+        let maintenance_restore = self
+            .config
+            .lock()
+            .await
+            .maintenance_restore
+            .clone()
+            .ok_or_else(|| Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("The instance is not in maintenance mode"),
+            })?;
+        // the server rewrites server.properties when the whitelist is toggled, so the command
+        // has to be processed before the restored properties are written
+        if !maintenance_restore.white_list && *self.state.lock().await == State::Running {
+            self.send_command_confirmed("whitelist off", caused_by)
+                .await?;
+        }
+        let _ = self.read_properties().await;
+        {
+            let mut configurable_manifest = self.configurable_manifest.lock().await;
+            for setting in [
+                ServerPropertySetting::WhiteList(maintenance_restore.white_list),
+                ServerPropertySetting::Motd(maintenance_restore.motd),
+            ] {
+                configurable_manifest
+                    .set_setting(ServerPropertySetting::get_section_id(), setting.into())?;
+            }
+            self.write_properties_from_manifest(&configurable_manifest)
+                .await?;
+        }
+        self.config.lock().await.maintenance_restore = None;
+        self.write_config_to_file().await
+    }
+}
+
+/// The manifest together with the token of the properties it was read with
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct PropertiesSnapshot {
+    pub manifest: ConfigurableManifest,
+    pub token: String,
+}
+
+/// Changes to the server properties, made against the properties identified by `token`
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct PropertiesUpdate {
+    pub token: String,
+    pub changes: BTreeMap<String, String>,
+}
+
+impl MinecraftInstance {
+    /// Reads server.properties as it is now, with the token `update_properties` must be given
+    pub async fn properties_snapshot(&self) -> PropertiesSnapshot {
+        let _ = self.read_properties().await;
+        let configurable_manifest = self.configurable_manifest.lock().await;
+        PropertiesSnapshot {
+            manifest: configurable_manifest.clone(),
+            token: properties_token(&configurable_manifest),
+        }
+    }
+
+    /// Sets several server properties at once
+    ///
+    /// Every change is validated against its typed key first, so either all of them are
+    /// written or none are. `token` is the one of the snapshot the changes were made against,
+    /// if the properties changed since then nothing is written and a conflict is returned
+    pub async fn update_properties(
+        &self,
+        token: &str,
+        changes: BTreeMap<String, String>,
+    ) -> Result<(), Error> {
+        let version = self.config.lock().await.version.clone();
+        let mut settings = Vec::with_capacity(changes.len());
+        for (key, value) in changes.iter() {
+            if key.is_empty()
+                || key.contains(|c: char| c == '=' || c == ':' || c.is_whitespace())
+                || value.contains(['\n', '\r'])
+            {
+                return Err(Error {
+                    kind: ErrorKind::BadRequest,
+                    source: eyre!("Invalid property {:?} = {:?}", key, value),
+                });
+            }
+            let setting = ServerPropertySetting::from_key_val(key, value).map_err(|e| Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("Invalid value for property {}: {}", key, e.source),
+            })?;
+            if !setting.is_supported_by(&version) {
+                return Err(Error {
+                    kind: ErrorKind::BadRequest,
+                    source: eyre!("Property {} is not supported by version {}", key, version),
+                });
+            }
+            if let ServerPropertySetting::GeneratorSettings(ref generator_settings) = setting {
+                if self.config.lock().await.has_started {
+                    return Err(Error {
+                        kind: ErrorKind::BadRequest,
+                        source: eyre!(
+                            "Generator settings can only be changed before the world is generated"
+                        ),
+                    });
+                }
+                validate_generator_settings(generator_settings)?;
+            }
+            settings.push(setting);
+        }
+
+        let _ = self.read_properties().await;
+        {
+            // held until the file is written so that concurrent updates can't both pass the check
+            let mut configurable_manifest = self.configurable_manifest.lock().await;
+            if properties_token(&configurable_manifest) != token {
+                return Err(Error {
+                    kind: ErrorKind::Conflict,
+                    source: eyre!(
+                        "The properties were changed since they were read, reload them and try again"
+                    ),
+                });
+            }
+            // applied to a copy so that a failure leaves the current properties untouched
+            let mut updated_manifest = configurable_manifest.clone();
+            for setting in settings {
+                updated_manifest
+                    .set_setting(ServerPropertySetting::get_section_id(), setting.into())?;
+            }
+            *configurable_manifest = updated_manifest;
+            self.write_properties_from_manifest(&configurable_manifest)
+                .await?;
+        }
+        self.sync_configurable_to_restore_config().await;
+        self.write_config_to_file().await
+    }
+
+    /// Lists the properties whose value differs from what a vanilla server of the installed
+    /// version generates
+    pub async fn properties_diff(&self) -> Vec<PropertyDiff> {
+        let _ = self.read_properties().await;
+        let version = self.config.lock().await.version.clone();
+        diff_properties(
+            property_values(&*self.configurable_manifest.lock().await),
+            &version,
+        )
+    }
+}
+
+/// Values the newest vanilla server writes to a freshly generated server.properties
+const DEFAULT_PROPERTIES: &[(&str, &str)] = &[
+    ("accepts-transfers", "false"),
+    ("allow-flight", "false"),
+    ("allow-nether", "true"),
+    ("broadcast-console-to-ops", "true"),
+    ("broadcast-rcon-to-ops", "true"),
+    ("bug-report-link", ""),
+    ("difficulty", "easy"),
+    ("enable-command-block", "false"),
+    ("enable-jmx-monitoring", "false"),
+    ("enable-query", "false"),
+    ("enable-rcon", "false"),
+    ("enable-status", "true"),
+    ("enforce-secure-profile", "true"),
+    ("enforce-whitelist", "false"),
+    ("entity-broadcast-range-percentage", "100"),
+    ("force-gamemode", "false"),
+    ("function-permission-level", "2"),
+    ("gamemode", "survival"),
+    ("generate-structures", "true"),
+    ("generator-settings", "{}"),
+    ("hardcore", "false"),
+    ("hide-online-players", "false"),
+    ("initial-disabled-packs", ""),
+    ("initial-enabled-packs", "vanilla"),
+    ("level-name", "world"),
+    ("level-seed", ""),
+    ("level-type", "minecraft\\:normal"),
+    ("log-ips", "true"),
+    ("max-build-height", "256"),
+    ("max-chained-neighbor-updates", "1000000"),
+    ("max-players", "20"),
+    ("max-tick-time", "60000"),
+    ("max-world-size", "29999984"),
+    ("motd", "A Minecraft Server"),
+    ("network-compression-threshold", "256"),
+    ("online-mode", "true"),
+    ("op-permission-level", "4"),
+    ("pause-when-empty-seconds", "60"),
+    ("player-idle-timeout", "0"),
+    ("prevent-proxy-connections", "false"),
+    ("previews-chat", "false"),
+    ("pvp", "true"),
+    ("query.port", "25565"),
+    ("rate-limit", "0"),
+    ("rcon.password", ""),
+    ("rcon.port", "25575"),
+    ("region-file-compression", "deflate"),
+    ("require-resource-pack", "false"),
+    ("resource-pack", ""),
+    ("resource-pack-id", ""),
+    ("resource-pack-prompt", ""),
+    ("resource-pack-sha1", ""),
+    ("server-ip", ""),
+    ("server-port", "25565"),
+    ("simulation-distance", "10"),
+    ("spawn-animals", "true"),
+    ("spawn-monsters", "true"),
+    ("spawn-npcs", "true"),
+    ("spawn-protection", "16"),
+    ("sync-chunk-writes", "true"),
+    ("text-filtering-config", ""),
+    ("use-native-transport", "true"),
+    ("view-distance", "10"),
+    ("white-list", "false"),
+];
+
+/// Defaults of older servers that differ from `DEFAULT_PROPERTIES`, with the version the default
+/// changed in, oldest first
+const CHANGED_DEFAULT_PROPERTIES: &[(&str, &str, [u32; 3])] = &[
+    ("difficulty", "1", [1, 14, 0]),
+    ("gamemode", "0", [1, 14, 0]),
+    ("level-type", "DEFAULT", [1, 13, 0]),
+    ("level-type", "default", [1, 19, 0]),
+];
+
+/// What a vanilla server of `version` writes for `key`, `None` for keys vanilla doesn't generate
+///
+/// Snapshots and other versions that aren't releases get the newest defaults
+fn default_property(key: &str, version: &str) -> Option<&'static str> {
+    let changed_default = parse_release_version(version).and_then(|version| {
+        CHANGED_DEFAULT_PROPERTIES
+            .iter()
+            .find(|(changed_key, _, changed_in)| *changed_key == key && version < *changed_in)
+    });
+    match changed_default {
+        Some((_, default, _)) => Some(*default),
+        None => DEFAULT_PROPERTIES
+            .iter()
+            .find(|(default_key, _)| *default_key == key)
+            .map(|(_, default)| *default),
+    }
+}
+
+/// A property set to something other than its vanilla default
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct PropertyDiff {
+    pub key: String,
+    /// `None` for properties vanilla doesn't generate, e.g. ones added by mods
+    pub default: Option<String>,
+    pub current: String,
+}
+
+fn diff_properties(properties: Vec<(String, String)>, version: &str) -> Vec<PropertyDiff> {
+    properties
+        .into_iter()
+        .filter_map(|(key, current)| {
+            let default = default_property(&key, version).map(|default| default.to_string());
+            if default.as_ref() == Some(&current) {
+                return None;
+            }
+            Some(PropertyDiff {
+                key,
+                default,
+                current,
+            })
+        })
+        .collect()
+}
+
+/// A newer build of the installed flavour for the same minecraft version
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct UpdateInfo {
+    pub build: String,
+    /// `None` if the flavour doesn't publish changelogs
+    pub changelog: Option<String>,
+}
+
+/// What's installed next to the newest builds and versions, for the dashboard's "is my server up
+/// to date?" check
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct VersionSummary {
+    pub flavour: String,
+    pub installed_version: String,
+    pub installed_build: Option<String>,
+    /// Newest build for the installed minecraft version, `None` for flavours built once per
+    /// version
+    pub latest_build: Option<String>,
+    /// Newest minecraft release the flavour supports
+    pub latest_version: Option<String>,
+    pub build_outdated: bool,
+    pub version_outdated: bool,
+}
+
+/// How long a looked up build is reused before asking the flavour's API again
+const LATEST_BUILD_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+static LATEST_BUILD_CACHE: Lazy<Mutex<HashMap<(FlavourKind, String), (Instant, Option<String>)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// The newest build of the flavour for the minecraft version
+///
+/// Only Paper, Forge and NeoForge are built more than once per version, other flavours return
+/// `None`
+async fn latest_build(flavour: FlavourKind, version: &str) -> Result<Option<String>, Error> {
+    let key = (flavour, version.to_string());
+    if let Some((fetched_at, build)) = LATEST_BUILD_CACHE.lock().await.get(&key) {
+        if fetched_at.elapsed() < LATEST_BUILD_CACHE_TTL {
+            return Ok(build.clone());
+        }
+    }
+    let build = match flavour {
+        FlavourKind::Paper => match get_paper_jar_url(version, &None).await {
+            Some((
+                _,
+                Flavour::Paper {
+                    build_version: Some(PaperBuildVersion(build)),
+                },
+            )) => Some(build.to_string()),
+            Some(_) => None,
+            None => {
+                return Err(Error {
+                    kind: ErrorKind::Internal,
+                    source: eyre!(
+                        "Failed to get the latest paper build for version {}",
+                        version
+                    ),
+                })
+            }
+        },
+        FlavourKind::Forge => match get_forge_jar_url(version, &None).await? {
+            (
+                _,
+                Flavour::Forge {
+                    build_version: Some(ForgeBuildVersion(build)),
+                },
+            ) => Some(build),
+            _ => None,
+        },
+        FlavourKind::NeoForge => match get_neoforge_jar_url(version, &None).await? {
+            (
+                _,
+                Flavour::NeoForge {
+                    build_version: Some(ForgeBuildVersion(build)),
+                },
+            ) => Some(build),
+            _ => None,
+        },
+        _ => None,
+    };
+    LATEST_BUILD_CACHE
+        .lock()
+        .await
+        .insert(key, (Instant::now(), build.clone()));
+    Ok(build)
+}
+
+/// Flavours `update_build` can update, forge builds have to be run through their installer
+fn supports_build_update(flavour: FlavourKind) -> bool {
+    matches!(flavour, FlavourKind::Paper)
+}
+
+/// Paper numbers its builds, forge builds are only ever replaced by newer ones
+fn is_newer_build(installed: &str, latest: &str) -> bool {
+    match (installed.parse::<i64>(), latest.parse::<i64>()) {
+        (Ok(installed), Ok(latest)) => latest > installed,
+        _ => installed != latest,
+    }
+}
+
+/// Snapshots and pre-releases are left out, e.g. `1.20.4` but not `24w14a` or `1.21-pre1`
+fn is_release_version(version: &str) -> bool {
+    !version.is_empty() && version.chars().all(|c| c.is_ascii_digit() || c == '.')
+}
+
+/// The newest release in `versions`, and whether `installed` comes before it
+///
+/// `versions` is ordered newest first, a version missing from it is never considered outdated
+fn latest_release(versions: &[String], installed: &str) -> (Option<String>, bool) {
+    let latest = match versions
+        .iter()
+        .position(|version| is_release_version(version))
+    {
+        Some(latest) => latest,
+        None => return (None, false),
+    };
+    let outdated = versions
+        .iter()
+        .position(|version| version == installed)
+        .map_or(false, |installed| installed > latest);
+    (Some(versions[latest].clone()), outdated)
+}
+
+impl MinecraftInstance {
+    /// Checks the flavour's API for a build newer than the installed one, without changing the
+    /// minecraft version
+    ///
+    /// Only flavours `update_build` can update are checked, other flavours return `None`
+    pub async fn available_update(&self) -> Result<Option<UpdateInfo>, Error> {
+        let config = self.config.lock().await.clone();
+        let flavour = FlavourKind::from(&config.flavour);
+        if !supports_build_update(flavour) {
+            return Ok(None);
+        }
+        let latest_build = match (
+            self.version_info().await.configured_build,
+            latest_build(flavour, &config.version).await?,
+        ) {
+            (Some(installed), Some(latest)) if is_newer_build(&installed, &latest) => latest,
+            _ => return Ok(None),
+        };
+        // the changelog is only informative, so failing to fetch it doesn't hide the update
+        let changelog = get_build_changelog(&flavour, &config.version, &latest_build)
+            .await
+            .ok()
+            .flatten();
+        Ok(Some(UpdateInfo {
+            build: latest_build,
+            changelog,
+        }))
+    }
+
+    /// Combines the installed version and build with the newest ones available, the upstream
+    /// lookups are cached so the dashboard can poll this
+    pub async fn get_flavour_version_summary(&self) -> Result<VersionSummary, Error> {
+        let flavour = FlavourKind::from(&self.config.lock().await.flavour);
+        let version_info = self.version_info().await;
+        let latest_build = latest_build(flavour, &version_info.configured_version).await?;
+        let build_outdated = match (&version_info.configured_build, &latest_build) {
+            (Some(installed), Some(latest)) => is_newer_build(installed, latest),
+            _ => false,
+        };
+        let (latest_version, version_outdated) = latest_release(
+            &MinecraftInstance::get_versions(&flavour).await?,
+            &version_info.configured_version,
+        );
+        Ok(VersionSummary {
+            flavour: flavour.to_string(),
+            installed_version: version_info.configured_version,
+            installed_build: version_info.configured_build,
+            latest_build,
+            latest_version,
+            build_outdated,
+            version_outdated,
+        })
+    }
+
+    /// Replaces the server jar with the newest build of the installed minecraft version, backing
+    /// up the world first
+    ///
+    /// Unlike `change_version` the minecraft version and worlds are left alone. Only Paper is
+    /// supported, forge builds have to be run through their installer
+    pub async fn update_build(&self) -> Result<(), Error> {
+        let installed_build = match &self.config.lock().await.flavour {
+            Flavour::Paper { build_version } => build_version.clone(),
+            _ => {
+                return Err(Error {
+                    kind: ErrorKind::UnsupportedOperation,
+                    source: eyre!("Updating the build is only supported for paper servers"),
+                })
+            }
+        };
+        // keeps the server from being started while its jar is being replaced
+        self.transition_maintenance(StateAction::BeginUpdate)
+            .await?;
+        let result: Result<(), Error> = async {
+            let version = self.config.lock().await.version.clone();
+            let (url, flavour) = get_paper_jar_url(&version, &None)
+                .await
+                .ok_or_else(|| Error {
+                    kind: ErrorKind::Internal,
+                    source: eyre!(
+                        "Failed to get the latest paper build for version {}",
+                        version
+                    ),
+                })?;
+            if let (
+                Some(PaperBuildVersion(installed)),
+                Flavour::Paper {
+                    build_version: Some(PaperBuildVersion(latest)),
+                },
+            ) = (&installed_build, &flavour)
+            {
+                if latest <= installed {
+                    return Ok(());
+                }
+            }
+            let temp_dir =
+                tempfile::tempdir_in(path_to_tmp().clone()).context("Failed to create temp dir")?;
+            download_file(
+                &url,
+                temp_dir.path(),
+                Some("server.jar"),
+                &Box::new(|_| {}),
+                true,
+            )
+            .await?;
+            if self.path_to_world().await.is_dir() {
+                self.backup_world("pre-build-update").await?;
+            }
+            crate::util::fs::rename(
+                temp_dir.path().join("server.jar"),
+                self.path().await.join("server.jar"),
+            )
+            .await?;
+            self.config.lock().await.flavour = flavour;
+            self.write_config_to_file().await
+        }
+        .await;
+        self.transition_maintenance(StateAction::EndMaintenance)
+            .await?;
+        result
+    }
+}
+
 pub(super) enum InstanceSetting {
     CmdArg(CmdArgSetting),
     ServerProperty(ServerPropertySetting),
@@ -503,6 +1414,24 @@ impl FromStr for Difficulty {
     }
 }
 
+const REGION_FILE_COMPRESSIONS: [&str; 3] = ["deflate", "lz4", "none"];
+
+/// Parses a release version like `1.20.4` into its components, `None` for snapshots
+fn parse_release_version(version: &str) -> Option<[u32; 3]> {
+    let mut components = [0; 3];
+    let mut parts = version.split('.');
+    for component in components.iter_mut() {
+        match parts.next() {
+            Some(part) => *component = part.parse().ok()?,
+            None => break,
+        }
+    }
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(components)
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(super) enum ServerPropertySetting {
     EnableJmxMonitoring(bool),
@@ -563,6 +1492,11 @@ pub(super) enum ServerPropertySetting {
     ResourcePackSha1(String),
     MaxWorldSize(u32),
     MaxBuildHeight(u32),
+    LogIps(bool),
+    AcceptsTransfers(bool),
+    RegionFileCompression(String),
+    BugReportLink(String),
+    PauseWhenEmptySeconds(u32),
     Unknown(String, String),
 }
 
@@ -1130,6 +2064,59 @@ impl From<ServerPropertySetting> for SettingManifest {
                 false,
                 true,
             ),
+            ServerPropertySetting::LogIps(inner_val) => Self::new_required_value(
+                value.get_identifier(),
+                value.get_name(),
+                value.get_description(),
+                ConfigurableValue::Boolean(inner_val),
+                None,
+                false,
+                true,
+            ),
+            ServerPropertySetting::AcceptsTransfers(inner_val) => Self::new_required_value(
+                value.get_identifier(),
+                value.get_name(),
+                value.get_description(),
+                ConfigurableValue::Boolean(inner_val),
+                None,
+                false,
+                true,
+            ),
+            ServerPropertySetting::RegionFileCompression(ref inner_val) => {
+                Self::new_value_with_type(
+                    value.get_identifier(),
+                    value.get_name(),
+                    value.get_description(),
+                    Some(ConfigurableValue::Enum(inner_val.clone())),
+                    ConfigurableValueType::Enum {
+                        options: REGION_FILE_COMPRESSIONS
+                            .iter()
+                            .map(|option| option.to_string())
+                            .collect(),
+                    },
+                    None,
+                    false,
+                    true,
+                )
+            }
+            ServerPropertySetting::BugReportLink(ref inner_val) => Self::new_required_value(
+                value.get_identifier(),
+                value.get_name(),
+                value.get_description(),
+                ConfigurableValue::String(inner_val.clone()),
+                None,
+                false,
+                true,
+            ),
+            ServerPropertySetting::PauseWhenEmptySeconds(inner_val) => Self::new_required_value(
+                value.get_identifier(),
+                value.get_name(),
+                value.get_description(),
+                ConfigurableValue::UnsignedInteger(inner_val),
+                None,
+                false,
+                true,
+            ),
         }
     }
 }
@@ -1173,23 +2160,19 @@ impl TryFrom<SettingManifest> for ServerPropertySetting {
             "enable-query" => Ok(ServerPropertySetting::EnableQuery(
                 value.get_value().context(err_msg)?.try_as_boolean()?,
             )),
-            "generator-settings" => Ok(ServerPropertySetting::GeneratorSettings(
-                value
-                    .get_value()
-                    .context(err_msg)?
-                    .try_as_string()?
-                    .to_string(),
-            )),
+            "generator-settings" => {
+                let generator_settings = value.get_value().context(err_msg)?.try_as_string()?;
+                validate_generator_settings(generator_settings)?;
+                Ok(ServerPropertySetting::GeneratorSettings(generator_settings.to_string()))
+            }
             "enforce-secure-profile" => Ok(ServerPropertySetting::EnforceSecureProfile(
                 value.get_value().context(err_msg)?.try_as_boolean()?,
             )),
-            "level-name" => Ok(ServerPropertySetting::LevelName(
-                value
-                    .get_value()
-                    .context(err_msg)?
-                    .try_as_string()?
-                    .to_string(),
-            )),
+            "level-name" => {
+                let level_name = value.get_value().context(err_msg)?.try_as_string()?;
+                validate_level_name(level_name)?;
+                Ok(ServerPropertySetting::LevelName(level_name.to_string()))
+            }
             "motd" => Ok(ServerPropertySetting::Motd(
                 value
                     .get_value()
@@ -1406,6 +2389,29 @@ impl TryFrom<SettingManifest> for ServerPropertySetting {
                     .context(err_msg)?
                     .try_as_unsigned_integer()?,
             )),
+            "log-ips" => Ok(ServerPropertySetting::LogIps(
+                value.get_value().context(err_msg)?.try_as_boolean()?,
+            )),
+            "accepts-transfers" => Ok(ServerPropertySetting::AcceptsTransfers(
+                value.get_value().context(err_msg)?.try_as_boolean()?,
+            )),
+            "region-file-compression" => Ok(ServerPropertySetting::from_key_val(
+                "region-file-compression",
+                value.get_value().context(err_msg)?.try_as_enum()?,
+            )?),
+            "bug-report-link" => Ok(ServerPropertySetting::BugReportLink(
+                value
+                    .get_value()
+                    .context(err_msg)?
+                    .try_as_string()?
+                    .to_string(),
+            )),
+            "pause-when-empty-seconds" => Ok(ServerPropertySetting::PauseWhenEmptySeconds(
+                value
+                    .get_value()
+                    .context(err_msg)?
+                    .try_as_unsigned_integer()?,
+            )),
             _ => Ok(ServerPropertySetting::Unknown(
                 value.get_identifier().to_string(),
                 value.get_value().context(err_msg)?.to_string(),
@@ -1419,6 +2425,28 @@ impl ServerPropertySetting {
         "server_properties_section"
     }
 
+    /// The first release that reads this property, `None` if it predates the oldest supported
+    /// release
+    fn introduced_in(&self) -> Option<[u32; 3]> {
+        match self {
+            Self::LogIps(_) => Some([1, 20, 2]),
+            Self::AcceptsTransfers(_) | Self::RegionFileCompression(_) => Some([1, 20, 5]),
+            Self::BugReportLink(_) => Some([1, 21, 0]),
+            Self::PauseWhenEmptySeconds(_) => Some([1, 21, 2]),
+            _ => None,
+        }
+    }
+
+    /// Whether a server of `version` reads this property
+    ///
+    /// Snapshots and other versions that aren't releases are assumed to support every property
+    pub fn is_supported_by(&self, version: &str) -> bool {
+        match (self.introduced_in(), parse_release_version(version)) {
+            (Some(introduced_in), Some(version)) => version >= introduced_in,
+            _ => true,
+        }
+    }
+
     pub fn get_identifier(&self) -> String {
         match self {
             Self::EnableJmxMonitoring(_) => "enable-jmx-monitoring",
@@ -1479,6 +2507,11 @@ impl ServerPropertySetting {
             Self::ResourcePackSha1(_) => "resource-pack-sha1",
             Self::MaxWorldSize(_) => "max-world-size",
             Self::MaxBuildHeight(_) => "max-build-height",
+            Self::LogIps(_) => "log-ips",
+            Self::AcceptsTransfers(_) => "accepts-transfers",
+            Self::RegionFileCompression(_) => "region-file-compression",
+            Self::BugReportLink(_) => "bug-report-link",
+            Self::PauseWhenEmptySeconds(_) => "pause-when-empty-seconds",
             Self::Unknown(key, _) => key,
         }
         .to_string()
@@ -1552,6 +2585,11 @@ impl ServerPropertySetting {
             Self::ResourcePackSha1(_) => "Resource Pack Sha1",
             Self::MaxWorldSize(_) => "Max World Size",
             Self::MaxBuildHeight(_) => "Max Build Height",
+            Self::LogIps(_) => "Log Ips",
+            Self::AcceptsTransfers(_) => "Accepts Transfers",
+            Self::RegionFileCompression(_) => "Region File Compression",
+            Self::BugReportLink(_) => "Bug Report Link",
+            Self::PauseWhenEmptySeconds(_) => "Pause When Empty Seconds",
             Self::Unknown(_, _) => unreachable!("Handled above"),
         }
         .to_string()
@@ -1623,6 +2661,11 @@ impl ServerPropertySetting {
             Self::ResourcePackSha1(_) => "The SHA1 hash of the resource pack that will be used by default.",
             Self::MaxWorldSize(_) => "The maximum size of the world in blocks.",
             Self::MaxBuildHeight(_) => "The maximum height of the world in blocks.",
+            Self::LogIps(_) => "Log the IP addresses of players when they join.",
+            Self::AcceptsTransfers(_) => "Accept players transferred from other servers.",
+            Self::RegionFileCompression(_) => "The compression algorithm used for region files.",
+            Self::BugReportLink(_) => "The link shown to players in the disconnect screen to report bugs.",
+            Self::PauseWhenEmptySeconds(_) => "Pause the server after it has been empty for this many seconds, 0 never pauses.",
             Self::Unknown(_, _) => unreachable!("Already handled above.")
         }.to_string()
     }
@@ -1877,6 +2920,31 @@ impl ServerPropertySetting {
                     || eyre!("Invalid value: {value} for \"max-build-height\", expected u32"),
                 )?))
             }
+            "log-ips" => {
+                Ok(Self::LogIps(value.parse::<bool>().with_context(
+                    || eyre!("Invalid value: {value} for \"log-ips\", expected bool"),
+                )?))
+            }
+            "accepts-transfers" => {
+                Ok(Self::AcceptsTransfers(value.parse::<bool>().with_context(
+                    || eyre!("Invalid value: {value} for \"accepts-transfers\", expected bool"),
+                )?))
+            }
+            "region-file-compression" => {
+                if !REGION_FILE_COMPRESSIONS.contains(&value) {
+                    return Err(Error {
+                        kind: ErrorKind::BadRequest,
+                        source: eyre!("Invalid value: {value} for \"region-file-compression\", expected one of: deflate, lz4, none"),
+                    });
+                }
+                Ok(Self::RegionFileCompression(value.to_string()))
+            }
+            "bug-report-link" => Ok(Self::BugReportLink(value.to_string())),
+            "pause-when-empty-seconds" => {
+                Ok(Self::PauseWhenEmptySeconds(value.parse::<u32>().with_context(
+                    || eyre!("Invalid value: {value} for \"pause-when-empty-seconds\", expected u32"),
+                )?))
+            }
             _ => Ok(Self::Unknown(key.to_string(), value.to_string())),
         }
     }
@@ -1941,6 +3009,11 @@ impl ServerPropertySetting {
             Self::ResourcePackSha1(v) => format!("{}={}", self.get_identifier(), v),
             Self::MaxWorldSize(v) => format!("{}={}", self.get_identifier(), v),
             Self::MaxBuildHeight(v) => format!("{}={}", self.get_identifier(), v),
+            Self::LogIps(v) => format!("{}={}", self.get_identifier(), v),
+            Self::AcceptsTransfers(v) => format!("{}={}", self.get_identifier(), v),
+            Self::RegionFileCompression(v) => format!("{}={}", self.get_identifier(), v),
+            Self::BugReportLink(v) => format!("{}={}", self.get_identifier(), v),
+            Self::PauseWhenEmptySeconds(v) => format!("{}={}", self.get_identifier(), v),
             Self::Unknown(_k, v) => format!("{}={}", self.get_identifier(), v),
         }
     }
@@ -1970,6 +3043,41 @@ mod test {
 
     use super::*;
 
+    #[test]
+    fn test_is_newer_build() {
+        assert!(is_newer_build("400", "455"));
+        assert!(!is_newer_build("455", "455"));
+        assert!(!is_newer_build("455", "400"));
+        assert!(is_newer_build("1.20.4-49.0.3", "1.20.4-49.0.31"));
+        assert!(!is_newer_build("1.20.4-49.0.3", "1.20.4-49.0.3"));
+    }
+
+    #[test]
+    fn test_latest_release() {
+        let versions: Vec<String> = ["24w14a", "1.20.5-pre1", "1.20.4", "1.20.3", "23w45a"]
+            .iter()
+            .map(|version| version.to_string())
+            .collect();
+        assert_eq!(
+            latest_release(&versions, "1.20.3"),
+            (Some("1.20.4".to_string()), true)
+        );
+        assert_eq!(
+            latest_release(&versions, "1.20.4"),
+            (Some("1.20.4".to_string()), false)
+        );
+        // a snapshot newer than the latest release isn't behind it
+        assert_eq!(
+            latest_release(&versions, "24w14a"),
+            (Some("1.20.4".to_string()), false)
+        );
+        assert_eq!(
+            latest_release(&versions, "1.7.10"),
+            (Some("1.20.4".to_string()), false)
+        );
+        assert_eq!(latest_release(&[], "1.20.4"), (None, false));
+    }
+
     #[test]
     fn test_parse_server_properties() {
         let properties =
@@ -1993,6 +3101,42 @@ mod test {
         assert_eq!(res[3], ServerPropertySetting::Difficulty(Difficulty::Easy));
     }
 
+    #[test]
+    fn test_property_version_support() {
+        let pause_when_empty = ServerPropertySetting::PauseWhenEmptySeconds(60);
+        assert!(pause_when_empty.is_supported_by("1.21.2"));
+        assert!(pause_when_empty.is_supported_by("1.21.10"));
+        assert!(!pause_when_empty.is_supported_by("1.21"));
+        assert!(!pause_when_empty.is_supported_by("1.20.6"));
+        assert!(pause_when_empty.is_supported_by("24w33a"));
+        assert!(ServerPropertySetting::LogIps(true).is_supported_by("1.20.2"));
+        assert!(!ServerPropertySetting::LogIps(true).is_supported_by("1.19.4"));
+        assert!(ServerPropertySetting::Pvp(true).is_supported_by("1.8.9"));
+        assert!(ServerPropertySetting::from_key_val("region-file-compression", "zstd").is_err());
+    }
+
+    #[test]
+    fn test_validate_generator_settings() {
+        assert!(validate_generator_settings("").is_ok());
+        assert!(validate_generator_settings("{}").is_ok());
+        assert!(validate_generator_settings(
+            r#"{"layers":[{"block":"minecraft:bedrock","height":1},{"block":"minecraft:dirt","height":2}],"biome":"minecraft:plains"}"#
+        )
+        .is_ok());
+        assert!(validate_generator_settings("{\"layers\": [").is_err());
+    }
+
+    #[test]
+    fn test_validate_level_name() {
+        assert!(validate_level_name("world").is_ok());
+        assert!(validate_level_name("My World 2").is_ok());
+        assert!(validate_level_name("").is_err());
+        assert!(validate_level_name("..").is_err());
+        assert!(validate_level_name("../other").is_err());
+        assert!(validate_level_name("worlds\\main").is_err());
+        assert!(validate_level_name("world\nmotd=hi").is_err());
+    }
+
     #[test]
     fn test_exhausiveness() {
         let properties_file = std::io::BufReader::new(
@@ -2084,4 +3228,48 @@ mod test {
 
         assert_eq!(property.to_line(), "resource-pack=".to_string());
     }
+
+    #[test]
+    fn test_diff_properties() {
+        let properties = vec![
+            ("difficulty".to_string(), "hard".to_string()),
+            ("level-type".to_string(), "minecraft\\:normal".to_string()),
+            ("max-players".to_string(), "20".to_string()),
+            ("some-mod-setting".to_string(), "on".to_string()),
+        ];
+        assert_eq!(
+            diff_properties(properties.clone(), "1.20.4"),
+            vec![
+                PropertyDiff {
+                    key: "difficulty".to_string(),
+                    default: Some("easy".to_string()),
+                    current: "hard".to_string(),
+                },
+                PropertyDiff {
+                    key: "some-mod-setting".to_string(),
+                    default: None,
+                    current: "on".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            diff_properties(properties, "1.18.2")[1],
+            PropertyDiff {
+                key: "level-type".to_string(),
+                default: Some("default".to_string()),
+                current: "minecraft\\:normal".to_string(),
+            }
+        );
+        assert_eq!(default_property("difficulty", "1.12.2"), Some("1"));
+        assert_eq!(default_property("level-type", "1.12.2"), Some("DEFAULT"));
+        assert_eq!(default_property("level-type", "1.16.5"), Some("default"));
+        assert_eq!(
+            default_property("level-type", "1.19"),
+            Some("minecraft\\:normal")
+        );
+        assert_eq!(
+            default_property("level-type", "24w14a"),
+            Some("minecraft\\:normal")
+        );
+    }
 }
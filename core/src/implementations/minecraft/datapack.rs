@@ -0,0 +1,217 @@
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::{eyre, Context};
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use tokio::io::AsyncReadExt;
+use tracing::warn;
+use ts_rs::TS;
+
+use crate::error::{Error, ErrorKind};
+use crate::traits::t_server::State;
+use crate::util::resolve_path_conflict;
+
+use super::configurable::validate_level_name;
+use super::MinecraftInstance;
+
+/// Checks that `archive` is a zip with a `pack.mcmeta` at its root, as the server only loads
+/// datapacks laid out that way
+pub fn validate_datapack(archive: impl Read + Seek) -> Result<(), Error> {
+    let mut zip = zip::ZipArchive::new(archive).map_err(|e| Error {
+        kind: ErrorKind::BadRequest,
+        source: eyre!("Datapack is not a valid zip archive: {}", e),
+    })?;
+    if zip.by_name("pack.mcmeta").is_err() {
+        // a common mistake is zipping the folder containing the datapack rather than its contents
+        let nested = zip
+            .file_names()
+            .find(|name| name.ends_with("/pack.mcmeta"))
+            .map(|name| format!(", found one at {} instead", name))
+            .unwrap_or_default();
+        return Err(Error {
+            kind: ErrorKind::BadRequest,
+            source: eyre!(
+                "Datapack has no pack.mcmeta at the root of the archive{}",
+                nested
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Where an uploaded datapack ended up
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct SavedDatapack {
+    /// Relative to the instance directory
+    pub path: PathBuf,
+    /// SHA-1 of the archive, identical uploads share it
+    pub id: String,
+    /// Whether the same archive was already saved, in which case nothing was written
+    pub already_saved: bool,
+}
+
+/// SHA-1 of a file, read in chunks so a large datapack isn't loaded into memory at once
+async fn sha1_of_file(path: &Path) -> Result<String, Error> {
+    let mut file = tokio::fs::File::open(path)
+        .await
+        .context(format!("Failed to read datapack {}", path.display()))?;
+    let mut hasher = Sha1::new();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let read = file
+            .read(&mut buf)
+            .await
+            .context(format!("Failed to read datapack {}", path.display()))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// The datapack in `path_to_datapacks` whose content hashes to `id`, if any
+async fn find_datapack_by_id(path_to_datapacks: &Path, id: &str) -> Result<Option<PathBuf>, Error> {
+    let mut entries = tokio::fs::read_dir(path_to_datapacks)
+        .await
+        .context(format!(
+            "Failed to read directory {}",
+            path_to_datapacks.display()
+        ))?;
+    while let Some(entry) = entries.next_entry().await.context(format!(
+        "Failed to read directory {}",
+        path_to_datapacks.display()
+    ))? {
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        if sha1_of_file(&path).await? == id {
+            return Ok(Some(path));
+        }
+    }
+    Ok(None)
+}
+
+impl MinecraftInstance {
+    /// Copies the datapack at `archive` into the `datapacks` folder of `world`, or of the main
+    /// world if `None`
+    ///
+    /// Uploading an archive that is already there does nothing, a different datapack with the
+    /// same file name is kept and the new one saved under a free name. If the server is running
+    /// it is reloaded and the datapack enabled so it takes effect without a restart, a failed
+    /// reload is only logged since the datapack is loaded on the next start anyway
+    pub async fn install_datapack(
+        &self,
+        file_name: &str,
+        archive: &Path,
+        world: Option<&str>,
+    ) -> Result<SavedDatapack, Error> {
+        let archive_to_validate = archive.to_owned();
+        tokio::task::spawn_blocking(move || {
+            validate_datapack(std::fs::File::open(&archive_to_validate).context(format!(
+                "Failed to read datapack {}",
+                archive_to_validate.display()
+            ))?)
+        })
+        .await
+        .context("Failed to validate datapack in a blocking task")??;
+        let file_name = sanitize_filename::sanitize(file_name);
+        if !file_name.to_lowercase().ends_with(".zip") {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("Datapack {} must be a .zip file", file_name),
+            });
+        }
+        let path_to_world = match world {
+            Some(world) => {
+                validate_level_name(world)?;
+                self.path_to_instance.join(world)
+            }
+            None => self.path_to_world().await,
+        };
+        let path_to_datapacks = path_to_world.join("datapacks");
+        crate::util::fs::create_dir_all(&path_to_datapacks).await?;
+        let id = sha1_of_file(archive).await?;
+        let relative_to_instance = |path: &Path| {
+            path.strip_prefix(&self.path_to_instance)
+                .unwrap_or(path)
+                .to_path_buf()
+        };
+        if let Some(path) = find_datapack_by_id(&path_to_datapacks, &id).await? {
+            return Ok(SavedDatapack {
+                path: relative_to_instance(&path),
+                id,
+                already_saved: true,
+            });
+        }
+        let path = resolve_path_conflict(path_to_datapacks.join(&file_name), None);
+        tokio::fs::copy(archive, &path)
+            .await
+            .context(format!("Failed to save datapack {}", path.display()))?;
+
+        if *self.state.lock().await == State::Running {
+            let saved_name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or(file_name);
+            // the server only discovers new datapacks on reload, and enables them by default then
+            let reloaded = async {
+                self.send_rcon("reload").await?;
+                self.send_rcon(&format!("datapack enable \"file/{}\"", saved_name))
+                    .await
+            }
+            .await;
+            if let Err(e) = reloaded {
+                warn!(
+                    "[{}] Saved datapack {} but failed to reload: {}",
+                    self.config.lock().await.name,
+                    saved_name,
+                    e
+                );
+            }
+        }
+        Ok(SavedDatapack {
+            path: relative_to_instance(&path),
+            id,
+            already_saved: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use super::*;
+
+    fn zip_with(files: &[&str]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for file in files {
+            writer
+                .start_file(*file, zip::write::FileOptions::default())
+                .unwrap();
+            writer.write_all(b"{}").unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_validate_datapack() {
+        assert!(validate_datapack(Cursor::new(zip_with(&[
+            "pack.mcmeta",
+            "data/ns/functions/a.mcfunction"
+        ])))
+        .is_ok());
+        assert!(
+            validate_datapack(Cursor::new(zip_with(&["data/ns/functions/a.mcfunction"]))).is_err()
+        );
+        let nested =
+            validate_datapack(Cursor::new(zip_with(&["my_pack/pack.mcmeta"]))).unwrap_err();
+        assert!(matches!(nested.kind, ErrorKind::BadRequest));
+        assert!(format!("{:#}", nested.source).contains("my_pack/pack.mcmeta"));
+        assert!(validate_datapack(Cursor::new(b"not a zip")).is_err());
+    }
+}
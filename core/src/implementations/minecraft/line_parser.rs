@@ -1,11 +1,27 @@
+use std::time::{Duration, Instant};
+
+use color_eyre::eyre::eyre;
 use fancy_regex::Regex;
 use lazy_static::lazy_static;
 
+use super::player::PlayerRoster;
+use super::{PromptResponse, ServerLocale};
+use crate::error::{Error, ErrorKind};
+use crate::events::PaperNotice;
+
 pub struct PlayerMessage {
     pub player: String,
     pub message: String,
 }
 
+/// The version a server reported running at startup
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerVersion {
+    pub version: String,
+    /// Only printed by flavours that have builds, like Paper and Forge
+    pub build: Option<String>,
+}
+
 pub fn parse_system_msg(msg: &str) -> Option<String> {
     lazy_static! {
         static ref RE: Regex = Regex::new(r"\[.+\]+: (?!<)(.+)").unwrap();
@@ -37,39 +53,568 @@ pub fn parse_player_msg(msg: &str) -> Option<PlayerMessage> {
     }
 }
 
-pub fn parse_player_joined(system_msg: &str) -> Option<String> {
+/// Takes the player name in front of a translated "<player> joined the game" style message
+fn parse_player_name(system_msg: &str, suffix: &str) -> Option<String> {
+    system_msg
+        .rsplit_once(&format!(" {suffix}"))
+        .map(|(player_name, _)| player_name.to_string())
+        .filter(|player_name| !player_name.is_empty())
+}
+
+/// Lines that don't match `locale` are still tried in english, the language most servers log in
+pub fn parse_player_joined(system_msg: &str, locale: ServerLocale) -> Option<String> {
+    parse_player_name(system_msg, locale.player_joined_suffix())
+        .or_else(|| parse_player_name(system_msg, ServerLocale::English.player_joined_suffix()))
+}
+
+pub fn parse_player_left(system_msg: &str, locale: ServerLocale) -> Option<String> {
+    parse_player_name(system_msg, locale.player_left_suffix())
+        .or_else(|| parse_player_name(system_msg, ServerLocale::English.player_left_suffix()))
+}
+
+pub fn parse_server_started(system_msg: &str) -> bool {
     lazy_static! {
-        static ref RE: Regex = Regex::new(r"(.+) joined the game").unwrap();
+        static ref RE: Regex = Regex::new(r#"Done \(.+\)!"#).unwrap();
     }
-    if RE.is_match(system_msg).unwrap() {
-        if let Some(cap) = RE.captures(system_msg).ok()? {
-            Some(cap.get(1)?.as_str().to_string())
-        } else {
-            None
-        }
+    RE.is_match(system_msg).unwrap()
+}
+
+/// Parses the port out of "Starting Minecraft server on *:25565", logged before the server binds
+pub fn parse_bound_port(system_msg: &str) -> Option<u16> {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r"Starting Minecraft server on .*:(\d+)\s*$").unwrap();
+    }
+    RE.captures(system_msg).ok()??.get(1)?.as_str().parse().ok()
+}
+
+/// Parses the version out of the lines servers print while starting
+///
+/// Handles vanilla's "Starting minecraft server version 1.20.4", Paper's "This server is running
+/// Paper version git-Paper-496 (MC: 1.20.4)" and its newer "1.21.1-119-master@7c2df2f" form,
+/// and Forge's "Forge mod loading, version 49.0.3, for MC 1.20.4"
+pub fn parse_server_version(system_msg: &str) -> Option<ServerVersion> {
+    lazy_static! {
+        static ref VANILLA_RE: Regex =
+            Regex::new(r"Starting minecraft server version (\S+)").unwrap();
+        static ref PAPER_LEGACY_RE: Regex =
+            Regex::new(r"This server is running \w+ version git-\w+-(\d+) \(MC: ([^)]+)\)")
+                .unwrap();
+        static ref PAPER_RE: Regex =
+            Regex::new(r"This server is running \w+ version (\d[\w.]*)-(\d+)-").unwrap();
+        static ref FORGE_RE: Regex =
+            Regex::new(r"Forge mod loading, version ([\w.-]+), for MC ([\w.-]+)").unwrap();
+    }
+    let capture = |re: &Regex| re.captures(system_msg).ok().flatten();
+    if let Some(captures) = capture(&PAPER_LEGACY_RE) {
+        Some(ServerVersion {
+            version: captures.get(2)?.as_str().to_string(),
+            build: Some(captures.get(1)?.as_str().to_string()),
+        })
+    } else if let Some(captures) = capture(&PAPER_RE) {
+        Some(ServerVersion {
+            version: captures.get(1)?.as_str().to_string(),
+            build: Some(captures.get(2)?.as_str().to_string()),
+        })
+    } else if let Some(captures) = capture(&FORGE_RE) {
+        Some(ServerVersion {
+            version: captures.get(2)?.as_str().to_string(),
+            build: Some(captures.get(1)?.as_str().to_string()),
+        })
     } else {
-        None
+        let captures = capture(&VANILLA_RE)?;
+        Some(ServerVersion {
+            version: captures.get(1)?.as_str().to_string(),
+            build: None,
+        })
     }
 }
 
-pub fn parse_player_left(system_msg: &str) -> Option<String> {
+/// Whether the line is the server reporting that its port is already taken, after which it exits
+pub fn is_port_bind_failure(system_msg: &str) -> bool {
+    system_msg.contains("FAILED TO BIND TO PORT")
+        || system_msg.contains("Perhaps a server is already running on that port?")
+}
+
+/// Parses vanilla's "Can't keep up!" warning into `(ms_behind, ticks_skipped)`
+///
+/// Handles both "Running 2000ms behind, skipping 40 tick(s)" and the newer "Running 2000ms or 40
+/// ticks behind". A server tick is 50ms, so the tick count is derived when the line omits it
+pub fn parse_lag_warning(system_msg: &str) -> Option<(u64, u64)> {
     lazy_static! {
-        static ref RE: Regex = Regex::new(r"(.+) left the game").unwrap();
+        static ref RE: Regex = Regex::new(
+            r"Can't keep up!.*Running (\d+)ms(?: or (\d+) ticks)? behind(?:, skipping (\d+) tick)?"
+        )
+        .unwrap();
     }
-    if RE.is_match(system_msg).unwrap() {
-        if let Some(cap) = RE.captures(system_msg).ok()? {
-            Some(cap.get(1)?.as_str().to_string())
-        } else {
-            None
-        }
+    let captures = RE.captures(system_msg).ok()??;
+    let ms_behind: u64 = captures.get(1)?.as_str().parse().ok()?;
+    let ticks_skipped = captures
+        .get(2)
+        .or_else(|| captures.get(3))
+        .and_then(|m| m.as_str().parse().ok())
+        .unwrap_or(ms_behind / 50);
+    Some((ms_behind, ticks_skipped))
+}
+
+/// Takes a system message, see `parse_system_msg`, so that chat can't fake a notice
+///
+/// The messages may be prefixed with the name of the logger that printed them
+pub fn parse_paper_notice(system_msg: &str) -> Option<PaperNotice> {
+    lazy_static! {
+        static ref LEGACY_PLUGIN_RE: Regex = Regex::new(
+            r"^(?:\[[^\]]+\] )?Legacy plugin (.+?) does not specify an api-version"
+        )
+        .unwrap();
+        static ref PLUGIN_LOAD_RE: Regex =
+            Regex::new(r"^(?:\[[^\]]+\] )?Could not load '(?:.*[/\\])?([^'/\\]+)' in folder")
+                .unwrap();
+        static ref PLUGIN_ENABLE_RE: Regex = Regex::new(
+            r"^(?:\[[^\]]+\] )?Error occurred while enabling (.+?) \(Is it up to date\?\)"
+        )
+        .unwrap();
+        static ref CONFIG_MIGRATION_RE: Regex = Regex::new(
+            r"(?i)^(?:\[[^\]]+\] )?(?:migrat(?:ed|ing)|converting)\b[^.]*\bpaper(?:-global|-world-defaults|-world)?\.yml\b"
+        )
+        .unwrap();
+    }
+    let capture = |re: &Regex| {
+        re.captures(system_msg)
+            .ok()
+            .flatten()
+            .and_then(|captures| Some(captures.get(1)?.as_str().to_string()))
+    };
+    if let Some(plugin) = capture(&LEGACY_PLUGIN_RE) {
+        Some(PaperNotice::LegacyPlugin { plugin })
+    } else if let Some(plugin) = capture(&PLUGIN_LOAD_RE).or_else(|| capture(&PLUGIN_ENABLE_RE)) {
+        Some(PaperNotice::PluginFailed { plugin })
+    } else if CONFIG_MIGRATION_RE.is_match(system_msg).ok()? {
+        Some(PaperNotice::ConfigMigrated)
     } else {
         None
     }
 }
 
-pub fn parse_server_started(system_msg: &str) -> bool {
+/// How long an unterminated output line has to stall before it's checked for a prompt
+pub const PARTIAL_LINE_PROMPT_DELAY: Duration = Duration::from_millis(500);
+
+/// Matches output lines against the configured prompts, compiled once per start
+///
+/// Only the message of a system line is matched, chat is skipped so players can't answer a
+/// prompt by typing its text
+#[derive(Debug, Default)]
+pub struct PromptResponder {
+    prompts: Vec<(Regex, String)>,
+}
+
+impl PromptResponder {
+    pub fn new(prompt_responses: &[PromptResponse]) -> Result<Self, Error> {
+        let prompts = prompt_responses
+            .iter()
+            .map(|prompt_response| {
+                let pattern = Regex::new(&prompt_response.pattern).map_err(|e| Error {
+                    kind: ErrorKind::BadRequest,
+                    source: eyre!(
+                        "Invalid prompt pattern {:?}: {}",
+                        prompt_response.pattern,
+                        e
+                    ),
+                })?;
+                Ok((pattern, prompt_response.response.clone()))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(PromptResponder { prompts })
+    }
+
+    /// The response of the first prompt matching the line, which may be unterminated
+    pub fn respond(&self, line: &str) -> Option<&str> {
+        lazy_static! {
+            // also catches unsigned chat like "[Not Secure] <Steve> hi"
+            static ref CHAT_RE: Regex = Regex::new(r"^(?:\[[^\]]+\]:? )*<[^>]+> ").unwrap();
+        }
+        // prompts printed without a logger prefix are matched as a whole
+        let system_msg = parse_system_msg(line).unwrap_or_else(|| line.to_string());
+        if CHAT_RE.is_match(&system_msg).unwrap_or(true) {
+            return None;
+        }
+        self.prompts
+            .iter()
+            .find(|(pattern, _)| pattern.is_match(&system_msg).unwrap_or(false))
+            .map(|(_, response)| response.as_str())
+    }
+}
+
+/// Lag warnings are emitted at most once per this interval
+pub const LAG_WARNING_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Aggregates lag warnings so a struggling server doesn't flood the event channel
+///
+/// Warnings that arrive within `LAG_WARNING_INTERVAL` of the last emitted one are held back and
+/// folded into the next emitted warning, which reports the worst `ms_behind` and the total
+/// `ticks_skipped` seen since the previous one
+#[derive(Debug, Default)]
+pub struct LagWarningAggregator {
+    last_emitted: Option<Instant>,
+    pending: Option<(u64, u64)>,
+}
+
+impl LagWarningAggregator {
+    /// Records a warning, returning the aggregated `(ms_behind, ticks_skipped)` if one should be
+    /// emitted now
+    pub fn record(
+        &mut self,
+        ms_behind: u64,
+        ticks_skipped: u64,
+        now: Instant,
+    ) -> Option<(u64, u64)> {
+        let (ms_behind, ticks_skipped) = match self.pending.take() {
+            Some((pending_ms, pending_ticks)) => (
+                pending_ms.max(ms_behind),
+                pending_ticks.saturating_add(ticks_skipped),
+            ),
+            None => (ms_behind, ticks_skipped),
+        };
+        match self.last_emitted {
+            Some(last) if now.duration_since(last) < LAG_WARNING_INTERVAL => {
+                self.pending = Some((ms_behind, ticks_skipped));
+                None
+            }
+            _ => {
+                self.last_emitted = Some(now);
+                Some((ms_behind, ticks_skipped))
+            }
+        }
+    }
+}
+
+/// Parses the response of the `list` command
+///
+/// Handles vanilla's "There are 3 of a max of 20 players online: a, b, c", the older "3/20" form
+/// and bukkit style responses that list players per group on separate lines
+pub fn parse_rcon_list(response: &str) -> Option<PlayerRoster> {
     lazy_static! {
-        static ref RE: Regex = Regex::new(r#"Done \(.+\)!"#).unwrap();
+        static ref FORMATTING_RE: Regex = Regex::new(r"§.").unwrap();
+        static ref COUNT_RE: Regex = Regex::new(r"(\d+)\D+?(\d+)\s+players online").unwrap();
+        static ref UUID_RE: Regex = Regex::new(r"\s*\([0-9a-fA-F-]{32,36}\)").unwrap();
+    }
+    let response = FORMATTING_RE.replace_all(response, "");
+    let count_match = COUNT_RE.captures(&response).ok()??;
+    let online = count_match.get(1)?.as_str().parse().ok()?;
+    let max = count_match.get(2)?.as_str().parse().ok()?;
+
+    let rest = &response[count_match.get(0)?.end()..];
+    let players = rest
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            // strip the ":" after "online" as well as bukkit group names like "default: "
+            match line.split_once(':') {
+                Some((_, names)) => Some(names),
+                // the first line is what's left of the header, e.g. "."
+                None if i > 0 => Some(line),
+                None => None,
+            }
+        })
+        .flat_map(|names| names.split(','))
+        .map(|name| UUID_RE.replace_all(name, "").trim().to_string())
+        .filter(|name| !name.is_empty() && !name.contains(' '))
+        .collect();
+
+    Some(PlayerRoster {
+        online,
+        max,
+        players,
+    })
+}
+
+/// Parses the difficulty out of a response of the `difficulty` command
+///
+/// e.g. "The difficulty is Normal" or "The difficulty has been set to Hard"
+pub fn parse_rcon_difficulty(response: &str) -> Option<String> {
+    response
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .map(|word| word.to_ascii_lowercase())
+        .filter(|word| ["peaceful", "easy", "normal", "hard"].contains(&word.as_str()))
+        .last()
+}
+
+/// Parses the names out of a response of the `whitelist list` command
+///
+/// e.g. "There are 2 whitelisted player(s): Steve, Alex" or "There are no whitelisted players"
+pub fn parse_rcon_whitelist(response: &str) -> Vec<String> {
+    lazy_static! {
+        static ref FORMATTING_RE: Regex = Regex::new(r"§.").unwrap();
+    }
+    let response = FORMATTING_RE.replace_all(response, "");
+    match response.split_once(':') {
+        Some((_, names)) => names
+            .split(',')
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rcon_list() {
+        let roster = parse_rcon_list("There are 3 of a max of 20 players online: a, b, c").unwrap();
+        assert_eq!(roster.online, 3);
+        assert_eq!(roster.max, 20);
+        assert_eq!(roster.players, vec!["a", "b", "c"]);
+
+        let roster = parse_rcon_list("There are 0 of a max of 20 players online: ").unwrap();
+        assert_eq!((roster.online, roster.max), (0, 20));
+        assert!(roster.players.is_empty());
+
+        let roster = parse_rcon_list("There are 2/10 players online:\nSteve, Alex").unwrap();
+        assert_eq!((roster.online, roster.max), (2, 10));
+        assert_eq!(roster.players, vec!["Steve", "Alex"]);
+
+        let roster = parse_rcon_list(
+            "There are 1 of a max of 20 players online: Steve (8667ba71-b85a-4004-af54-457a9734eed7)",
+        )
+        .unwrap();
+        assert_eq!(roster.players, vec!["Steve"]);
+
+        let roster = parse_rcon_list(
+            "§6There are §c2§6 out of maximum §c20§6 players online.\n§6admins§r: §fSteve\n§6default§r: §fAlex",
+        )
+        .unwrap();
+        assert_eq!((roster.online, roster.max), (2, 20));
+        assert_eq!(roster.players, vec!["Steve", "Alex"]);
+
+        assert!(parse_rcon_list("Unknown command").is_none());
+    }
+
+    #[test]
+    fn test_parse_rcon_whitelist() {
+        assert_eq!(
+            parse_rcon_whitelist("There are 2 whitelisted player(s): Steve, Alex"),
+            vec!["Steve", "Alex"]
+        );
+        assert_eq!(
+            parse_rcon_whitelist("There are 1 whitelisted players: §eNotch"),
+            vec!["Notch"]
+        );
+        assert!(parse_rcon_whitelist("There are no whitelisted players").is_empty());
+    }
+
+    #[test]
+    fn test_parse_player_joined_left() {
+        assert_eq!(
+            parse_player_joined("Steve joined the game", ServerLocale::English),
+            Some("Steve".to_string())
+        );
+        assert_eq!(
+            parse_player_joined("Steve hat das Spiel betreten", ServerLocale::German),
+            Some("Steve".to_string())
+        );
+        assert_eq!(
+            parse_player_left("Alex a quitté la partie", ServerLocale::French),
+            Some("Alex".to_string())
+        );
+        // falls back to english
+        assert_eq!(
+            parse_player_left("Alex left the game", ServerLocale::Spanish),
+            Some("Alex".to_string())
+        );
+        assert_eq!(
+            parse_player_joined("Steve a rejoint la partie", ServerLocale::English),
+            None
+        );
+    }
+
+    #[test]
+    fn test_is_port_bind_failure() {
+        assert!(is_port_bind_failure(
+            "[Server thread/WARN]: **** FAILED TO BIND TO PORT!"
+        ));
+        assert!(is_port_bind_failure(
+            "[Server thread/WARN]: Perhaps a server is already running on that port?"
+        ));
+        assert!(!is_port_bind_failure(
+            "[Server thread/INFO]: Starting Minecraft server on *:25565"
+        ));
+    }
+
+    #[test]
+    fn test_parse_bound_port() {
+        assert_eq!(
+            parse_bound_port("[Server thread/INFO]: Starting Minecraft server on *:25566"),
+            Some(25566)
+        );
+        assert_eq!(
+            parse_bound_port("[Server thread/INFO]: Starting Minecraft server on [::]:25565"),
+            Some(25565)
+        );
+        assert_eq!(parse_bound_port("Starting minecraft server version 1.20.1"), None);
+    }
+
+    #[test]
+    fn test_parse_server_version() {
+        let version = |version: &str, build: Option<&str>| {
+            Some(ServerVersion {
+                version: version.to_string(),
+                build: build.map(|build| build.to_string()),
+            })
+        };
+        assert_eq!(
+            parse_server_version("[Server thread/INFO]: Starting minecraft server version 1.20.4"),
+            version("1.20.4", None)
+        );
+        assert_eq!(
+            parse_server_version("[Server thread/INFO]: This server is running Paper version git-Paper-496 (MC: 1.20.4) (Implementing API version 1.20.4-R0.1-SNAPSHOT) (Git: 7ac24a1)"),
+            version("1.20.4", Some("496"))
+        );
+        assert_eq!(
+            parse_server_version("[Server thread/INFO]: This server is running Paper version 1.21.1-119-master@7c2df2f (2024-10-03T15:38:34Z) (Implementing API version 1.21.1-R0.1-SNAPSHOT)"),
+            version("1.21.1", Some("119"))
+        );
+        assert_eq!(
+            parse_server_version("[main/INFO]: Forge mod loading, version 49.0.3, for MC 1.20.4 with MCP 20231207.154220"),
+            version("1.20.4", Some("49.0.3"))
+        );
+        assert_eq!(
+            parse_server_version("[Server thread/INFO]: Starting Minecraft server on *:25565"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_lag_warning() {
+        assert_eq!(
+            parse_lag_warning("[Server thread/WARN]: Can't keep up! Is the server overloaded? Running 5000ms behind, skipping 100 tick(s)"),
+            Some((5000, 100))
+        );
+        assert_eq!(
+            parse_lag_warning("[Server thread/WARN]: Can't keep up! Is the server overloaded? Running 2345ms or 46 ticks behind"),
+            Some((2345, 46))
+        );
+        assert_eq!(
+            parse_lag_warning("[Server thread/WARN]: Can't keep up! Did the system time change, or is the server overloaded? Running 2500ms behind"),
+            Some((2500, 50))
+        );
+        assert_eq!(
+            parse_lag_warning("[Server thread/INFO]: Steve joined the game"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_paper_notice() {
+        assert_eq!(
+            parse_paper_notice("Legacy plugin Essentials v2.13.1 does not specify an api-version."),
+            Some(PaperNotice::LegacyPlugin {
+                plugin: "Essentials v2.13.1".to_string()
+            })
+        );
+        assert_eq!(
+            parse_paper_notice("Could not load 'plugins/OldPlugin.jar' in folder 'plugins'"),
+            Some(PaperNotice::PluginFailed {
+                plugin: "OldPlugin.jar".to_string()
+            })
+        );
+        assert_eq!(
+            parse_paper_notice(
+                "[PluginManager] Error occurred while enabling WorldEdit v7.2.0 (Is it up to date?)"
+            ),
+            Some(PaperNotice::PluginFailed {
+                plugin: "WorldEdit v7.2.0".to_string()
+            })
+        );
+        assert_eq!(
+            parse_paper_notice("Migrating legacy paper.yml to the new config files"),
+            Some(PaperNotice::ConfigMigrated)
+        );
+        assert_eq!(parse_paper_notice("Preparing level \"world\""), None);
+        assert_eq!(
+            parse_paper_notice("Migrated 3 entries of the old config to the new format"),
+            None
+        );
+        assert_eq!(
+            parse_paper_notice(
+                "[Not Secure] <Steve> Legacy plugin Fake does not specify an api-version"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_prompt_responder() {
+        let responder = PromptResponder::new(&[
+            PromptResponse {
+                pattern: r"(?i)convert.*world.*\[y/n\]".to_string(),
+                response: "y".to_string(),
+            },
+            PromptResponse {
+                pattern: r"\[y/n\]".to_string(),
+                response: "n".to_string(),
+            },
+        ])
+        .unwrap();
+        assert_eq!(
+            responder.respond("[12:00:00 INFO]: Convert the world to the new format? [y/n]"),
+            Some("y")
+        );
+        assert_eq!(
+            responder.respond("[12:00:00 INFO]: Continue anyway? [y/n]"),
+            Some("n")
+        );
+        assert_eq!(responder.respond("Continue anyway? [y/n] "), Some("n"));
+        assert_eq!(responder.respond("[12:00:00 INFO]: Done (3.2s)!"), None);
+        assert_eq!(
+            responder.respond("[12:00:00 INFO]: <Steve> Convert the world? [y/n]"),
+            None
+        );
+        assert_eq!(
+            responder.respond("[12:00:00 INFO]: [Not Secure] <Steve> [y/n]"),
+            None
+        );
+        assert_eq!(responder.respond("<Steve> [y/n]"), None);
+        assert!(PromptResponder::new(&[PromptResponse {
+            pattern: "[y/n".to_string(),
+            response: "y".to_string(),
+        }])
+        .is_err());
+    }
+
+    #[test]
+    fn test_lag_warning_aggregator() {
+        let mut aggregator = LagWarningAggregator::default();
+        let start = Instant::now();
+        assert_eq!(aggregator.record(2000, 40, start), Some((2000, 40)));
+        assert_eq!(
+            aggregator.record(3000, 60, start + Duration::from_secs(5)),
+            None
+        );
+        assert_eq!(
+            aggregator.record(2500, 50, start + Duration::from_secs(10)),
+            None
+        );
+        assert_eq!(
+            aggregator.record(2100, 42, start + LAG_WARNING_INTERVAL),
+            Some((3000, 152))
+        );
+        assert_eq!(
+            aggregator.record(2000, 40, start + LAG_WARNING_INTERVAL * 2),
+            Some((2000, 40))
+        );
+    }
+
+    #[test]
+    fn test_parse_rcon_difficulty() {
+        assert_eq!(
+            parse_rcon_difficulty("The difficulty is Normal"),
+            Some("normal".to_string())
+        );
+        assert_eq!(
+            parse_rcon_difficulty("The difficulty did not change; it is already set to Hard"),
+            Some("hard".to_string())
+        );
+        assert_eq!(parse_rcon_difficulty("Unknown command"), None);
     }
-    RE.is_match(system_msg).unwrap()
 }
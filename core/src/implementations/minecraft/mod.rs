@@ -1,43 +1,69 @@
+pub mod backup;
+pub mod bukkit_config;
 pub mod configurable;
+pub mod datapack;
 pub mod fabric;
 mod forge;
 mod line_parser;
 pub mod r#macro;
+pub mod mod_dependency;
+pub mod mod_loader;
+mod nbt;
+mod neoforge;
 mod paper;
 pub mod player;
 mod players_manager;
+mod quilt;
+pub mod restart_schedule;
+pub mod runtime_content;
 pub mod server;
+mod server_list_ping;
+mod spigot;
+pub mod startup_estimate;
+pub mod support;
+pub mod tps;
 pub mod util;
 mod vanilla;
 pub mod versions;
 
 use color_eyre::eyre::{eyre, Context, ContextCompat};
 use enum_kinds::EnumKind;
+use futures::Stream;
 use indexmap::IndexMap;
+use once_cell::sync::Lazy;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
 use std::process::Stdio;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
-use sysinfo::SystemExt;
-use tokio::io::AsyncWriteExt;
+use std::time::{Duration, Instant};
+use sysinfo::{PidExt, ProcessExt, SystemExt};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::process::{Child, Command};
 
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
 use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
 
 use ::serde::{Deserialize, Serialize};
 use serde_json::to_string_pretty;
 
-use tracing::error;
+use tracing::{error, info, warn};
 
 use tokio;
 use ts_rs::TS;
 
-use crate::error::Error;
-use crate::event_broadcaster::EventBroadcaster;
-use crate::events::{Event, ProgressionEventID};
+use crate::error::{Error, ErrorKind};
+use crate::event_broadcaster::{EventBroadcaster, EventFilter};
+use crate::events::{
+    new_fs_event, CausedBy, Event, EventInner, FSOperation, FSTarget, InstanceEvent,
+    InstanceEventInner, ProgressionEventID,
+};
 use crate::macro_executor::{MacroExecutor, MacroPID};
-use crate::prelude::path_to_binaries;
+use crate::port_manager::PortKind;
+use crate::prelude::{path_to_binaries, path_to_tmp, path_to_trash};
 use crate::traits::t_configurable::PathBuf;
 
 use crate::traits::t_configurable::manifest::{
@@ -46,20 +72,48 @@ use crate::traits::t_configurable::manifest::{
 };
 
 use crate::traits::t_macro::TaskEntry;
-use crate::traits::t_server::State;
+use crate::traits::t_server::{State, StateAction, TServer};
 use crate::traits::TInstance;
-use crate::types::{DotLodestoneConfig, InstanceUuid};
+use crate::trash::move_to_trash;
+use crate::types::{DotLodestoneConfig, InstanceUuid, Snowflake};
 use crate::util::{
-    dont_spawn_terminal, download_file, format_byte, format_byte_download, unzip_file_async,
-    UnzipOption,
+    dont_spawn_terminal, download_file, format_byte, format_byte_download, is_out_of_space,
+    rand_alphanumeric, unzip_file_async, zip_files_rate_limited_async, UnzipOption,
 };
 
-use self::configurable::{CmdArgSetting, ServerPropertySetting};
+use self::backup::{
+    backups_beyond_retention, extract_backup_world, validate_backup_name, BackupDestination,
+    BackupInfo, BackupInstruction, BackupOnStopMode, BackupSink,
+};
+use self::bukkit_config::{BukkitConfig, BukkitConfigFile, BukkitSettingValue};
+use self::configurable::{
+    properties_to_string, validate_level_name, CmdArgSetting, Difficulty, Gamemode,
+    ServerPropertySetting,
+};
 use self::fabric::get_fabric_minecraft_versions;
 use self::forge::get_forge_minecraft_versions;
+use self::line_parser::{PromptResponder, ServerVersion};
+use self::mod_dependency::{scan_mod_issues, ModIssue};
+use self::mod_loader::{find_mismatched_mods, ModLoaderMismatchPolicy};
+use self::nbt::{parse_seed, write_seeded_level_dat};
+use self::neoforge::{find_neoforge_args_file, get_neoforge_minecraft_versions};
 use self::paper::get_paper_minecraft_versions;
 use self::players_manager::PlayersManager;
-use self::util::{get_jre_url, get_server_jar_url, read_properties_from_path};
+use self::quilt::get_quilt_minecraft_versions;
+use self::restart_schedule::RestartSchedule;
+use self::runtime_content::{find_not_loaded, parse_content_list, RuntimeContent};
+use self::server_list_ping::server_list_ping;
+use self::spigot::{build_spigot, get_spigot_minecraft_versions};
+use self::startup_estimate::{
+    count_mods, estimate_startup, record_startup, StartupEstimate, StartupRecord,
+};
+use self::tps::{
+    parse_forge_tps, parse_paper_mspt, parse_paper_tps, parse_tick_query, tps_command, TpsReport,
+};
+use self::util::{
+    escape_property_value, get_jre_url, get_server_jar_url, java_major_version,
+    read_properties_from_path, write_properties_to_path,
+};
 use self::vanilla::get_vanilla_minecraft_versions;
 
 #[derive(Debug, Clone, TS, Serialize, Deserialize, PartialEq)]
@@ -70,6 +124,12 @@ pub struct FabricLoaderVersion(String);
 pub struct FabricInstallerVersion(String);
 #[derive(Debug, Clone, TS, Serialize, Deserialize, PartialEq)]
 #[ts(export)]
+pub struct QuiltLoaderVersion(String);
+#[derive(Debug, Clone, TS, Serialize, Deserialize, PartialEq)]
+#[ts(export)]
+pub struct QuiltInstallerVersion(String);
+#[derive(Debug, Clone, TS, Serialize, Deserialize, PartialEq)]
+#[ts(export)]
 pub struct PaperBuildVersion(i64);
 #[derive(Debug, Clone, TS, Serialize, Deserialize, PartialEq)]
 #[ts(export)]
@@ -78,13 +138,17 @@ pub struct ForgeBuildVersion(String);
 /// A parameter for constructor of `MinecraftInstance`
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, EnumKind)]
 #[serde(rename_all = "snake_case")]
-#[enum_kind(FlavourKind, derive(Serialize, Deserialize, TS))]
+#[enum_kind(FlavourKind, derive(Serialize, Deserialize, TS, Hash))]
 pub enum Flavour {
     Vanilla,
     Fabric {
         loader_version: Option<FabricLoaderVersion>,
         installer_version: Option<FabricInstallerVersion>,
     },
+    Quilt {
+        loader_version: Option<QuiltLoaderVersion>,
+        installer_version: Option<QuiltInstallerVersion>,
+    },
     Paper {
         build_version: Option<PaperBuildVersion>,
     },
@@ -92,6 +156,10 @@ pub enum Flavour {
     Forge {
         build_version: Option<ForgeBuildVersion>,
     },
+    /// NeoForge builds are numbered like `20.4.237`, unlike forge they don't repeat the version
+    NeoForge {
+        build_version: Option<ForgeBuildVersion>,
+    },
 }
 
 impl From<FlavourKind> for Flavour {
@@ -102,6 +170,10 @@ impl From<FlavourKind> for Flavour {
                 loader_version: None,
                 installer_version: None,
             },
+            FlavourKind::Quilt => Flavour::Quilt {
+                loader_version: None,
+                installer_version: None,
+            },
             FlavourKind::Paper => Flavour::Paper {
                 build_version: None,
             },
@@ -109,6 +181,9 @@ impl From<FlavourKind> for Flavour {
             FlavourKind::Forge => Flavour::Forge {
                 build_version: None,
             },
+            FlavourKind::NeoForge => Flavour::NeoForge {
+                build_version: None,
+            },
         }
     }
 }
@@ -118,9 +193,11 @@ impl ToString for Flavour {
         match self {
             Flavour::Vanilla => "vanilla".to_string(),
             Flavour::Fabric { .. } => "fabric".to_string(),
+            Flavour::Quilt { .. } => "quilt".to_string(),
             Flavour::Paper { .. } => "paper".to_string(),
             Flavour::Spigot => "spigot".to_string(),
             Flavour::Forge { .. } => "forge".to_string(),
+            Flavour::NeoForge { .. } => "neoforge".to_string(),
         }
     }
 }
@@ -130,9 +207,11 @@ impl ToString for FlavourKind {
         match self {
             FlavourKind::Vanilla => "vanilla".to_string(),
             FlavourKind::Fabric => "fabric".to_string(),
+            FlavourKind::Quilt => "quilt".to_string(),
             FlavourKind::Paper => "paper".to_string(),
             FlavourKind::Spigot => "spigot".to_string(),
             FlavourKind::Forge => "forge".to_string(),
+            FlavourKind::NeoForge => "neoforge".to_string(),
         }
     }
 }
@@ -150,6 +229,28 @@ pub struct SetupConfig {
     pub auto_start: Option<bool>,
     pub restart_on_crash: Option<bool>,
     pub backup_period: Option<u32>,
+    /// Replace an existing server.properties instead of only updating the keys lodestone manages
+    pub overwrite_properties: Option<bool>,
+    /// Where the managed java runtimes are kept instead of the global binaries directory
+    pub runtimes_path: Option<PathBuf>,
+    /// Absolute path to a java binary to launch the server with, no runtime is downloaded then
+    pub java_path: Option<PathBuf>,
+    /// Folder name of the main world, written to server.properties as `level-name`
+    pub level_name: Option<String>,
+    /// Written to server.properties as `level-seed`, a number or any text like in the property
+    pub level_seed: Option<String>,
+    /// Also writes `level_seed` to a new `level.dat` before the world is generated, since the
+    /// property alone isn't always honoured, e.g. together with `generator-settings`
+    pub preseed_level_dat: Option<bool>,
+    /// Written to server.properties as `difficulty`, one of peaceful, easy, normal or hard
+    pub difficulty: Option<String>,
+    /// Written to server.properties as `gamemode`, one of survival, creative, adventure or
+    /// spectator
+    pub gamemode: Option<String>,
+    /// Written to server.properties as `motd`, may contain `§` formatting codes
+    pub motd: Option<String>,
+    /// Written to server.properties as `max-players`
+    pub max_players: Option<u32>,
 }
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RestoreConfig {
@@ -164,9 +265,441 @@ pub struct RestoreConfig {
     pub max_ram: u32,
     pub auto_start: bool,
     pub restart_on_crash: bool,
+    /// Minutes between periodic world backups
     pub backup_period: Option<u32>,
+    /// Pauses periodic backups while keeping `backup_period`
+    #[serde(default = "default_backups_enabled")]
+    pub backups_enabled: bool,
+    /// What backups contain besides the main world, relative to the instance directory
+    ///
+    /// A leading `world` stands for the configured `level-name`, so the default also covers the
+    /// nether and end folders bukkit servers keep next to the main world
+    #[serde(default = "default_backup_include")]
+    pub backup_include: Vec<String>,
     pub jre_major_version: u64,
     pub has_started: bool,
+    #[serde(default)]
+    pub stdin_terminator: StdinTerminator,
+    /// How long to wait after a crash before restarting, giving an operator a chance to intervene
+    #[serde(default)]
+    pub crash_restart_cooldown: Option<Duration>,
+    #[serde(default)]
+    pub watchdog: Option<WatchdogConfig>,
+    /// Stops restarting a server that keeps crashing, `None` restarts it every time
+    #[serde(default = "default_crash_loop_guard")]
+    pub crash_loop_guard: Option<CrashLoopGuard>,
+    /// Restarts a running server at set times, e.g. nightly
+    #[serde(default)]
+    pub restart_schedule: Option<RestartSchedule>,
+    #[serde(default)]
+    pub startup_timeout: Option<StartupTimeout>,
+    /// Stores the world outside the instance directory, e.g. on a faster disk
+    #[serde(default)]
+    pub world_dir_override: Option<PathBuf>,
+    #[serde(default)]
+    pub resources_dir_override: Option<PathBuf>,
+    /// Where backups are stored, defaults to the `backups` directory of the instance
+    #[serde(default)]
+    pub backup_destination: Option<BackupDestination>,
+    /// Flushes the world to disk with `save-all` this often, independent of backups
+    #[serde(default)]
+    pub autosave_interval: Option<Duration>,
+    /// Removes the oldest backups when there isn't enough free space for a new one
+    #[serde(default)]
+    pub prune_backups_on_low_space: bool,
+    /// Backs up the world whenever the server is stopped, `None` to not
+    #[serde(default)]
+    pub backup_on_stop_mode: Option<BackupOnStopMode>,
+    /// Caps how fast backups read the world in bytes per second, so they don't lag the server
+    #[serde(default)]
+    pub backup_io_limit: Option<u64>,
+    /// Backups kept by the backup task, older ones are removed after each backup. `None` keeps
+    /// all of them
+    #[serde(default)]
+    pub backup_retention: Option<u32>,
+    #[serde(default)]
+    pub timeout_settings: TimeoutSettings,
+    #[serde(default)]
+    pub already_running_policy: AlreadyRunningPolicy,
+    #[serde(default)]
+    pub java_agents: Vec<JavaAgent>,
+    #[serde(default)]
+    pub jvm_flags_preset: JvmFlagsPreset,
+    /// Language the server logs in, so join and leave messages can be recognized
+    #[serde(default)]
+    pub server_locale: ServerLocale,
+    /// Console commands issued in order every time the server finishes starting
+    #[serde(default)]
+    pub commands_on_start: Vec<String>,
+    /// Bytes of a single line of server output kept, the rest of the line is dropped
+    #[serde(default = "default_max_log_line_length")]
+    pub max_log_line_length: usize,
+    /// Also log the server's output to Lodestone's own log, prefixed with the instance name
+    #[serde(default)]
+    pub mirror_console_output: bool,
+    /// Cores the server process is pinned to, only supported on Linux
+    #[serde(default)]
+    pub cpu_affinity: Option<Vec<usize>>,
+    /// Scheduling priority of the server process from -20 to 19, only supported on Unix
+    #[serde(default)]
+    pub nice: Option<i32>,
+    /// Where the managed java runtimes are kept, defaults to the global binaries directory
+    #[serde(default)]
+    pub runtimes_path: Option<PathBuf>,
+    /// A java binary supplied by the user, used instead of the managed runtime
+    #[serde(default)]
+    pub java_path: Option<PathBuf>,
+    #[serde(default)]
+    pub mod_loader_mismatch_policy: ModLoaderMismatchPolicy,
+    /// The `@` arguments file the server is launched with, relative to the instance directory
+    #[serde(default)]
+    pub launch_args_file: Option<PathBuf>,
+    /// The latest startups, used to estimate how long the next one takes
+    #[serde(default)]
+    pub startup_history: Vec<StartupRecord>,
+    /// Answered automatically so the server doesn't hang waiting on stdin, first match wins
+    #[serde(default)]
+    pub prompt_responses: Vec<PromptResponse>,
+    /// The last RCON password generated by lodestone, mirrored to `rcon.password`
+    #[serde(default)]
+    pub rcon_password: Option<String>,
+    /// Enables RCON on start with `rcon_password` and a free port, so lodestone can connect
+    #[serde(default)]
+    pub manage_rcon: bool,
+    #[serde(default)]
+    pub rcon: RconConfig,
+    /// Set while in maintenance mode, stored so maintenance can still be ended after a restart
+    #[serde(default)]
+    pub maintenance_restore: Option<MaintenanceRestore>,
+}
+
+/// The properties `start_maintenance` changed, as they were before
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MaintenanceRestore {
+    pub white_list: bool,
+    pub motd: String,
+}
+
+/// Languages the join and leave messages in server logs can be parsed in
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, TS)]
+#[ts(export)]
+pub enum ServerLocale {
+    #[default]
+    English,
+    German,
+    French,
+    Spanish,
+    Portuguese,
+}
+
+impl ServerLocale {
+    /// `multiplayer.player.joined` in the game's language files, without the player name
+    pub fn player_joined_suffix(&self) -> &'static str {
+        match self {
+            ServerLocale::English => "joined the game",
+            ServerLocale::German => "hat das Spiel betreten",
+            ServerLocale::French => "a rejoint la partie",
+            ServerLocale::Spanish => "se ha unido a la partida",
+            ServerLocale::Portuguese => "entrou no jogo",
+        }
+    }
+
+    /// `multiplayer.player.left` in the game's language files, without the player name
+    pub fn player_left_suffix(&self) -> &'static str {
+        match self {
+            ServerLocale::English => "left the game",
+            ServerLocale::German => "hat das Spiel verlassen",
+            ServerLocale::French => "a quitté la partie",
+            ServerLocale::Spanish => "ha abandonado la partida",
+            ServerLocale::Portuguese => "saiu do jogo",
+        }
+    }
+}
+
+/// A jar in the instance's `agents` directory passed to the JVM with `-javaagent`
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct JavaAgent {
+    /// File name of the jar inside the `agents` directory
+    pub jar: String,
+    /// Appended to the flag as `-javaagent:<jar>=<options>`
+    pub options: Option<String>,
+}
+
+/// Curated JVM flags the server is launched with, on top of `cmd_args`
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, TS)]
+#[ts(export)]
+pub enum JvmFlagsPreset {
+    #[default]
+    None,
+    /// The G1GC tuning recommended for Minecraft servers at https://mcflags.emc.gs
+    Aikar,
+    Custom(Vec<String>),
+}
+
+impl JvmFlagsPreset {
+    /// The flags for a heap of `max_ram` megabytes
+    ///
+    /// Heap sizes are left out, the launch command always sets them from the min and max RAM
+    pub fn flags(&self, max_ram: u32) -> Vec<String> {
+        match self {
+            JvmFlagsPreset::None => Vec::new(),
+            JvmFlagsPreset::Aikar => {
+                // larger heaps get a larger young generation and regions
+                let (new_size, max_new_size, region_size, reserve, occupancy) =
+                    if max_ram > 12 * 1024 {
+                        (40, 50, "16M", 15, 20)
+                    } else {
+                        (30, 40, "8M", 20, 15)
+                    };
+                vec![
+                    "-XX:+UseG1GC".to_string(),
+                    "-XX:+ParallelRefProcEnabled".to_string(),
+                    "-XX:MaxGCPauseMillis=200".to_string(),
+                    "-XX:+UnlockExperimentalVMOptions".to_string(),
+                    "-XX:+DisableExplicitGC".to_string(),
+                    "-XX:+AlwaysPreTouch".to_string(),
+                    format!("-XX:G1NewSizePercent={}", new_size),
+                    format!("-XX:G1MaxNewSizePercent={}", max_new_size),
+                    format!("-XX:G1HeapRegionSize={}", region_size),
+                    format!("-XX:G1ReservePercent={}", reserve),
+                    "-XX:G1HeapWastePercent=5".to_string(),
+                    "-XX:G1MixedGCCountTarget=4".to_string(),
+                    format!("-XX:InitiatingHeapOccupancyPercent={}", occupancy),
+                    "-XX:G1MixedGCLiveThresholdPercent=90".to_string(),
+                    "-XX:G1RSetUpdatingPauseTimePercent=5".to_string(),
+                    "-XX:SurvivorRatio=32".to_string(),
+                    "-XX:+PerfDisableSharedMem".to_string(),
+                    "-XX:MaxTenuringThreshold=1".to_string(),
+                    "-Dusing.aikars.flags=https://mcflags.emc.gs".to_string(),
+                    "-Daikars.new.flags=true".to_string(),
+                ]
+            }
+            JvmFlagsPreset::Custom(flags) => flags
+                .iter()
+                .map(|flag| flag.trim())
+                .filter(|flag| !flag.is_empty() && !is_heap_size_flag(flag))
+                .map(str::to_string)
+                .collect(),
+        }
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        if let JvmFlagsPreset::Custom(flags) = self {
+            if let Some(flag) = flags.iter().map(|flag| flag.trim()).find(|flag| {
+                !flag.is_empty() && (!flag.starts_with('-') || *flag == "-jar" || *flag == "-cp")
+            }) {
+                return Err(Error {
+                    kind: ErrorKind::BadRequest,
+                    source: eyre!("Invalid JVM flag \"{}\"", flag),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether the flag sets the heap size, which the launch command derives from min and max RAM
+fn is_heap_size_flag(flag: &str) -> bool {
+    ["-Xms", "-Xmx", "-XX:InitialHeapSize=", "-XX:MaxHeapSize="]
+        .iter()
+        .any(|prefix| flag.starts_with(prefix))
+}
+
+fn default_backups_enabled() -> bool {
+    true
+}
+
+fn default_crash_loop_guard() -> Option<CrashLoopGuard> {
+    Some(CrashLoopGuard::default())
+}
+
+fn default_backup_include() -> Vec<String> {
+    vec![
+        "world".to_string(),
+        "world_nether".to_string(),
+        "world_the_end".to_string(),
+    ]
+}
+
+fn default_max_log_line_length() -> usize {
+    32 * 1024
+}
+
+/// Lines shorter than this are too short to be parsed reliably
+const MIN_MAX_LOG_LINE_LENGTH: usize = 1024;
+
+/// What an automatic start does when a server is found already running in the instance directory
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, TS)]
+#[ts(export)]
+pub enum AlreadyRunningPolicy {
+    /// Leave the running server alone and skip the start
+    #[default]
+    Skip,
+    /// Kill the running server and start a new one
+    Replace,
+}
+
+/// When an instance stops or starts on its own, see `MinecraftInstance::set_timeout_settings`
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct TimeoutSettings {
+    /// Seconds with no players online before the server is stopped
+    pub idle_timeout: Option<u32>,
+    /// Seconds without anyone joining, leaving or chatting before the server is stopped
+    pub no_activity_timeout: Option<u32>,
+    /// Start the server when a player tries to connect to it
+    pub start_on_connection: bool,
+}
+
+impl TimeoutSettings {
+    /// Checks the settings against each other and against `auto_start`
+    pub fn validate(&self, auto_start: bool) -> Result<(), Error> {
+        if self.idle_timeout == Some(0) || self.no_activity_timeout == Some(0) {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("Timeouts must be positive"),
+            });
+        }
+        // an empty server is also an inactive one, so the idle timeout would never be reached
+        if let (Some(idle_timeout), Some(no_activity_timeout)) =
+            (self.idle_timeout, self.no_activity_timeout)
+        {
+            if no_activity_timeout < idle_timeout {
+                return Err(Error {
+                    kind: ErrorKind::BadRequest,
+                    source: eyre!("No activity timeout cannot be shorter than the idle timeout"),
+                });
+            }
+        }
+        if self.start_on_connection && auto_start {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("Start on connection cannot be used together with auto start"),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Periodically pings a running server and restarts it if it stops responding
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WatchdogConfig {
+    pub interval: Duration,
+    /// Number of consecutive failed pings before the server is considered hung
+    pub failure_threshold: u32,
+}
+
+/// How lodestone dials RCON
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RconConfig {
+    /// Dials before giving up on a connection, waiting twice as long after every failed one
+    pub max_attempts: u32,
+}
+
+impl Default for RconConfig {
+    fn default() -> Self {
+        Self { max_attempts: 3 }
+    }
+}
+
+/// Turns `restart_on_crash` off once the server crashes more than `max_crashes` times within
+/// `window`, instead of restarting a broken server forever
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CrashLoopGuard {
+    pub max_crashes: u32,
+    pub window: Duration,
+    /// Running this long without crashing forgets the earlier crashes
+    pub min_uptime: Duration,
+}
+
+impl Default for CrashLoopGuard {
+    fn default() -> Self {
+        Self {
+            max_crashes: 3,
+            window: Duration::from_secs(10 * 60),
+            min_uptime: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+/// Records a crash at `now` in `recent_crashes`, returning whether the server is crash looping
+///
+/// Crashes that fell out of the window are forgotten, as are all of them if the server was up
+/// for `min_uptime` before this one
+fn record_crash(
+    recent_crashes: &mut Vec<Instant>,
+    now: Instant,
+    running_for: Option<Duration>,
+    guard: &CrashLoopGuard,
+) -> bool {
+    if running_for.map_or(false, |running_for| running_for >= guard.min_uptime) {
+        recent_crashes.clear();
+    }
+    recent_crashes.retain(|crash| now.duration_since(*crash) < guard.window);
+    recent_crashes.push(now);
+    recent_crashes.len() > guard.max_crashes as usize
+}
+
+/// Fails a start that takes too long, instead of leaving the instance in `State::Starting`
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StartupTimeout {
+    pub timeout: Duration,
+    /// Kill the server once the timeout runs out, otherwise it is left to keep starting
+    pub kill: bool,
+}
+
+/// Answers a question the server asks on its output and then waits on stdin for, e.g. whether
+/// to convert an old world
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct PromptResponse {
+    /// Regular expression matched against every line of output
+    pub pattern: String,
+    /// Written to stdin when a line matches
+    pub response: String,
+}
+
+/// The line terminator appended to every command written to the server's stdin
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StdinTerminator {
+    Lf,
+    CrLf,
+}
+
+impl Default for StdinTerminator {
+    fn default() -> Self {
+        if cfg!(windows) {
+            StdinTerminator::CrLf
+        } else {
+            StdinTerminator::Lf
+        }
+    }
+}
+
+impl StdinTerminator {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StdinTerminator::Lf => "\n",
+            StdinTerminator::CrLf => "\r\n",
+        }
+    }
+}
+
+/// Writes `command` as a single UTF-8 encoded line, ignoring any terminator it already has
+pub async fn write_stdin_line(
+    stdin: &mut (impl tokio::io::AsyncWrite + Unpin),
+    command: &str,
+    terminator: StdinTerminator,
+) -> std::io::Result<()> {
+    let line = format!(
+        "{}{}",
+        command.trim_end_matches(['\r', '\n']),
+        terminator.as_str()
+    );
+    stdin.write_all(line.as_bytes()).await?;
+    stdin.flush().await
 }
 #[allow(dead_code)]
 #[derive(Clone)]
@@ -176,6 +709,9 @@ pub struct MinecraftInstance {
     creation_time: i64,
     state: Arc<Mutex<State>>,
     event_broadcaster: EventBroadcaster,
+    /// Why the config couldn't be restored, the instance runs on a placeholder config until
+    /// the file is fixed and Lodestone restarted
+    needs_repair: Option<String>,
     // file paths
     path_to_instance: PathBuf,
     path_to_config: PathBuf,
@@ -188,6 +724,7 @@ pub struct MinecraftInstance {
     // variables which can be changed at runtime
     auto_start: Arc<AtomicBool>,
     restart_on_crash: Arc<AtomicBool>,
+    mirror_console_output: Arc<AtomicBool>,
     backup_period: Option<u32>,
     process: Arc<Mutex<Option<Child>>>,
     stdin: Arc<Mutex<Option<tokio::process::ChildStdin>>>,
@@ -198,6 +735,118 @@ pub struct MinecraftInstance {
     rcon_conn: Arc<Mutex<Option<rcon::Connection<tokio::net::TcpStream>>>>,
     macro_name_to_last_run: Arc<Mutex<HashMap<String, i64>>>,
     pid_to_task_entry: Arc<Mutex<IndexMap<MacroPID, TaskEntry>>>,
+    pending_crash_restart: Arc<Mutex<Option<tokio::task::JoinHandle<()>>>>,
+    watchdog_task: Arc<Mutex<Option<tokio::task::JoinHandle<()>>>>,
+    startup_timeout_task: Arc<Mutex<Option<tokio::task::JoinHandle<()>>>>,
+    /// When the current start began and how many mods were installed at the time
+    startup_began_at: Arc<Mutex<Option<(Instant, usize)>>>,
+    /// When the server last finished starting, cleared once it exits
+    running_since: Arc<Mutex<Option<Instant>>>,
+    /// Crashes within the window of the crash loop guard
+    recent_crashes: Arc<Mutex<Vec<Instant>>>,
+    restart_after_exit: Arc<AtomicBool>,
+    /// Set by `stop` and `kill`, so the process exiting afterwards isn't taken for a crash
+    stop_requested: Arc<AtomicBool>,
+    stdout_task: Arc<Mutex<Option<tokio::task::JoinHandle<()>>>>,
+    autosave_task: Arc<Mutex<Option<tokio::task::JoinHandle<()>>>>,
+    restart_schedule_task: Arc<Mutex<Option<tokio::task::JoinHandle<()>>>>,
+    /// The port the server reported binding to, which can differ from the configured one
+    bound_port: Arc<Mutex<Option<u16>>>,
+    /// Why the server last crashed, if it could be classified
+    crash_cause: Arc<Mutex<Option<CrashCause>>>,
+    /// The version the server reported the last time it started
+    detected_version: Arc<Mutex<Option<ServerVersion>>>,
+    last_launch_command: Arc<Mutex<Option<Vec<String>>>>,
+    backup_sender: tokio::sync::mpsc::UnboundedSender<BackupInstruction>,
+    backup_task: Arc<Mutex<Option<tokio::task::JoinHandle<()>>>>,
+    /// Name and cancellation token of the backup being archived
+    running_backup: Arc<Mutex<Option<(String, CancellationToken)>>>,
+    /// Set while a live backup flushes the world with `save-all` over RCON
+    rcon_save_in_progress: Arc<AtomicBool>,
+    /// The last tick rate the server reported, see `TPS_CACHE_TTL`
+    last_tps_report: Arc<Mutex<Option<(Instant, TpsReport)>>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct InstanceAge {
+    /// Unix timestamp in seconds
+    pub created_at: i64,
+    /// Seconds since the instance was created
+    pub age: u64,
+}
+
+/// How to connect to a running instance
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct ConnectionInfo {
+    pub configured_port: u32,
+    /// `None` until the server logs the port it is listening on
+    pub bound_port: Option<u16>,
+}
+
+impl ConnectionInfo {
+    pub fn port_mismatch(&self) -> bool {
+        self.bound_port
+            .map_or(false, |bound_port| bound_port as u32 != self.configured_port)
+    }
+}
+
+/// The version lodestone installed alongside what the server reported running at startup
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct VersionInfo {
+    pub configured_version: String,
+    /// `None` for flavours without builds, or if the build isn't known
+    pub configured_build: Option<String>,
+    /// `None` until the server logs its version
+    pub detected_version: Option<String>,
+    pub detected_build: Option<String>,
+}
+
+impl VersionInfo {
+    /// Whether the server isn't running what lodestone thinks is installed, e.g. after the jar
+    /// was swapped manually
+    pub fn drifted(&self) -> bool {
+        let version_drifted = self
+            .detected_version
+            .as_ref()
+            .map_or(false, |detected| detected != &self.configured_version);
+        // forge builds are stored as "1.20.4-49.0.3" but logged as "49.0.3"
+        let build_drifted = match (&self.configured_build, &self.detected_build) {
+            (Some(configured), Some(detected)) => {
+                !configured.split('-').any(|part| part == detected)
+            }
+            _ => false,
+        };
+        version_drifted || build_drifted
+    }
+}
+
+/// A process other than the server that is holding on to a resource the server needs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct ConflictingProcess {
+    pub pid: u32,
+    pub name: String,
+}
+
+/// A recognized reason for the server crashing, along with what can be done to fix it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(tag = "type")]
+pub enum CrashCause {
+    /// The server could not bind to its port, which can be fixed by switching to
+    /// `suggested_port` or killing `conflicting_process`
+    PortInUse {
+        port: u32,
+        suggested_port: u32,
+        /// `None` unless the port is held by a server left running out of the instance's directory,
+        /// the only process that is safe to kill
+        conflicting_process: Option<ConflictingProcess>,
+    },
+    /// The server did not finish starting within the startup timeout
+    StartupTimeout { timeout_secs: u64 },
 }
 
 #[tokio::test]
@@ -209,16 +858,120 @@ async fn test_setup_manifest() {
     println!("{manifest_json_string}");
 }
 
+/// How long the server has to print anything after a command before it is considered hung
+const STDIN_RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Pins the process spawned by `command` to `cpu_affinity` and sets its `nice` value
+///
+/// They are set in the child right before java is executed, so every thread of the JVM
+/// inherits them
+#[cfg(unix)]
+pub(super) fn apply_process_priority(
+    command: &mut Command,
+    cpu_affinity: Option<Vec<usize>>,
+    nice: Option<i32>,
+) {
+    if cpu_affinity.is_none() && nice.is_none() {
+        return;
+    }
+    // SAFETY: only async-signal-safe syscalls are made between fork and exec
+    unsafe {
+        command.pre_exec(move || {
+            #[cfg(target_os = "linux")]
+            if let Some(cpu_affinity) = &cpu_affinity {
+                let mut cpu_set: libc::cpu_set_t = std::mem::zeroed();
+                for cpu in cpu_affinity {
+                    libc::CPU_SET(*cpu, &mut cpu_set);
+                }
+                let cpu_set_size = std::mem::size_of::<libc::cpu_set_t>();
+                if libc::sched_setaffinity(0, cpu_set_size, &cpu_set) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+            }
+            if let Some(nice) = nice {
+                if libc::setpriority(libc::PRIO_PROCESS, 0, nice) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+            }
+            Ok(())
+        });
+    }
+}
+
+#[cfg(not(unix))]
+pub(super) fn apply_process_priority(
+    _command: &mut Command,
+    _cpu_affinity: Option<Vec<usize>>,
+    _nice: Option<i32>,
+) {
+}
+
+/// Length of passwords generated by `rotate_rcon_password`
+const RCON_PASSWORD_LENGTH: usize = 32;
+
+/// The port the server defaults to, tried first when picking a free RCON port
+const DEFAULT_RCON_PORT: u32 = 25575;
+
+/// Wait after the first failed RCON dial, doubled after each one after it
+const RCON_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// What `save-all` prints once the world is written, older versions say world instead of game
+const SAVE_CONFIRMATIONS: [&str; 2] = ["Saved the game", "Saved the world"];
+
+/// Pause before each command on start
+const COMMANDS_ON_START_DELAY: Duration = Duration::from_secs(1);
+
+/// How long a tick rate reading is reused, so polling it doesn't flood the console
+const TPS_CACHE_TTL: Duration = Duration::from_secs(5);
+
+/// How long a fetched version list is reused before asking the flavour's API again
+const VERSION_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+static VERSION_CACHE: Lazy<Mutex<HashMap<FlavourKind, (Instant, Vec<String>)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Returns the versions containing `query`, in the given order
+fn filter_versions(versions: &[String], query: &str) -> Vec<String> {
+    let query = query.trim().to_lowercase();
+    versions
+        .iter()
+        .filter(|version| version.to_lowercase().contains(&query))
+        .cloned()
+        .collect()
+}
+
 impl MinecraftInstance {
-    pub async fn setup_manifest(flavour: &FlavourKind) -> Result<SetupManifest, Error> {
+    /// Returns the available versions of a flavour, newest first
+    pub async fn get_versions(flavour: &FlavourKind) -> Result<Vec<String>, Error> {
+        if let Some((fetched_at, versions)) = VERSION_CACHE.lock().await.get(flavour) {
+            if fetched_at.elapsed() < VERSION_CACHE_TTL {
+                return Ok(versions.clone());
+            }
+        }
         let versions = match flavour {
             FlavourKind::Vanilla => get_vanilla_minecraft_versions().await,
             FlavourKind::Fabric => get_fabric_minecraft_versions().await,
+            FlavourKind::Quilt => get_quilt_minecraft_versions().await,
             FlavourKind::Paper => get_paper_minecraft_versions().await,
-            FlavourKind::Spigot => todo!(),
+            FlavourKind::Spigot => get_spigot_minecraft_versions().await,
             FlavourKind::Forge => get_forge_minecraft_versions().await,
+            FlavourKind::NeoForge => get_neoforge_minecraft_versions().await,
         }
         .context("Failed to get minecraft versions")?;
+        VERSION_CACHE
+            .lock()
+            .await
+            .insert(*flavour, (Instant::now(), versions.clone()));
+        Ok(versions)
+    }
+
+    /// Searches the versions of a flavour for type-ahead in the setup flow
+    pub async fn search_versions(flavour: &FlavourKind, query: &str) -> Result<Vec<String>, Error> {
+        Ok(filter_versions(&Self::get_versions(flavour).await?, query))
+    }
+
+    pub async fn setup_manifest(flavour: &FlavourKind) -> Result<SetupManifest, Error> {
+        let versions = Self::get_versions(flavour).await?;
 
         let version_setting = SettingManifest::new_value_with_type(
             "version".to_string(),
@@ -276,10 +1029,130 @@ impl MinecraftInstance {
             true,
         );
 
+        let runtimes_path_setting = SettingManifest::new_optional_value(
+            "runtimes_path".to_string(),
+            "Java Runtimes Directory".to_string(),
+            "Absolute path to keep the downloaded java runtimes in, instead of the shared one"
+                .to_string(),
+            None,
+            ConfigurableValueType::String { regex: None },
+            None,
+            false,
+            true,
+        );
+
+        let java_path_setting = SettingManifest::new_optional_value(
+            "java_path".to_string(),
+            "Java Binary".to_string(),
+            "Absolute path to a java binary to run the server with instead of downloading one"
+                .to_string(),
+            None,
+            ConfigurableValueType::String { regex: None },
+            None,
+            false,
+            true,
+        );
+
+        let level_name_setting = SettingManifest::new_optional_value(
+            "level_name".to_string(),
+            "World Folder Name".to_string(),
+            "The folder the main world is kept in. Defaults to world.".to_string(),
+            None,
+            ConfigurableValueType::String { regex: None },
+            None,
+            false,
+            true,
+        );
+
+        let level_seed_setting = SettingManifest::new_optional_value(
+            "level_seed".to_string(),
+            "World Seed".to_string(),
+            "The seed to generate the world from. Random if empty.".to_string(),
+            None,
+            ConfigurableValueType::String { regex: None },
+            None,
+            false,
+            true,
+        );
+
+        let preseed_level_dat_setting = SettingManifest::new_required_value(
+            "preseed_level_dat".to_string(),
+            "Pre-seed level.dat".to_string(),
+            "Write the seed to level.dat before the first start too, for when the level-seed property is ignored".to_string(),
+            ConfigurableValue::Boolean(false),
+            Some(ConfigurableValue::Boolean(false)),
+            false,
+            true,
+        );
+
+        let motd_setting = SettingManifest::new_optional_value(
+            "motd".to_string(),
+            "MOTD".to_string(),
+            "The message shown under the server in the server list. Supports § formatting codes."
+                .to_string(),
+            None,
+            ConfigurableValueType::String { regex: None },
+            None,
+            false,
+            true,
+        );
+
+        let max_players_setting = SettingManifest::new_value_with_type(
+            "max_players".to_string(),
+            "Max Players".to_string(),
+            "The maximum number of players that can be online at once".to_string(),
+            Some(ConfigurableValue::UnsignedInteger(20)),
+            ConfigurableValueType::UnsignedInteger {
+                min: Some(1),
+                max: None,
+            },
+            Some(ConfigurableValue::UnsignedInteger(20)),
+            false,
+            true,
+        );
+
+        let difficulty_setting = SettingManifest::new_value_with_type(
+            "difficulty".to_string(),
+            "Difficulty".to_string(),
+            "The difficulty the world starts with".to_string(),
+            Some(ConfigurableValue::Enum(Difficulty::Easy.to_string())),
+            ConfigurableValueType::Enum {
+                options: vec![
+                    Difficulty::Peaceful.to_string(),
+                    Difficulty::Easy.to_string(),
+                    Difficulty::Normal.to_string(),
+                    Difficulty::Hard.to_string(),
+                ],
+            },
+            Some(ConfigurableValue::Enum(Difficulty::Easy.to_string())),
+            false,
+            true,
+        );
+
+        let gamemode_setting = SettingManifest::new_value_with_type(
+            "gamemode".to_string(),
+            "Gamemode".to_string(),
+            "The gamemode new players join in".to_string(),
+            Some(ConfigurableValue::Enum(Gamemode::Survival.to_string())),
+            ConfigurableValueType::Enum {
+                options: vec![
+                    Gamemode::Survival.to_string(),
+                    Gamemode::Creative.to_string(),
+                    Gamemode::Adventure.to_string(),
+                    Gamemode::Spectator.to_string(),
+                ],
+            },
+            Some(ConfigurableValue::Enum(Gamemode::Survival.to_string())),
+            false,
+            true,
+        );
+
         let mut section_1_map = IndexMap::new();
 
         section_1_map.insert("version".to_string(), version_setting);
         section_1_map.insert("port".to_string(), port_setting);
+        section_1_map.insert("motd".to_string(), motd_setting);
+        section_1_map.insert("max_players".to_string(), max_players_setting);
 
         let mut section_2_map = IndexMap::new();
 
@@ -289,6 +1162,22 @@ impl MinecraftInstance {
 
         section_2_map.insert("cmd_args".to_string(), command_line_args_setting);
 
+        section_2_map.insert("runtimes_path".to_string(), runtimes_path_setting);
+
+        section_2_map.insert("java_path".to_string(), java_path_setting);
+
+        section_2_map.insert("level_name".to_string(), level_name_setting);
+
+        section_2_map.insert("level_seed".to_string(), level_seed_setting);
+
+        section_2_map.insert("preseed_level_dat".to_string(), preseed_level_dat_setting);
+
+        let mut section_3_map = IndexMap::new();
+
+        section_3_map.insert("difficulty".to_string(), difficulty_setting);
+
+        section_3_map.insert("gamemode".to_string(), gamemode_setting);
+
         let section_1 = SectionManifest::new(
             "section_1".to_string(),
             "Basic Settings".to_string(),
@@ -303,10 +1192,18 @@ impl MinecraftInstance {
             section_2_map,
         );
 
+        let section_3 = SectionManifest::new(
+            "section_3".to_string(),
+            "Gameplay Settings".to_string(),
+            "Settings for the world, applied before it is first generated.".to_string(),
+            section_3_map,
+        );
+
         let mut sections = IndexMap::new();
 
         sections.insert("section_1".to_string(), section_1);
         sections.insert("section_2".to_string(), section_2);
+        sections.insert("section_3".to_string(), section_3);
 
         Ok(SetupManifest {
             setting_sections: sections,
@@ -368,6 +1265,72 @@ impl MinecraftInstance {
             .map(|s| s.to_string())
             .collect();
 
+        let runtimes_path = setup_value
+            .get_unique_setting("runtimes_path")
+            .and_then(|v| v.get_value())
+            .and_then(|v| v.try_as_string().ok())
+            .filter(|path| !path.trim().is_empty())
+            .map(PathBuf::from);
+
+        let java_path = setup_value
+            .get_unique_setting("java_path")
+            .and_then(|v| v.get_value())
+            .and_then(|v| v.try_as_string().ok())
+            .filter(|path| !path.trim().is_empty())
+            .map(PathBuf::from);
+
+        let level_name = setup_value
+            .get_unique_setting("level_name")
+            .and_then(|v| v.get_value())
+            .and_then(|v| v.try_as_string().ok())
+            .map(|level_name| level_name.trim().to_string())
+            .filter(|level_name| !level_name.is_empty());
+        if let Some(level_name) = &level_name {
+            validate_level_name(level_name)?;
+        }
+
+        let level_seed = setup_value
+            .get_unique_setting("level_seed")
+            .and_then(|v| v.get_value())
+            .and_then(|v| v.try_as_string().ok())
+            .map(|level_seed| level_seed.trim().to_string())
+            .filter(|level_seed| !level_seed.is_empty());
+
+        let preseed_level_dat = setup_value
+            .get_unique_setting("preseed_level_dat")
+            .and_then(|v| v.get_value())
+            .and_then(|v| v.try_as_boolean().ok());
+
+        let difficulty = setup_value
+            .get_unique_setting("difficulty")
+            .and_then(|v| v.get_value())
+            .and_then(|v| v.try_as_enum().ok())
+            .cloned();
+        if let Some(difficulty) = &difficulty {
+            difficulty.parse::<Difficulty>()?;
+        }
+
+        let gamemode = setup_value
+            .get_unique_setting("gamemode")
+            .and_then(|v| v.get_value())
+            .and_then(|v| v.try_as_enum().ok())
+            .cloned();
+        if let Some(gamemode) = &gamemode {
+            gamemode.parse::<Gamemode>()?;
+        }
+
+        let motd = setup_value
+            .get_unique_setting("motd")
+            .and_then(|v| v.get_value())
+            .and_then(|v| v.try_as_string().ok())
+            .filter(|motd| !motd.trim().is_empty())
+            .cloned();
+
+        let max_players = setup_value
+            .get_unique_setting("max_players")
+            .and_then(|v| v.get_value())
+            .and_then(|v| v.try_as_unsigned_integer().ok());
+
         Ok(SetupConfig {
             name,
             description,
@@ -380,6 +1343,16 @@ impl MinecraftInstance {
             auto_start: Some(setup_value.auto_start),
             restart_on_crash: Some(setup_value.restart_on_crash),
             backup_period: None,
+            overwrite_properties: None,
+            runtimes_path,
+            java_path,
+            level_name,
+            level_seed,
+            preseed_level_dat,
+            difficulty,
+            gamemode,
+            motd,
+            max_players,
         })
     }
 
@@ -437,9 +1410,29 @@ impl MinecraftInstance {
         let path_to_config = path_to_instance.join(".lodestone_minecraft_config.json");
         let path_to_eula = path_to_instance.join("eula.txt");
         let path_to_macros = path_to_instance.join("macros");
-        let path_to_resources = path_to_instance.join("resources");
+        let path_to_resources = resolve_path_to_resources(&path_to_instance, None);
         let path_to_properties = path_to_instance.join("server.properties");
-        let path_to_runtimes = path_to_binaries().to_owned();
+        if let Some(runtimes_path) = &config.runtimes_path {
+            validate_dir_override(runtimes_path).await?;
+        }
+        if let Some(level_name) = &config.level_name {
+            validate_level_name(level_name)?;
+        }
+        if let Some(difficulty) = &config.difficulty {
+            difficulty.parse::<Difficulty>()?;
+        }
+        if let Some(gamemode) = &config.gamemode {
+            gamemode.parse::<Gamemode>()?;
+        }
+        // checked before anything is created, a binary that doesn't run would only fail at start
+        let custom_java = match &config.java_path {
+            Some(java_path) => Some((java_path.clone(), java_major_version(java_path).await?)),
+            None => None,
+        };
+        let path_to_runtimes = config
+            .runtimes_path
+            .clone()
+            .unwrap_or_else(|| path_to_binaries().to_owned());
 
         // Step 1: Create Directories
         event_broadcaster.send(Event::new_progression_event_update(
@@ -450,87 +1443,146 @@ impl MinecraftInstance {
         tokio::fs::create_dir_all(&path_to_instance)
             .await
             .and(tokio::fs::create_dir_all(&path_to_macros).await)
-            .and(tokio::fs::create_dir_all(&path_to_resources.join("mods")).await)
-            .and(tokio::fs::create_dir_all(&path_to_resources.join("worlds")).await)
-            .and(tokio::fs::create_dir_all(&path_to_resources.join("defaults")).await)
             .and(tokio::fs::write(&path_to_eula, "#generated by Lodestone\neula=true").await)
-            .and(
-                tokio::fs::write(&path_to_properties, format!("server-port={}", config.port)).await,
-            )
             .context("Could not create some files or directories for instance")
             .map_err(|e| {
                 error!("{e}");
                 e
             })?;
-
-        // Step 2: Download JRE
-        let (url, jre_major_version) = get_jre_url(config.version.as_str())
-            .await
-            .context("Could not get JRE URL")?;
-        if !path_to_runtimes
-            .join("java")
-            .join(format!("jre{}", jre_major_version))
-            .exists()
-        {
-            let downloaded = download_file(
-                &url,
-                &path_to_runtimes.join("java"),
-                None,
-                {
-                    let event_broadcaster = event_broadcaster.clone();
-                    &move |dl| {
-                        if let Some(total) = dl.total {
-                            event_broadcaster.send(Event::new_progression_event_update(
-                                progression_event_id,
-                                format!(
-                                    "2/4: Downloading JRE {}",
-                                    format_byte_download(dl.downloaded, total)
-                                ),
-                                (dl.step as f64 / total as f64) * 4.0,
-                            ));
-                        }
-                    }
-                },
-                true,
-            )
-            .await?;
-
-            let unzipped_content = unzip_file_async(
-                &downloaded,
-                UnzipOption::ToDir(path_to_runtimes.join("java")),
-            )
-            .await?;
-            if unzipped_content.len() != 1 {
-                return Err(eyre!(
-                    "Expected only one file in the JRE archive, got {}",
-                    unzipped_content.len()
-                )
-                .into());
+        create_resource_dirs(&path_to_resources).await?;
+        // keep the properties of a server that is being adopted, only setting the managed keys
+        if path_to_properties.is_file() && !config.overwrite_properties.unwrap_or(false) {
+            let mut properties = read_properties_from_path(&path_to_properties).await?;
+            properties.insert("server-port".to_string(), config.port.to_string());
+            if let Some(level_name) = &config.level_name {
+                properties.insert("level-name".to_string(), level_name.clone());
             }
-
-            tokio::fs::remove_file(&downloaded).await.context(format!(
-                "Could not remove downloaded JRE file {}",
-                downloaded.display()
-            ))?;
-
-            tokio::fs::rename(
-                unzipped_content.iter().last().unwrap(),
-                path_to_runtimes
-                    .join("java")
-                    .join(format!("jre{}", jre_major_version)),
-            )
-            .await
-            .context(format!(
-                "Could not rename JRE directory {}",
-                unzipped_content.iter().last().unwrap().display()
-            ))?;
+            if let Some(level_seed) = &config.level_seed {
+                properties.insert("level-seed".to_string(), escape_property_value(level_seed));
+            }
+            if let Some(difficulty) = &config.difficulty {
+                properties.insert("difficulty".to_string(), difficulty.clone());
+            }
+            if let Some(gamemode) = &config.gamemode {
+                properties.insert("gamemode".to_string(), gamemode.clone());
+            }
+            if let Some(motd) = &config.motd {
+                properties.insert("motd".to_string(), escape_property_value(motd));
+            }
+            if let Some(max_players) = config.max_players {
+                properties.insert("max-players".to_string(), max_players.to_string());
+            }
+            write_properties_to_path(&path_to_properties, &properties).await?;
         } else {
+            let mut properties = format!("server-port={}", config.port);
+            if let Some(level_name) = &config.level_name {
+                properties.push_str(&format!("\nlevel-name={}", level_name));
+            }
+            if let Some(level_seed) = &config.level_seed {
+                properties.push_str(&format!(
+                    "\nlevel-seed={}",
+                    escape_property_value(level_seed)
+                ));
+            }
+            if let Some(difficulty) = &config.difficulty {
+                properties.push_str(&format!("\ndifficulty={}", difficulty));
+            }
+            if let Some(gamemode) = &config.gamemode {
+                properties.push_str(&format!("\ngamemode={}", gamemode));
+            }
+            if let Some(motd) = &config.motd {
+                properties.push_str(&format!("\nmotd={}", escape_property_value(motd)));
+            }
+            if let Some(max_players) = config.max_players {
+                properties.push_str(&format!("\nmax-players={}", max_players));
+            }
+            tokio::fs::write(&path_to_properties, properties)
+                .await
+                .context("Could not create server.properties for instance")?;
+        }
+
+        // Step 2: Download JRE, unless a java binary was supplied
+        let jre_major_version = if let Some((java_path, jre_major_version)) = &custom_java {
             event_broadcaster.send(Event::new_progression_event_update(
                 progression_event_id,
-                "2/4: JRE already downloaded",
+                format!(
+                    "2/4: Using java {} at {}",
+                    jre_major_version,
+                    java_path.display()
+                ),
                 4.0,
             ));
-        }
+            *jre_major_version
+        } else {
+            let (url, jre_major_version) = get_jre_url(config.version.as_str())
+                .await
+                .context("Could not get JRE URL")?;
+            if !path_to_runtimes
+                .join("java")
+                .join(format!("jre{}", jre_major_version))
+                .exists()
+            {
+                let downloaded = download_file(
+                    &url,
+                    &path_to_runtimes.join("java"),
+                    None,
+                    {
+                        let event_broadcaster = event_broadcaster.clone();
+                        &move |dl| {
+                            if let Some(total) = dl.total {
+                                event_broadcaster.send(Event::new_progression_event_update(
+                                    progression_event_id,
+                                    format!(
+                                        "2/4: Downloading JRE {}",
+                                        format_byte_download(dl.downloaded, total)
+                                    ),
+                                    (dl.step as f64 / total as f64) * 4.0,
+                                ));
+                            }
+                        }
+                    },
+                    true,
+                )
+                .await?;
+
+                let unzipped_content = unzip_file_async(
+                    &downloaded,
+                    UnzipOption::ToDir(path_to_runtimes.join("java")),
+                )
+                .await?;
+                if unzipped_content.len() != 1 {
+                    return Err(eyre!(
+                        "Expected only one file in the JRE archive, got {}",
+                        unzipped_content.len()
+                    )
+                    .into());
+                }
+
+                tokio::fs::remove_file(&downloaded).await.context(format!(
+                    "Could not remove downloaded JRE file {}",
+                    downloaded.display()
+                ))?;
+
+                tokio::fs::rename(
+                    unzipped_content.iter().last().unwrap(),
+                    path_to_runtimes
+                        .join("java")
+                        .join(format!("jre{}", jre_major_version)),
+                )
+                .await
+                .context(format!(
+                    "Could not rename JRE directory {}",
+                    unzipped_content.iter().last().unwrap().display()
+                ))?;
+            } else {
+                event_broadcaster.send(Event::new_progression_event_update(
+                    progression_event_id,
+                    "2/4: JRE already downloaded",
+                    4.0,
+                ));
+            }
+            jre_major_version
+        };
 
         // Step 3: Download server.jar
         let flavour_name = config.flavour.to_string();
@@ -547,6 +1599,9 @@ impl MinecraftInstance {
             })?;
         let jar_name = match flavour {
             Flavour::Forge { .. } => "forge-installer.jar",
+            Flavour::NeoForge { .. } => "neoforge-installer.jar",
+            Flavour::Quilt { .. } => "quilt-installer.jar",
+            Flavour::Spigot => "BuildTools.jar",
             _ => "server.jar",
         };
 
@@ -585,27 +1640,35 @@ impl MinecraftInstance {
             true,
         )
         .await?;
-        let jre = path_to_runtimes
-            .join("java")
-            .join(format!("jre{}", jre_major_version))
-            .join(if std::env::consts::OS == "macos" {
-                "Contents/Home/bin"
-            } else {
-                "bin"
-            })
-            .join("java");
+        let jre = match &custom_java {
+            Some((java_path, _)) => java_path.clone(),
+            None => path_to_runtimes
+                .join("java")
+                .join(format!("jre{}", jre_major_version))
+                .join(if std::env::consts::OS == "macos" {
+                    "Contents/Home/bin"
+                } else {
+                    "bin"
+                })
+                .join("java"),
+        };
         // Step 3 (part 2): Forge Setup
-        if let Flavour::Forge { .. } = flavour.clone() {
+        let mut launch_args_file = None;
+        if let Flavour::Forge { .. } | Flavour::NeoForge { .. } = flavour.clone() {
+            let forge_name = match flavour {
+                Flavour::NeoForge { .. } => "NeoForge",
+                _ => "Forge",
+            };
             event_broadcaster.send(Event::new_progression_event_update(
                 progression_event_id,
-                "3/4: Installing Forge Server",
+                format!("3/4: Installing {} Server", forge_name),
                 1.0,
             ));
 
             if !dont_spawn_terminal(
                 Command::new(&jre)
                     .arg("-jar")
-                    .arg(&path_to_instance.join("forge-installer.jar"))
+                    .arg(&path_to_instance.join(jar_name))
                     .arg("--installServer")
                     .arg(&path_to_instance)
                     .current_dir(&path_to_instance),
@@ -614,13 +1677,20 @@ impl MinecraftInstance {
             .stdout(Stdio::null())
             .stdin(Stdio::null())
             .spawn()
-            .context("Failed to start forge-installer.jar")?
+            .context(format!("Failed to start {}", jar_name))?
             .wait()
             .await
-            .context("forge-installer.jar failed")?
+            .context(format!("{} failed", jar_name))?
             .success()
             {
-                return Err(eyre!("Failed to install forge server").into());
+                return Err(eyre!("Failed to install {} server", forge_name).into());
+            }
+
+            if let Flavour::NeoForge { .. } = flavour {
+                let args_file = find_neoforge_args_file(&path_to_instance).ok_or_else(|| {
+                    eyre!("NeoForge was installed but the arguments file it generates is missing")
+                })?;
+                launch_args_file = Some(args_file);
             }
 
             tokio::fs::write(
@@ -630,6 +1700,69 @@ impl MinecraftInstance {
             .await
             .context("Could not create user_jvm_args.txt")?;
         }
+        // Step 3 (part 2): Quilt Setup
+        if let Flavour::Quilt {
+            loader_version: Some(QuiltLoaderVersion(loader_version)),
+            ..
+        } = &flavour
+        {
+            event_broadcaster.send(Event::new_progression_event_update(
+                progression_event_id,
+                "3/4: Installing Quilt Server",
+                1.0,
+            ));
+
+            let mut install_dir = std::ffi::OsString::from("--install-dir=");
+            install_dir.push(&path_to_instance);
+            if !dont_spawn_terminal(
+                Command::new(&jre)
+                    .arg("-jar")
+                    .arg(&path_to_instance.join("quilt-installer.jar"))
+                    .arg("install")
+                    .arg("server")
+                    .arg(&config.version)
+                    .arg(loader_version)
+                    .arg("--download-server")
+                    .arg(install_dir)
+                    .current_dir(&path_to_instance),
+            )
+            .stderr(Stdio::null())
+            .stdout(Stdio::null())
+            .stdin(Stdio::null())
+            .spawn()
+            .context("Failed to start quilt-installer.jar")?
+            .wait()
+            .await
+            .context("quilt-installer.jar failed")?
+            .success()
+            {
+                return Err(eyre!("Failed to install quilt server").into());
+            }
+
+            tokio::fs::remove_file(&path_to_instance.join("quilt-installer.jar"))
+                .await
+                .context("Could not remove quilt-installer.jar")?;
+        }
+        // Step 3 (part 2): Spigot Build
+        if let Flavour::Spigot = flavour {
+            event_broadcaster.send(Event::new_progression_event_update(
+                progression_event_id,
+                "3/4: Building Spigot Server, this can take several minutes",
+                1.0,
+            ));
+            let build_tools_jar = path_to_instance.join("BuildTools.jar");
+            build_spigot(
+                &jre,
+                &build_tools_jar,
+                &path_to_instance.join("BuildTools"),
+                &config.version,
+                &path_to_instance.join("server.jar"),
+            )
+            .await?;
+            tokio::fs::remove_file(&build_tools_jar)
+                .await
+                .context("Could not remove BuildTools.jar")?;
+        }
 
         // Step 4: Finishing Up
         event_broadcaster.send(Event::new_progression_event_update(
@@ -650,9 +1783,45 @@ impl MinecraftInstance {
             auto_start: config.auto_start.unwrap_or(false),
             restart_on_crash: config.restart_on_crash.unwrap_or(false),
             backup_period: config.backup_period,
+            backups_enabled: true,
+            backup_include: default_backup_include(),
             jre_major_version,
             has_started: false,
             java_cmd: Some(jre.to_string_lossy().to_string()),
+            stdin_terminator: StdinTerminator::default(),
+            crash_restart_cooldown: None,
+            watchdog: None,
+            crash_loop_guard: default_crash_loop_guard(),
+            restart_schedule: None,
+            startup_timeout: None,
+            world_dir_override: None,
+            resources_dir_override: None,
+            backup_destination: None,
+            autosave_interval: None,
+            prune_backups_on_low_space: false,
+            backup_on_stop_mode: None,
+            backup_io_limit: None,
+            backup_retention: None,
+            timeout_settings: TimeoutSettings::default(),
+            already_running_policy: AlreadyRunningPolicy::default(),
+            java_agents: Vec::new(),
+            jvm_flags_preset: JvmFlagsPreset::default(),
+            server_locale: ServerLocale::default(),
+            commands_on_start: Vec::new(),
+            max_log_line_length: default_max_log_line_length(),
+            mirror_console_output: false,
+            cpu_affinity: None,
+            nice: None,
+            runtimes_path: config.runtimes_path,
+            java_path: config.java_path,
+            launch_args_file,
+            startup_history: Vec::new(),
+            prompt_responses: Vec::new(),
+            rcon_password: None,
+            manage_rcon: true,
+            rcon: RconConfig::default(),
+            maintenance_restore: None,
+            mod_loader_mismatch_policy: ModLoaderMismatchPolicy::default(),
         };
         // create config file
         tokio::fs::write(
@@ -666,13 +1835,17 @@ impl MinecraftInstance {
             "Failed to write config file at {}",
             &path_to_config.display()
         ))?;
-        MinecraftInstance::restore(
+        let instance = MinecraftInstance::restore(
             path_to_instance,
             dot_lodestone_config,
             event_broadcaster,
             macro_executor,
         )
-        .await
+        .await?;
+        if let (Some(true), Some(level_seed)) = (config.preseed_level_dat, &config.level_seed) {
+            instance.preseed_level_dat(level_seed).await?;
+        }
+        Ok(instance)
     }
 
     pub async fn restore(
@@ -682,19 +1855,43 @@ impl MinecraftInstance {
         macro_executor: MacroExecutor,
     ) -> Result<MinecraftInstance, Error> {
         let path_to_config = path_to_instance.join(".lodestone_minecraft_config.json");
-        let restore_config: RestoreConfig =
-            serde_json::from_reader(std::fs::File::open(&path_to_config).context(format!(
-                "Failed to open config file at {}",
-                &path_to_config.display()
-            ))?)
-            .context(
-                "Failed to deserialize config from string. Was the config file modified manually?",
-            )?;
+        let (restore_config, needs_repair) = match read_restore_config(&path_to_config).await {
+            Ok(restore_config) => (restore_config, None),
+            Err(e) => {
+                error!(
+                    "Failed to restore config of instance at {}, loading it for repair: {:?}",
+                    path_to_instance.display(),
+                    e
+                );
+                let name = path_to_instance
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                (
+                    placeholder_restore_config(name),
+                    Some(format!("{:#}", e.source)),
+                )
+            }
+        };
         let path_to_macros = path_to_instance.join("macros");
         let path_to_properties = path_to_instance.join("server.properties");
-        let path_to_runtimes = path_to_binaries().clone();
+        // an override that went missing shouldn't keep the instance from loading
+        let path_to_runtimes = match &restore_config.runtimes_path {
+            Some(runtimes_path) => match validate_dir_override(runtimes_path).await {
+                Ok(()) => runtimes_path.clone(),
+                Err(e) => {
+                    warn!(
+                        "Ignoring the runtimes directory override of instance at {}: {}",
+                        path_to_instance.display(),
+                        e
+                    );
+                    path_to_binaries().clone()
+                }
+            },
+            None => path_to_binaries().clone(),
+        };
         // if the properties file doesn't exist, create it
-        if !path_to_properties.exists() {
+        if !path_to_properties.exists() && needs_repair.is_none() {
             tokio::fs::write(
                 &path_to_properties,
                 format!("server-port={}", restore_config.port),
@@ -712,17 +1909,22 @@ impl MinecraftInstance {
             })
             .join("java");
 
+        let java_path = restore_config.java_path.clone().unwrap_or(java_path);
+
         let configurable_manifest = Arc::new(Mutex::new(Self::init_configurable_manifest(
             &restore_config,
             java_path.to_string_lossy().to_string(),
         )));
 
+        let (backup_sender, backup_receiver) = tokio::sync::mpsc::unbounded_channel();
         let instance = MinecraftInstance {
             state: Arc::new(Mutex::new(State::Stopped)),
             uuid: dot_lodestone_config.uuid().clone(),
             creation_time: dot_lodestone_config.creation_time(),
+            needs_repair,
             auto_start: Arc::new(AtomicBool::new(restore_config.auto_start)),
             restart_on_crash: Arc::new(AtomicBool::new(restore_config.restart_on_crash)),
+            mirror_console_output: Arc::new(AtomicBool::new(restore_config.mirror_console_output)),
             backup_period: restore_config.backup_period,
             players_manager: Arc::new(Mutex::new(PlayersManager::new(
                 event_broadcaster.clone(),
@@ -743,36 +1945,104 @@ impl MinecraftInstance {
             configurable_manifest,
             macro_name_to_last_run: Arc::new(Mutex::new(HashMap::new())),
             pid_to_task_entry: Arc::new(Mutex::new(IndexMap::new())),
+            pending_crash_restart: Arc::new(Mutex::new(None)),
+            watchdog_task: Arc::new(Mutex::new(None)),
+            startup_timeout_task: Arc::new(Mutex::new(None)),
+            startup_began_at: Arc::new(Mutex::new(None)),
+            running_since: Arc::new(Mutex::new(None)),
+            recent_crashes: Arc::new(Mutex::new(Vec::new())),
+            restart_after_exit: Arc::new(AtomicBool::new(false)),
+            stop_requested: Arc::new(AtomicBool::new(false)),
+            stdout_task: Arc::new(Mutex::new(None)),
+            autosave_task: Arc::new(Mutex::new(None)),
+            restart_schedule_task: Arc::new(Mutex::new(None)),
+            bound_port: Arc::new(Mutex::new(None)),
+            crash_cause: Arc::new(Mutex::new(None)),
+            detected_version: Arc::new(Mutex::new(None)),
+            last_launch_command: Arc::new(Mutex::new(None)),
+            backup_sender,
+            backup_task: Arc::new(Mutex::new(None)),
+            running_backup: Arc::new(Mutex::new(None)),
+            last_tps_report: Arc::new(Mutex::new(None)),
+            rcon_save_in_progress: Arc::new(AtomicBool::new(false)),
         };
-        instance
-            .read_properties()
-            .await
-            .context("Failed to read properties")?;
+        match instance.read_properties().await {
+            Ok(()) => {}
+            // the properties file isn't created for an instance loaded for repair
+            Err(e) if instance.needs_repair.is_some() => warn!(
+                "Failed to read properties of instance at {}, which needs repair: {}",
+                instance.path_to_instance.display(),
+                e
+            ),
+            Err(e) => Err(e).context("Failed to read properties")?,
+        }
+        instance.spawn_periodic_backup(backup_receiver).await;
+        instance.spawn_restart_schedule().await;
         Ok(instance)
     }
 
+    /// Writes the config to a temporary file first and renames it over the config, so that an
+    /// interrupted write never leaves a truncated config behind
+    ///
+    /// The config stays locked until the rename, since concurrent writers share the temporary file
     async fn write_config_to_file(&self) -> Result<(), Error> {
-        tokio::fs::write(
-            &self.path_to_config,
-            to_string_pretty(&*self.config.lock().await)
-                .context("Failed to serialize config to string, this is a bug, please report it")?,
-        )
-        .await
-        .context(format!(
-            "Failed to write config to file at {}",
-            &self.path_to_config.display()
-        ))?;
+        self.ensure_not_needs_repair()?;
+        let path_to_tmp_config = tmp_config_path(&self.path_to_config);
+        let config = self.config.lock().await;
+        let content = to_string_pretty(&*config)
+            .context("Failed to serialize config to string, this is a bug, please report it")?;
+        if let Err(e) = tokio::fs::write(&path_to_tmp_config, content)
+            .await
+            .context(format!(
+                "Failed to write config to file at {}",
+                &path_to_tmp_config.display()
+            ))
+        {
+            // the config is only replaced once the write completed, so it is still intact
+            let _ = tokio::fs::remove_file(&path_to_tmp_config).await;
+            drop(config);
+            let e = Error::from(e);
+            self.report_out_of_space(&e, &path_to_tmp_config).await;
+            return Err(e);
+        }
+        tokio::fs::rename(&path_to_tmp_config, &self.path_to_config)
+            .await
+            .context(format!(
+                "Failed to write config to file at {}",
+                &self.path_to_config.display()
+            ))?;
+        drop(config);
         Ok(())
     }
 
+    /// Refuses operations that need the real config while the instance is loaded for repair
+    fn ensure_not_needs_repair(&self) -> Result<(), Error> {
+        match &self.needs_repair {
+            Some(reason) => Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!(
+                    "The config at {} needs to be repaired and Lodestone restarted first: {}",
+                    self.path_to_config.display(),
+                    reason
+                ),
+            }),
+            None => Ok(()),
+        }
+    }
+
     async fn read_properties(&self) -> Result<(), Error> {
         let properties = read_properties_from_path(&self.path_to_properties).await?;
+        let version = self.config.lock().await.version.clone();
         let mut lock = self.configurable_manifest.lock().await;
         for (key, value) in properties.iter() {
             let _ = lock
                 .set_setting(
                     ServerPropertySetting::get_section_id(),
                     match ServerPropertySetting::from_key_val(key, value) {
+                        // kept as is, but not managed, for versions that ignore the property
+                        Ok(v) if !v.is_supported_by(&version) => {
+                            ServerPropertySetting::Unknown(key.clone(), value.clone()).into()
+                        }
                         Ok(v) => v.into(),
                         Err(e) => {
                             error!(
@@ -791,35 +2061,40 @@ impl MinecraftInstance {
     }
 
     async fn write_properties_to_file(&self) -> Result<(), Error> {
-        // open the file in write-only mode, returns `io::Result<File>`
-        let mut file = tokio::fs::File::create(&self.path_to_properties)
+        let configurable_manifest = self.configurable_manifest.lock().await;
+        self.write_properties_from_manifest(&configurable_manifest)
             .await
-            .context(format!(
-                "Failed to open properties file at {}",
-                &self.path_to_properties.display()
-            ))?;
-        let mut setting_str = "".to_string();
-        for (key, value) in self
-            .configurable_manifest
-            .lock()
+    }
+
+    /// Writes the properties of `configurable_manifest`, for callers that already hold the lock
+    ///
+    /// Like `write_config_to_file`, the properties are only replaced once the whole file is
+    /// written to a temporary file next to them
+    async fn write_properties_from_manifest(
+        &self,
+        configurable_manifest: &ConfigurableManifest,
+    ) -> Result<(), Error> {
+        let path_to_tmp_properties = tmp_config_path(&self.path_to_properties);
+        let setting_str = properties_to_string(configurable_manifest);
+        if let Err(e) = tokio::fs::write(&path_to_tmp_properties, setting_str)
             .await
-            .get_section(ServerPropertySetting::get_section_id())
-            .unwrap()
-            .all_settings()
-            .iter()
+            .context(format!(
+                "Failed to write properties to file at {}",
+                &path_to_tmp_properties.display()
+            ))
         {
-            // print the key and value separated by a =
-            // println!("{}={}", key, value);
-            setting_str.push_str(&format!(
-                "{}={}\n",
-                key,
-                value
-                    .get_value()
-                    .expect("Programming error, value is not set")
-                    .to_string()
-            ));
+            let _ = tokio::fs::remove_file(&path_to_tmp_properties).await;
+            let e = Error::from(e);
+            if is_out_of_space(&e) {
+                // reporting locks the config, which mustn't wait while the manifest is held
+                let __self = self.clone();
+                tokio::task::spawn(async move {
+                    __self.notify_out_of_space(&path_to_tmp_properties).await;
+                });
+            }
+            return Err(e);
         }
-        file.write_all(setting_str.as_bytes())
+        tokio::fs::rename(&path_to_tmp_properties, &self.path_to_properties)
             .await
             .context(format!(
                 "Failed to write properties to file at {}",
@@ -890,20 +2165,2919 @@ impl MinecraftInstance {
         self.rcon_conn.clone()
     }
 
+    /// Sends a command over RCON, dialing first if there is no connection yet and redialing once
+    /// if the connection went stale, e.g. because the server restarted
     pub async fn send_rcon(&self, cmd: &str) -> Result<String, Error> {
-        let a = self
-            .rcon_conn
-            .lock()
+        // held across the redial so concurrent callers don't each open a connection
+        let mut rcon_conn = self.rcon_conn.lock().await;
+        if let Some(rcon) = rcon_conn.as_mut() {
+            let e = match rcon.cmd(cmd).await {
+                Ok(response) => return Ok(response),
+                Err(e) => e,
+            };
+            warn!("RCON connection failed, reconnecting: {}", e);
+            rcon_conn.take();
+        }
+        let mut rcon = self.dial_rcon().await?;
+        let response = rcon.cmd(cmd).await.context("Failed to send rcon command")?;
+        rcon_conn.replace(rcon);
+        Ok(response)
+    }
+
+    /// Changes the display name of the instance
+    ///
+    /// The instance directory is keyed by uuid and is left untouched, so this is safe while running
+    pub async fn rename(&self, new_name: String) -> Result<(), Error> {
+        let new_name = new_name.trim().to_string();
+        if new_name.is_empty() {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("Name cannot be empty"),
+            });
+        }
+        if new_name.chars().count() > 100 {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("Name cannot be longer than 100 characters"),
+            });
+        }
+        if new_name.chars().any(char::is_control) {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("Name cannot contain control characters"),
+            });
+        }
+        let old_name = std::mem::replace(&mut self.config.lock().await.name, new_name.clone());
+        if old_name == new_name {
+            return Ok(());
+        }
+        if let Err(e) = self.write_config_to_file().await {
+            self.config.lock().await.name = old_name;
+            return Err(e);
+        }
+        self.event_broadcaster.send(Event {
+            event_inner: EventInner::InstanceEvent(InstanceEvent {
+                instance_uuid: self.uuid.clone(),
+                instance_name: new_name.clone(),
+                instance_event_inner: InstanceEventInner::InstanceRenamed { old_name, new_name },
+            }),
+            details: "Instance renamed".to_string(),
+            snowflake: Snowflake::default(),
+            caused_by: CausedBy::System,
+        });
+        Ok(())
+    }
+
+    /// Stops the server if needed and waits for all background tasks of the instance to finish
+    pub async fn shutdown(self) {
+        let name = self.config.lock().await.name.clone();
+        // these would otherwise restart the server while we are tearing it down
+        for task in [
+            self.pending_crash_restart.lock().await.take(),
+            self.watchdog_task.lock().await.take(),
+            self.startup_timeout_task.lock().await.take(),
+            self.autosave_task.lock().await.take(),
+            self.restart_schedule_task.lock().await.take(),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            task.abort();
+            let _ = task.await;
+        }
+        // aborting a backup would leave a partial archive behind, so let it finish
+        let _ = self.backup_sender.send(BackupInstruction::Shutdown);
+        let backup_task = self.backup_task.lock().await.take();
+        if let Some(backup_task) = backup_task {
+            if let Err(e) = backup_task.await {
+                error!("[{}] Backup task failed during shutdown: {}", name, e);
+            }
+        }
+        match self.state().await {
+            State::Running => {
+                if let Err(e) = self.stop(CausedBy::System, true).await {
+                    error!("[{}] Failed to stop instance during shutdown: {}", name, e);
+                }
+            }
+            State::Starting | State::Error => {
+                let _ = self.kill(CausedBy::System).await;
+            }
+            State::Stopping | State::Stopped | State::BackingUp | State::Updating => {}
+        }
+        // finishes once the server process has exited and its output is drained
+        let stdout_task = self.stdout_task.lock().await.take();
+        if let Some(stdout_task) = stdout_task {
+            if let Err(e) = stdout_task.await {
+                error!("[{}] Output task failed during shutdown: {}", name, e);
+            }
+        }
+    }
+
+    /// Unix timestamp in seconds of when the instance was created
+    pub async fn created_at(&self) -> i64 {
+        self.creation_time
+    }
+
+    /// Most RAM the server may use in megabytes
+    pub async fn max_ram(&self) -> u32 {
+        self.config.lock().await.max_ram
+    }
+
+    pub async fn age(&self) -> Duration {
+        let age = chrono::Utc::now().timestamp() - self.created_at().await;
+        Duration::from_secs(age.max(0) as u64)
+    }
+
+    pub async fn connection_info(&self) -> ConnectionInfo {
+        ConnectionInfo {
+            configured_port: self.config.lock().await.port,
+            bound_port: *self.bound_port.lock().await,
+        }
+    }
+
+    pub async fn version_info(&self) -> VersionInfo {
+        let config = self.config.lock().await.clone();
+        let configured_build = match config.flavour {
+            Flavour::Paper {
+                build_version: Some(PaperBuildVersion(build)),
+            } => Some(build.to_string()),
+            Flavour::Forge {
+                build_version: Some(ForgeBuildVersion(build)),
+            }
+            | Flavour::NeoForge {
+                build_version: Some(ForgeBuildVersion(build)),
+            } => Some(build),
+            _ => None,
+        };
+        let detected_version = self.detected_version.lock().await.clone();
+        VersionInfo {
+            configured_version: config.version,
+            configured_build,
+            detected_version: detected_version.as_ref().map(|v| v.version.clone()),
+            detected_build: detected_version.and_then(|v| v.build),
+        }
+    }
+
+    /// Records the version the server reported, warning if it isn't what lodestone installed
+    ///
+    /// Paper logs the vanilla version line before its own, so a build already detected for the
+    /// same version is kept
+    async fn record_server_version(&self, server_version: ServerVersion) {
+        let was_drifted = self.version_info().await.drifted();
+        {
+            let mut detected_version = self.detected_version.lock().await;
+            let build = server_version.build.or_else(|| {
+                detected_version
+                    .as_ref()
+                    .filter(|detected| detected.version == server_version.version)
+                    .and_then(|detected| detected.build.clone())
+            });
+            *detected_version = Some(ServerVersion {
+                version: server_version.version,
+                build,
+            });
+        }
+        let version_info = self.version_info().await;
+        if was_drifted || !version_info.drifted() {
+            return;
+        }
+        let name = self.config.lock().await.name.clone();
+        let message = format!(
+            "Server is running version {}{} but {}{} is installed",
+            version_info.detected_version.unwrap_or_default(),
+            version_info
+                .detected_build
+                .map(|build| format!(" build {build}"))
+                .unwrap_or_default(),
+            version_info.configured_version,
+            version_info
+                .configured_build
+                .map(|build| format!(" build {build}"))
+                .unwrap_or_default(),
+        );
+        warn!("[{}] {}", name, message);
+        self.event_broadcaster.send(Event {
+            event_inner: EventInner::InstanceEvent(InstanceEvent {
+                instance_uuid: self.uuid.clone(),
+                instance_name: name,
+                instance_event_inner: InstanceEventInner::InstanceWarning { message },
+            }),
+            details: "".to_string(),
+            snowflake: Snowflake::default(),
+            caused_by: CausedBy::System,
+        });
+    }
+
+    /// Records the port the server reported binding to, warning if it isn't the configured one
+    async fn set_bound_port(&self, bound_port: Option<u16>) {
+        *self.bound_port.lock().await = bound_port;
+        let connection_info = self.connection_info().await;
+        if !connection_info.port_mismatch() {
+            return;
+        }
+        let name = self.config.lock().await.name.clone();
+        let message = format!(
+            "Server is listening on port {} instead of the configured port {}",
+            connection_info.bound_port.unwrap_or_default(),
+            connection_info.configured_port
+        );
+        warn!("[{}] {}", name, message);
+        self.event_broadcaster.send(Event {
+            event_inner: EventInner::InstanceEvent(InstanceEvent {
+                instance_uuid: self.uuid.clone(),
+                instance_name: name,
+                instance_event_inner: InstanceEventInner::InstanceWarning { message },
+            }),
+            details: "".to_string(),
+            snowflake: Snowflake::default(),
+            caused_by: CausedBy::System,
+        });
+    }
+
+    /// Ports the server binds, with RCON and query only counted when enabled
+    pub async fn bound_ports(&self) -> Vec<(PortKind, u32)> {
+        let mut ports = vec![(PortKind::Game, self.config.lock().await.port)];
+        let lock = self.configurable_manifest.lock().await;
+        let get_bool = |key: &str| {
+            lock.get_unique_setting_key(key)
+                .and_then(|v| v.get_value().map(|v| v.try_as_boolean().ok()))
+                .flatten()
+        };
+        let get_port = |key: &str| {
+            lock.get_unique_setting_key(key)
+                .and_then(|v| v.get_value().map(|v| v.try_as_unsigned_integer().ok()))
+                .flatten()
+        };
+        if let (Some(true), Some(rcon_port)) = (get_bool("enable-rcon"), get_port("rcon.port")) {
+            ports.push((PortKind::Rcon, rcon_port));
+        }
+        if let (Some(true), Some(query_port)) = (get_bool("enable-query"), get_port("query.port")) {
+            ports.push((PortKind::Query, query_port));
+        }
+        ports
+    }
+
+    pub async fn crash_cause(&self) -> Option<CrashCause> {
+        self.crash_cause.lock().await.clone()
+    }
+
+    /// Finds a process matching `predicate`, other than the server process this instance tracks
+    async fn find_untracked_process(
+        &self,
+        predicate: impl Fn(&sysinfo::Process) -> bool,
+    ) -> Option<ConflictingProcess> {
+        let own_pid = self.process.lock().await.as_ref().and_then(|p| p.id());
+        let mut sys = self.system.lock().await;
+        sys.refresh_processes();
+        sys.processes()
+            .values()
+            .filter(|process| Some(process.pid().as_u32()) != own_pid)
+            .find(|process| predicate(process))
+            .map(|process| ConflictingProcess {
+                pid: process.pid().as_u32(),
+                name: process.name().to_string(),
+            })
+    }
+
+    /// Looks for a server left running out of this instance's directory, e.g. by a previous run
+    /// of lodestone that didn't get to stop it
+    async fn find_orphaned_server(&self) -> Option<ConflictingProcess> {
+        self.find_untracked_process(|process| {
+            is_server_process(
+                process.name(),
+                process.cmd(),
+                process.cwd(),
+                &self.path_to_instance,
+            )
+        })
+        .await
+    }
+
+    /// Looks for a process that was passed `port` as an argument
+    ///
+    /// Which process owns a socket isn't exposed by sysinfo, so this is only a guess at what is
+    /// holding on to the port, and isn't offered to be killed
+    async fn find_process_passed_port(&self, port: u32) -> Option<ConflictingProcess> {
+        let port = port.to_string();
+        self.find_untracked_process(|process| {
+            process
+                .cmd()
+                .iter()
+                .any(|arg| arg == &port || arg.rsplit_once(':').map_or(false, |(_, p)| p == port))
+        })
+        .await
+    }
+
+    /// Kills a process found by `find_untracked_process`
+    async fn kill_untracked_process(&self, target: &ConflictingProcess) -> Result<(), Error> {
+        let mut sys = self.system.lock().await;
+        let pid = sysinfo::Pid::from_u32(target.pid);
+        sys.refresh_process(pid);
+        // the pid could have been reused since the process was found
+        match sys.process(pid) {
+            Some(process) if process.name() == target.name => {
+                if process.kill() {
+                    Ok(())
+                } else {
+                    Err(Error {
+                        kind: ErrorKind::Internal,
+                        source: eyre!("Failed to kill process {}", target.pid),
+                    })
+                }
+            }
+            _ => Err(Error {
+                kind: ErrorKind::NotFound,
+                source: eyre!("Process {} is no longer running", target.pid),
+            }),
+        }
+    }
+
+    /// Whether an automatic start, on launch or after a crash, should go ahead
+    ///
+    /// Starting while a server is already up would spawn a second java process for the same world
+    pub async fn should_auto_start(&self) -> bool {
+        let config = self.config.lock().await.clone();
+        let state = *self.state.lock().await;
+        if state != State::Stopped {
+            info!(
+                "[{}] Instance is already {:?}, skipping automatic start",
+                config.name, state
+            );
+            return false;
+        }
+        let orphan = match self.find_orphaned_server().await {
+            Some(orphan) => orphan,
+            None => return true,
+        };
+        match config.already_running_policy {
+            AlreadyRunningPolicy::Skip => {
+                warn!(
+                    "[{}] {} (pid {}) is already running in the instance directory, skipping automatic start",
+                    config.name, orphan.name, orphan.pid
+                );
+                false
+            }
+            AlreadyRunningPolicy::Replace => {
+                warn!(
+                    "[{}] Killing {} (pid {}) left running in the instance directory",
+                    config.name, orphan.name, orphan.pid
+                );
+                match self.kill_untracked_process(&orphan).await {
+                    Ok(()) => true,
+                    Err(e) => {
+                        error!("[{}] Failed to kill leftover server: {}", config.name, e);
+                        false
+                    }
+                }
+            }
+        }
+    }
+
+    pub async fn set_already_running_policy(
+        &self,
+        already_running_policy: AlreadyRunningPolicy,
+    ) -> Result<(), Error> {
+        self.config.lock().await.already_running_policy = already_running_policy;
+        self.write_config_to_file().await
+    }
+
+    pub async fn set_mod_loader_mismatch_policy(
+        &self,
+        mod_loader_mismatch_policy: ModLoaderMismatchPolicy,
+    ) -> Result<(), Error> {
+        self.config.lock().await.mod_loader_mismatch_policy = mod_loader_mismatch_policy;
+        self.write_config_to_file().await
+    }
+
+    async fn mods_dirs(&self) -> Vec<PathBuf> {
+        vec![
+            self.path_to_instance.join("mods"),
+            self.path_to_resources().await.join("mods"),
+        ]
+    }
+
+    async fn mod_count(&self) -> usize {
+        let mods_dirs = self.mods_dirs().await;
+        tokio::task::spawn_blocking(move || count_mods(&mods_dirs))
+            .await
+            .unwrap_or(0)
+    }
+
+    /// Asks the running server over RCON which plugins or mods it loaded
+    ///
+    /// Paper and Spigot are asked with `plugins`, Forge and NeoForge with their mod list command.
+    /// Other flavours have no such command
+    pub async fn runtime_installed_content(&self) -> Result<Vec<String>, Error> {
+        let command = match FlavourKind::from(&self.config.lock().await.flavour) {
+            FlavourKind::Paper | FlavourKind::Spigot => "plugins",
+            FlavourKind::Forge => "forge mods",
+            FlavourKind::NeoForge => "neoforge mods",
+            _ => {
+                return Err(Error {
+                    kind: ErrorKind::UnsupportedOperation,
+                    source: eyre!("This flavour can't list the plugins or mods it loaded"),
+                })
+            }
+        };
+        if *self.state.lock().await != State::Running {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("The server must be running to list what it loaded"),
+            });
+        }
+        Ok(parse_content_list(&self.send_rcon(command).await?))
+    }
+
+    /// The plugins or mods the server loaded, along with the jars on disk it didn't load
+    pub async fn runtime_content(&self) -> Result<RuntimeContent, Error> {
+        let loaded = self.runtime_installed_content().await?;
+        let dirs = match FlavourKind::from(&self.config.lock().await.flavour) {
+            FlavourKind::Paper | FlavourKind::Spigot => vec![self.path_to_instance.join("plugins")],
+            _ => self.mods_dirs().await,
+        };
+        let listed = loaded.clone();
+        let not_loaded = tokio::task::spawn_blocking(move || find_not_loaded(&dirs, &listed))
             .await
-            .as_mut()
-            .ok_or_else(|| {
-                eyre!("Failed to send rcon command, rcon connection is not initialized")
-            })?
-            .cmd(cmd)
+            .context("Failed to read plugins and mods in a blocking task")?;
+        Ok(RuntimeContent { loaded, not_loaded })
+    }
+
+    /// Looks for duplicate Fabric mods and for dependencies that are missing or of the wrong
+    /// version among the installed mods, before they crash the server on start
+    pub async fn validate_mods(&self) -> Result<Vec<ModIssue>, Error> {
+        match FlavourKind::from(&self.config.lock().await.flavour) {
+            FlavourKind::Fabric | FlavourKind::Quilt => {}
+            _ => {
+                return Err(Error {
+                    kind: ErrorKind::UnsupportedOperation,
+                    source: eyre!("Only Fabric mods can be checked for conflicts"),
+                })
+            }
+        }
+        let mods_dirs = self.mods_dirs().await;
+        Ok(
+            tokio::task::spawn_blocking(move || scan_mod_issues(&mods_dirs))
+                .await
+                .context("Failed to scan mods in a blocking task")?,
+        )
+    }
+
+    /// Looks for mods made for another loader than the flavour's, which crash the server on start
+    ///
+    /// Depending on the policy the start is refused or a warning listing the mods is emitted
+    async fn check_mod_loaders(&self, config: &RestoreConfig) -> Result<(), Error> {
+        let flavour = FlavourKind::from(&config.flavour);
+        let mods_dirs = self.mods_dirs().await;
+        let mismatched_mods = tokio::task::spawn_blocking(move || {
+            mods_dirs
+                .iter()
+                .flat_map(|mods_dir| find_mismatched_mods(mods_dir, flavour))
+                .collect::<Vec<_>>()
+        })
+        .await
+        .context("Failed to scan mods in a blocking task")?;
+        if mismatched_mods.is_empty() {
+            return Ok(());
+        }
+        let message = format!(
+            "Mods not made for {:?} found: {}",
+            flavour,
+            mismatched_mods
+                .iter()
+                .map(|mismatched_mod| format!(
+                    "{} ({:?})",
+                    mismatched_mod.file_name, mismatched_mod.loaders
+                ))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        if config.mod_loader_mismatch_policy == ModLoaderMismatchPolicy::Block {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("{}", message),
+            });
+        }
+        warn!("[{}] {}", config.name, message);
+        self.event_broadcaster.send(Event {
+            event_inner: EventInner::InstanceEvent(InstanceEvent {
+                instance_uuid: self.uuid.clone(),
+                instance_name: config.name.clone(),
+                instance_event_inner: InstanceEventInner::InstanceWarning { message },
+            }),
+            details: "".to_string(),
+            snowflake: Snowflake::default(),
+            caused_by: CausedBy::System,
+        });
+        Ok(())
+    }
+
+    async fn load_bukkit_config(&self, file: BukkitConfigFile) -> Result<BukkitConfig, Error> {
+        let flavour = FlavourKind::from(&self.config.lock().await.flavour);
+        if !file.is_supported_by(flavour) {
+            return Err(Error {
+                kind: ErrorKind::UnsupportedOperation,
+                source: eyre!(
+                    "{} is not used by {} servers",
+                    file.relative_path(),
+                    flavour.to_string()
+                ),
+            });
+        }
+        BukkitConfig::load(&self.path_to_instance, file).await
+    }
+
+    pub async fn bukkit_settings(
+        &self,
+        file: BukkitConfigFile,
+    ) -> Result<BTreeMap<String, BukkitSettingValue>, Error> {
+        Ok(self.load_bukkit_config(file).await?.settings())
+    }
+
+    /// Sets several managed keys of one of the YAML configs at once, the server has to be
+    /// restarted for them to take effect
+    pub async fn update_bukkit_settings(
+        &self,
+        file: BukkitConfigFile,
+        changes: BTreeMap<String, BukkitSettingValue>,
+    ) -> Result<(), Error> {
+        let mut bukkit_config = self.load_bukkit_config(file).await?;
+        bukkit_config.update_settings(changes)?;
+        bukkit_config.save().await
+    }
+
+    /// Streams this instance's events, narrowed down further by `filter`
+    ///
+    /// The instance uuid of the filter is ignored, events of other instances are never included
+    pub fn subscribe_events(&self, filter: EventFilter) -> impl Stream<Item = Event> {
+        self.event_broadcaster.subscribe_filtered(EventFilter {
+            instance_uuid: Some(self.uuid.clone()),
+            ..filter
+        })
+    }
+
+    /// Waits for the server to print anything, `false` if it stays silent for `timeout`
+    ///
+    /// `event_receiver` has to be subscribed before the command is written, otherwise the
+    /// response could be missed
+    async fn wait_for_output(&self, event_receiver: Receiver<Event>, timeout: Duration) -> bool {
+        self.wait_for_output_matching(event_receiver, timeout, |_| true)
             .await
-            .context("Failed to send rcon command")?;
-        Ok(a)
     }
-}
 
-impl TInstance for MinecraftInstance {}
+    /// Waits for the server to print a line matching `predicate`, `false` if it doesn't within
+    /// `timeout`
+    async fn wait_for_output_matching(
+        &self,
+        mut event_receiver: Receiver<Event>,
+        timeout: Duration,
+        predicate: impl Fn(&str) -> bool,
+    ) -> bool {
+        tokio::time::timeout(timeout, async {
+            loop {
+                match event_receiver.recv().await {
+                    Ok(Event {
+                        event_inner:
+                            EventInner::InstanceEvent(InstanceEvent {
+                                instance_uuid,
+                                instance_event_inner: InstanceEventInner::InstanceOutput { message },
+                                ..
+                            }),
+                        ..
+                    }) if instance_uuid == self.uuid && predicate(&message) => return true,
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return false,
+                }
+            }
+        })
+        .await
+        .unwrap_or(false)
+    }
+
+    /// Sends a console command and checks that the server printed something in response
+    ///
+    /// Returns `false` and warns if the server stayed silent, in which case it is possibly hung
+    /// and the command was not processed
+    pub async fn send_command_confirmed(
+        &self,
+        command: &str,
+        cause_by: CausedBy,
+    ) -> Result<bool, Error> {
+        let event_receiver = self.event_broadcaster.subscribe();
+        self.send_command(command, cause_by).await?;
+        if self
+            .wait_for_output(event_receiver, STDIN_RESPONSE_TIMEOUT)
+            .await
+        {
+            return Ok(true);
+        }
+        let name = self.config.lock().await.name.clone();
+        let message = format!(
+            "Command \"{}\" was possibly not processed, the server did not respond within {} seconds",
+            command,
+            STDIN_RESPONSE_TIMEOUT.as_secs()
+        );
+        warn!("[{}] {}", name, message);
+        self.event_broadcaster.send(Event {
+            event_inner: EventInner::InstanceEvent(InstanceEvent {
+                instance_uuid: self.uuid.clone(),
+                instance_name: name,
+                instance_event_inner: InstanceEventInner::InstanceWarning { message },
+            }),
+            details: "".to_string(),
+            snowflake: Snowflake::default(),
+            caused_by: CausedBy::System,
+        });
+        Ok(false)
+    }
+
+    /// Saves the world, then stops the server and kills it if it hasn't exited within `timeout`
+    ///
+    /// The save goes over RCON if connected and over stdin otherwise. The stop goes ahead even
+    /// if the save isn't confirmed, since the server saves on stop as well
+    pub async fn stop_graceful(&self, timeout: Duration) -> Result<(), Error> {
+        if self.state().await != State::Running {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("Instance is not running"),
+            });
+        }
+        let name = self.name().await;
+        let is_save_confirmation = |line: &str| {
+            SAVE_CONFIRMATIONS
+                .iter()
+                .any(|needle| line.contains(needle))
+        };
+        let saved = match self.send_rcon("save-all flush").await {
+            Ok(response) => is_save_confirmation(&response),
+            Err(_) => {
+                let event_receiver = self.event_broadcaster.subscribe();
+                self.send_command("save-all flush", CausedBy::System)
+                    .await?;
+                self.wait_for_output_matching(event_receiver, timeout, is_save_confirmation)
+                    .await
+            }
+        };
+        if !saved {
+            warn!(
+                "[{}] Saving the world was not confirmed, stopping anyway",
+                name
+            );
+        }
+
+        let mut event_receiver = self.event_broadcaster.subscribe();
+        self.stop(CausedBy::System, false).await?;
+        let stopped = tokio::time::timeout(timeout, async {
+            loop {
+                match event_receiver.recv().await {
+                    Ok(Event {
+                        event_inner:
+                            EventInner::InstanceEvent(InstanceEvent {
+                                instance_uuid,
+                                instance_event_inner:
+                                    InstanceEventInner::StateTransition { to: State::Stopped },
+                                ..
+                            }),
+                        ..
+                    }) if instance_uuid == self.uuid => return true,
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return false,
+                }
+            }
+        })
+        .await
+        .unwrap_or(false);
+        if stopped || self.state().await == State::Stopped {
+            return Ok(());
+        }
+        let message = format!(
+            "Server did not exit within {} seconds of stopping, killing it",
+            timeout.as_secs()
+        );
+        warn!("[{}] {}", name, message);
+        self.event_broadcaster.send(Event {
+            event_inner: EventInner::InstanceEvent(InstanceEvent {
+                instance_uuid: self.uuid.clone(),
+                instance_name: name,
+                instance_event_inner: InstanceEventInner::InstanceWarning { message },
+            }),
+            details: "".to_string(),
+            snowflake: Snowflake::default(),
+            caused_by: CausedBy::System,
+        });
+        self.kill(CausedBy::System).await
+    }
+
+    /// Called when the server logs that its port is taken, the server exits right after
+    async fn on_port_bind_failure(&self) {
+        // the failure is reported over several lines
+        if self.crash_cause.lock().await.is_some() {
+            return;
+        }
+        let port = self.config.lock().await.port;
+        let conflicting_process = self.find_orphaned_server().await;
+        let possible_process = match conflicting_process {
+            Some(_) => None,
+            None => self.find_process_passed_port(port).await,
+        };
+        let suggested_port = (port + 1..=u16::MAX as u32)
+            .find(|port| port_scanner::local_port_available(*port as u16))
+            .unwrap_or(port);
+        let name = self.config.lock().await.name.clone();
+        let message = match (&conflicting_process, &possible_process) {
+            (Some(process), _) => format!(
+                "Port {} is already in use by {} (pid {}), kill it or change the port to {}",
+                port, process.name, process.pid, suggested_port
+            ),
+            (None, Some(process)) => format!(
+                "Port {} is already in use, possibly by {} (pid {}), change the port to {}",
+                port, process.name, process.pid, suggested_port
+            ),
+            (None, None) => format!(
+                "Port {} is already in use, change the port to {}",
+                port, suggested_port
+            ),
+        };
+        warn!("[{}] {}", name, message);
+        *self.crash_cause.lock().await = Some(CrashCause::PortInUse {
+            port,
+            suggested_port,
+            conflicting_process,
+        });
+        self.event_broadcaster.send(Event {
+            event_inner: EventInner::InstanceEvent(InstanceEvent {
+                instance_uuid: self.uuid.clone(),
+                instance_name: name,
+                instance_event_inner: InstanceEventInner::InstanceWarning { message },
+            }),
+            details: "".to_string(),
+            snowflake: Snowflake::default(),
+            caused_by: CausedBy::System,
+        });
+    }
+
+    /// Kills the process identified as holding on to the server's port when it last crashed
+    pub async fn kill_port_conflict(&self) -> Result<(), Error> {
+        let conflicting_process = match self.crash_cause().await {
+            Some(CrashCause::PortInUse {
+                conflicting_process: Some(conflicting_process),
+                ..
+            }) => conflicting_process,
+            _ => {
+                return Err(Error {
+                    kind: ErrorKind::BadRequest,
+                    source: eyre!("No conflicting process was identified for this instance"),
+                })
+            }
+        };
+        self.kill_untracked_process(&conflicting_process).await?;
+        self.crash_cause.lock().await.take();
+        Ok(())
+    }
+
+    /// Stops the instance and moves its directory to the trash, returning where it was moved to
+    ///
+    /// The instance can be restored until it is purged after the deletion grace period. Refused
+    /// while it is being backed up or updated, since those are still writing to the directory
+    pub async fn mark_for_deletion(&self) -> Result<PathBuf, Error> {
+        if self.state().await.is_maintenance() {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("Cannot delete an instance while it is being backed up or updated"),
+            });
+        }
+        self.clone().shutdown().await;
+        move_to_trash(&self.path_to_instance, path_to_trash()).await
+    }
+
+    pub async fn set_crash_restart_cooldown(
+        &self,
+        crash_restart_cooldown: Option<Duration>,
+    ) -> Result<(), Error> {
+        self.config.lock().await.crash_restart_cooldown = crash_restart_cooldown;
+        self.write_config_to_file().await
+    }
+
+    pub async fn set_crash_loop_guard(
+        &self,
+        crash_loop_guard: Option<CrashLoopGuard>,
+    ) -> Result<(), Error> {
+        if let Some(CrashLoopGuard {
+            max_crashes,
+            window,
+            ..
+        }) = crash_loop_guard
+        {
+            if max_crashes == 0 || window.is_zero() {
+                return Err(Error {
+                    kind: ErrorKind::BadRequest,
+                    source: eyre!("Crash loop threshold and window must be positive"),
+                });
+            }
+        }
+        self.config.lock().await.crash_loop_guard = crash_loop_guard;
+        self.recent_crashes.lock().await.clear();
+        self.write_config_to_file().await
+    }
+
+    /// Records a crash and turns `restart_on_crash` off if the server is crash looping
+    ///
+    /// Returns whether it is, in which case the server must not be restarted
+    async fn detect_crash_loop(&self, running_for: Option<Duration>) -> bool {
+        let config = self.config.lock().await.clone();
+        let guard = match config.crash_loop_guard {
+            Some(guard) => guard,
+            None => return false,
+        };
+        {
+            let mut recent_crashes = self.recent_crashes.lock().await;
+            if !record_crash(&mut recent_crashes, Instant::now(), running_for, &guard) {
+                return false;
+            }
+            recent_crashes.clear();
+        }
+        self.config.lock().await.restart_on_crash = false;
+        self.restart_on_crash
+            .store(false, std::sync::atomic::Ordering::Relaxed);
+        if let Err(e) = self.write_config_to_file().await {
+            error!(
+                "[{}] Failed to save restart on crash being disabled: {}",
+                config.name, e
+            );
+        }
+        let message = format!(
+            "Server crashed more than {} times within {} seconds, restarting on crash was disabled until the server is fixed",
+            guard.max_crashes,
+            guard.window.as_secs()
+        );
+        error!("[{}] {}", config.name, message);
+        self.event_broadcaster.send(Event {
+            event_inner: EventInner::InstanceEvent(InstanceEvent {
+                instance_uuid: self.uuid.clone(),
+                instance_name: config.name.clone(),
+                instance_event_inner: InstanceEventInner::InstanceError { message },
+            }),
+            details: "".to_string(),
+            snowflake: Snowflake::default(),
+            caused_by: CausedBy::System,
+        });
+        true
+    }
+
+    /// Restarts the instance after a crash, waiting out `crash_restart_cooldown` first if set
+    ///
+    /// The pending restart can be cancelled with `cancel_pending_restart` until the countdown ends
+    async fn schedule_crash_restart(&self) {
+        let config = self.config.lock().await.clone();
+        // hold the lock until the handle is stored so the task can't take it before then
+        let mut pending_crash_restart = self.pending_crash_restart.lock().await;
+        let __self = self.clone();
+        let handle = tokio::task::spawn(async move {
+            if let Some(cooldown) = config.crash_restart_cooldown {
+                let total = cooldown.as_secs();
+                tokio::time::sleep(Duration::from_nanos(cooldown.subsec_nanos() as u64)).await;
+                for remaining in (1..=total).rev() {
+                    if remaining == total || remaining % 10 == 0 || remaining <= 5 {
+                        __self.event_broadcaster.send(Event::new_system_message(
+                            __self.uuid.clone(),
+                            config.name.clone(),
+                            format!("Server crashed, restarting in {remaining} seconds"),
+                        ));
+                    }
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+            }
+            __self.pending_crash_restart.lock().await.take();
+            if !__self.should_auto_start().await {
+                return;
+            }
+            info!("[{}] Restarting instance after crash", config.name);
+            if let Err(e) = __self.start(CausedBy::System, false).await {
+                error!("[{}] Failed to restart instance after crash: {}", config.name, e);
+            }
+        });
+        if let Some(previous) = pending_crash_restart.replace(handle) {
+            previous.abort();
+        }
+    }
+
+    pub async fn set_watchdog(&self, watchdog: Option<WatchdogConfig>) -> Result<(), Error> {
+        if let Some(WatchdogConfig {
+            interval,
+            failure_threshold,
+        }) = watchdog
+        {
+            if interval.is_zero() || failure_threshold == 0 {
+                return Err(Error {
+                    kind: ErrorKind::BadRequest,
+                    source: eyre!("Watchdog interval and failure threshold must be positive"),
+                });
+            }
+        }
+        self.config.lock().await.watchdog = watchdog;
+        self.write_config_to_file().await?;
+        if self.state().await == State::Running {
+            self.spawn_watchdog().await;
+        }
+        Ok(())
+    }
+
+    pub async fn set_startup_timeout(
+        &self,
+        startup_timeout: Option<StartupTimeout>,
+    ) -> Result<(), Error> {
+        if startup_timeout.map_or(false, |startup_timeout| startup_timeout.timeout.is_zero()) {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("Startup timeout must be positive"),
+            });
+        }
+        self.config.lock().await.startup_timeout = startup_timeout;
+        self.write_config_to_file().await
+    }
+
+    /// Fails the start if the server is still starting once the startup timeout runs out,
+    /// replacing the timeout of any previous start
+    ///
+    /// The instance is moved to `State::Error`, a server that isn't killed can still finish
+    /// starting or be stopped from there
+    async fn spawn_startup_timeout(&self) {
+        let mut startup_timeout_task = self.startup_timeout_task.lock().await;
+        if let Some(previous) = startup_timeout_task.take() {
+            previous.abort();
+        }
+        let config = self.config.lock().await.clone();
+        let startup_timeout = match config.startup_timeout {
+            Some(startup_timeout) => startup_timeout,
+            None => return,
+        };
+        let __self = self.clone();
+        startup_timeout_task.replace(tokio::task::spawn(async move {
+            tokio::time::sleep(startup_timeout.timeout).await;
+            if __self.state().await != State::Starting {
+                return;
+            }
+            let timeout_secs = startup_timeout.timeout.as_secs();
+            let message = format!(
+                "Server did not finish starting within {} seconds",
+                timeout_secs
+            );
+            error!("[{}] {}", config.name, message);
+            *__self.crash_cause.lock().await = Some(CrashCause::StartupTimeout { timeout_secs });
+            *__self.state.lock().await = State::Error;
+            __self
+                .event_broadcaster
+                .send(Event::new_instance_state_transition(
+                    __self.uuid.clone(),
+                    config.name.clone(),
+                    State::Error,
+                ));
+            __self.event_broadcaster.send(Event {
+                event_inner: EventInner::InstanceEvent(InstanceEvent {
+                    instance_uuid: __self.uuid.clone(),
+                    instance_name: config.name.clone(),
+                    instance_event_inner: InstanceEventInner::InstanceError { message },
+                }),
+                details: "".to_string(),
+                snowflake: Snowflake::default(),
+                caused_by: CausedBy::System,
+            });
+            if startup_timeout.kill {
+                if let Err(e) = __self.kill(CausedBy::System).await {
+                    error!(
+                        "[{}] Failed to kill server stuck starting: {}",
+                        config.name, e
+                    );
+                }
+            }
+        }));
+    }
+
+    /// Moves the instance back to stopped when its start fails before the server is launched
+    async fn abort_start(&self, name: &str, caused_by: CausedBy) {
+        let _ = self.state.lock().await.try_transition(
+            StateAction::InstanceStop,
+            Some(&|state| {
+                self.event_broadcaster.send(Event {
+                    event_inner: EventInner::InstanceEvent(InstanceEvent {
+                        instance_name: name.to_string(),
+                        instance_uuid: self.uuid.clone(),
+                        instance_event_inner: InstanceEventInner::StateTransition { to: state },
+                    }),
+                    snowflake: Snowflake::default(),
+                    details: "Failed to start server".to_string(),
+                    caused_by: caused_by.clone(),
+                });
+            }),
+        );
+    }
+
+    /// Remembers when the start began, so its duration can be recorded once the server is running
+    async fn begin_startup_timing(&self) {
+        let mod_count = self.mod_count().await;
+        *self.startup_began_at.lock().await = Some((Instant::now(), mod_count));
+    }
+
+    /// Adds the start that just finished to the history the startup estimate is based on
+    async fn record_startup_duration(&self) {
+        let (began_at, mod_count) = match self.startup_began_at.lock().await.take() {
+            Some(startup_began_at) => startup_began_at,
+            None => return,
+        };
+        let record = StartupRecord {
+            duration_ms: began_at.elapsed().as_millis() as u64,
+            mod_count,
+        };
+        record_startup(&mut self.config.lock().await.startup_history, record);
+        if let Err(e) = self.write_config_to_file().await {
+            warn!(
+                "[{}] Failed to save the startup duration: {}",
+                self.config.lock().await.name,
+                e
+            );
+        }
+    }
+
+    /// Estimates how long a start takes from the previous ones, `None` if it never started
+    ///
+    /// While starting, the time elapsed and the time likely remaining are filled in as well
+    pub async fn startup_estimate(&self) -> Option<StartupEstimate> {
+        let startup_began_at = if self.state().await == State::Starting {
+            *self.startup_began_at.lock().await
+        } else {
+            None
+        };
+        let mod_count = match startup_began_at {
+            Some((_, mod_count)) => mod_count,
+            None => self.mod_count().await,
+        };
+        let mut estimate = estimate_startup(&self.config.lock().await.startup_history, mod_count)?;
+        if let Some((began_at, _)) = startup_began_at {
+            let elapsed_ms = began_at.elapsed().as_millis() as u64;
+            estimate.elapsed_ms = Some(elapsed_ms);
+            estimate.remaining_ms = Some(estimate.estimated_ms.saturating_sub(elapsed_ms));
+        }
+        Some(estimate)
+    }
+
+    /// Asks the running server for its tick rate over RCON with the command of its flavour
+    ///
+    /// Readings are reused for `TPS_CACHE_TTL`. Vanilla servers before 1.20.3 have no command
+    /// for it and are unsupported, as are modded servers without one
+    pub async fn get_tps(&self) -> Result<TpsReport, Error> {
+        if self.state().await != State::Running {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("The server is not running"),
+            });
+        }
+        let mut last_tps_report = self.last_tps_report.lock().await;
+        if let Some((measured_at, report)) = last_tps_report.as_ref() {
+            if measured_at.elapsed() < TPS_CACHE_TTL {
+                return Ok(report.clone());
+            }
+        }
+        let flavour = FlavourKind::from(&self.config.lock().await.flavour);
+        let response = self.send_rcon(tps_command(flavour)).await?;
+        let (tps_1m, tps_5m, tps_15m, mspt) = match flavour {
+            FlavourKind::Paper | FlavourKind::Spigot => parse_paper_tps(&response)
+                .map(|(tps_1m, tps_5m, tps_15m)| (tps_1m, Some(tps_5m), Some(tps_15m), None)),
+            FlavourKind::Forge | FlavourKind::NeoForge => {
+                parse_forge_tps(&response).map(|(tps, mspt)| (tps, None, None, Some(mspt)))
+            }
+            FlavourKind::Vanilla | FlavourKind::Fabric | FlavourKind::Quilt => {
+                parse_tick_query(&response).map(|(tps, mspt)| (tps, None, None, Some(mspt)))
+            }
+        }
+        .ok_or_else(|| Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!(
+                "The server did not report its tick rate, it responded with \"{}\"",
+                response.trim()
+            ),
+        })?;
+        // only paper has the tick time on a separate command, spigot doesn't know it
+        let mspt = match (mspt, flavour) {
+            (None, FlavourKind::Paper) => self
+                .send_rcon("mspt")
+                .await
+                .ok()
+                .and_then(|response| parse_paper_mspt(&response)),
+            (mspt, _) => mspt,
+        };
+        let report = TpsReport {
+            tps_1m,
+            tps_5m,
+            tps_15m,
+            mspt,
+            measured_at: chrono::Utc::now().timestamp(),
+        };
+        last_tps_report.replace((Instant::now(), report.clone()));
+        Ok(report)
+    }
+
+    /// Starts pinging the server if a watchdog is configured, replacing any previous watchdog
+    ///
+    /// The watchdog exits on its own once the server is no longer running
+    async fn spawn_watchdog(&self) {
+        let mut watchdog_task = self.watchdog_task.lock().await;
+        if let Some(previous) = watchdog_task.take() {
+            previous.abort();
+        }
+        let config = self.config.lock().await.clone();
+        let watchdog = match config.watchdog {
+            Some(watchdog) => watchdog,
+            None => return,
+        };
+        let __self = self.clone();
+        watchdog_task.replace(tokio::task::spawn(async move {
+            let mut failures = 0;
+            loop {
+                tokio::time::sleep(watchdog.interval).await;
+                if __self.state().await != State::Running {
+                    break;
+                }
+                let port = __self.config.lock().await.port as u16;
+                match server_list_ping("127.0.0.1", port, watchdog.interval).await {
+                    Ok(_) => failures = 0,
+                    Err(e) => {
+                        failures += 1;
+                        warn!(
+                            "[{}] Health check failed ({}/{}): {}",
+                            config.name, failures, watchdog.failure_threshold, e
+                        );
+                        __self.event_broadcaster.send(Event {
+                            event_inner: EventInner::InstanceEvent(InstanceEvent {
+                                instance_uuid: __self.uuid.clone(),
+                                instance_name: config.name.clone(),
+                                instance_event_inner: InstanceEventInner::InstanceWarning {
+                                    message: format!(
+                                        "Server did not respond to health check ({}/{})",
+                                        failures, watchdog.failure_threshold
+                                    ),
+                                },
+                            }),
+                            details: "".to_string(),
+                            snowflake: Snowflake::default(),
+                            caused_by: CausedBy::System,
+                        });
+                    }
+                }
+                if failures >= watchdog.failure_threshold {
+                    error!("[{}] Server is hung, restarting", config.name);
+                    __self.event_broadcaster.send(Event {
+                        event_inner: EventInner::InstanceEvent(InstanceEvent {
+                            instance_uuid: __self.uuid.clone(),
+                            instance_name: config.name.clone(),
+                            instance_event_inner: InstanceEventInner::InstanceError {
+                                message: "Server is not responding, restarting".to_string(),
+                            },
+                        }),
+                        details: "".to_string(),
+                        snowflake: Snowflake::default(),
+                        caused_by: CausedBy::System,
+                    });
+                    // a hung server won't process a stop command, so kill it and let the exit
+                    // handler restart it like it would after a crash
+                    __self
+                        .restart_after_exit
+                        .store(true, std::sync::atomic::Ordering::Relaxed);
+                    if let Some(process) = __self.process.lock().await.as_mut() {
+                        if let Err(e) = process.start_kill() {
+                            error!("[{}] Failed to kill hung server: {}", config.name, e);
+                        }
+                    }
+                    break;
+                }
+            }
+        }));
+    }
+
+    pub async fn set_world_dir_override(&self, path: Option<PathBuf>) -> Result<(), Error> {
+        if self.state().await != State::Stopped {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("Cannot move the world while the server is running"),
+            });
+        }
+        if let Some(path) = &path {
+            validate_dir_override(path).await?;
+        }
+        self.config.lock().await.world_dir_override = path;
+        self.write_config_to_file().await
+    }
+
+    pub async fn set_resources_dir_override(&self, path: Option<PathBuf>) -> Result<(), Error> {
+        if let Some(path) = &path {
+            validate_dir_override(path).await?;
+        }
+        create_resource_dirs(&resolve_path_to_resources(
+            &self.path_to_instance,
+            path.as_deref(),
+        ))
+        .await?;
+        self.config.lock().await.resources_dir_override = path;
+        self.write_config_to_file().await
+    }
+
+    /// Every use of the resources directory goes through here so that the override is honoured
+    pub async fn path_to_resources(&self) -> PathBuf {
+        resolve_path_to_resources(
+            &self.path_to_instance,
+            self.config.lock().await.resources_dir_override.as_deref(),
+        )
+    }
+
+    pub async fn set_autosave_interval(
+        &self,
+        autosave_interval: Option<Duration>,
+    ) -> Result<(), Error> {
+        if autosave_interval.map_or(false, |interval| interval.is_zero()) {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("Autosave interval must be positive"),
+            });
+        }
+        self.config.lock().await.autosave_interval = autosave_interval;
+        self.write_config_to_file().await?;
+        if self.state().await == State::Running {
+            self.spawn_autosave().await;
+        }
+        Ok(())
+    }
+
+    /// Starts issuing `save-all` periodically if an autosave interval is configured
+    ///
+    /// Like the watchdog, the task exits on its own once the server is no longer running
+    async fn spawn_autosave(&self) {
+        let mut autosave_task = self.autosave_task.lock().await;
+        if let Some(previous) = autosave_task.take() {
+            previous.abort();
+        }
+        let config = self.config.lock().await.clone();
+        let autosave_interval = match config.autosave_interval {
+            Some(autosave_interval) => autosave_interval,
+            None => return,
+        };
+        let __self = self.clone();
+        autosave_task.replace(tokio::task::spawn(async move {
+            loop {
+                tokio::time::sleep(autosave_interval).await;
+                if __self.state().await != State::Running {
+                    break;
+                }
+                if __self.rcon_conn.lock().await.is_none() {
+                    continue;
+                }
+                match __self.send_rcon("save-all").await {
+                    Ok(_) => __self.event_broadcaster.send(Event::new_system_message(
+                        __self.uuid.clone(),
+                        config.name.clone(),
+                        "World saved".to_string(),
+                    )),
+                    Err(e) => warn!("[{}] Failed to autosave: {}", config.name, e),
+                }
+            }
+        }));
+    }
+
+    pub async fn restart_schedule(&self) -> Option<RestartSchedule> {
+        self.config.lock().await.restart_schedule.clone()
+    }
+
+    pub async fn set_restart_schedule(
+        &self,
+        restart_schedule: Option<RestartSchedule>,
+    ) -> Result<(), Error> {
+        if let Some(restart_schedule) = &restart_schedule {
+            restart_schedule.validate()?;
+        }
+        self.config.lock().await.restart_schedule = restart_schedule;
+        self.write_config_to_file().await?;
+        self.spawn_restart_schedule().await;
+        Ok(())
+    }
+
+    /// Restarts the server at the times of the restart schedule, if it is running then
+    ///
+    /// Unlike the autosave task this one runs for as long as the instance is loaded
+    async fn spawn_restart_schedule(&self) {
+        let mut restart_schedule_task = self.restart_schedule_task.lock().await;
+        if let Some(previous) = restart_schedule_task.take() {
+            previous.abort();
+        }
+        let config = self.config.lock().await.clone();
+        let restart_schedule = match config.restart_schedule {
+            Some(restart_schedule) => restart_schedule,
+            None => return,
+        };
+        let __self = self.clone();
+        restart_schedule_task.replace(tokio::task::spawn(async move {
+            loop {
+                let now = chrono::Utc::now();
+                let next_restart = match restart_schedule.next_restart(&now) {
+                    Some(next_restart) => next_restart,
+                    None => break,
+                };
+                tokio::time::sleep((next_restart - now).to_std().unwrap_or_default()).await;
+                // the sleep doesn't follow changes to the system clock, so check it is time
+                if chrono::Utc::now() < next_restart {
+                    continue;
+                }
+                if __self.state().await != State::Running {
+                    continue;
+                }
+                info!("[{}] Restarting instance on schedule", config.name);
+                __self.event_broadcaster.send(Event::new_system_message(
+                    __self.uuid.clone(),
+                    config.name.clone(),
+                    "Restarting on schedule".to_string(),
+                ));
+                // blocking, so a failed stop or start is logged here instead of panicking a task
+                if let Err(e) = __self.restart(CausedBy::System, true).await {
+                    error!("[{}] Scheduled restart failed: {}", config.name, e);
+                }
+            }
+        }));
+    }
+
+    /// Issues the configured commands on start in the background, one at a time
+    async fn run_commands_on_start(&self) {
+        let config = self.config.lock().await.clone();
+        if config.commands_on_start.is_empty() {
+            return;
+        }
+        let __self = self.clone();
+        tokio::task::spawn(async move {
+            for command in config.commands_on_start {
+                // gives plugins a moment to handle the previous command
+                tokio::time::sleep(COMMANDS_ON_START_DELAY).await;
+                if __self.state().await != State::Running {
+                    break;
+                }
+                if let Err(e) = __self.send_command(&command, CausedBy::System).await {
+                    warn!(
+                        "[{}] Failed to run command on start \"{}\": {}",
+                        config.name, command, e
+                    );
+                }
+            }
+        });
+    }
+
+    async fn path_to_world(&self) -> PathBuf {
+        if let Some(world_dir_override) = self.config.lock().await.world_dir_override.clone() {
+            return world_dir_override;
+        }
+        let level_name = read_properties_from_path(&self.path_to_properties)
+            .await
+            .ok()
+            .and_then(|properties| properties.get("level-name").cloned())
+            .filter(|level_name| !level_name.is_empty())
+            .unwrap_or_else(|| "world".to_string());
+        self.path_to_instance.join(level_name)
+    }
+
+    /// Writes a `level.dat` with `seed` for the world to be generated from, which the server
+    /// honours even where `level-seed` alone isn't
+    ///
+    /// Only possible before the first start, since the world is generated then
+    pub async fn preseed_level_dat(&self, seed: &str) -> Result<(), Error> {
+        if self.config.lock().await.has_started {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("level.dat can only be pre-seeded before the first start"),
+            });
+        }
+        let seed = parse_seed(seed).ok_or_else(|| Error {
+            kind: ErrorKind::BadRequest,
+            source: eyre!("Seed cannot be empty"),
+        })?;
+        let level_type = read_properties_from_path(&self.path_to_properties)
+            .await
+            .ok()
+            .and_then(|properties| properties.get("level-type").cloned())
+            .unwrap_or_default();
+        write_seeded_level_dat(&self.path_to_world().await, seed, &level_type).await
+    }
+
+    /// Backs up the world every `backup_period` minutes while backups are enabled
+    async fn spawn_periodic_backup(
+        &self,
+        mut backup_receiver: tokio::sync::mpsc::UnboundedReceiver<BackupInstruction>,
+    ) {
+        let config = self.config.lock().await.clone();
+        let mut backup_period = config.backup_period;
+        let mut backups_enabled = config.backups_enabled;
+        // the task's own copy of the instance mustn't keep the channel open, or it never closes
+        let mut __self = self.clone();
+        __self.backup_sender = tokio::sync::mpsc::unbounded_channel().0;
+        self.backup_task
+            .lock()
+            .await
+            .replace(tokio::task::spawn(async move {
+                loop {
+                    let next_backup = async move {
+                        match backup_period.filter(|period| backups_enabled && *period > 0) {
+                            Some(period) => {
+                                tokio::time::sleep(Duration::from_secs(period as u64 * 60)).await
+                            }
+                            None => std::future::pending().await,
+                        }
+                    };
+                    tokio::select! {
+                        instruction = backup_receiver.recv() => match instruction {
+                            Some(BackupInstruction::SetPeriod(period)) => backup_period = period,
+                            Some(BackupInstruction::Pause) => backups_enabled = false,
+                            Some(BackupInstruction::Resume) => backups_enabled = true,
+                            Some(BackupInstruction::BackupNow(done)) => {
+                                let result = __self.backup_world("manual").await;
+                                match &result {
+                                    Ok(_) => __self.prune_backups().await,
+                                    Err(e) => {
+                                        error!("[{}] Manual backup failed: {}", config.name, e)
+                                    }
+                                }
+                                let _ = done.send(result.map(|_| ()));
+                            }
+                            Some(BackupInstruction::Shutdown) | None => break,
+                        },
+                        _ = next_backup => {
+                            match __self.backup_world("periodic").await {
+                                Ok(_) => __self.prune_backups().await,
+                                Err(e) => {
+                                    error!("[{}] Periodic backup failed: {}", config.name, e)
+                                }
+                            }
+                        }
+                    }
+                }
+            }));
+    }
+
+    /// Backs up the world through the backup task, returning once the archive is written
+    pub async fn backup_now(&self) -> Result<(), Error> {
+        let (done_sender, done_receiver) = tokio::sync::oneshot::channel();
+        self.backup_sender
+            .send(BackupInstruction::BackupNow(done_sender))
+            .map_err(|_| eyre!("The backup task is not running"))?;
+        done_receiver
+            .await
+            .context("The backup task stopped before the backup finished")?
+    }
+
+    /// Pauses or resumes periodic backups, which stays in effect across restarts
+    pub async fn set_backups_enabled(&self, backups_enabled: bool) -> Result<(), Error> {
+        self.config.lock().await.backups_enabled = backups_enabled;
+        let _ = self.backup_sender.send(if backups_enabled {
+            BackupInstruction::Resume
+        } else {
+            BackupInstruction::Pause
+        });
+        self.write_config_to_file().await
+    }
+
+    pub async fn set_backup_destination(
+        &self,
+        backup_destination: Option<BackupDestination>,
+    ) -> Result<(), Error> {
+        if let Some(backup_destination) = &backup_destination {
+            backup_destination.sink().validate().await?;
+        }
+        self.config.lock().await.backup_destination = backup_destination;
+        self.write_config_to_file().await
+    }
+
+    /// Lists the backups in the configured destination along with the worlds each one contains,
+    /// newest first
+    pub async fn list_backups(&self) -> Result<Vec<BackupInfo>, Error> {
+        let sink = self.backup_sink().await;
+        let mut ret = Vec::new();
+        for entry in sink.list().await?.into_iter().rev() {
+            let worlds = sink.list_worlds(&entry.name).await.unwrap_or_else(|e| {
+                warn!("Failed to read the worlds in backup {}: {}", entry.name, e);
+                Vec::new()
+            });
+            ret.push(BackupInfo {
+                name: entry.name,
+                size: entry.size,
+                creation_time: entry.creation_time,
+                format: entry.format,
+                worlds,
+            });
+        }
+        Ok(ret)
+    }
+
+    /// Replaces every world in a backup with its copy in the backup
+    ///
+    /// All worlds are extracted before any of them is touched. The current worlds are moved to
+    /// `<world>.pre-restore` until every world is in place, so a failure midway puts them back
+    pub async fn restore_backup(&self, backup_name: &str) -> Result<(), Error> {
+        // checked here as well so a name like `..` can't be taken for the destination's parent
+        validate_backup_name(backup_name)?;
+        if self.state().await != State::Stopped {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("The server must be stopped to restore a backup"),
+            });
+        }
+        let sink = self.backup_sink().await;
+        // rejects names that don't exist or would escape the backup destination
+        let archive = sink.retrieve(backup_name).await?;
+        let world_names: Vec<String> = sink
+            .list_worlds(backup_name)
+            .await?
+            .into_iter()
+            .map(|world| world.name)
+            .collect();
+        if world_names.is_empty() {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("Backup {} does not contain any world", backup_name),
+            });
+        }
+        let path_to_main_world = self.path_to_world().await;
+        let path_to_worlds = path_to_main_world
+            .parent()
+            .context("Failed to get the directory the world is in")?
+            .to_owned();
+
+        self.transition_maintenance(StateAction::BeginBackup)
+            .await?;
+        let result = async {
+            // extracted next to the worlds so that they can be moved into place with a rename
+            let staging_dir =
+                tempfile::tempdir_in(&path_to_worlds).context("Failed to create temp dir")?;
+            let staging_path = staging_dir.path().to_owned();
+            let worlds = world_names.clone();
+            tokio::task::spawn_blocking(move || {
+                worlds
+                    .iter()
+                    .try_for_each(|world| extract_backup_world(&archive, world, &staging_path))
+            })
+            .await
+            .context("Failed to extract worlds in a blocking task")??;
+
+            // each restored world along with where the world it replaced was moved to
+            let mut replaced: Vec<(PathBuf, Option<PathBuf>)> = Vec::new();
+            let replace_result: Result<(), Error> = async {
+                for world_name in &world_names {
+                    let path_to_world = if path_to_main_world.file_name()
+                        == Some(std::ffi::OsStr::new(world_name))
+                    {
+                        path_to_main_world.clone()
+                    } else {
+                        path_to_worlds.join(world_name)
+                    };
+                    let pre_restore_path = if path_to_world.exists() {
+                        let mut pre_restore_path = path_to_world.clone().into_os_string();
+                        pre_restore_path.push(".pre-restore");
+                        let pre_restore_path = PathBuf::from(pre_restore_path);
+                        // left over from a restore that failed to roll back
+                        if pre_restore_path.exists() {
+                            crate::util::fs::remove_dir_all(&pre_restore_path).await?;
+                        }
+                        crate::util::fs::rename(&path_to_world, &pre_restore_path).await?;
+                        Some(pre_restore_path)
+                    } else {
+                        None
+                    };
+                    replaced.push((path_to_world.clone(), pre_restore_path));
+                    crate::util::fs::rename(staging_dir.path().join(world_name), &path_to_world)
+                        .await?;
+                }
+                Ok(())
+            }
+            .await;
+
+            if let Err(e) = replace_result {
+                for (path_to_world, pre_restore_path) in replaced.into_iter().rev() {
+                    if path_to_world.exists() {
+                        if let Err(e) = crate::util::fs::remove_dir_all(&path_to_world).await {
+                            error!("Failed to remove partially restored world: {}", e);
+                            continue;
+                        }
+                    }
+                    if let Some(pre_restore_path) = pre_restore_path {
+                        if let Err(e) =
+                            crate::util::fs::rename(&pre_restore_path, &path_to_world).await
+                        {
+                            error!("Failed to roll back world restore: {}", e);
+                        }
+                    }
+                }
+                return Err(e);
+            }
+            for pre_restore_path in replaced.into_iter().filter_map(|(_, path)| path) {
+                crate::util::fs::remove_dir_all(&pre_restore_path).await?;
+            }
+            Ok(())
+        }
+        .await;
+        self.transition_maintenance(StateAction::EndMaintenance)
+            .await?;
+        result
+    }
+
+    /// Replaces a single world with its copy in a backup, leaving the other worlds untouched
+    ///
+    /// The current copy of the world is backed up first. Worlds other than the main one, like
+    /// `world_nether` on bukkit servers, are restored next to the main world
+    pub async fn restore_backup_world(
+        &self,
+        backup_name: &str,
+        world_name: &str,
+    ) -> Result<(), Error> {
+        validate_backup_name(backup_name)?;
+        if self.state().await != State::Stopped {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("The server must be stopped to restore a world"),
+            });
+        }
+        if world_name.is_empty()
+            || world_name == "."
+            || world_name == ".."
+            || world_name.contains(['/', '\\'])
+        {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("Invalid world name {}", world_name),
+            });
+        }
+        let sink = self.backup_sink().await;
+        if !sink
+            .list_worlds(backup_name)
+            .await?
+            .iter()
+            .any(|world| world.name == world_name)
+        {
+            return Err(Error {
+                kind: ErrorKind::NotFound,
+                source: eyre!(
+                    "Backup {} does not contain world {}",
+                    backup_name,
+                    world_name
+                ),
+            });
+        }
+        let path_to_main_world = self.path_to_world().await;
+        let is_main_world =
+            path_to_main_world.file_name() == Some(std::ffi::OsStr::new(world_name));
+        let path_to_world = if is_main_world {
+            path_to_main_world
+        } else {
+            path_to_main_world
+                .parent()
+                .context("Failed to get the directory the world is in")?
+                .join(world_name)
+        };
+
+        self.transition_maintenance(StateAction::BeginBackup)
+            .await?;
+        let result = async {
+            if path_to_world.is_dir() {
+                self.archive_dir(
+                    vec![path_to_world.clone()],
+                    &format!("pre-restore-{world_name}"),
+                )
+                .await?;
+            }
+            let archive = sink.retrieve(backup_name).await?;
+            // extracted next to the world so that it can be moved into place with a rename
+            let staging_dir = tempfile::tempdir_in(
+                path_to_world
+                    .parent()
+                    .context("Failed to get the directory the world is in")?,
+            )
+            .context("Failed to create temp dir")?;
+            let staging_path = staging_dir.path().to_owned();
+            let world = world_name.to_string();
+            tokio::task::spawn_blocking(move || {
+                extract_backup_world(&archive, &world, &staging_path)
+            })
+            .await
+            .context("Failed to extract world in a blocking task")??;
+            if path_to_world.exists() {
+                crate::util::fs::remove_dir_all(&path_to_world).await?;
+            }
+            crate::util::fs::rename(staging_dir.path().join(world_name), &path_to_world).await
+        }
+        .await;
+        self.transition_maintenance(StateAction::EndMaintenance)
+            .await?;
+        result
+    }
+
+    pub async fn set_prune_backups_on_low_space(&self, prune: bool) -> Result<(), Error> {
+        self.config.lock().await.prune_backups_on_low_space = prune;
+        self.write_config_to_file().await
+    }
+
+    pub async fn set_backup_include(&self, backup_include: Vec<String>) -> Result<(), Error> {
+        if let Some(entry) = backup_include.iter().find(|entry| {
+            let path = Path::new(entry);
+            entry.trim().is_empty()
+                || path.is_absolute()
+                || path
+                    .components()
+                    .any(|component| !matches!(component, std::path::Component::Normal(_)))
+        }) {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!(
+                    "Invalid backup entry {:?}, it must be relative to the instance directory",
+                    entry
+                ),
+            });
+        }
+        self.config.lock().await.backup_include = backup_include;
+        self.write_config_to_file().await
+    }
+
+    pub async fn set_backup_on_stop_mode(
+        &self,
+        backup_on_stop_mode: Option<BackupOnStopMode>,
+    ) -> Result<(), Error> {
+        self.config.lock().await.backup_on_stop_mode = backup_on_stop_mode;
+        self.write_config_to_file().await
+    }
+
+    pub async fn timeout_settings(&self) -> TimeoutSettings {
+        self.config.lock().await.timeout_settings
+    }
+
+    pub async fn set_timeout_settings(
+        &self,
+        timeout_settings: TimeoutSettings,
+    ) -> Result<(), Error> {
+        let mut config = self.config.lock().await;
+        timeout_settings.validate(config.auto_start)?;
+        config.timeout_settings = timeout_settings;
+        drop(config);
+        self.write_config_to_file().await
+    }
+
+    /// The java binary the server is launched with, either the configured one or the managed runtime
+    fn path_to_java(&self, config: &RestoreConfig) -> PathBuf {
+        if let Some(jre) = &config.java_cmd {
+            PathBuf::from(jre)
+        } else {
+            self.path_to_runtimes
+                .join("java")
+                .join(format!("jre{}", config.jre_major_version))
+                .join(if std::env::consts::OS == "macos" {
+                    "Contents/Home/bin"
+                } else {
+                    "bin"
+                })
+                .join("java")
+        }
+    }
+
+    /// Where agent jars have to be placed to be used with `set_java_agents`
+    pub fn path_to_agents(&self) -> PathBuf {
+        self.path_to_instance.join("agents")
+    }
+
+    /// Checks that the agent is a jar in the agents directory, returning the jar's path
+    async fn resolve_java_agent(&self, java_agent: &JavaAgent) -> Result<PathBuf, Error> {
+        let jar = Path::new(&java_agent.jar);
+        // only plain file names, so agents can't point outside of the agents directory
+        if jar.file_name() != Some(jar.as_os_str())
+            || jar.extension().map_or(true, |extension| extension != "jar")
+        {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("Java agent {} is not a jar file name", java_agent.jar),
+            });
+        }
+        let path = self.path_to_agents().join(jar);
+        let mut file = tokio::fs::File::open(&path).await.map_err(|e| Error {
+            kind: ErrorKind::NotFound,
+            source: eyre!("Java agent {} not found: {}", path.display(), e),
+        })?;
+        let mut header = [0_u8; 4];
+        // jars are zip archives, which start with a local file header
+        if file.read_exact(&mut header).await.is_err() || header != *b"PK\x03\x04" {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("Java agent {} is not a valid jar", java_agent.jar),
+            });
+        }
+        Ok(path)
+    }
+
+    /// The `-javaagent` flags for the configured agents, failing if any of them became invalid
+    async fn java_agent_args(&self) -> Result<Vec<String>, Error> {
+        let java_agents = self.config.lock().await.java_agents.clone();
+        let mut args = Vec::with_capacity(java_agents.len());
+        for java_agent in &java_agents {
+            let path = self.resolve_java_agent(java_agent).await?;
+            args.push(match &java_agent.options {
+                Some(options) => format!("-javaagent:{}={}", path.display(), options),
+                None => format!("-javaagent:{}", path.display()),
+            });
+        }
+        Ok(args)
+    }
+
+    pub async fn java_agents(&self) -> Vec<JavaAgent> {
+        self.config.lock().await.java_agents.clone()
+    }
+
+    pub async fn set_java_agents(&self, java_agents: Vec<JavaAgent>) -> Result<(), Error> {
+        for java_agent in &java_agents {
+            self.resolve_java_agent(java_agent).await?;
+        }
+        self.config.lock().await.java_agents = java_agents;
+        self.write_config_to_file().await
+    }
+
+    pub async fn jvm_flags_preset(&self) -> JvmFlagsPreset {
+        self.config.lock().await.jvm_flags_preset.clone()
+    }
+
+    pub async fn set_jvm_flags_preset(
+        &self,
+        jvm_flags_preset: JvmFlagsPreset,
+    ) -> Result<(), Error> {
+        jvm_flags_preset.validate()?;
+        self.config.lock().await.jvm_flags_preset = jvm_flags_preset;
+        self.write_config_to_file().await
+    }
+
+    pub async fn commands_on_start(&self) -> Vec<String> {
+        self.config.lock().await.commands_on_start.clone()
+    }
+
+    pub async fn set_commands_on_start(&self, commands_on_start: Vec<String>) -> Result<(), Error> {
+        if let Some(command) = commands_on_start
+            .iter()
+            .find(|command| command.trim().is_empty() || command.contains(['\n', '\r']))
+        {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("Invalid command on start: {:?}", command),
+            });
+        }
+        self.config.lock().await.commands_on_start = commands_on_start;
+        self.write_config_to_file().await
+    }
+
+    pub async fn prompt_responses(&self) -> Vec<PromptResponse> {
+        self.config.lock().await.prompt_responses.clone()
+    }
+
+    /// Takes effect the next time the server is started
+    pub async fn set_prompt_responses(
+        &self,
+        prompt_responses: Vec<PromptResponse>,
+    ) -> Result<(), Error> {
+        if let Some(prompt_response) = prompt_responses.iter().find(|prompt_response| {
+            prompt_response.pattern.is_empty() || prompt_response.response.contains(['\n', '\r'])
+        }) {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("Invalid prompt response: {:?}", prompt_response),
+            });
+        }
+        PromptResponder::new(&prompt_responses)?;
+        self.config.lock().await.prompt_responses = prompt_responses;
+        self.write_config_to_file().await
+    }
+
+    /// Writes the configured response to a prompt the server is waiting on
+    async fn answer_prompt(&self, prompt: &str, response: &str) {
+        let config = self.config.lock().await.clone();
+        let written = match self.stdin.lock().await.as_mut() {
+            Some(stdin) => write_stdin_line(stdin, response, config.stdin_terminator).await,
+            None => return,
+        };
+        if let Err(e) = written {
+            warn!("[{}] Failed to answer a prompt: {}", config.name, e);
+            return;
+        }
+        info!(
+            "[{}] Answered \"{}\" to \"{}\"",
+            config.name,
+            response,
+            prompt.trim_end()
+        );
+        self.event_broadcaster.send(Event {
+            event_inner: EventInner::InstanceEvent(InstanceEvent {
+                instance_uuid: self.uuid.clone(),
+                instance_name: config.name.clone(),
+                instance_event_inner: InstanceEventInner::PromptAnswered {
+                    prompt: prompt.trim_end().to_string(),
+                    response: response.to_string(),
+                },
+            }),
+            details: "".to_string(),
+            snowflake: Snowflake::default(),
+            caused_by: CausedBy::System,
+        });
+    }
+
+    /// Takes effect the next time the server is started
+    pub async fn set_max_log_line_length(&self, max_log_line_length: usize) -> Result<(), Error> {
+        if max_log_line_length < MIN_MAX_LOG_LINE_LENGTH {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!(
+                    "Maximum log line length must be at least {} bytes",
+                    MIN_MAX_LOG_LINE_LENGTH
+                ),
+            });
+        }
+        self.config.lock().await.max_log_line_length = max_log_line_length;
+        self.write_config_to_file().await
+    }
+
+    /// Takes effect immediately, also for a server that is already running
+    pub async fn set_mirror_console_output(
+        &self,
+        mirror_console_output: bool,
+    ) -> Result<(), Error> {
+        self.config.lock().await.mirror_console_output = mirror_console_output;
+        self.mirror_console_output
+            .store(mirror_console_output, std::sync::atomic::Ordering::Relaxed);
+        self.write_config_to_file().await
+    }
+
+    /// Takes effect the next time the server is started
+    pub async fn set_process_priority(
+        &self,
+        cpu_affinity: Option<Vec<usize>>,
+        nice: Option<i32>,
+    ) -> Result<(), Error> {
+        if let Some(cpu_affinity) = &cpu_affinity {
+            if !cfg!(target_os = "linux") {
+                return Err(Error {
+                    kind: ErrorKind::UnsupportedOperation,
+                    source: eyre!("CPU affinity is only supported on Linux"),
+                });
+            }
+            let mut system = sysinfo::System::new();
+            system.refresh_cpu();
+            let cpu_count = system.cpus().len();
+            if cpu_affinity.is_empty() {
+                return Err(Error {
+                    kind: ErrorKind::BadRequest,
+                    source: eyre!("CPU affinity must contain at least one core"),
+                });
+            }
+            if let Some(cpu) = cpu_affinity.iter().find(|cpu| **cpu >= cpu_count) {
+                return Err(Error {
+                    kind: ErrorKind::BadRequest,
+                    source: eyre!(
+                        "Core {} does not exist, this machine has {} cores",
+                        cpu,
+                        cpu_count
+                    ),
+                });
+            }
+        }
+        if let Some(nice) = nice {
+            if !cfg!(unix) {
+                return Err(Error {
+                    kind: ErrorKind::UnsupportedOperation,
+                    source: eyre!("Nice values are only supported on Unix"),
+                });
+            }
+            if !(-20..=19).contains(&nice) {
+                return Err(Error {
+                    kind: ErrorKind::BadRequest,
+                    source: eyre!("Nice value must be between -20 and 19"),
+                });
+            }
+        }
+        let mut config = self.config.lock().await;
+        config.cpu_affinity = cpu_affinity;
+        config.nice = nice;
+        drop(config);
+        self.write_config_to_file().await
+    }
+
+    /// Replaces the RCON password with a new random one, which takes effect the next time the
+    /// server is started
+    ///
+    /// Only allowed while stopped, a running server keeps the old password until it restarts
+    /// and redialing it with the new one would fail
+    pub async fn rotate_rcon_password(&self) -> Result<(), Error> {
+        if self.state().await != State::Stopped {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("Stop the instance before rotating the RCON password"),
+            });
+        }
+        let password = rand_alphanumeric(RCON_PASSWORD_LENGTH);
+        self.read_properties().await?;
+        self.configurable_manifest.lock().await.set_setting(
+            ServerPropertySetting::get_section_id(),
+            ServerPropertySetting::RconPassword(password.clone()).into(),
+        )?;
+        self.config.lock().await.rcon_password = Some(password);
+        self.write_config_to_file().await?;
+        self.write_properties_to_file().await?;
+        Ok(())
+    }
+
+    /// Takes effect the next time the server is started
+    pub async fn set_manage_rcon(&self, manage_rcon: bool) -> Result<(), Error> {
+        self.config.lock().await.manage_rcon = manage_rcon;
+        self.write_config_to_file().await
+    }
+
+    /// Writes `enable-rcon`, the password in `RestoreConfig` and a free `rcon.port` to the
+    /// properties, generating the password the first time
+    ///
+    /// Does nothing unless `manage_rcon` is set
+    async fn prepare_rcon(&self) -> Result<(), Error> {
+        let mut config = self.config.lock().await;
+        if !config.manage_rcon {
+            return Ok(());
+        }
+        let game_port = config.port;
+        let generated = config.rcon_password.is_none();
+        let password = config
+            .rcon_password
+            .get_or_insert_with(|| rand_alphanumeric(RCON_PASSWORD_LENGTH))
+            .clone();
+        drop(config);
+        if generated {
+            self.write_config_to_file().await?;
+        }
+        self.read_properties().await?;
+        let mut lock = self.configurable_manifest.lock().await;
+        let is_free =
+            |port: &u32| *port != game_port && port_scanner::local_port_available(*port as u16);
+        let port = lock
+            .get_unique_setting_key("rcon.port")
+            .and_then(|v| v.get_value().map(|v| v.try_as_unsigned_integer().ok()))
+            .flatten()
+            .filter(is_free)
+            .or_else(|| (DEFAULT_RCON_PORT..=u16::MAX as u32).find(is_free))
+            .ok_or_else(|| eyre!("No free port for RCON"))?;
+        for setting in [
+            ServerPropertySetting::EnableRcon(true),
+            ServerPropertySetting::RconPassword(password),
+            ServerPropertySetting::RconPort(port as u16),
+        ] {
+            lock.set_setting(ServerPropertySetting::get_section_id(), setting.into())?;
+        }
+        self.write_properties_from_manifest(&lock).await
+    }
+
+    /// The RCON password and port in the properties, `None` unless RCON is enabled
+    async fn rcon_credentials(&self) -> Option<(String, u32)> {
+        let lock = self.configurable_manifest.lock().await;
+        let enabled = lock
+            .get_unique_setting_key("enable-rcon")
+            .and_then(|v| v.get_value().map(|v| v.try_as_boolean().ok()))
+            .flatten();
+        let password = lock
+            .get_unique_setting_key("rcon.password")
+            .and_then(|v| v.get_value().map(|v| v.try_as_string().ok()))
+            .flatten()
+            .cloned();
+        let port = lock
+            .get_unique_setting_key("rcon.port")
+            .and_then(|v| v.get_value().map(|v| v.try_as_unsigned_integer().ok()))
+            .flatten();
+        match (enabled, password, port) {
+            (Some(true), Some(password), Some(port)) => Some((password, port)),
+            _ => None,
+        }
+    }
+
+    /// Dials RCON with the credentials in the properties, unless already connected
+    pub async fn ensure_rcon_connected(&self) -> Result<(), Error> {
+        // held across the dial so concurrent callers don't each open a connection
+        let mut rcon_conn = self.rcon_conn.lock().await;
+        if rcon_conn.is_none() {
+            rcon_conn.replace(self.dial_rcon().await?);
+        }
+        Ok(())
+    }
+
+    /// Dials RCON up to `RconConfig::max_attempts` times, backing off exponentially
+    async fn dial_rcon(&self) -> Result<rcon::Connection<tokio::net::TcpStream>, Error> {
+        let (password, port) = self.rcon_credentials().await.ok_or_else(|| Error {
+            kind: ErrorKind::BadRequest,
+            source: eyre!("RCON is not enabled or misconfigured"),
+        })?;
+        let config = self.config.lock().await.clone();
+        let max_attempts = config.rcon.max_attempts.max(1);
+        let mut attempt = 0;
+        loop {
+            let e = match <rcon::Connection<tokio::net::TcpStream>>::builder()
+                .enable_minecraft_quirks(true)
+                .connect(&format!("localhost:{}", port), &password)
+                .await
+            {
+                Ok(rcon) => return Ok(rcon),
+                Err(e) => e,
+            };
+            attempt += 1;
+            if attempt >= max_attempts {
+                return Err(eyre!(e)
+                    .wrap_err(format!(
+                        "Failed to connect to RCON after {} attempts",
+                        max_attempts
+                    ))
+                    .into());
+            }
+            warn!(
+                "[{}] Failed to connect to RCON: {}, retry {}/{}",
+                config.name, e, attempt, max_attempts
+            );
+            tokio::time::sleep(RCON_RETRY_DELAY * 2_u32.saturating_pow(attempt - 1)).await;
+        }
+    }
+
+    pub async fn set_rcon_config(&self, rcon: RconConfig) -> Result<(), Error> {
+        if rcon.max_attempts == 0 {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("RCON attempts must be positive"),
+            });
+        }
+        self.config.lock().await.rcon = rcon;
+        self.write_config_to_file().await
+    }
+
+    /// Takes effect the next time the server is started
+    pub async fn set_server_locale(&self, server_locale: ServerLocale) -> Result<(), Error> {
+        self.config.lock().await.server_locale = server_locale;
+        self.write_config_to_file().await
+    }
+
+    pub async fn set_backup_io_limit(&self, backup_io_limit: Option<u64>) -> Result<(), Error> {
+        if backup_io_limit == Some(0) {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("Backup IO limit must be positive"),
+            });
+        }
+        self.config.lock().await.backup_io_limit = backup_io_limit;
+        self.write_config_to_file().await
+    }
+
+    pub async fn set_backup_retention(&self, backup_retention: Option<u32>) -> Result<(), Error> {
+        if backup_retention == Some(0) {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("Backup retention must be positive"),
+            });
+        }
+        self.config.lock().await.backup_retention = backup_retention;
+        self.write_config_to_file().await
+    }
+
+    /// Removes the oldest backups past `backup_retention`, files in the destination that aren't
+    /// named like a backup are left alone
+    ///
+    /// Failures are only logged, the backup itself already succeeded
+    async fn prune_backups(&self) {
+        let config = self.config.lock().await.clone();
+        let backup_retention = match config.backup_retention {
+            Some(backup_retention) => backup_retention,
+            None => return,
+        };
+        let sink = self.backup_sink().await;
+        let backups = match sink.list().await {
+            Ok(backups) => backups,
+            Err(e) => {
+                error!("[{}] Failed to list backups to prune: {}", config.name, e);
+                return;
+            }
+        };
+        for name in backups_beyond_retention(&backups, backup_retention) {
+            match sink.remove(&name).await {
+                Ok(path) => {
+                    info!("[{}] Removed backup {} past retention", config.name, name);
+                    self.event_broadcaster.send(new_fs_event(
+                        FSOperation::Delete,
+                        FSTarget::File(path),
+                        CausedBy::System,
+                    ));
+                }
+                Err(e) => error!("[{}] Failed to remove backup {}: {}", config.name, name, e),
+            }
+        }
+    }
+
+    async fn backup_sink(&self) -> Box<dyn BackupSink> {
+        self.config
+            .lock()
+            .await
+            .backup_destination
+            .clone()
+            .unwrap_or_else(|| BackupDestination::Local {
+                path: self.path_to_instance.join("backups"),
+            })
+            .sink()
+    }
+
+    /// Moves the instance in or out of a maintenance state, notifying clients of the change
+    async fn transition_maintenance(&self, action: StateAction) -> Result<(), Error> {
+        let name = self.config.lock().await.name.clone();
+        self.state
+            .lock()
+            .await
+            .try_transition(
+                action,
+                Some(&|state| {
+                    self.event_broadcaster
+                        .send(Event::new_instance_state_transition(
+                            self.uuid.clone(),
+                            name.clone(),
+                            state,
+                        ));
+                }),
+            )
+            .map_err(|e| Error {
+                kind: ErrorKind::BadRequest,
+                source: e.source,
+            })
+    }
+
+    /// Backs up the world, emitting `BackupCompleted` or `BackupFailed` with the result
+    async fn backup_world(&self, reason: &str) -> Result<PathBuf, Error> {
+        let started_at = Instant::now();
+        let result = self.backup_world_inner(reason).await;
+        let instance_event_inner = match &result {
+            Ok(backup) => InstanceEventInner::BackupCompleted {
+                name: backup
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                size: tokio::fs::metadata(backup)
+                    .await
+                    .map(|metadata| metadata.len())
+                    .unwrap_or(0),
+                duration_ms: started_at.elapsed().as_millis() as u64,
+            },
+            Err(e) => InstanceEventInner::BackupFailed {
+                reason: format!("{:#}", e.source),
+            },
+        };
+        self.event_broadcaster.send(Event {
+            event_inner: EventInner::InstanceEvent(InstanceEvent {
+                instance_uuid: self.uuid.clone(),
+                instance_name: self.config.lock().await.name.clone(),
+                instance_event_inner,
+            }),
+            details: "".to_string(),
+            snowflake: Snowflake::default(),
+            caused_by: CausedBy::System,
+        });
+        result
+    }
+
+    /// Backs up the world, keeping a stopped instance in `State::BackingUp` until it is done
+    async fn backup_world_inner(&self, reason: &str) -> Result<PathBuf, Error> {
+        let state = *self.state.lock().await;
+        match state {
+            State::Stopped => {
+                self.transition_maintenance(StateAction::BeginBackup)
+                    .await?;
+                let result = self.archive_world(reason).await;
+                self.transition_maintenance(StateAction::EndMaintenance)
+                    .await?;
+                result
+            }
+            State::BackingUp => Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("A backup is already in progress"),
+            }),
+            State::Running => {
+                self.flush_world_over_rcon().await;
+                self.archive_world(reason).await
+            }
+            // updates back up the world they replace
+            _ => self.archive_world(reason).await,
+        }
+    }
+
+    /// Makes a running server write the world to disk so a live backup is consistent
+    ///
+    /// Best effort, the backup goes ahead without RCON or if the save fails
+    async fn flush_world_over_rcon(&self) {
+        if self.rcon_conn.lock().await.is_none() {
+            return;
+        }
+        self.rcon_save_in_progress
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        if let Err(e) = self.send_rcon("save-all flush").await {
+            warn!(
+                "[{}] Failed to save the world before backup: {}",
+                self.name().await,
+                e
+            );
+        }
+        self.rcon_save_in_progress
+            .store(false, std::sync::atomic::Ordering::SeqCst);
+    }
+
+    /// Archives the main world along with everything in `backup_include` that exists
+    async fn archive_world(&self, reason: &str) -> Result<PathBuf, Error> {
+        let path_to_world = self.path_to_world().await;
+        let backup_include = self.config.lock().await.backup_include.clone();
+        let mut paths = vec![path_to_world.clone()];
+        paths.extend(resolve_backup_include(
+            &backup_include,
+            &self.path_to_instance,
+            &path_to_world,
+        ));
+        self.archive_dir(paths, reason).await
+    }
+
+    /// Archives `paths` into a single backup, the first one being the world, which must exist
+    async fn archive_dir(&self, paths: Vec<PathBuf>, reason: &str) -> Result<PathBuf, Error> {
+        let path_to_world = paths.first().context("Nothing to back up")?;
+        if !path_to_world.is_dir() {
+            return Err(Error {
+                kind: ErrorKind::NotFound,
+                source: eyre!("World {} does not exist", path_to_world.display()),
+            });
+        }
+        let archive_name = format!(
+            "{}-{}.zip",
+            chrono::Utc::now().format("%Y-%m-%d_%H-%M-%S"),
+            reason
+        );
+        let sink = self.backup_sink().await;
+        sink.validate().await?;
+        self.ensure_backup_space(sink.as_ref(), &paths).await?;
+        let temp_dir = tempfile::tempdir_in(path_to_tmp()).context("Failed to create temp dir")?;
+        let backup_io_limit = self.config.lock().await.backup_io_limit;
+        let cancellation = CancellationToken::new();
+        self.running_backup
+            .lock()
+            .await
+            .replace((archive_name.clone(), cancellation.clone()));
+        let zipped = tokio::select! {
+            zipped = zip_files_rate_limited_async(
+                &paths,
+                temp_dir.path().join(&archive_name),
+                false,
+                backup_io_limit,
+                Some(cancellation.clone()),
+            ) => zipped.context("Failed to backup world").map_err(Error::from),
+            // a read stuck on e.g. an unresponsive network mount never gets to check the token
+            _ = cancellation.cancelled() => Err(Error {
+                kind: ErrorKind::Conflict,
+                source: eyre!("Backup {} was cancelled", archive_name),
+            }),
+        };
+        self.running_backup.lock().await.take();
+        let archive = match zipped {
+            Ok(archive) => archive,
+            Err(e) => {
+                self.report_out_of_space(&e, &temp_dir.path().join(&archive_name))
+                    .await;
+                return Err(e);
+            }
+        };
+        let stored = sink.store(&archive, &archive_name).await;
+        if let Err(e) = &stored {
+            self.report_out_of_space(e, Path::new(&archive_name)).await;
+        }
+        stored
+    }
+
+    /// Cancels the backup that is being archived, e.g. because it is stuck on a slow disk
+    ///
+    /// The partial archive is removed and the backup task carries on with its schedule
+    pub async fn cancel_running_backup(&self, caused_by: CausedBy) -> Result<(), Error> {
+        let (backup_name, cancellation) =
+            self.running_backup
+                .lock()
+                .await
+                .take()
+                .ok_or_else(|| Error {
+                    kind: ErrorKind::BadRequest,
+                    source: eyre!("No backup is in progress"),
+                })?;
+        cancellation.cancel();
+        let name = self.config.lock().await.name.clone();
+        info!("[{}] Cancelled backup {}", name, backup_name);
+        self.event_broadcaster.send(Event {
+            event_inner: EventInner::InstanceEvent(InstanceEvent {
+                instance_uuid: self.uuid.clone(),
+                instance_name: name,
+                instance_event_inner: InstanceEventInner::BackupCancelled { backup_name },
+            }),
+            details: "".to_string(),
+            snowflake: Snowflake::default(),
+            caused_by,
+        });
+        Ok(())
+    }
+
+    /// Emits `OutOfDiskSpace` if `error` is the disk filling up while writing `path`
+    async fn report_out_of_space(&self, error: &Error, path: &Path) {
+        if is_out_of_space(error) {
+            self.notify_out_of_space(path).await;
+        }
+    }
+
+    /// Emits `OutOfDiskSpace` for `path`, for callers that already checked the error
+    async fn notify_out_of_space(&self, path: &Path) {
+        let name = self.config.lock().await.name.clone();
+        error!("[{}] Out of disk space writing {}", name, path.display());
+        self.event_broadcaster.send(Event {
+            event_inner: EventInner::InstanceEvent(InstanceEvent {
+                instance_uuid: self.uuid.clone(),
+                instance_name: name,
+                instance_event_inner: InstanceEventInner::OutOfDiskSpace {
+                    path: path.display().to_string(),
+                },
+            }),
+            details: "".to_string(),
+            snowflake: Snowflake::default(),
+            caused_by: CausedBy::System,
+        });
+    }
+
+    /// Makes sure a new backup won't fill up the disk, pruning old backups first if enabled
+    ///
+    /// The size of the new backup is estimated from the last one, or the size of what is backed
+    /// up if there are no backups yet
+    async fn ensure_backup_space(
+        &self,
+        sink: &dyn BackupSink,
+        paths: &[PathBuf],
+    ) -> Result<(), Error> {
+        let mut backups = sink.list().await?;
+        let estimated_size = match backups.last() {
+            Some(last_backup) => last_backup.size,
+            None => {
+                let paths = paths.to_vec();
+                tokio::task::spawn_blocking(move || {
+                    paths.iter().map(|path| dir_size(path)).sum::<u64>()
+                })
+                .await
+                .context("Failed to get world size in a blocking task")?
+            }
+        };
+        // the archive is written to the tmp dir first, then moved to the destination
+        let tmp_space = fs3::available_space(path_to_tmp())
+            .context("Failed to get available space of the tmp directory")?;
+        let mut destination_space = sink.available_space().await?;
+        let config = self.config.lock().await.clone();
+        if config.prune_backups_on_low_space {
+            // always keep the newest backup around
+            while destination_space < estimated_size && backups.len() > 1 {
+                let oldest = backups.remove(0);
+                info!("[{}] Removing backup {} to free up space", config.name, oldest.name);
+                sink.remove(&oldest.name).await?;
+                destination_space = sink.available_space().await?;
+            }
+        }
+        let available_space = tmp_space.min(destination_space);
+        if available_space >= estimated_size {
+            return Ok(());
+        }
+        let message = format!(
+            "Skipped backup, it needs about {} but only {} is free",
+            format_byte(estimated_size),
+            format_byte(available_space)
+        );
+        warn!("[{}] {}", config.name, message);
+        self.event_broadcaster.send(Event {
+            event_inner: EventInner::InstanceEvent(InstanceEvent {
+                instance_uuid: self.uuid.clone(),
+                instance_name: config.name.clone(),
+                instance_event_inner: InstanceEventInner::InstanceWarning {
+                    message: message.clone(),
+                },
+            }),
+            details: "".to_string(),
+            snowflake: Snowflake::default(),
+            caused_by: CausedBy::System,
+        });
+        Err(Error {
+            kind: ErrorKind::Internal,
+            source: eyre!(message),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl TInstance for MinecraftInstance {
+    async fn needs_repair(&self) -> Option<String> {
+        self.needs_repair.clone()
+    }
+}
+
+fn tmp_config_path(path_to_config: &Path) -> PathBuf {
+    let mut file_name = path_to_config.file_name().unwrap_or_default().to_owned();
+    file_name.push(".tmp");
+    path_to_config.with_file_name(file_name)
+}
+
+/// Reads the config, falling back to the temporary file of a write that was interrupted
+/// before it could replace the config
+async fn read_restore_config(path_to_config: &Path) -> Result<RestoreConfig, Error> {
+    let read = |path: PathBuf| async move {
+        let content = tokio::fs::read_to_string(&path)
+            .await
+            .context(format!("Failed to open config file at {}", path.display()))?;
+        serde_json::from_str::<RestoreConfig>(&content)
+            .context(format!(
+                "Failed to deserialize config at {}. Was the config file modified manually?",
+                path.display()
+            ))
+            .map_err(Error::from)
+    };
+    let e = match read(path_to_config.to_owned()).await {
+        Ok(restore_config) => return Ok(restore_config),
+        Err(e) => e,
+    };
+    let path_to_tmp_config = tmp_config_path(path_to_config);
+    match read(path_to_tmp_config.clone()).await {
+        Ok(restore_config) => {
+            warn!(
+                "Recovered config at {} from {}",
+                path_to_config.display(),
+                path_to_tmp_config.display()
+            );
+            tokio::fs::rename(&path_to_tmp_config, path_to_config)
+                .await
+                .context(format!(
+                    "Failed to move recovered config to {}",
+                    path_to_config.display()
+                ))?;
+            Ok(restore_config)
+        }
+        Err(_) => Err(e),
+    }
+}
+
+/// Stands in for a config that couldn't be restored, it never starts on its own
+fn placeholder_restore_config(name: String) -> RestoreConfig {
+    serde_json::from_value(serde_json::json!({
+        "name": name,
+        "version": "",
+        "flavour": "vanilla",
+        "description": "",
+        "cmd_args": [],
+        "port": 25565,
+        "min_ram": 1024,
+        "max_ram": 2048,
+        "auto_start": false,
+        "restart_on_crash": false,
+        "jre_major_version": 17,
+        "has_started": false,
+    }))
+    .expect("Placeholder config is invalid, this is a bug")
+}
+
+/// Resolves `backup_include` to the paths that exist, leaving out the main world itself
+///
+/// Entries derived from `world` are resolved next to the main world, since that's where the
+/// server keeps the other dimensions when the world directory is overridden
+fn resolve_backup_include(
+    backup_include: &[String],
+    path_to_instance: &Path,
+    path_to_world: &Path,
+) -> Vec<PathBuf> {
+    let level_name = path_to_world
+        .file_name()
+        .map(|level_name| level_name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "world".to_string());
+    let worlds_dir = path_to_world.parent().unwrap_or(path_to_instance);
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for entry in backup_include {
+        let path = if entry == "world" {
+            path_to_world.to_owned()
+        } else if let Some(suffix) = entry.strip_prefix("world_") {
+            worlds_dir.join(format!("{}_{}", level_name, suffix))
+        } else {
+            path_to_instance.join(entry)
+        };
+        if path != path_to_world && path.exists() && !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+/// Total size of the files in a directory in bytes
+fn dir_size(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Whether a process is a java server launched out of `path_to_instance`
+///
+/// Other processes working in the directory, like a shell or an editor, don't count. The server
+/// is recognised by the jar or `@` arguments file it was launched with being in the instance
+fn is_server_process(name: &str, cmd: &[String], cwd: &Path, path_to_instance: &Path) -> bool {
+    if cwd != path_to_instance || !name.to_lowercase().starts_with("java") {
+        return false;
+    }
+    // relative paths are resolved against the working directory, which is the instance
+    let in_instance = |arg: &str| {
+        let path = Path::new(arg);
+        path.is_relative() || path.starts_with(path_to_instance)
+    };
+    cmd.windows(2)
+        .any(|args| args[0] == "-jar" && in_instance(&args[1]))
+        || cmd
+            .iter()
+            .filter_map(|arg| arg.strip_prefix('@'))
+            .any(in_instance)
+}
+
+/// The resources directory of an instance, `resources` inside it unless overridden
+fn resolve_path_to_resources(
+    path_to_instance: &Path,
+    resources_dir_override: Option<&Path>,
+) -> PathBuf {
+    resources_dir_override
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| path_to_instance.join("resources"))
+}
+
+/// Creates the subdirectories a resources directory is expected to have
+async fn create_resource_dirs(path_to_resources: &Path) -> Result<(), Error> {
+    for dir in ["mods", "worlds", "defaults"] {
+        crate::util::fs::create_dir_all(path_to_resources.join(dir)).await?;
+    }
+    Ok(())
+}
+
+/// Checks that a directory override is an existing, writable, absolute path
+async fn validate_dir_override(path: &Path) -> Result<(), Error> {
+    if !path.is_absolute() {
+        return Err(Error {
+            kind: ErrorKind::BadRequest,
+            source: eyre!("{} is not an absolute path", path.display()),
+        });
+    }
+    if !path.is_dir() {
+        return Err(Error {
+            kind: ErrorKind::BadRequest,
+            source: eyre!("{} is not an existing directory", path.display()),
+        });
+    }
+    tempfile::tempfile_in(path).map_err(|e| Error {
+        kind: ErrorKind::BadRequest,
+        source: eyre!("{} is not writable: {}", path.display(), e),
+    })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_server_process() {
+        let path_to_instance = Path::new("/srv/lodestone/instances/survival");
+        let cmd = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert!(is_server_process(
+            "java",
+            &cmd(&[
+                "java",
+                "-Xmx4096M",
+                "-jar",
+                "/srv/lodestone/instances/survival/server.jar",
+                "nogui"
+            ]),
+            path_to_instance,
+            path_to_instance
+        ));
+        assert!(is_server_process(
+            "java",
+            &cmd(&[
+                "java",
+                "@libraries/net/minecraftforge/forge/1.20.1-47.2.0/unix_args.txt",
+                "nogui"
+            ]),
+            path_to_instance,
+            path_to_instance
+        ));
+        // a shell or an editor open in the instance directory
+        assert!(!is_server_process(
+            "bash",
+            &cmd(&["bash"]),
+            path_to_instance,
+            path_to_instance
+        ));
+        assert!(!is_server_process(
+            "vim",
+            &cmd(&["vim", "server.properties"]),
+            path_to_instance,
+            path_to_instance
+        ));
+        // some other java program
+        assert!(!is_server_process(
+            "java",
+            &cmd(&["java", "-version"]),
+            path_to_instance,
+            path_to_instance
+        ));
+        // the server of another instance
+        assert!(!is_server_process(
+            "java",
+            &cmd(&[
+                "java",
+                "-jar",
+                "/srv/lodestone/instances/creative/server.jar"
+            ]),
+            path_to_instance,
+            path_to_instance
+        ));
+        assert!(!is_server_process(
+            "java",
+            &cmd(&["java", "-jar", "server.jar"]),
+            Path::new("/srv/lodestone/instances/creative"),
+            path_to_instance
+        ));
+    }
+
+    #[test]
+    fn test_resolve_backup_include() {
+        let instance_dir = tempfile::tempdir().unwrap();
+        let path_to_instance = instance_dir.path();
+        for dir in ["survival", "survival_nether", "plugins"] {
+            std::fs::create_dir(path_to_instance.join(dir)).unwrap();
+        }
+        std::fs::write(path_to_instance.join("server.properties"), "").unwrap();
+        let mut backup_include = default_backup_include();
+        backup_include.push("plugins".to_string());
+        backup_include.push("server.properties".to_string());
+        backup_include.push("config".to_string());
+        assert_eq!(
+            resolve_backup_include(
+                &backup_include,
+                path_to_instance,
+                &path_to_instance.join("survival")
+            ),
+            vec![
+                path_to_instance.join("survival_nether"),
+                path_to_instance.join("plugins"),
+                path_to_instance.join("server.properties"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_write_stdin_line() {
+        let mut written = Vec::new();
+        write_stdin_line(&mut written, "say héllo", StdinTerminator::Lf)
+            .await
+            .unwrap();
+        write_stdin_line(&mut written, "stop\n", StdinTerminator::CrLf)
+            .await
+            .unwrap();
+        write_stdin_line(&mut written, "list\r\n", StdinTerminator::Lf)
+            .await
+            .unwrap();
+        assert_eq!(written, "say héllo\nstop\r\nlist\n".as_bytes());
+    }
+
+    #[test]
+    fn test_timeout_settings_validate() {
+        let mut timeout_settings = TimeoutSettings {
+            idle_timeout: Some(300),
+            no_activity_timeout: Some(600),
+            start_on_connection: true,
+        };
+        assert!(timeout_settings.validate(false).is_ok());
+        assert!(timeout_settings.validate(true).is_err());
+
+        timeout_settings.no_activity_timeout = Some(60);
+        assert!(timeout_settings.validate(false).is_err());
+
+        timeout_settings.no_activity_timeout = None;
+        timeout_settings.idle_timeout = Some(0);
+        assert!(timeout_settings.validate(false).is_err());
+
+        assert!(TimeoutSettings::default().validate(true).is_ok());
+    }
+
+    #[test]
+    fn test_jvm_flags_preset() {
+        assert!(JvmFlagsPreset::None.flags(4096).is_empty());
+
+        let aikar = JvmFlagsPreset::Aikar.flags(4096);
+        assert!(aikar.contains(&"-XX:+UseG1GC".to_string()));
+        assert!(aikar.contains(&"-XX:G1HeapRegionSize=8M".to_string()));
+        assert!(!aikar.iter().any(|flag| is_heap_size_flag(flag)));
+        assert!(JvmFlagsPreset::Aikar
+            .flags(16 * 1024)
+            .contains(&"-XX:G1HeapRegionSize=16M".to_string()));
+
+        let custom = JvmFlagsPreset::Custom(vec![
+            "-XX:+UseZGC".to_string(),
+            "-Xmx8G".to_string(),
+            " ".to_string(),
+            "-Xms2G".to_string(),
+        ]);
+        assert!(custom.validate().is_ok());
+        assert_eq!(custom.flags(4096), vec!["-XX:+UseZGC".to_string()]);
+        assert!(JvmFlagsPreset::Custom(vec!["nogui".to_string()])
+            .validate()
+            .is_err());
+        assert!(JvmFlagsPreset::Custom(vec!["-jar".to_string()])
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn test_dir_size() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("region")).unwrap();
+        std::fs::write(dir.path().join("level.dat"), [0_u8; 10]).unwrap();
+        std::fs::write(dir.path().join("region/r.0.0.mca"), [0_u8; 32]).unwrap();
+        assert_eq!(dir_size(dir.path()), 42);
+    }
+
+    #[test]
+    fn test_quilt_flavour_round_trip() {
+        let flavour = Flavour::Quilt {
+            loader_version: Some(QuiltLoaderVersion("0.26.0".to_string())),
+            installer_version: Some(QuiltInstallerVersion("0.9.2".to_string())),
+        };
+        let json = serde_json::to_string(&flavour).unwrap();
+        assert_eq!(
+            json,
+            r#"{"quilt":{"loader_version":"0.26.0","installer_version":"0.9.2"}}"#
+        );
+        assert_eq!(serde_json::from_str::<Flavour>(&json).unwrap(), flavour);
+    }
+
+    #[tokio::test]
+    async fn test_read_restore_config_recovers_tmp_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path_to_config = dir.path().join(".lodestone_minecraft_config.json");
+        std::fs::write(&path_to_config, "{\"name\": \"trunc").unwrap();
+        assert!(read_restore_config(&path_to_config).await.is_err());
+
+        std::fs::write(
+            tmp_config_path(&path_to_config),
+            to_string_pretty(&placeholder_restore_config("survival".to_string())).unwrap(),
+        )
+        .unwrap();
+        let restore_config = read_restore_config(&path_to_config).await.unwrap();
+        assert_eq!(restore_config.name, "survival");
+        assert!(!tmp_config_path(&path_to_config).exists());
+        assert_eq!(
+            read_restore_config(&path_to_config).await.unwrap().name,
+            "survival"
+        );
+    }
+
+    #[test]
+    fn test_filter_versions() {
+        let versions = ["1.20.1", "1.20", "23w51a", "1.19.4", "1.12.0"].map(String::from);
+        assert_eq!(filter_versions(&versions, "1.20"), vec!["1.20.1", "1.20"]);
+        assert_eq!(
+            filter_versions(&versions, "2"),
+            vec!["1.20.1", "1.20", "23w51a", "1.12.0"]
+        );
+        assert_eq!(filter_versions(&versions, " W51 "), vec!["23w51a"]);
+        assert_eq!(filter_versions(&versions, "").len(), versions.len());
+    }
+
+    #[test]
+    fn test_record_crash() {
+        let guard = CrashLoopGuard {
+            max_crashes: 2,
+            window: Duration::from_secs(60),
+            min_uptime: Duration::from_secs(30),
+        };
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let mut recent_crashes = Vec::new();
+        assert!(!record_crash(&mut recent_crashes, at(0), None, &guard));
+        assert!(!record_crash(&mut recent_crashes, at(10), None, &guard));
+        assert!(record_crash(&mut recent_crashes, at(20), None, &guard));
+
+        // the first crash fell out of the window
+        let mut recent_crashes = vec![at(0), at(40)];
+        assert!(!record_crash(&mut recent_crashes, at(70), None, &guard));
+        assert_eq!(recent_crashes, vec![at(40), at(70)]);
+
+        // a clean run forgets the earlier crashes
+        let mut recent_crashes = vec![at(0), at(10)];
+        assert!(!record_crash(
+            &mut recent_crashes,
+            at(50),
+            Some(Duration::from_secs(35)),
+            &guard
+        ));
+        assert_eq!(recent_crashes, vec![at(50)]);
+    }
+}
@@ -0,0 +1,324 @@
+use std::collections::BTreeMap;
+use std::io::{Cursor, Read, Seek};
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::Context;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::warn;
+use ts_rs::TS;
+
+use crate::error::Error;
+
+/// Ids the loader itself provides, never shipped as a mod jar
+const BUILTIN_MOD_IDS: [&str; 4] = ["minecraft", "java", "fabricloader", "fabric-loader"];
+
+/// A problem among the Fabric mods of an instance that would stop the server from starting
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, TS)]
+#[ts(export)]
+#[serde(tag = "type")]
+pub enum ModIssue {
+    /// More than one jar contains a mod with the same id
+    DuplicateId {
+        mod_id: String,
+        file_names: Vec<String>,
+    },
+    /// A mod depends on a mod that isn't installed
+    MissingDependency {
+        file_name: String,
+        mod_id: String,
+        dependency: String,
+    },
+    /// A mod depends on a version of a mod other than the one installed
+    VersionMismatch {
+        file_name: String,
+        mod_id: String,
+        dependency: String,
+        /// Any of these is accepted
+        required: Vec<String>,
+        found: String,
+    },
+}
+
+/// The parts of a `fabric.mod.json` checked for conflicts
+#[derive(Deserialize)]
+struct FabricModJson {
+    id: String,
+    version: String,
+    #[serde(default)]
+    provides: Vec<String>,
+    /// Each value is a version predicate, or a list of predicates any of which will do
+    #[serde(default)]
+    depends: BTreeMap<String, Value>,
+    /// Mods bundled inside the jar
+    #[serde(default)]
+    jars: Vec<NestedJar>,
+}
+
+#[derive(Deserialize)]
+struct NestedJar {
+    file: String,
+}
+
+/// A mod found in a jar, including the ones nested in it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FabricMod {
+    /// Of the jar in the mods directory, for nested mods the jar containing them
+    pub file_name: String,
+    pub id: String,
+    pub version: String,
+    pub provides: Vec<String>,
+    pub depends: BTreeMap<String, Vec<String>>,
+    pub nested: bool,
+}
+
+fn read_fabric_mods_from<R: Read + Seek>(
+    archive: R,
+    file_name: &str,
+    nested: bool,
+    ret: &mut Vec<FabricMod>,
+) -> Result<(), Error> {
+    let mut zip =
+        zip::ZipArchive::new(archive).context(format!("Failed to read jar {}", file_name))?;
+    let mod_json: FabricModJson = match zip.by_name("fabric.mod.json") {
+        Ok(entry) => serde_json::from_reader(entry)
+            .context(format!("Failed to parse fabric.mod.json of {}", file_name))?,
+        Err(_) => return Ok(()),
+    };
+    for jar in &mod_json.jars {
+        let mut content = Vec::new();
+        match zip.by_name(&jar.file) {
+            Ok(mut entry) => entry
+                .read_to_end(&mut content)
+                .context(format!("Failed to read {} in {}", jar.file, file_name))?,
+            Err(_) => continue,
+        };
+        if let Err(e) = read_fabric_mods_from(Cursor::new(content), file_name, true, ret) {
+            warn!("Failed to read {} nested in {}: {}", jar.file, file_name, e);
+        }
+    }
+    ret.push(FabricMod {
+        file_name: file_name.to_string(),
+        id: mod_json.id,
+        version: mod_json.version,
+        provides: mod_json.provides,
+        depends: mod_json
+            .depends
+            .into_iter()
+            .map(|(dependency, predicates)| {
+                let predicates = match predicates {
+                    Value::String(predicate) => vec![predicate],
+                    Value::Array(predicates) => predicates
+                        .into_iter()
+                        .filter_map(|predicate| predicate.as_str().map(str::to_string))
+                        .collect(),
+                    _ => vec!["*".to_string()],
+                };
+                (dependency, predicates)
+            })
+            .collect(),
+        nested,
+    });
+    Ok(())
+}
+
+/// Reads the Fabric mods a jar contains, empty if it isn't a Fabric mod
+pub fn read_fabric_mods(jar: &Path) -> Result<Vec<FabricMod>, Error> {
+    let file = std::fs::File::open(jar).context(format!("Failed to open jar {}", jar.display()))?;
+    let file_name = jar
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut ret = Vec::new();
+    read_fabric_mods_from(file, &file_name, false, &mut ret)?;
+    Ok(ret)
+}
+
+/// Whether `version` satisfies a Fabric version predicate, e.g. `>=1.2.3`, `~1.2`, `1.20.x` or
+/// several of them separated by spaces
+///
+/// Versions that aren't semver are only compared for equality, like the loader does
+fn matches_predicate(version: &str, predicate: &str) -> bool {
+    let predicate = predicate.trim();
+    if predicate.is_empty() || predicate == "*" {
+        return true;
+    }
+    let requirement = predicate
+        .split_whitespace()
+        .map(|comparator| {
+            let comparator = comparator.replace(".x", ".*").replace(".X", ".*");
+            // fabric reads a bare version as exactly that version, semver as a caret requirement
+            if comparator.starts_with(|c: char| c.is_ascii_digit()) && !comparator.contains('*') {
+                format!("={}", comparator)
+            } else {
+                comparator
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    match (
+        semver::Version::parse(version),
+        semver::VersionReq::parse(&requirement),
+    ) {
+        (Ok(version), Ok(requirement)) => requirement.matches(&version),
+        _ => predicate.trim_start_matches('=') == version,
+    }
+}
+
+/// Finds duplicate mod ids and dependencies that aren't installed or have the wrong version
+pub fn find_mod_issues(mods: &[FabricMod]) -> Vec<ModIssue> {
+    let mut ret = Vec::new();
+
+    let mut files_by_id: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for fabric_mod in mods.iter().filter(|fabric_mod| !fabric_mod.nested) {
+        files_by_id
+            .entry(&fabric_mod.id)
+            .or_default()
+            .push(fabric_mod.file_name.clone());
+    }
+    for (mod_id, mut file_names) in files_by_id {
+        if file_names.len() > 1 {
+            file_names.sort();
+            ret.push(ModIssue::DuplicateId {
+                mod_id: mod_id.to_string(),
+                file_names,
+            });
+        }
+    }
+
+    let mut versions_by_id: BTreeMap<&str, &str> = BTreeMap::new();
+    for fabric_mod in mods {
+        versions_by_id.insert(&fabric_mod.id, &fabric_mod.version);
+        for provided in &fabric_mod.provides {
+            versions_by_id.insert(provided, &fabric_mod.version);
+        }
+    }
+    for fabric_mod in mods {
+        for (dependency, predicates) in &fabric_mod.depends {
+            if BUILTIN_MOD_IDS.contains(&dependency.as_str()) {
+                continue;
+            }
+            match versions_by_id.get(dependency.as_str()) {
+                None => ret.push(ModIssue::MissingDependency {
+                    file_name: fabric_mod.file_name.clone(),
+                    mod_id: fabric_mod.id.clone(),
+                    dependency: dependency.clone(),
+                }),
+                Some(version)
+                    if !predicates.is_empty()
+                        && !predicates
+                            .iter()
+                            .any(|predicate| matches_predicate(version, predicate)) =>
+                {
+                    ret.push(ModIssue::VersionMismatch {
+                        file_name: fabric_mod.file_name.clone(),
+                        mod_id: fabric_mod.id.clone(),
+                        dependency: dependency.clone(),
+                        required: predicates.clone(),
+                        found: version.to_string(),
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+    }
+    ret
+}
+
+/// Reads the Fabric mods of the jars in `mods_dirs` and finds the issues among them
+///
+/// Jars that can't be read are skipped, the server reports those itself
+pub fn scan_mod_issues(mods_dirs: &[PathBuf]) -> Vec<ModIssue> {
+    let mut mods = Vec::new();
+    for mods_dir in mods_dirs {
+        let read_dir = match std::fs::read_dir(mods_dir) {
+            Ok(read_dir) => read_dir,
+            Err(_) => continue,
+        };
+        for entry in read_dir.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            if !path.is_file()
+                || path
+                    .extension()
+                    .map_or(true, |extension| extension != "jar")
+            {
+                continue;
+            }
+            match read_fabric_mods(&path) {
+                Ok(jar_mods) => mods.extend(jar_mods),
+                Err(e) => warn!("Failed to read mod {}: {}", path.display(), e),
+            }
+        }
+    }
+    find_mod_issues(&mods)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    fn write_mod(path: &Path, mod_json: &str) {
+        let mut writer = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+        writer
+            .start_file("fabric.mod.json", zip::write::FileOptions::default())
+            .unwrap();
+        writer.write_all(mod_json.as_bytes()).unwrap();
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn test_matches_predicate() {
+        assert!(matches_predicate("1.2.3", "*"));
+        assert!(matches_predicate("1.2.3", ">=1.2.0"));
+        assert!(!matches_predicate("1.2.3", ">=1.3.0"));
+        assert!(matches_predicate("1.2.3", "1.2.3"));
+        assert!(!matches_predicate("1.2.4", "1.2.3"));
+        assert!(matches_predicate("1.20.1", "1.20.x"));
+        assert!(matches_predicate("1.2.3", ">=1.2.0 <1.3.0"));
+        assert!(matches_predicate("build-7", "build-7"));
+        assert!(!matches_predicate("build-7", "build-8"));
+    }
+
+    #[test]
+    fn test_scan_mod_issues() {
+        let mods_dir = tempfile::tempdir().unwrap();
+        write_mod(
+            &mods_dir.path().join("sodium.jar"),
+            r#"{"id": "sodium", "version": "0.5.3", "depends": {"minecraft": "1.20.1", "fabric-api": ">=0.80.0"}}"#,
+        );
+        write_mod(
+            &mods_dir.path().join("sodium-copy.jar"),
+            r#"{"id": "sodium", "version": "0.5.3"}"#,
+        );
+        write_mod(
+            &mods_dir.path().join("fabric-api.jar"),
+            r#"{"id": "fabric-api", "version": "0.70.0"}"#,
+        );
+        write_mod(
+            &mods_dir.path().join("iris.jar"),
+            r#"{"id": "iris", "version": "1.6.4", "depends": {"sodium": ["0.4.x", "0.5.x"], "indium": "*"}}"#,
+        );
+        std::fs::write(mods_dir.path().join("not_a_jar.jar"), b"garbage").unwrap();
+
+        let issues = scan_mod_issues(&[mods_dir.path().to_path_buf()]);
+        assert_eq!(issues.len(), 3);
+        assert!(issues.contains(&ModIssue::DuplicateId {
+            mod_id: "sodium".to_string(),
+            file_names: vec!["sodium-copy.jar".to_string(), "sodium.jar".to_string()],
+        }));
+        assert!(issues.contains(&ModIssue::MissingDependency {
+            file_name: "iris.jar".to_string(),
+            mod_id: "iris".to_string(),
+            dependency: "indium".to_string(),
+        }));
+        assert!(issues.contains(&ModIssue::VersionMismatch {
+            file_name: "sodium.jar".to_string(),
+            mod_id: "sodium".to_string(),
+            dependency: "fabric-api".to_string(),
+            required: vec![">=0.80.0".to_string()],
+            found: "0.70.0".to_string(),
+        }));
+    }
+}
@@ -0,0 +1,184 @@
+use std::path::Path;
+
+use color_eyre::eyre::Context;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use ts_rs::TS;
+
+use crate::error::Error;
+
+use super::FlavourKind;
+
+/// A mod loader, recognized by the metadata file its mods ship with
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, TS)]
+#[ts(export)]
+pub enum ModLoader {
+    Fabric,
+    Quilt,
+    Forge,
+    NeoForge,
+}
+
+impl ModLoader {
+    fn metadata_files(&self) -> &'static [&'static str] {
+        match self {
+            ModLoader::Fabric => &["fabric.mod.json"],
+            ModLoader::Quilt => &["quilt.mod.json"],
+            ModLoader::Forge => &["META-INF/mods.toml", "mcmod.info"],
+            ModLoader::NeoForge => &["META-INF/neoforge.mods.toml"],
+        }
+    }
+}
+
+const ALL_MOD_LOADERS: [ModLoader; 4] = [
+    ModLoader::Fabric,
+    ModLoader::Quilt,
+    ModLoader::Forge,
+    ModLoader::NeoForge,
+];
+
+/// What a start does when mods are found that the flavour can't load
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, TS)]
+#[ts(export)]
+pub enum ModLoaderMismatchPolicy {
+    /// Start anyway and emit a warning listing the mods
+    #[default]
+    Warn,
+    /// Refuse to start until the mods are removed
+    Block,
+}
+
+/// A mod jar built only for loaders the instance's flavour can't load
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct MismatchedMod {
+    pub file_name: String,
+    pub loaders: Vec<ModLoader>,
+}
+
+/// Lists the loaders a jar has metadata for, empty if it isn't a mod, e.g. a library
+pub fn read_mod_loaders(jar: &Path) -> Result<Vec<ModLoader>, Error> {
+    let file = std::fs::File::open(jar).context(format!("Failed to open jar {}", jar.display()))?;
+    let zip =
+        zip::ZipArchive::new(file).context(format!("Failed to read jar {}", jar.display()))?;
+    let file_names: Vec<&str> = zip.file_names().collect();
+    Ok(ALL_MOD_LOADERS
+        .into_iter()
+        .filter(|loader| {
+            loader
+                .metadata_files()
+                .iter()
+                .any(|metadata_file| file_names.contains(metadata_file))
+        })
+        .collect())
+}
+
+/// Loaders whose mods the flavour can load, empty if the flavour doesn't load mods at all
+fn supported_mod_loaders(flavour: FlavourKind) -> &'static [ModLoader] {
+    match flavour {
+        FlavourKind::Fabric => &[ModLoader::Fabric],
+        // quilt loads most fabric mods as well
+        FlavourKind::Quilt => &[ModLoader::Quilt, ModLoader::Fabric],
+        FlavourKind::Forge => &[ModLoader::Forge],
+        // neoforge kept the forge metadata file until 1.20.5
+        FlavourKind::NeoForge => &[ModLoader::NeoForge, ModLoader::Forge],
+        FlavourKind::Vanilla | FlavourKind::Paper | FlavourKind::Spigot => &[],
+    }
+}
+
+/// Finds the jars in `mods_dir` that are mods, but for none of the loaders the flavour supports
+///
+/// Jars that can't be read are skipped, the server reports those itself
+pub fn find_mismatched_mods(mods_dir: &Path, flavour: FlavourKind) -> Vec<MismatchedMod> {
+    let supported_mod_loaders = supported_mod_loaders(flavour);
+    if supported_mod_loaders.is_empty() {
+        return Vec::new();
+    }
+    let read_dir = match std::fs::read_dir(mods_dir) {
+        Ok(read_dir) => read_dir,
+        Err(_) => return Vec::new(),
+    };
+    let mut ret = Vec::new();
+    for entry in read_dir.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        if !path.is_file()
+            || path
+                .extension()
+                .map_or(true, |extension| extension != "jar")
+        {
+            continue;
+        }
+        let loaders = match read_mod_loaders(&path) {
+            Ok(loaders) => loaders,
+            Err(e) => {
+                warn!("Failed to read mod {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        if !loaders.is_empty()
+            && !loaders
+                .iter()
+                .any(|loader| supported_mod_loaders.contains(loader))
+        {
+            ret.push(MismatchedMod {
+                file_name: entry.file_name().to_string_lossy().to_string(),
+                loaders,
+            });
+        }
+    }
+    ret.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    fn write_jar(path: &Path, files: &[&str]) {
+        let mut writer = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+        for file in files {
+            writer
+                .start_file(*file, zip::write::FileOptions::default())
+                .unwrap();
+            writer.write_all(b"{}").unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn test_find_mismatched_mods() {
+        let mods_dir = tempfile::tempdir().unwrap();
+        write_jar(&mods_dir.path().join("sodium.jar"), &["fabric.mod.json"]);
+        write_jar(
+            &mods_dir.path().join("jei.jar"),
+            &["META-INF/mods.toml", "META-INF/MANIFEST.MF"],
+        );
+        write_jar(
+            &mods_dir.path().join("both.jar"),
+            &["fabric.mod.json", "META-INF/mods.toml"],
+        );
+        write_jar(
+            &mods_dir.path().join("library.jar"),
+            &["META-INF/MANIFEST.MF"],
+        );
+        std::fs::write(mods_dir.path().join("not_a_jar.jar"), b"garbage").unwrap();
+
+        assert_eq!(
+            find_mismatched_mods(mods_dir.path(), FlavourKind::Fabric),
+            vec![MismatchedMod {
+                file_name: "jei.jar".to_string(),
+                loaders: vec![ModLoader::Forge],
+            }]
+        );
+        assert_eq!(
+            find_mismatched_mods(mods_dir.path(), FlavourKind::Forge),
+            vec![MismatchedMod {
+                file_name: "sodium.jar".to_string(),
+                loaders: vec![ModLoader::Fabric],
+            }]
+        );
+        assert!(find_mismatched_mods(mods_dir.path(), FlavourKind::Paper).is_empty());
+    }
+}
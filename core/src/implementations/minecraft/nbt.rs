@@ -0,0 +1,504 @@
+use std::io::{Read, Write};
+use std::path::Path;
+
+use color_eyre::eyre::{eyre, Context};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use indexmap::IndexMap;
+
+use crate::error::{Error, ErrorKind};
+
+/// A minimal NBT tag tree, enough to inspect files such as `level.dat`
+#[derive(Debug, Clone, PartialEq)]
+pub enum NbtTag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    List(Vec<NbtTag>),
+    Compound(IndexMap<String, NbtTag>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl NbtTag {
+    pub fn get(&self, key: &str) -> Option<&NbtTag> {
+        match self {
+            NbtTag::Compound(map) => map.get(key),
+            _ => None,
+        }
+    }
+
+    pub fn as_byte(&self) -> Option<i8> {
+        match self {
+            NbtTag::Byte(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i32> {
+        match self {
+            NbtTag::Int(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_long(&self) -> Option<i64> {
+        match self {
+            NbtTag::Long(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            NbtTag::String(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    fn tag_type(&self) -> u8 {
+        match self {
+            NbtTag::Byte(_) => 1,
+            NbtTag::Short(_) => 2,
+            NbtTag::Int(_) => 3,
+            NbtTag::Long(_) => 4,
+            NbtTag::Float(_) => 5,
+            NbtTag::Double(_) => 6,
+            NbtTag::ByteArray(_) => 7,
+            NbtTag::String(_) => 8,
+            NbtTag::List(_) => 9,
+            NbtTag::Compound(_) => 10,
+            NbtTag::IntArray(_) => 11,
+            NbtTag::LongArray(_) => 12,
+        }
+    }
+}
+
+fn read_exact<const N: usize>(reader: &mut impl Read) -> Result<[u8; N], Error> {
+    let mut buf = [0_u8; N];
+    reader
+        .read_exact(&mut buf)
+        .context("Unexpected end of NBT data")?;
+    Ok(buf)
+}
+
+fn read_len(reader: &mut impl Read) -> Result<usize, Error> {
+    let len = i32::from_be_bytes(read_exact(reader)?);
+    if len < 0 {
+        return Err(eyre!("Negative length in NBT data").into());
+    }
+    Ok(len as usize)
+}
+
+fn read_string(reader: &mut impl Read) -> Result<String, Error> {
+    let len = u16::from_be_bytes(read_exact(reader)?) as usize;
+    let mut buf = vec![0_u8; len];
+    reader
+        .read_exact(&mut buf)
+        .context("Unexpected end of NBT data")?;
+    // NBT uses modified UTF-8, which only differs from UTF-8 for characters we don't care about
+    Ok(String::from_utf8_lossy(&buf).to_string())
+}
+
+fn read_payload(reader: &mut impl Read, tag_type: u8) -> Result<NbtTag, Error> {
+    Ok(match tag_type {
+        1 => NbtTag::Byte(i8::from_be_bytes(read_exact(reader)?)),
+        2 => NbtTag::Short(i16::from_be_bytes(read_exact(reader)?)),
+        3 => NbtTag::Int(i32::from_be_bytes(read_exact(reader)?)),
+        4 => NbtTag::Long(i64::from_be_bytes(read_exact(reader)?)),
+        5 => NbtTag::Float(f32::from_be_bytes(read_exact(reader)?)),
+        6 => NbtTag::Double(f64::from_be_bytes(read_exact(reader)?)),
+        7 => {
+            let len = read_len(reader)?;
+            let mut ret = Vec::with_capacity(len);
+            for _ in 0..len {
+                ret.push(i8::from_be_bytes(read_exact(reader)?));
+            }
+            NbtTag::ByteArray(ret)
+        }
+        8 => NbtTag::String(read_string(reader)?),
+        9 => {
+            let [inner_type] = read_exact(reader)?;
+            let len = read_len(reader)?;
+            let mut ret = Vec::with_capacity(len);
+            for _ in 0..len {
+                ret.push(read_payload(reader, inner_type)?);
+            }
+            NbtTag::List(ret)
+        }
+        10 => {
+            let mut ret = IndexMap::new();
+            loop {
+                let [inner_type] = read_exact(reader)?;
+                if inner_type == 0 {
+                    break;
+                }
+                let name = read_string(reader)?;
+                ret.insert(name, read_payload(reader, inner_type)?);
+            }
+            NbtTag::Compound(ret)
+        }
+        11 => {
+            let len = read_len(reader)?;
+            let mut ret = Vec::with_capacity(len);
+            for _ in 0..len {
+                ret.push(i32::from_be_bytes(read_exact(reader)?));
+            }
+            NbtTag::IntArray(ret)
+        }
+        12 => {
+            let len = read_len(reader)?;
+            let mut ret = Vec::with_capacity(len);
+            for _ in 0..len {
+                ret.push(i64::from_be_bytes(read_exact(reader)?));
+            }
+            NbtTag::LongArray(ret)
+        }
+        _ => return Err(eyre!("Unknown NBT tag type {}", tag_type).into()),
+    })
+}
+
+/// Reads an uncompressed NBT stream, returning the root tag
+pub fn read_nbt(reader: &mut impl Read) -> Result<NbtTag, Error> {
+    let [root_type] = read_exact(reader)?;
+    if root_type != 10 {
+        return Err(eyre!("NBT root is not a compound tag").into());
+    }
+    // the root tag's name is almost always empty, we don't need it
+    let _ = read_string(reader)?;
+    read_payload(reader, root_type)
+}
+
+/// Reads a gzip compressed NBT file such as `level.dat`
+pub fn read_gzip_nbt_file(path: &Path) -> Result<NbtTag, Error> {
+    let file =
+        std::fs::File::open(path).context(format!("Failed to open {}", path.display()))?;
+    read_nbt(&mut GzDecoder::new(file)).context(format!("Failed to parse {}", path.display()))
+}
+
+fn write_bytes(writer: &mut impl Write, bytes: &[u8]) -> Result<(), Error> {
+    writer
+        .write_all(bytes)
+        .context("Failed to write NBT data")?;
+    Ok(())
+}
+
+fn write_len(writer: &mut impl Write, len: usize) -> Result<(), Error> {
+    let len = i32::try_from(len).context("NBT array is too long")?;
+    write_bytes(writer, &len.to_be_bytes())
+}
+
+fn write_string(writer: &mut impl Write, value: &str) -> Result<(), Error> {
+    let len = u16::try_from(value.len()).context("NBT string is too long")?;
+    write_bytes(writer, &len.to_be_bytes())?;
+    write_bytes(writer, value.as_bytes())
+}
+
+fn write_payload(writer: &mut impl Write, tag: &NbtTag) -> Result<(), Error> {
+    match tag {
+        NbtTag::Byte(v) => write_bytes(writer, &v.to_be_bytes()),
+        NbtTag::Short(v) => write_bytes(writer, &v.to_be_bytes()),
+        NbtTag::Int(v) => write_bytes(writer, &v.to_be_bytes()),
+        NbtTag::Long(v) => write_bytes(writer, &v.to_be_bytes()),
+        NbtTag::Float(v) => write_bytes(writer, &v.to_be_bytes()),
+        NbtTag::Double(v) => write_bytes(writer, &v.to_be_bytes()),
+        NbtTag::ByteArray(v) => {
+            write_len(writer, v.len())?;
+            for b in v {
+                write_bytes(writer, &b.to_be_bytes())?;
+            }
+            Ok(())
+        }
+        NbtTag::String(v) => write_string(writer, v),
+        NbtTag::List(v) => {
+            // an empty list is typed as `TAG_End`
+            let inner_type = v.first().map_or(0, NbtTag::tag_type);
+            if v.iter().any(|inner| inner.tag_type() != inner_type) {
+                return Err(eyre!("NBT list has elements of different types").into());
+            }
+            write_bytes(writer, &[inner_type])?;
+            write_len(writer, v.len())?;
+            for inner in v {
+                write_payload(writer, inner)?;
+            }
+            Ok(())
+        }
+        NbtTag::Compound(v) => {
+            for (name, inner) in v {
+                write_bytes(writer, &[inner.tag_type()])?;
+                write_string(writer, name)?;
+                write_payload(writer, inner)?;
+            }
+            write_bytes(writer, &[0])
+        }
+        NbtTag::IntArray(v) => {
+            write_len(writer, v.len())?;
+            for i in v {
+                write_bytes(writer, &i.to_be_bytes())?;
+            }
+            Ok(())
+        }
+        NbtTag::LongArray(v) => {
+            write_len(writer, v.len())?;
+            for l in v {
+                write_bytes(writer, &l.to_be_bytes())?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Writes `root` as an uncompressed NBT stream with an unnamed root tag
+pub fn write_nbt(writer: &mut impl Write, root: &NbtTag) -> Result<(), Error> {
+    if root.tag_type() != 10 {
+        return Err(eyre!("NBT root is not a compound tag").into());
+    }
+    write_bytes(writer, &[10])?;
+    write_string(writer, "")?;
+    write_payload(writer, root)
+}
+
+/// Writes a gzip compressed NBT file such as `level.dat`
+pub fn write_gzip_nbt_file(path: &Path, root: &NbtTag) -> Result<(), Error> {
+    let file =
+        std::fs::File::create(path).context(format!("Failed to create {}", path.display()))?;
+    let mut encoder = GzEncoder::new(file, flate2::Compression::default());
+    write_nbt(&mut encoder, root).context(format!("Failed to write {}", path.display()))?;
+    encoder
+        .finish()
+        .context(format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+/// Parses a seed the way `level-seed` is, `None` if empty, i.e. random
+///
+/// Numbers are used as is, any other text is hashed like java's `String::hashCode`
+pub fn parse_seed(seed: &str) -> Option<i64> {
+    let seed = seed.trim();
+    if seed.is_empty() {
+        return None;
+    }
+    Some(seed.parse::<i64>().unwrap_or_else(|_| {
+        seed.encode_utf16().fold(0_i32, |hash, c| {
+            hash.wrapping_mul(31).wrapping_add(c as i32)
+        }) as i64
+    }))
+}
+
+/// The pre-1.16 `generatorName` of a `level-type`, modded and custom types fall back to the
+/// default generator
+fn generator_name(level_type: &str) -> &'static str {
+    match level_type
+        .trim_start_matches("minecraft:")
+        .to_lowercase()
+        .as_str()
+    {
+        "flat" => "flat",
+        "largebiomes" | "large_biomes" => "largeBiomes",
+        "amplified" => "amplified",
+        _ => "default",
+    }
+}
+
+/// Writes a `level.dat` for a world that hasn't been generated yet, so it is generated from
+/// `seed`
+///
+/// The file is in the 1.12 format, which every newer server upgrades on load. The world is
+/// marked as uninitialized, so the server still picks the spawn and generates the terrain
+pub async fn write_seeded_level_dat(
+    path_to_world: &Path,
+    seed: i64,
+    level_type: &str,
+) -> Result<(), Error> {
+    let path_to_level_dat = path_to_world.join("level.dat");
+    if path_to_level_dat.exists() {
+        return Err(Error {
+            kind: ErrorKind::Conflict,
+            source: eyre!(
+                "{} already exists, the world was generated already",
+                path_to_level_dat.display()
+            ),
+        });
+    }
+    let level_name = path_to_world
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "world".to_string());
+    let data = NbtTag::Compound(IndexMap::from([
+        ("version".to_string(), NbtTag::Int(19133)),
+        // 1.12.2
+        ("DataVersion".to_string(), NbtTag::Int(1343)),
+        ("LevelName".to_string(), NbtTag::String(level_name)),
+        ("RandomSeed".to_string(), NbtTag::Long(seed)),
+        (
+            "generatorName".to_string(),
+            NbtTag::String(generator_name(level_type).to_string()),
+        ),
+        ("generatorVersion".to_string(), NbtTag::Int(1)),
+        ("MapFeatures".to_string(), NbtTag::Byte(1)),
+        ("initialized".to_string(), NbtTag::Byte(0)),
+    ]));
+    let root = NbtTag::Compound(IndexMap::from([("Data".to_string(), data)]));
+    crate::util::fs::create_dir_all(path_to_world).await?;
+    tokio::task::spawn_blocking(move || write_gzip_nbt_file(&path_to_level_dat, &root))
+        .await
+        .context("Failed to write level.dat in a blocking task")?
+}
+
+/// Returns the `Data` tag of the `level.dat` in `path_to_world`
+///
+/// `None` if the world doesn't exist yet
+async fn read_level_data(path_to_world: &Path) -> Result<Option<NbtTag>, Error> {
+    let path_to_level_dat = path_to_world.join("level.dat");
+    if !path_to_level_dat.is_file() {
+        return Ok(None);
+    }
+    let level_dat = tokio::task::spawn_blocking(move || read_gzip_nbt_file(&path_to_level_dat))
+        .await
+        .context("Failed to read level.dat in a blocking task")??;
+    let data = level_dat
+        .get("Data")
+        .cloned()
+        .ok_or_else(|| eyre!("level.dat is missing the Data tag"))?;
+    Ok(Some(data))
+}
+
+/// The version a world was last saved with, as recorded in its `level.dat`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorldVersion {
+    pub data_version: Option<i32>,
+    pub name: Option<String>,
+}
+
+/// Returns the version of the world stored in `path_to_world`
+///
+/// `None` if the world doesn't exist yet
+pub async fn read_world_version(path_to_world: &Path) -> Result<Option<WorldVersion>, Error> {
+    let data = match read_level_data(path_to_world).await? {
+        Some(data) => data,
+        None => return Ok(None),
+    };
+    Ok(Some(WorldVersion {
+        data_version: data.get("DataVersion").and_then(NbtTag::as_int),
+        name: data
+            .get("Version")
+            .and_then(|version| version.get("Name"))
+            .and_then(NbtTag::as_str)
+            .map(ToOwned::to_owned),
+    }))
+}
+
+/// Whether the difficulty of a world can be changed, as recorded in its `level.dat`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorldDifficultyLock {
+    /// Hardcore worlds are always on hard
+    pub hardcore: bool,
+    pub difficulty_locked: bool,
+}
+
+/// Returns the difficulty lock of the world stored in `path_to_world`
+///
+/// `None` if the world doesn't exist yet
+pub async fn read_world_difficulty_lock(
+    path_to_world: &Path,
+) -> Result<Option<WorldDifficultyLock>, Error> {
+    let data = match read_level_data(path_to_world).await? {
+        Some(data) => data,
+        None => return Ok(None),
+    };
+    let flag = |key: &str| data.get(key).and_then(NbtTag::as_byte).unwrap_or(0) != 0;
+    Ok(Some(WorldDifficultyLock {
+        hardcore: flag("hardcore"),
+        difficulty_locked: flag("DifficultyLocked"),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_nbt() {
+        let mut data: Vec<u8> = vec![10, 0, 0];
+        // Data compound
+        data.extend([10, 0, 4]);
+        data.extend(b"Data");
+        // DataVersion int
+        data.extend([3, 0, 11]);
+        data.extend(b"DataVersion");
+        data.extend(3465_i32.to_be_bytes());
+        // LevelName string
+        data.extend([8, 0, 9]);
+        data.extend(b"LevelName");
+        data.extend([0, 5]);
+        data.extend(b"world");
+        // end of Data, end of root
+        data.extend([0, 0]);
+
+        let root = read_nbt(&mut data.as_slice()).unwrap();
+        let level = root.get("Data").unwrap();
+        assert_eq!(level.get("DataVersion").unwrap().as_int(), Some(3465));
+        assert_eq!(level.get("LevelName").unwrap().as_str(), Some("world"));
+        assert!(read_nbt(&mut [10_u8, 0].as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_write_nbt() {
+        let root = NbtTag::Compound(IndexMap::from([(
+            "Data".to_string(),
+            NbtTag::Compound(IndexMap::from([
+                ("RandomSeed".to_string(), NbtTag::Long(-42)),
+                (
+                    "Names".to_string(),
+                    NbtTag::List(vec![NbtTag::String("world".to_string())]),
+                ),
+                ("Empty".to_string(), NbtTag::List(Vec::new())),
+                ("Heights".to_string(), NbtTag::LongArray(vec![1, 2])),
+            ])),
+        )]));
+        let mut data = Vec::new();
+        write_nbt(&mut data, &root).unwrap();
+        assert_eq!(read_nbt(&mut data.as_slice()).unwrap(), root);
+
+        let mixed = NbtTag::Compound(IndexMap::from([(
+            "Mixed".to_string(),
+            NbtTag::List(vec![NbtTag::Int(1), NbtTag::Long(1)]),
+        )]));
+        assert!(write_nbt(&mut Vec::new(), &mixed).is_err());
+    }
+
+    #[test]
+    fn test_parse_seed() {
+        assert_eq!(parse_seed("-12345"), Some(-12345));
+        // "abc".hashCode() in java
+        assert_eq!(parse_seed("abc"), Some(96354));
+        assert_eq!(parse_seed("  "), None);
+    }
+
+    #[tokio::test]
+    async fn test_write_seeded_level_dat() {
+        let instance_dir = tempfile::tempdir().unwrap();
+        let path_to_world = instance_dir.path().join("survival");
+        write_seeded_level_dat(&path_to_world, 42, "minecraft:large_biomes")
+            .await
+            .unwrap();
+        let data = read_level_data(&path_to_world).await.unwrap().unwrap();
+        assert_eq!(data.get("RandomSeed").and_then(NbtTag::as_long), Some(42));
+        assert_eq!(
+            data.get("generatorName").and_then(NbtTag::as_str),
+            Some("largeBiomes")
+        );
+        assert_eq!(
+            data.get("LevelName").and_then(NbtTag::as_str),
+            Some("survival")
+        );
+        assert!(write_seeded_level_dat(&path_to_world, 42, "default")
+            .await
+            .is_err());
+    }
+}
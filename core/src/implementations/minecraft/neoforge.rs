@@ -0,0 +1,221 @@
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::{eyre, Context};
+
+use crate::error::Error;
+
+const NEOFORGE_MAVEN_URL: &str = "https://maven.neoforged.net/releases/net/neoforged";
+
+/// Every published NeoForge build, oldest first
+///
+/// The 1.20.1 builds were published as `forge` before NeoForge got its own artifact, they are
+/// named like `1.20.1-47.1.106`
+pub async fn get_neoforge_builds() -> Result<Vec<String>, Error> {
+    let mut builds = Vec::new();
+    for artifact in ["forge", "neoforge"] {
+        let metadata = reqwest::get(format!(
+            "{}/{}/maven-metadata.xml",
+            NEOFORGE_MAVEN_URL, artifact
+        ))
+        .await
+        .context("Failed to get neoforge versions, http request failed")?
+        .text()
+        .await
+        .context("Failed to get neoforge versions, text conversion failed")?;
+        builds.extend(parse_maven_versions(&metadata));
+    }
+    if builds.is_empty() {
+        return Err(eyre!("Failed to get neoforge versions, the maven metadata lists none").into());
+    }
+    Ok(builds)
+}
+
+/// The maven artifact the build was published as, see `get_neoforge_builds`
+fn neoforge_artifact(build: &str) -> &'static str {
+    if build.starts_with("1.") {
+        "forge"
+    } else {
+        "neoforge"
+    }
+}
+
+pub fn neoforge_installer_url(build: &str) -> String {
+    let artifact = neoforge_artifact(build);
+    format!(
+        "{}/{}/{}/{}-{}-installer.jar",
+        NEOFORGE_MAVEN_URL, artifact, build, artifact, build
+    )
+}
+
+pub async fn get_neoforge_minecraft_versions() -> Result<Vec<String>, Error> {
+    let mut versions: Vec<String> = Vec::new();
+    for version in get_neoforge_builds()
+        .await?
+        .iter()
+        .filter_map(|build| neoforge_minecraft_version(build))
+    {
+        if !versions.contains(&version) {
+            versions.push(version);
+        }
+    }
+    versions.reverse();
+    Ok(versions)
+}
+
+/// The newest build for the minecraft version, betas are only picked if there is no release yet
+pub fn latest_neoforge_build<'a>(builds: &'a [String], version: &str) -> Option<&'a String> {
+    let builds: Vec<&String> = builds
+        .iter()
+        .filter(|build| neoforge_minecraft_version(build).as_deref() == Some(version))
+        .collect();
+    builds
+        .iter()
+        .rev()
+        .find(|build| {
+            // the minecraft version in front of the 1.20.1 builds isn't a beta marker
+            let number = match neoforge_artifact(build) {
+                "forge" => build
+                    .split_once('-')
+                    .map_or(build.as_str(), |(_, number)| number),
+                _ => build.as_str(),
+            };
+            !number.contains('-')
+        })
+        .or_else(|| builds.last())
+        .copied()
+}
+
+fn parse_maven_versions(metadata: &str) -> Vec<String> {
+    metadata
+        .split("<version>")
+        .skip(1)
+        .filter_map(|version| version.split_once("</version>"))
+        .map(|(version, _)| version.trim().to_string())
+        .collect()
+}
+
+/// NeoForge builds are numbered after the minecraft version they target, without the leading `1.`
+///
+/// `20.4.237` is for 1.20.4 and `21.0.10-beta` for 1.21, the 1.20.1 builds start with the
+/// minecraft version instead
+fn neoforge_minecraft_version(build: &str) -> Option<String> {
+    if neoforge_artifact(build) == "forge" {
+        return build
+            .split_once('-')
+            .map(|(minecraft_version, _)| minecraft_version.to_string());
+    }
+    let mut components = build.split(['.', '-']);
+    let major: u32 = components.next()?.parse().ok()?;
+    let minor: u32 = components.next()?.parse().ok()?;
+    Some(if minor == 0 {
+        format!("1.{}", major)
+    } else {
+        format!("1.{}.{}", major, minor)
+    })
+}
+
+/// Finds the arguments file the NeoForge installer generated, relative to the instance directory
+///
+/// The server is launched with `@<args file>` instead of `-jar server.jar`
+pub fn find_neoforge_args_file(path_to_instance: &Path) -> Option<PathBuf> {
+    let args_file_name = match std::env::consts::OS {
+        "windows" => "win_args.txt",
+        _ => "unix_args.txt",
+    };
+    let libraries = Path::new("libraries").join("net").join("neoforged");
+    // libraries/net/neoforged/<artifact>/<build>/<args file>
+    std::fs::read_dir(path_to_instance.join(&libraries))
+        .ok()?
+        .filter_map(|artifact| artifact.ok())
+        .filter_map(|artifact| std::fs::read_dir(artifact.path()).ok())
+        .flatten()
+        .filter_map(|build| build.ok())
+        .find(|build| build.path().join(args_file_name).is_file())
+        .and_then(|build| {
+            build
+                .path()
+                .join(args_file_name)
+                .strip_prefix(path_to_instance)
+                .ok()
+                .map(Path::to_path_buf)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_neoforge_minecraft_versions() {
+        let metadata = r#"<?xml version="1.0" encoding="UTF-8"?>
+<metadata>
+  <groupId>net.neoforged</groupId>
+  <artifactId>neoforge</artifactId>
+  <versioning>
+    <versions>
+      <version>20.2.3-beta</version>
+      <version>20.4.80-beta</version>
+      <version>20.4.237</version>
+      <version>20.4.238-beta</version>
+      <version>21.0.10-beta</version>
+    </versions>
+  </versioning>
+</metadata>"#;
+        let builds = parse_maven_versions(metadata);
+        assert_eq!(builds.len(), 5);
+        assert_eq!(
+            neoforge_minecraft_version("21.0.10-beta"),
+            Some("1.21".to_string())
+        );
+        assert_eq!(
+            latest_neoforge_build(&builds, "1.20.4"),
+            Some(&"20.4.237".to_string())
+        );
+        assert_eq!(
+            latest_neoforge_build(&builds, "1.20.2"),
+            Some(&"20.2.3-beta".to_string())
+        );
+        assert_eq!(latest_neoforge_build(&builds, "1.19.2"), None);
+    }
+
+    #[test]
+    fn test_legacy_neoforge_builds() {
+        let builds = vec![
+            "1.20.1-47.1.3".to_string(),
+            "1.20.1-47.1.106".to_string(),
+            "1.20.1-47.1.107-beta".to_string(),
+            "20.4.237".to_string(),
+        ];
+        assert_eq!(
+            neoforge_minecraft_version("1.20.1-47.1.106"),
+            Some("1.20.1".to_string())
+        );
+        assert_eq!(
+            latest_neoforge_build(&builds, "1.20.1"),
+            Some(&"1.20.1-47.1.106".to_string())
+        );
+        assert_eq!(
+            neoforge_installer_url("1.20.1-47.1.106"),
+            "https://maven.neoforged.net/releases/net/neoforged/forge/1.20.1-47.1.106/forge-1.20.1-47.1.106-installer.jar"
+        );
+        assert_eq!(
+            neoforge_installer_url("20.4.237"),
+            "https://maven.neoforged.net/releases/net/neoforged/neoforge/20.4.237/neoforge-20.4.237-installer.jar"
+        );
+    }
+
+    #[test]
+    fn test_find_neoforge_args_file() {
+        let instance_dir = tempfile::tempdir().unwrap();
+        assert_eq!(find_neoforge_args_file(instance_dir.path()), None);
+        let build_dir = instance_dir
+            .path()
+            .join("libraries/net/neoforged/neoforge/20.4.237");
+        std::fs::create_dir_all(&build_dir).unwrap();
+        std::fs::write(build_dir.join("unix_args.txt"), "").unwrap();
+        std::fs::write(build_dir.join("win_args.txt"), "").unwrap();
+        let args_file = find_neoforge_args_file(instance_dir.path()).unwrap();
+        assert!(args_file.is_relative());
+        assert!(args_file.starts_with("libraries/net/neoforged/neoforge/20.4.237"));
+    }
+}
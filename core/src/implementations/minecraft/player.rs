@@ -1,13 +1,22 @@
 use async_trait::async_trait;
+use color_eyre::eyre::{eyre, Context};
 
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use tracing::warn;
 use ts_rs::TS;
 
+use crate::error::ErrorKind;
+use crate::events::{CausedBy, Event, EventInner, InstanceEvent, InstanceEventInner};
 use crate::traits::t_player::Player;
 use crate::traits::t_player::{TPlayer, TPlayerManagement};
+use crate::traits::t_server::State;
+use crate::types::Snowflake;
 use crate::Error;
 
 use super::configurable::ServerPropertySetting;
+use super::line_parser::{parse_rcon_list, parse_rcon_whitelist};
+use super::util::{offline_player_uuid, read_properties_from_path, resolve_player_uuid};
 use super::MinecraftInstance;
 
 #[derive(Eq, Debug, Clone, Serialize, Deserialize, TS)]
@@ -51,6 +60,652 @@ impl TPlayer for MinecraftPlayer {
     }
 }
 
+/// The players online according to the server itself, see `MinecraftInstance::online_players`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct PlayerRoster {
+    pub online: u32,
+    pub max: u32,
+    pub players: Vec<String>,
+}
+
+/// An entry of `whitelist.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct WhitelistEntry {
+    pub uuid: String,
+    pub name: String,
+}
+
+/// An entry of `ops.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct OpEntry {
+    pub uuid: String,
+    pub name: String,
+    /// 1 to 4, see `op-permission-level`
+    pub level: u8,
+    #[serde(rename = "bypassesPlayerLimit")]
+    pub bypasses_player_limit: bool,
+}
+
+/// An entry of `banned-players.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct BannedPlayerEntry {
+    pub uuid: String,
+    pub name: String,
+    pub created: String,
+    pub source: String,
+    pub expires: String,
+    pub reason: String,
+}
+
+/// An entry of `banned-ips.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct BannedIpEntry {
+    pub ip: String,
+    pub created: String,
+    pub source: String,
+    pub expires: String,
+    pub reason: String,
+}
+
+/// A moderation action taken through lodestone
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub enum ModerationAction {
+    Kick,
+    Ban,
+    Pardon,
+    BanIp,
+    PardonIp,
+}
+
+/// The reason the server uses when none is given
+const DEFAULT_BAN_REASON: &str = "Banned by an operator.";
+
+/// Rejects reasons that would end the command early, they are sent as part of it
+fn validate_reason(reason: Option<&str>) -> Result<(), Error> {
+    if reason.map_or(false, |reason| reason.contains(['\n', '\r'])) {
+        return Err(Error {
+            kind: ErrorKind::BadRequest,
+            source: eyre!("The reason must be a single line"),
+        });
+    }
+    Ok(())
+}
+
+/// `created` of a ban entry, in the format the server writes
+fn ban_timestamp() -> String {
+    chrono::Local::now()
+        .format("%Y-%m-%d %H:%M:%S %z")
+        .to_string()
+}
+
+/// Rejects anything that isn't a minecraft username, so it can't be used to inject commands
+fn validate_player_name(player: &str) -> Result<(), Error> {
+    if player.is_empty()
+        || player.len() > 16
+        || !player
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return Err(Error {
+            kind: ErrorKind::BadRequest,
+            source: eyre!("Invalid player name \"{}\"", player),
+        });
+    }
+    Ok(())
+}
+
+/// A player's uuid as the server knows it, without asking mojang
+///
+/// Offline mode servers derive it from the name as the player typed it, online mode ones use
+/// the account's, which is only known for players in `usercache.json`, i.e. who joined before
+fn local_player_uuid(
+    player: &str,
+    online_mode: bool,
+    user_cache: &[WhitelistEntry],
+) -> Option<String> {
+    let cached = user_cache
+        .iter()
+        .find(|entry| entry.name.eq_ignore_ascii_case(player));
+    if !online_mode {
+        // the cache could be from before the server was switched to offline mode
+        let name = cached.map_or(player, |entry| entry.name.as_str());
+        return Some(offline_player_uuid(name).hyphenated().to_string());
+    }
+    cached.map(|entry| entry.uuid.clone())
+}
+
+/// Gives a player `level` in the entries of `ops.json`, adding them if they aren't an operator
+///
+/// Adding a player needs their uuid, which has to be resolved without mojang since the server
+/// must be able to tell who it is, see `local_player_uuid`
+fn set_op_level(
+    ops: &mut Vec<OpEntry>,
+    name: &str,
+    level: u8,
+    uuid: Option<String>,
+) -> Result<(), Error> {
+    if let Some(op) = ops.iter_mut().find(|op| op.name.eq_ignore_ascii_case(name)) {
+        op.level = level;
+        return Ok(());
+    }
+    let uuid = uuid.ok_or_else(|| Error {
+        kind: ErrorKind::NotFound,
+        source: eyre!(
+            "Failed to resolve the uuid of {} offline, they have to join the server once first",
+            name
+        ),
+    })?;
+    ops.push(OpEntry {
+        uuid,
+        name: name.to_string(),
+        level,
+        bypasses_player_limit: false,
+    });
+    Ok(())
+}
+
+impl MinecraftInstance {
+    /// Asks the server for its player list over RCON
+    pub async fn online_players(&self) -> Result<PlayerRoster, Error> {
+        let response = self.send_rcon("list").await?;
+        parse_rcon_list(&response)
+            .ok_or_else(|| eyre!("Failed to parse player list from \"{}\"", response).into())
+    }
+
+    /// Reads one of the player lists the server keeps, e.g. `whitelist.json`
+    async fn read_player_file<T: DeserializeOwned>(
+        &self,
+        file_name: &str,
+    ) -> Result<Vec<T>, Error> {
+        let path_to_file = self.path_to_instance.join(file_name);
+        if !path_to_file.is_file() {
+            return Ok(Vec::new());
+        }
+        let contents = tokio::fs::read(&path_to_file)
+            .await
+            .context(format!("Failed to read {}", path_to_file.display()))?;
+        // the server writes an empty file before anyone is added
+        if contents.iter().all(|b| b.is_ascii_whitespace()) {
+            return Ok(Vec::new());
+        }
+        Ok(serde_json::from_slice(&contents)
+            .context(format!("Failed to parse {}", path_to_file.display()))?)
+    }
+
+    async fn write_player_file<T: Serialize>(
+        &self,
+        file_name: &str,
+        entries: &[T],
+    ) -> Result<(), Error> {
+        let path_to_file = self.path_to_instance.join(file_name);
+        tokio::fs::write(
+            &path_to_file,
+            serde_json::to_string_pretty(entries)
+                .context(format!("Failed to serialize {}", file_name))?,
+        )
+        .await
+        .context(format!("Failed to write {}", path_to_file.display()))?;
+        Ok(())
+    }
+
+    /// Whether the server checks players with mojang, `online-mode` is on unless turned off
+    async fn online_mode(&self) -> bool {
+        read_properties_from_path(&self.path_to_properties)
+            .await
+            .ok()
+            .and_then(|properties| {
+                properties
+                    .get("online-mode")
+                    .map(|online_mode| online_mode != "false")
+            })
+            .unwrap_or(true)
+    }
+
+    /// See `local_player_uuid`
+    async fn read_local_player_uuid(&self, player: &str) -> Option<String> {
+        // usercache.json has the same name and uuid fields, besides an expiry
+        let user_cache: Vec<WhitelistEntry> = self
+            .read_player_file("usercache.json")
+            .await
+            .unwrap_or_default();
+        local_player_uuid(player, self.online_mode().await, &user_cache)
+    }
+
+    /// Looks a player's uuid up locally, then with mojang
+    async fn lookup_player_uuid(&self, player: &str) -> Result<String, Error> {
+        if let Some(uuid) = self.read_local_player_uuid(player).await {
+            return Ok(uuid);
+        }
+        resolve_player_uuid(player)
+            .await
+            .map(|uuid| uuid.hyphenated().to_string())
+    }
+
+    /// Sends a command over RCON, or the console if RCON is disabled or fails
+    ///
+    /// Returns the response, `None` if the command went over the console, whose output can't be
+    /// told apart from the rest of the log
+    async fn send_rcon_or_console(&self, command: &str) -> Result<Option<String>, Error> {
+        match self.send_rcon(command).await {
+            Ok(response) => Ok(Some(response)),
+            Err(e) => {
+                warn!(
+                    "Failed to send \"{}\" over RCON, using the console: {}",
+                    command, e
+                );
+                self.send_command_confirmed(command, CausedBy::System)
+                    .await?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Names of the whitelisted players, asked over RCON while the server is running since it
+    /// only writes `whitelist.json` when the whitelist changes
+    ///
+    /// `whitelist.json` is read if RCON is disabled or fails, it is only stale if it was edited
+    /// while the server is running
+    pub async fn get_whitelist(&self) -> Result<Vec<String>, Error> {
+        if *self.state.lock().await == State::Running {
+            match self.send_rcon("whitelist list").await {
+                Ok(response) => return Ok(parse_rcon_whitelist(&response)),
+                Err(e) => warn!(
+                    "Failed to get the whitelist over RCON, reading whitelist.json: {}",
+                    e
+                ),
+            }
+        }
+        Ok(self
+            .read_player_file::<WhitelistEntry>("whitelist.json")
+            .await?
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect())
+    }
+
+    /// Whitelists a player, over RCON or the console while the server is running and otherwise
+    /// in `whitelist.json` so it applies on the next start
+    ///
+    /// Editing the file needs the player's uuid, see `lookup_player_uuid`
+    pub async fn add_to_whitelist(&self, player: &str) -> Result<(), Error> {
+        validate_player_name(player)?;
+        if *self.state.lock().await == State::Running {
+            let response = self
+                .send_rcon_or_console(&format!("whitelist add {}", player))
+                .await?;
+            if response.is_some_and(|response| response.contains("does not exist")) {
+                return Err(Error {
+                    kind: ErrorKind::NotFound,
+                    source: eyre!("Player {} does not exist", player),
+                });
+            }
+            return Ok(());
+        }
+        let mut whitelist: Vec<WhitelistEntry> = self.read_player_file("whitelist.json").await?;
+        if whitelist
+            .iter()
+            .any(|entry| entry.name.eq_ignore_ascii_case(player))
+        {
+            return Ok(());
+        }
+        whitelist.push(WhitelistEntry {
+            uuid: self.lookup_player_uuid(player).await?,
+            name: player.to_string(),
+        });
+        self.write_player_file("whitelist.json", &whitelist).await
+    }
+
+    /// Removes a player from the whitelist, like `add_to_whitelist`
+    pub async fn remove_from_whitelist(&self, player: &str) -> Result<(), Error> {
+        validate_player_name(player)?;
+        if *self.state.lock().await == State::Running {
+            self.send_rcon_or_console(&format!("whitelist remove {}", player))
+                .await?;
+            return Ok(());
+        }
+        let mut whitelist: Vec<WhitelistEntry> = self.read_player_file("whitelist.json").await?;
+        let len = whitelist.len();
+        whitelist.retain(|entry| !entry.name.eq_ignore_ascii_case(player));
+        if whitelist.len() == len {
+            return Err(Error {
+                kind: ErrorKind::NotFound,
+                source: eyre!("Player {} is not whitelisted", player),
+            });
+        }
+        self.write_player_file("whitelist.json", &whitelist).await
+    }
+
+    /// Turns the whitelist on or off by setting `white-list` in server.properties
+    ///
+    /// A running server is told with `whitelist on` or `off` instead, it rewrites the properties
+    /// itself, which are then read back
+    pub async fn set_whitelist_enabled(
+        &self,
+        enabled: bool,
+        caused_by: CausedBy,
+    ) -> Result<(), Error> {
+        if *self.state.lock().await == State::Running {
+            let command = if enabled {
+                "whitelist on"
+            } else {
+                "whitelist off"
+            };
+            if self.send_rcon(command).await.is_err() {
+                self.send_command_confirmed(command, caused_by).await?;
+            }
+            return self.read_properties().await;
+        }
+        let _ = self.read_properties().await;
+        let mut configurable_manifest = self.configurable_manifest.lock().await;
+        configurable_manifest.set_setting(
+            ServerPropertySetting::get_section_id(),
+            ServerPropertySetting::WhiteList(enabled).into(),
+        )?;
+        self.write_properties_from_manifest(&configurable_manifest)
+            .await
+    }
+
+    fn emit_moderation_event(
+        &self,
+        instance_name: String,
+        player: &str,
+        action: ModerationAction,
+        reason: Option<&str>,
+        caused_by: CausedBy,
+    ) {
+        self.event_broadcaster.send(Event {
+            event_inner: EventInner::InstanceEvent(InstanceEvent {
+                instance_uuid: self.uuid.clone(),
+                instance_name,
+                instance_event_inner: InstanceEventInner::PlayerModerated {
+                    player: player.to_string(),
+                    action,
+                    reason: reason.map(|reason| reason.to_string()),
+                },
+            }),
+            details: "".to_string(),
+            snowflake: Snowflake::default(),
+            caused_by,
+        });
+    }
+
+    /// Sends a moderation command with the reason appended if there is one, see
+    /// `send_rcon_or_console` for the response
+    async fn send_moderation_command(
+        &self,
+        command: &str,
+        target: &str,
+        reason: Option<&str>,
+    ) -> Result<Option<String>, Error> {
+        match reason {
+            Some(reason) if !reason.trim().is_empty() => {
+                self.send_rcon_or_console(&format!("{} {} {}", command, target, reason.trim()))
+                    .await
+            }
+            _ => {
+                self.send_rcon_or_console(&format!("{} {}", command, target))
+                    .await
+            }
+        }
+    }
+
+    /// Kicks a player from the running server
+    ///
+    /// Kicking a player who isn't online, or while the server isn't running, does nothing
+    pub async fn kick_player(
+        &self,
+        name: &str,
+        reason: Option<&str>,
+        caused_by: CausedBy,
+    ) -> Result<(), Error> {
+        validate_player_name(name)?;
+        validate_reason(reason)?;
+        if *self.state.lock().await != State::Running {
+            return Ok(());
+        }
+        let response = self.send_moderation_command("kick", name, reason).await?;
+        if response.is_some_and(|response| response.contains("No player was found")) {
+            return Ok(());
+        }
+        let instance_name = self.config.lock().await.name.clone();
+        self.emit_moderation_event(
+            instance_name,
+            name,
+            ModerationAction::Kick,
+            reason,
+            caused_by,
+        );
+        Ok(())
+    }
+
+    /// Bans a player, with a command while the server is running and otherwise in
+    /// `banned-players.json` so it applies on the next start
+    pub async fn ban_player(
+        &self,
+        name: &str,
+        reason: Option<&str>,
+        caused_by: CausedBy,
+    ) -> Result<(), Error> {
+        validate_player_name(name)?;
+        validate_reason(reason)?;
+        if *self.state.lock().await == State::Running {
+            let response = self.send_moderation_command("ban", name, reason).await?;
+            if response.is_some_and(|response| response.contains("does not exist")) {
+                return Err(Error {
+                    kind: ErrorKind::NotFound,
+                    source: eyre!("Player {} does not exist", name),
+                });
+            }
+        } else {
+            let mut bans: Vec<BannedPlayerEntry> =
+                self.read_player_file("banned-players.json").await?;
+            if !bans.iter().any(|ban| ban.name.eq_ignore_ascii_case(name)) {
+                bans.push(BannedPlayerEntry {
+                    uuid: self.lookup_player_uuid(name).await?,
+                    name: name.to_string(),
+                    created: ban_timestamp(),
+                    source: "Lodestone".to_string(),
+                    expires: "forever".to_string(),
+                    reason: reason
+                        .filter(|reason| !reason.trim().is_empty())
+                        .unwrap_or(DEFAULT_BAN_REASON)
+                        .trim()
+                        .to_string(),
+                });
+                self.write_player_file("banned-players.json", &bans).await?;
+            }
+        }
+        let instance_name = self.config.lock().await.name.clone();
+        self.emit_moderation_event(
+            instance_name,
+            name,
+            ModerationAction::Ban,
+            reason,
+            caused_by,
+        );
+        Ok(())
+    }
+
+    /// Lifts a player's ban, like `ban_player`
+    pub async fn pardon_player(&self, name: &str, caused_by: CausedBy) -> Result<(), Error> {
+        validate_player_name(name)?;
+        if *self.state.lock().await == State::Running {
+            self.send_rcon_or_console(&format!("pardon {}", name))
+                .await?;
+        } else {
+            let mut bans: Vec<BannedPlayerEntry> =
+                self.read_player_file("banned-players.json").await?;
+            let len = bans.len();
+            bans.retain(|ban| !ban.name.eq_ignore_ascii_case(name));
+            if bans.len() == len {
+                return Err(Error {
+                    kind: ErrorKind::NotFound,
+                    source: eyre!("Player {} is not banned", name),
+                });
+            }
+            self.write_player_file("banned-players.json", &bans).await?;
+        }
+        let instance_name = self.config.lock().await.name.clone();
+        self.emit_moderation_event(
+            instance_name,
+            name,
+            ModerationAction::Pardon,
+            None,
+            caused_by,
+        );
+        Ok(())
+    }
+
+    /// Bans an IP address, like `ban_player` but in `banned-ips.json`
+    pub async fn ban_ip(
+        &self,
+        ip: &str,
+        reason: Option<&str>,
+        caused_by: CausedBy,
+    ) -> Result<(), Error> {
+        let ip = ip
+            .parse::<std::net::IpAddr>()
+            .map_err(|_| Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("Invalid IP address \"{}\"", ip),
+            })?
+            .to_string();
+        validate_reason(reason)?;
+        if *self.state.lock().await == State::Running {
+            self.send_moderation_command("ban-ip", &ip, reason).await?;
+        } else {
+            let mut bans: Vec<BannedIpEntry> = self.read_player_file("banned-ips.json").await?;
+            if !bans.iter().any(|ban| ban.ip == ip) {
+                bans.push(BannedIpEntry {
+                    ip: ip.clone(),
+                    created: ban_timestamp(),
+                    source: "Lodestone".to_string(),
+                    expires: "forever".to_string(),
+                    reason: reason
+                        .filter(|reason| !reason.trim().is_empty())
+                        .unwrap_or(DEFAULT_BAN_REASON)
+                        .trim()
+                        .to_string(),
+                });
+                self.write_player_file("banned-ips.json", &bans).await?;
+            }
+        }
+        let instance_name = self.config.lock().await.name.clone();
+        self.emit_moderation_event(
+            instance_name,
+            &ip,
+            ModerationAction::BanIp,
+            reason,
+            caused_by,
+        );
+        Ok(())
+    }
+
+    /// Lifts the ban of an IP address, like `pardon_player`
+    pub async fn pardon_ip(&self, ip: &str, caused_by: CausedBy) -> Result<(), Error> {
+        let ip = ip
+            .parse::<std::net::IpAddr>()
+            .map_err(|_| Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("Invalid IP address \"{}\"", ip),
+            })?
+            .to_string();
+        if *self.state.lock().await == State::Running {
+            self.send_rcon_or_console(&format!("pardon-ip {}", ip))
+                .await?;
+        } else {
+            let mut bans: Vec<BannedIpEntry> = self.read_player_file("banned-ips.json").await?;
+            let len = bans.len();
+            bans.retain(|ban| ban.ip != ip);
+            if bans.len() == len {
+                return Err(Error {
+                    kind: ErrorKind::NotFound,
+                    source: eyre!("IP address {} is not banned", ip),
+                });
+            }
+            self.write_player_file("banned-ips.json", &bans).await?;
+        }
+        let instance_name = self.config.lock().await.name.clone();
+        self.emit_moderation_event(
+            instance_name,
+            &ip,
+            ModerationAction::PardonIp,
+            None,
+            caused_by,
+        );
+        Ok(())
+    }
+
+    /// Makes a player an operator, over RCON while the server is running and otherwise in
+    /// `ops.json` so it applies on the next start
+    ///
+    /// `level` is clamped to 1..=4. The `op` command can't set it and gives the player the
+    /// server's `op-permission-level`, so any other level is refused while running. Editing the
+    /// file fails for players whose uuid can't be resolved offline, see `set_op_level`
+    pub async fn op_player(&self, name: &str, level: u8) -> Result<(), Error> {
+        validate_player_name(name)?;
+        let level = level.clamp(1, 4);
+        if *self.state.lock().await == State::Running {
+            let op_permission_level = self
+                .configurable_manifest
+                .lock()
+                .await
+                .get_unique_setting_key(
+                    &ServerPropertySetting::OpPermissionLevel(0).get_identifier(),
+                )
+                .and_then(|v| v.get_value().map(|v| v.try_as_unsigned_integer()))
+                .unwrap_or(Ok(4))?;
+            if u32::from(level) != op_permission_level {
+                return Err(Error {
+                    kind: ErrorKind::BadRequest,
+                    source: eyre!(
+                        "A running server can only op players at its op-permission-level {}, stop it to set level {}",
+                        op_permission_level,
+                        level
+                    ),
+                });
+            }
+            let response = self.send_rcon_or_console(&format!("op {}", name)).await?;
+            if response.is_some_and(|response| response.contains("does not exist")) {
+                return Err(Error {
+                    kind: ErrorKind::NotFound,
+                    source: eyre!("Player {} does not exist", name),
+                });
+            }
+            return Ok(());
+        }
+        let mut ops: Vec<OpEntry> = self.read_player_file("ops.json").await?;
+        let uuid = self.read_local_player_uuid(name).await;
+        set_op_level(&mut ops, name, level, uuid)?;
+        self.write_player_file("ops.json", &ops).await
+    }
+
+    /// Takes operator away from a player, like `op_player`
+    pub async fn deop_player(&self, name: &str) -> Result<(), Error> {
+        validate_player_name(name)?;
+        if *self.state.lock().await == State::Running {
+            self.send_rcon_or_console(&format!("deop {}", name)).await?;
+            return Ok(());
+        }
+        let mut ops: Vec<OpEntry> = self.read_player_file("ops.json").await?;
+        let len = ops.len();
+        ops.retain(|op| !op.name.eq_ignore_ascii_case(name));
+        if ops.len() == len {
+            return Err(Error {
+                kind: ErrorKind::NotFound,
+                source: eyre!("Player {} is not an operator", name),
+            });
+        }
+        self.write_player_file("ops.json", &ops).await
+    }
+}
+
 #[async_trait]
 impl TPlayerManagement for MinecraftInstance {
     async fn get_player_count(&self) -> Result<u32, Error> {
@@ -70,3 +725,74 @@ impl TPlayerManagement for MinecraftInstance {
         Ok(self.players_manager.lock().await.clone().into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_player_uuid() {
+        let user_cache = vec![WhitelistEntry {
+            uuid: "069a79f4-44e9-4726-a5be-fca90e38aaf5".to_string(),
+            name: "Notch".to_string(),
+        }];
+        assert_eq!(
+            local_player_uuid("notch", true, &user_cache).as_deref(),
+            Some("069a79f4-44e9-4726-a5be-fca90e38aaf5")
+        );
+        // only mojang knows the account of a player who never joined
+        assert_eq!(local_player_uuid("Steve", true, &user_cache), None);
+        // offline mode ignores the account and uses the name as the player typed it
+        assert_eq!(
+            local_player_uuid("notch", false, &user_cache).as_deref(),
+            Some("b50ad385-829d-3141-a216-7e7d7539ba7f")
+        );
+        assert_eq!(
+            local_player_uuid("Steve", false, &user_cache).as_deref(),
+            Some("5627dd98-e6be-3c21-b8a8-e92344183641")
+        );
+    }
+
+    #[test]
+    fn test_set_op_level() {
+        let user_cache = vec![WhitelistEntry {
+            uuid: "069a79f4-44e9-4726-a5be-fca90e38aaf5".to_string(),
+            name: "Notch".to_string(),
+        }];
+        let mut ops = Vec::new();
+        // an online mode server only knows the players who joined
+        let err = set_op_level(
+            &mut ops,
+            "Steve",
+            4,
+            local_player_uuid("Steve", true, &user_cache),
+        )
+        .unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::NotFound));
+        assert!(ops.is_empty());
+        set_op_level(
+            &mut ops,
+            "Notch",
+            4,
+            local_player_uuid("Notch", true, &user_cache),
+        )
+        .unwrap();
+        assert_eq!(ops[0].uuid, "069a79f4-44e9-4726-a5be-fca90e38aaf5");
+
+        // an offline mode server gives players the uuid of their name instead
+        set_op_level(
+            &mut ops,
+            "Steve",
+            2,
+            local_player_uuid("Steve", false, &user_cache),
+        )
+        .unwrap();
+        assert_eq!(ops[1].uuid, "5627dd98-e6be-3c21-b8a8-e92344183641");
+        assert_eq!(ops[1].level, 2);
+
+        // existing operators only have their level changed
+        set_op_level(&mut ops, "steve", 3, None).unwrap();
+        assert_eq!(ops.len(), 2);
+        assert_eq!(ops[1].level, 3);
+    }
+}
@@ -0,0 +1,87 @@
+use color_eyre::eyre::{eyre, Context};
+use serde_json::Value;
+
+use crate::error::Error;
+
+pub async fn get_quilt_minecraft_versions() -> Result<Vec<String>, Error> {
+    let http = reqwest::Client::new();
+
+    let response: Value = serde_json::from_str(
+        http.get("https://meta.quiltmc.org/v3/versions/game")
+            .send()
+            .await
+            .context("Failed to get quilt versions")?
+            .text()
+            .await
+            .context("Failed to get quilt versions")?
+            .as_str(),
+    )
+    .context("Failed to get quilt versions")?;
+
+    response
+        .as_array()
+        .ok_or_else(|| eyre!("Failed to get quilt versions. Response is not an array"))?
+        .iter()
+        .map(|item| {
+            item["version"]
+                .as_str()
+                .ok_or_else(|| {
+                    eyre!("Failed to get quilt versions. Version string is not a string").into()
+                })
+                .map(|version| version.to_string())
+        })
+        .collect::<Result<Vec<String>, Error>>()
+}
+
+/// The newest loader release supporting the minecraft version, betas are skipped
+pub async fn get_latest_quilt_loader_version(version: &str) -> Result<String, Error> {
+    let http = reqwest::Client::new();
+
+    let response: Value = serde_json::from_str(
+        http.get(format!(
+            "https://meta.quiltmc.org/v3/versions/loader/{}",
+            version
+        ))
+        .send()
+        .await
+        .context("Failed to get quilt loader versions")?
+        .text()
+        .await
+        .context("Failed to get quilt loader versions")?
+        .as_str(),
+    )
+    .context("Failed to get quilt loader versions")?;
+
+    // the meta lists the newest loader first
+    response
+        .as_array()
+        .ok_or_else(|| eyre!("Failed to get quilt loader versions. Response is not an array"))?
+        .iter()
+        .filter_map(|item| item["loader"]["version"].as_str())
+        .find(|loader_version| !loader_version.contains('-'))
+        .map(|loader_version| loader_version.to_string())
+        .ok_or_else(|| eyre!("No quilt loader release supports minecraft {}", version).into())
+}
+
+pub async fn get_latest_quilt_installer_version() -> Result<String, Error> {
+    let http = reqwest::Client::new();
+
+    let response: Value = serde_json::from_str(
+        http.get("https://meta.quiltmc.org/v3/versions/installer")
+            .send()
+            .await
+            .context("Failed to get quilt installer versions")?
+            .text()
+            .await
+            .context("Failed to get quilt installer versions")?
+            .as_str(),
+    )
+    .context("Failed to get quilt installer versions")?;
+
+    response
+        .as_array()
+        .and_then(|installers| installers.first())
+        .and_then(|installer| installer["version"].as_str())
+        .map(|installer_version| installer_version.to_string())
+        .ok_or_else(|| eyre!("Failed to get quilt installer versions. No installer found").into())
+}
@@ -0,0 +1,234 @@
+use chrono::{
+    DateTime, Datelike, Duration, Local, LocalResult, NaiveDateTime, TimeZone, Utc, Weekday,
+};
+use chrono_tz::Tz;
+use color_eyre::eyre::eyre;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::error::{Error, ErrorKind};
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, TS)]
+#[ts(export)]
+pub enum ScheduleDay {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl From<Weekday> for ScheduleDay {
+    fn from(weekday: Weekday) -> Self {
+        match weekday {
+            Weekday::Mon => ScheduleDay::Monday,
+            Weekday::Tue => ScheduleDay::Tuesday,
+            Weekday::Wed => ScheduleDay::Wednesday,
+            Weekday::Thu => ScheduleDay::Thursday,
+            Weekday::Fri => ScheduleDay::Friday,
+            Weekday::Sat => ScheduleDay::Saturday,
+            Weekday::Sun => ScheduleDay::Sunday,
+        }
+    }
+}
+
+/// A recurring restart, e.g. every day at 4:00 or on Mondays at 3:00
+///
+/// The time is in the schedule's timezone, so it stays at the same wall clock time across
+/// daylight saving changes
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct RestartSchedule {
+    /// Days the restart happens on, every day if empty
+    pub days: Vec<ScheduleDay>,
+    pub hour: u32,
+    pub minute: u32,
+    /// IANA name of the timezone the time is in, e.g. `Europe/Berlin`, that of the machine
+    /// lodestone runs on if `None`
+    #[serde(default)]
+    pub timezone: Option<String>,
+}
+
+impl RestartSchedule {
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.hour >= 24 || self.minute >= 60 {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!(
+                    "Invalid restart time {}:{:02}, expected 0:00 to 23:59",
+                    self.hour,
+                    self.minute
+                ),
+            });
+        }
+        if let Some(timezone) = &self.timezone {
+            timezone.parse::<Tz>().map_err(|e| Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("Invalid timezone {}: {}", timezone, e),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// The first restart strictly after `after`, with the time taken in the schedule's timezone
+    pub fn next_restart(&self, after: &DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self
+            .timezone
+            .as_ref()
+            .and_then(|timezone| timezone.parse::<Tz>().ok())
+        {
+            Some(timezone) => self
+                .next_after(&after.with_timezone(&timezone))
+                .map(|restart| restart.with_timezone(&Utc)),
+            None => self
+                .next_after(&after.with_timezone(&Local))
+                .map(|restart| restart.with_timezone(&Utc)),
+        }
+    }
+
+    /// The first restart strictly after `after`, `None` if the time is out of range
+    ///
+    /// A time skipped by a daylight saving change happens an hour later that day, a time that
+    /// occurs twice happens the first time only
+    pub fn next_after<Tz: TimeZone>(&self, after: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let timezone = after.timezone();
+        let today = after.naive_local().date();
+        // a week and a day, since today's time may have passed already
+        for day_offset in 0..=7 {
+            let date = today + Duration::days(day_offset);
+            if !self.days.is_empty() && !self.days.contains(&date.weekday().into()) {
+                continue;
+            }
+            let time = date.and_hms_opt(self.hour, self.minute, 0)?;
+            let restart = match resolve_local(&timezone, &time) {
+                Some(restart) => restart,
+                None => continue,
+            };
+            if restart > *after {
+                return Some(restart);
+            }
+        }
+        None
+    }
+}
+
+fn resolve_local<Tz: TimeZone>(timezone: &Tz, time: &NaiveDateTime) -> Option<DateTime<Tz>> {
+    match timezone.from_local_datetime(time) {
+        LocalResult::Single(time) => Some(time),
+        LocalResult::Ambiguous(earliest, _) => Some(earliest),
+        LocalResult::None => timezone
+            .from_local_datetime(&(*time + Duration::hours(1)))
+            .earliest(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{FixedOffset, NaiveDate};
+
+    use super::*;
+
+    fn january(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 1, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_next_after() {
+        let every_day = RestartSchedule {
+            days: Vec::new(),
+            hour: 4,
+            minute: 0,
+            timezone: None,
+        };
+        // 2024-01-01 is a Monday
+        let before = Utc.from_utc_datetime(&january(1, 3, 0));
+        let after = Utc.from_utc_datetime(&january(1, 4, 0));
+        assert_eq!(
+            every_day.next_after(&before),
+            Some(Utc.from_utc_datetime(&january(1, 4, 0)))
+        );
+        assert_eq!(
+            every_day.next_after(&after),
+            Some(Utc.from_utc_datetime(&january(2, 4, 0)))
+        );
+
+        let mondays = RestartSchedule {
+            days: vec![ScheduleDay::Monday],
+            hour: 3,
+            minute: 30,
+            timezone: None,
+        };
+        assert_eq!(
+            mondays.next_after(&after),
+            Some(Utc.from_utc_datetime(&january(8, 3, 30)))
+        );
+
+        // the time is in the given timezone, not in UTC
+        let offset = FixedOffset::east_opt(2 * 3600).unwrap();
+        let before = offset.from_local_datetime(&january(1, 3, 0)).unwrap();
+        assert_eq!(
+            every_day.next_after(&before).unwrap().naive_local(),
+            january(1, 4, 0)
+        );
+
+        let invalid = RestartSchedule {
+            days: Vec::new(),
+            hour: 24,
+            minute: 0,
+            timezone: None,
+        };
+        assert!(invalid.validate().is_err());
+        assert_eq!(invalid.next_after(&before), None);
+    }
+
+    fn utc(month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, month, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_next_restart_across_daylight_saving() {
+        // Berlin skips 2:00 to 3:00 on 2024-03-31 and repeats 2:00 to 3:00 on 2024-10-27
+        let schedule = RestartSchedule {
+            days: Vec::new(),
+            hour: 2,
+            minute: 30,
+            timezone: Some("Europe/Berlin".to_string()),
+        };
+        schedule.validate().unwrap();
+        // CET is UTC+1, CEST is UTC+2
+        assert_eq!(
+            schedule.next_restart(&utc(3, 30, 0, 0)),
+            Some(utc(3, 30, 1, 30))
+        );
+        // the skipped 2:30 happens at 3:30 CEST instead
+        assert_eq!(
+            schedule.next_restart(&utc(3, 30, 12, 0)),
+            Some(utc(3, 31, 1, 30))
+        );
+        assert_eq!(
+            schedule.next_restart(&utc(3, 31, 1, 30)),
+            Some(utc(4, 1, 0, 30))
+        );
+        // the repeated 2:30 happens the first time, in CEST, only
+        assert_eq!(
+            schedule.next_restart(&utc(10, 26, 12, 0)),
+            Some(utc(10, 27, 0, 30))
+        );
+        assert_eq!(
+            schedule.next_restart(&utc(10, 27, 0, 30)),
+            Some(utc(10, 28, 1, 30))
+        );
+
+        let invalid = RestartSchedule {
+            timezone: Some("Europe/Atlantis".to_string()),
+            ..schedule
+        };
+        assert!(invalid.validate().is_err());
+    }
+}
@@ -0,0 +1,235 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::Context;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::error::Error;
+
+/// The plugins or mods a running server reports, reconciled with the jars on disk
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct RuntimeContent {
+    /// As listed by the server
+    pub loaded: Vec<String>,
+    /// File names of the jars on disk the server didn't list, e.g. because they failed to enable
+    pub not_loaded: Vec<String>,
+}
+
+/// Removes the `§` formatting codes the server colours its command output with
+fn strip_formatting(text: &str) -> String {
+    let mut ret = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '§' {
+            chars.next();
+        } else {
+            ret.push(c);
+        }
+    }
+    ret
+}
+
+/// Parses the response of `plugins` or a mod list command into the names it lists
+///
+/// Handles both the single line format, `Plugins (2): Vault, WorldEdit`, and newer multi-line
+/// formats where the names follow headers like `Bukkit Plugins:` and are prefixed with `- `
+pub fn parse_content_list(response: &str) -> Vec<String> {
+    let mut ret = Vec::new();
+    for line in strip_formatting(response).lines() {
+        let line = match line.split_once(':') {
+            Some((header, rest))
+                if header.to_lowercase().contains("plugins")
+                    || header.to_lowercase().contains("mod list") =>
+            {
+                rest
+            }
+            _ => line,
+        };
+        for name in line.split(',') {
+            let name = name.trim().trim_start_matches('-').trim();
+            if !name.is_empty() {
+                ret.push(name.to_string());
+            }
+        }
+    }
+    ret
+}
+
+/// The part of a `mods.toml` naming the mods in a jar
+#[derive(Deserialize)]
+struct ModsToml {
+    #[serde(default)]
+    mods: Vec<ModsTomlEntry>,
+}
+
+#[derive(Deserialize)]
+struct ModsTomlEntry {
+    #[serde(rename = "modId")]
+    mod_id: String,
+}
+
+/// Names a jar's plugin or mods are listed under, empty if it is neither, e.g. a library
+///
+/// Plugins are named by the top-level `name` in `plugin.yml`, mods by the `modId` of each
+/// `[[mods]]` table in their `mods.toml`, other tables like `[[dependencies.<modId>]]` also have
+/// a `modId` but name other mods
+pub fn read_content_identifiers(jar: &Path) -> Result<Vec<String>, Error> {
+    let file = std::fs::File::open(jar).context(format!("Failed to open jar {}", jar.display()))?;
+    let mut zip =
+        zip::ZipArchive::new(file).context(format!("Failed to read jar {}", jar.display()))?;
+    let mut ret: Vec<String> = Vec::new();
+    for metadata_file in [
+        "plugin.yml",
+        "paper-plugin.yml",
+        "META-INF/mods.toml",
+        "META-INF/neoforge.mods.toml",
+    ] {
+        let mut contents = String::new();
+        match zip.by_name(metadata_file) {
+            Ok(mut entry) => entry.read_to_string(&mut contents).context(format!(
+                "Failed to read {} in {}",
+                metadata_file,
+                jar.display()
+            ))?,
+            Err(_) => continue,
+        };
+        let identifiers = if metadata_file.ends_with(".toml") {
+            toml::from_str::<ModsToml>(&contents)
+                .context(format!(
+                    "Failed to parse {} in {}",
+                    metadata_file,
+                    jar.display()
+                ))?
+                .mods
+                .into_iter()
+                .map(|entry| entry.mod_id)
+                .collect()
+        } else {
+            // keys of nested mappings, like the commands a plugin adds, are indented
+            contents
+                .lines()
+                .filter_map(|line| line.strip_prefix("name:"))
+                .map(|value| value.trim().trim_matches(['"', '\'']).to_string())
+                .collect::<Vec<_>>()
+        };
+        for identifier in identifiers {
+            if !identifier.is_empty() && !ret.contains(&identifier) {
+                ret.push(identifier);
+            }
+        }
+    }
+    Ok(ret)
+}
+
+/// Whether the server listed any of the names, ignoring case
+///
+/// Mod lists print more than the id, e.g. `jei-15.2.jar JEI (jei)`, so the words of each entry
+/// are matched as well
+fn is_listed(identifiers: &[String], loaded: &[String]) -> bool {
+    identifiers.iter().any(|identifier| {
+        loaded.iter().any(|entry| {
+            entry.eq_ignore_ascii_case(identifier)
+                || entry
+                    .split(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | ':' | '[' | ']'))
+                    .any(|word| word.eq_ignore_ascii_case(identifier))
+        })
+    })
+}
+
+/// File names of the plugin and mod jars in `dirs` that aren't in `loaded`
+///
+/// Jars that can't be read are skipped, the server reports those itself
+pub fn find_not_loaded(dirs: &[PathBuf], loaded: &[String]) -> Vec<String> {
+    let mut ret: Vec<String> = dirs
+        .iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let path = entry.path();
+            path.is_file()
+                && path
+                    .extension()
+                    .map_or(false, |extension| extension == "jar")
+        })
+        .filter(|entry| match read_content_identifiers(&entry.path()) {
+            Ok(identifiers) => !identifiers.is_empty() && !is_listed(&identifiers, loaded),
+            Err(_) => false,
+        })
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+    ret.sort();
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_content_list() {
+        assert_eq!(
+            parse_content_list("Plugins (3): §aEssentials§f, §aVault§f, §cWorldEdit"),
+            vec!["Essentials", "Vault", "WorldEdit"]
+        );
+        assert_eq!(
+            parse_content_list(
+                "ℹ Server Plugins (2):\nBukkit Plugins:\n - LuckPerms, Vault\nPaper Plugins:\n"
+            ),
+            vec!["LuckPerms", "Vault"]
+        );
+        assert!(parse_content_list("Plugins (0):").is_empty());
+    }
+
+    #[test]
+    fn test_find_not_loaded() {
+        let plugins_dir = tempfile::tempdir().unwrap();
+        let write_jar = |name: &str, metadata_file: &str, contents: &str| {
+            let mut writer =
+                zip::ZipWriter::new(std::fs::File::create(plugins_dir.path().join(name)).unwrap());
+            writer
+                .start_file(metadata_file, zip::write::FileOptions::default())
+                .unwrap();
+            writer.write_all(contents.as_bytes()).unwrap();
+            writer.finish().unwrap();
+        };
+        write_jar("Vault.jar", "plugin.yml", "name: Vault\nversion: 1.7\n");
+        write_jar("broken.jar", "plugin.yml", "name: 'Broken'\n");
+        write_jar("jei.jar", "META-INF/mods.toml", "[[mods]]\nmodId=\"jei\"\n");
+        write_jar(
+            "appleskin.jar",
+            "META-INF/mods.toml",
+            "modLoader=\"javafml\"\n\
+             [[mods]]\n\
+             modId=\"appleskin\"\n\
+             version=\"${file.jarVersion}\"\n\
+             [[dependencies.appleskin]]\n\
+             modId=\"forge\"\n\
+             mandatory=true\n",
+        );
+        write_jar("library.jar", "META-INF/MANIFEST.MF", "");
+
+        assert_eq!(
+            read_content_identifiers(&plugins_dir.path().join("broken.jar")).unwrap(),
+            vec!["Broken"]
+        );
+        assert_eq!(
+            read_content_identifiers(&plugins_dir.path().join("appleskin.jar")).unwrap(),
+            vec!["appleskin"]
+        );
+        // appleskin only depends on forge, so it isn't loaded just because forge is
+        let loaded = vec![
+            "vault".to_string(),
+            "jei-15.2.jar JEI (jei)".to_string(),
+            "forge".to_string(),
+        ];
+        assert_eq!(
+            find_not_loaded(&[plugins_dir.path().to_owned()], &loaded),
+            vec!["appleskin.jar", "broken.jar"]
+        );
+    }
+}
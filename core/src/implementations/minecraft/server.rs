@@ -1,36 +1,53 @@
-use std::path::PathBuf;
 use std::process::Stdio;
-use std::time::Duration;
 
 use color_eyre::eyre::{eyre, Context};
 use sysinfo::{Pid, PidExt, ProcessExt, SystemExt};
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::io::BufReader;
 use tokio::process::Command;
 
 use crate::error::{Error, ErrorKind};
 use crate::events::{CausedBy, Event, EventInner, InstanceEvent, InstanceEventInner};
 use crate::implementations::minecraft::line_parser::{
+    is_port_bind_failure, parse_bound_port, parse_lag_warning, parse_paper_notice,
     parse_player_joined, parse_player_left, parse_player_msg, parse_server_started,
-    parse_system_msg, PlayerMessage,
+    parse_server_version, parse_system_msg, LagWarningAggregator, PlayerMessage, PromptResponder,
+    PARTIAL_LINE_PROMPT_DELAY,
 };
 use crate::implementations::minecraft::player::MinecraftPlayer;
 use crate::implementations::minecraft::util::name_to_uuid;
 use crate::macro_executor::{DefaultWorkerOptionGenerator, SpawnResult};
+use crate::resource_guardrail::check_start_ram_guardrail;
 use crate::traits::t_configurable::TConfigurable;
 use crate::traits::t_macro::TaskEntry;
 use crate::traits::t_server::{MonitorReport, State, StateAction, TServer};
 
 use crate::types::Snowflake;
-use crate::util::{dont_spawn_terminal, list_dir};
+use crate::util::{dont_spawn_terminal, list_dir, read_line_bounded, TRUNCATED_LINE_MARKER};
 
+use super::backup::BackupOnStopMode;
+use super::neoforge::find_neoforge_args_file;
 use super::r#macro::resolve_macro_invocation;
-use super::{Flavour, ForgeBuildVersion, MinecraftInstance};
+use super::{
+    apply_process_priority, write_stdin_line, Flavour, ForgeBuildVersion, MinecraftInstance,
+    RestoreConfig, STDIN_RESPONSE_TIMEOUT,
+};
 use tracing::{error, info, warn};
 
 #[async_trait::async_trait]
 impl TServer for MinecraftInstance {
     async fn start(&self, cause_by: CausedBy, block: bool) -> Result<(), Error> {
+        self.ensure_not_needs_repair()?;
         let config = self.config.lock().await.clone();
+        self.check_mod_loaders(&config).await?;
+        // validated before the transition, so a bad agent doesn't leave the instance starting
+        let java_agent_args = self.java_agent_args().await?;
+        check_start_ram_guardrail(&self.uuid, &config.name, config.max_ram).await?;
+        if !port_scanner::local_port_available(config.port as u16) {
+            return Err(Error {
+                kind: ErrorKind::Internal,
+                source: eyre!("Port {} is already in use", config.port),
+            });
+        }
         self.state.lock().await.try_transition(
             StateAction::UserStart,
             Some(&|state| {
@@ -46,12 +63,20 @@ impl TServer for MinecraftInstance {
                 });
             }),
         )?;
-
-        if !port_scanner::local_port_available(config.port as u16) {
-            return Err(Error {
-                kind: ErrorKind::Internal,
-                source: eyre!("Port {} is already in use", config.port),
-            });
+        // starting supersedes any restart still counting down after a crash
+        if let Some(pending_crash_restart) = self.pending_crash_restart.lock().await.take() {
+            pending_crash_restart.abort();
+        }
+        self.stop_requested
+            .store(false, std::sync::atomic::Ordering::Relaxed);
+        self.bound_port.lock().await.take();
+        self.crash_cause.lock().await.take();
+        self.detected_version.lock().await.take();
+        self.begin_startup_timing().await;
+
+        if let Err(e) = self.prepare_rcon().await {
+            self.abort_start(&config.name, cause_by.clone()).await;
+            return Err(e);
         }
 
         let prelaunch = resolve_macro_invocation(&self.path_to_instance, "prelaunch");
@@ -98,177 +123,126 @@ impl TServer for MinecraftInstance {
             );
         }
 
-        let jre = if let Some(jre) = &config.java_cmd {
-            PathBuf::from(jre)
-        } else {
-            self.path_to_runtimes
-                .join("java")
-                .join(format!("jre{}", config.jre_major_version))
-                .join(if std::env::consts::OS == "macos" {
-                    "Contents/Home/bin"
-                } else {
-                    "bin"
-                })
-                .join("java")
-        };
-
-        let mut server_start_command = Command::new(&jre);
-        let server_start_command = server_start_command
-            .arg(format!("-Xmx{}M", config.max_ram))
-            .arg(format!("-Xms{}M", config.min_ram))
-            .args(
-                &config
-                    .cmd_args
-                    .iter()
-                    .filter(|s| !s.is_empty())
-                    .collect::<Vec<&String>>(),
-            );
-
-        let server_start_command = match &config.flavour {
-            Flavour::Forge { build_version } => {
-                let ForgeBuildVersion(build_version) = build_version
-                    .as_ref()
-                    .ok_or_else(|| eyre!("Forge version not found"))?;
-                let version_parts: Vec<&str> = config.version.split('.').collect();
-                let major_version: i32 = version_parts[1]
-                    .parse()
-                    .context("Unable to parse major Minecraft version for Forge")?;
-
-                if 17 <= major_version {
-                    let forge_args = match std::env::consts::OS {
-                        "windows" => "win_args.txt",
-                        _ => "unix_args.txt",
-                    };
-
-                    let mut full_forge_args = std::ffi::OsString::from("@");
-                    full_forge_args.push(
-                        self.path_to_instance
-                            .join("libraries")
-                            .join("net")
-                            .join("minecraftforge")
-                            .join("forge")
-                            .join(build_version.as_str())
-                            .join(forge_args)
-                            .into_os_string()
-                            .as_os_str(),
-                    );
-
-                    server_start_command.arg(full_forge_args)
-                } else if (7..=16).contains(&major_version) {
-                    let files = list_dir(&self.path_to_instance, Some(false))
-                        .await
-                        .context("Failed to find forge.jar")?;
-                    let forge_jar_name = files
-                        .iter()
-                        .find(|p| {
-                            p.extension().unwrap_or_default() == "jar"
-                                && p.file_name()
-                                    .unwrap_or_default()
-                                    .to_str()
-                                    .unwrap_or_default()
-                                    .starts_with(format!("forge-{}-", config.version,).as_str())
-                        })
-                        .ok_or_else(|| eyre!("Failed to find forge.jar"))?;
-                    server_start_command
-                        .arg("-jar")
-                        .arg(&self.path_to_instance.join(forge_jar_name))
-                } else {
-                    // 1.5 doesn't work due to JRE issues
-                    // 1.4 doesn't work since forge doesn't provide an installer
-                    let files = list_dir(&self.path_to_instance, Some(false))
-                        .await
-                        .context("Failed to find minecraftforge.jar")?;
-                    let server_jar_name = files
-                        .iter()
-                        .find(|p| {
-                            p.extension().unwrap_or_default() == "jar"
-                                && p.file_name()
-                                    .unwrap_or_default()
-                                    .to_str()
-                                    .unwrap_or_default()
-                                    .starts_with("minecraftforge")
-                        })
-                        .ok_or_else(|| eyre!("Failed to find minecraftforge.jar"))?;
-                    server_start_command
-                        .arg("-jar")
-                        .arg(&self.path_to_instance.join(server_jar_name))
+        let mut server_start_command =
+            match self.server_start_command(&config, &java_agent_args).await {
+                Ok(server_start_command) => server_start_command,
+                Err(e) => {
+                    self.abort_start(&config.name, cause_by.clone()).await;
+                    return Err(e);
                 }
-            }
-            _ => server_start_command
-                .arg("-jar")
-                .arg(&self.path_to_instance.join("server.jar")),
-        };
+            };
 
-        let server_start_command = server_start_command
-            .arg("nogui")
-            .current_dir(&self.path_to_instance);
+        let std_command = server_start_command.as_std();
+        *self.last_launch_command.lock().await = Some(
+            std::iter::once(std_command.get_program())
+                .chain(std_command.get_args())
+                .map(|arg| arg.to_string_lossy().to_string())
+                .collect(),
+        );
 
-        match dont_spawn_terminal(server_start_command)
+        match dont_spawn_terminal(&mut server_start_command)
             .stdout(Stdio::piped())
             .stdin(Stdio::piped())
             .stderr(Stdio::piped())
             .spawn()
         {
             Ok(mut proc) => {
-                let stdin = proc.stdin.take().ok_or_else(|| {
-                    error!(
-                        "[{}] Failed to take stdin during startup",
-                        config.name.clone()
-                    );
-                    eyre!("Failed to take stdin during startup")
-                })?;
+                let (stdin, stdout, stderr) =
+                    match (proc.stdin.take(), proc.stdout.take(), proc.stderr.take()) {
+                        (Some(stdin), Some(stdout), Some(stderr)) => (stdin, stdout, stderr),
+                        _ => {
+                            error!(
+                                "[{}] Failed to take stdio during startup",
+                                config.name.clone()
+                            );
+                            let _ = proc.kill().await;
+                            self.abort_start(&config.name, cause_by.clone()).await;
+                            return Err(eyre!("Failed to take stdio during startup").into());
+                        }
+                    };
                 self.stdin.lock().await.replace(stdin);
-                let stdout = proc.stdout.take().ok_or_else(|| {
-                    error!(
-                        "[{}] Failed to take stdout during startup",
-                        config.name.clone()
-                    );
-                    eyre!("Failed to take stdout during startup")
-                })?;
-                let stderr = proc.stderr.take().ok_or_else(|| {
-                    error!(
-                        "[{}] Failed to take stderr during startup",
-                        config.name.clone()
-                    );
-                    eyre!("Failed to take stderr during startup")
-                })?;
                 *self.process.lock().await = Some(proc);
-                tokio::task::spawn({
+                let startup_timeout = config.startup_timeout;
+                let parse_paper_notices =
+                    matches!(config.flavour, Flavour::Paper { .. } | Flavour::Spigot);
+                let prompt_responder = PromptResponder::new(&config.prompt_responses)
+                    .unwrap_or_else(|e| {
+                        warn!("[{}] Ignoring the prompt responses: {}", config.name, e);
+                        PromptResponder::default()
+                    });
+                let stdout_task = tokio::task::spawn({
                     let mut __self = self.clone();
                     let event_broadcaster = __self.event_broadcaster.clone();
                     let uuid = __self.uuid.clone();
                     let name = config.name.clone();
                     let players_manager = __self.players_manager.clone();
+                    let mirror_console_output = __self.mirror_console_output.clone();
                     async move {
                         let mut did_start = false;
+                        let mut lag_warnings = LagWarningAggregator::default();
 
                         let mut stdout_reader = BufReader::new(stdout);
                         let mut stderr_reader = BufReader::new(stderr);
+                        // kept across iterations so a read cancelled by the other stream
+                        // resumes where it left off
+                        let mut stdout_line = Vec::new();
+                        let mut stderr_line = Vec::new();
+                        let mut partial_line_checked = false;
+                        let mut partial_prompt_answered = false;
 
                         loop {
                             let (line_res, is_stdout) = tokio::select!(
                                 line_res = async {
-                                    let mut line = Vec::new();
-                                    match stdout_reader.read_until(b'\n', &mut line).await {
-                                        Ok(0) => return Ok(None),
+                                    match read_line_bounded(
+                                        &mut stdout_reader,
+                                        &mut stdout_line,
+                                        config.max_log_line_length,
+                                    )
+                                    .await
+                                    {
+                                        Ok((0, _)) if stdout_line.is_empty() => return Ok(None),
                                         Err(e) => return Err(e),
-                                        Ok(_) => {}
-
+                                        Ok((_, truncated)) => if truncated {
+                                            stdout_line.extend_from_slice(TRUNCATED_LINE_MARKER.as_bytes());
+                                            stdout_line.push(b'\n');
+                                        }
                                     };
-                                    Ok(Some(line))
+                                    Ok(Some(std::mem::take(&mut stdout_line)))
                                 } => {
                                     (line_res, true)
                                 },
                                 line_res = async {
-                                    let mut line = Vec::new();
-                                    match stderr_reader.read_until(b'\n', &mut line).await {
-                                        Ok(0) => return Ok(None),
+                                    match read_line_bounded(
+                                        &mut stderr_reader,
+                                        &mut stderr_line,
+                                        config.max_log_line_length,
+                                    )
+                                    .await
+                                    {
+                                        Ok((0, _)) if stderr_line.is_empty() => return Ok(None),
                                         Err(e) => return Err(e),
-                                        Ok(_) => {}
+                                        Ok((_, truncated)) => if truncated {
+                                            stderr_line.extend_from_slice(TRUNCATED_LINE_MARKER.as_bytes());
+                                            stderr_line.push(b'\n');
+                                        }
                                     };
-                                    Ok(Some(line))
+                                    Ok(Some(std::mem::take(&mut stderr_line)))
                                 } => {
                                     (line_res, false)
+                                },
+                                // prompts like "Continue? [y/n] " wait for input on the same
+                                // line, so an unterminated line that stalls is checked as well
+                                _ = tokio::time::sleep(PARTIAL_LINE_PROMPT_DELAY),
+                                    if !stdout_line.is_empty() && !partial_line_checked => {
+                                    partial_line_checked = true;
+                                    if __self.state().await == State::Starting {
+                                        let partial = String::from_utf8_lossy(&stdout_line);
+                                        if let Some(response) = prompt_responder.respond(&partial) {
+                                            partial_prompt_answered = true;
+                                            __self.answer_prompt(&partial, response).await;
+                                        }
+                                    }
+                                    continue;
                                 }
                             );
                             let _ = line_res.as_ref().map_err(|e| {
@@ -278,9 +252,18 @@ impl TServer for MinecraftInstance {
                             if let Ok(line) = line_res {
                                 if let Some(line) = line {
                                     let line = String::from_utf8_lossy(&line).to_string();
+                                    let answered_as_partial = is_stdout && partial_prompt_answered;
+                                    if is_stdout {
+                                        partial_line_checked = false;
+                                        partial_prompt_answered = false;
+                                    }
                                     if !is_stdout {
                                         // info!("[{}] {}", name, line);
                                         warn!("[{}] {}", name, line);
+                                    } else if mirror_console_output
+                                        .load(std::sync::atomic::Ordering::Relaxed)
+                                    {
+                                        info!("[{}] {}", name, line.trim_end());
                                     }
                                     event_broadcaster.send(Event {
                                         event_inner: EventInner::InstanceEvent(InstanceEvent {
@@ -323,68 +306,92 @@ impl TServer for MinecraftInstance {
                                             )
                                             .unwrap();
                                         info!("[{}] Instance started", name);
-
-                                        if let (Some(true), Some(rcon_psw), Some(rcon_port)) = {
-                                            let lock = __self.configurable_manifest.lock().await;
-
-                                            let a = lock
-                                                .get_unique_setting_key("enable-rcon")
-                                                .and_then(|v| {
-                                                    v.get_value().map(|v| v.try_as_boolean().ok())
-                                                })
-                                                .flatten();
-
-                                            let b = lock
-                                                .get_unique_setting_key("rcon.password")
-                                                .and_then(|v| {
-                                                    v.get_value().map(|v| v.try_as_string().ok())
-                                                })
-                                                .flatten()
-                                                .cloned();
-
-                                            let c = lock
-                                                .get_unique_setting_key("rcon.port")
-                                                .and_then(|v| {
-                                                    v.get_value()
-                                                        .map(|v| v.try_as_unsigned_integer().ok())
-                                                })
-                                                .flatten();
-                                            (a, b, c)
-                                        } {
-                                            let max_retry = 3;
-                                            for i in 0..max_retry {
-                                                let rcon =
-                                                <rcon::Connection<tokio::net::TcpStream>>::builder(
-                                                )
-                                                .enable_minecraft_quirks(true)
-                                                .connect(
-                                                    &format!("localhost:{}", rcon_port),
-                                                    &rcon_psw,
-                                                )
-                                                .await
-                                                .map_err(|e| {
-                                                    warn!(
-                                                    "[{}] Failed to connect to RCON: {}, retry {}/{}",
-                                                    config.name,
-                                                    e, i, max_retry
-                                                );
-                                                    e
-                                                });
-                                                if let Ok(rcon) = rcon {
-                                                    info!("[{}] Connected to RCON", config.name);
-                                                    __self.rcon_conn.lock().await.replace(rcon);
-                                                    break;
+                                        __self.record_startup_duration().await;
+                                        __self
+                                            .running_since
+                                            .lock()
+                                            .await
+                                            .replace(std::time::Instant::now());
+                                        __self.spawn_watchdog().await;
+                                        __self.spawn_autosave().await;
+                                        __self.run_commands_on_start().await;
+
+                                        if __self.rcon_credentials().await.is_some() {
+                                            match __self.ensure_rcon_connected().await {
+                                                Ok(()) => {
+                                                    info!("[{}] Connected to RCON", config.name)
                                                 }
-                                                tokio::time::sleep(Duration::from_secs(
-                                                    2_u64.pow(i),
-                                                ))
-                                                .await;
+                                                Err(e) => warn!(
+                                                    "[{}] Failed to connect to RCON: {}",
+                                                    config.name, e
+                                                ),
                                             }
                                         } else {
                                             warn!("RCON is not enabled or misconfigured, skipping");
                                             __self.rcon_conn.lock().await.take();
                                         }
                                     }
+                                    if let Some(bound_port) = parse_bound_port(&line) {
+                                        __self.set_bound_port(Some(bound_port)).await;
+                                    }
+                                    if let Some(server_version) = parse_server_version(&line) {
+                                        __self.record_server_version(server_version).await;
+                                    }
+                                    if is_port_bind_failure(&line) {
+                                        __self.on_port_bind_failure().await;
+                                    }
+                                    let lag_warning = parse_lag_warning(&line).and_then(
+                                        |(ms_behind, ticks_skipped)| {
+                                            lag_warnings.record(
+                                                ms_behind,
+                                                ticks_skipped,
+                                                std::time::Instant::now(),
+                                            )
+                                        },
+                                    );
+                                    if let Some((ms_behind, ticks_skipped)) = lag_warning {
+                                        let _ = event_broadcaster.send(Event {
+                                            event_inner: EventInner::InstanceEvent(InstanceEvent {
+                                                instance_uuid: uuid.clone(),
+                                                instance_event_inner:
+                                                    InstanceEventInner::ServerLagWarning {
+                                                        ms_behind,
+                                                        ticks_skipped,
+                                                    },
+                                                instance_name: name.clone(),
+                                            }),
+                                            details: "".to_string(),
+                                            snowflake: Snowflake::default(),
+                                            caused_by: CausedBy::System,
+                                        });
+                                    }
+                                    if !answered_as_partial
+                                        && __self.state().await == State::Starting
+                                    {
+                                        if let Some(response) = prompt_responder.respond(&line) {
+                                            __self.answer_prompt(&line, response).await;
+                                        }
+                                    }
+                                    let paper_notice = parse_paper_notices
+                                        .then(|| parse_system_msg(&line))
+                                        .flatten()
+                                        .and_then(|system_msg| parse_paper_notice(&system_msg));
+                                    if let Some(notice) = paper_notice {
+                                        let _ = event_broadcaster.send(Event {
+                                            event_inner: EventInner::InstanceEvent(InstanceEvent {
+                                                instance_uuid: uuid.clone(),
+                                                instance_event_inner:
+                                                    InstanceEventInner::PaperNotice {
+                                                        message: notice.describe(),
+                                                        notice,
+                                                    },
+                                                instance_name: name.clone(),
+                                            }),
+                                            details: "".to_string(),
+                                            snowflake: Snowflake::default(),
+                                            caused_by: CausedBy::System,
+                                        });
+                                    }
                                     if let Some(system_msg) = parse_system_msg(&line) {
                                         let _ = event_broadcaster.send(Event {
                                             event_inner: EventInner::InstanceEvent(InstanceEvent {
@@ -399,7 +406,8 @@ impl TServer for MinecraftInstance {
                                             snowflake: Snowflake::default(),
                                             caused_by: CausedBy::System,
                                         });
-                                        if let Some(player_name) = parse_player_joined(&system_msg)
+                                        if let Some(player_name) =
+                                            parse_player_joined(&system_msg, config.server_locale)
                                         {
                                             players_manager.lock().await.add_player(
                                                 MinecraftPlayer {
@@ -409,7 +417,7 @@ impl TServer for MinecraftInstance {
                                                 __self.name().await,
                                             );
                                         } else if let Some(player_name) =
-                                            parse_player_left(&system_msg)
+                                            parse_player_left(&system_msg, config.server_locale)
                                         {
                                             players_manager
                                                 .lock()
@@ -440,6 +448,21 @@ impl TServer for MinecraftInstance {
                             }
                         }
                         info!("Instance {} process shutdown", name);
+                        // the process exiting without a stop request means it crashed
+                        let crashed = !__self
+                            .stop_requested
+                            .swap(false, std::sync::atomic::Ordering::Relaxed);
+                        // backed up while still stopping so that nothing can start the server
+                        // before the backup is done, a killed server is already stopped
+                        if !crashed
+                            && __self.state().await == State::Stopping
+                            && __self.config.lock().await.backup_on_stop_mode
+                                == Some(BackupOnStopMode::AfterExit)
+                        {
+                            if let Err(e) = __self.backup_world("post-stop").await {
+                                error!("[{}] Failed to backup after stopping: {}", name, e);
+                            }
+                        }
                         __self.state
                             .lock()
                             .await
@@ -463,61 +486,78 @@ impl TServer for MinecraftInstance {
                             .unwrap();
                         __self.players_manager.lock().await.clear(name);
                         __self.rcon_conn.lock().await.take();
+                        __self.set_bound_port(None).await;
+                        let hung = __self
+                            .restart_after_exit
+                            .swap(false, std::sync::atomic::Ordering::Relaxed);
+                        let running_for = __self
+                            .running_since
+                            .lock()
+                            .await
+                            .take()
+                            .map(|running_since| running_since.elapsed());
+                        if crashed
+                            && (hung || __self.config.lock().await.restart_on_crash)
+                            && !__self.detect_crash_loop(running_for).await
+                        {
+                            __self.schedule_crash_restart().await;
+                        }
                     }
                 });
+                self.stdout_task.lock().await.replace(stdout_task);
+                self.spawn_startup_timeout().await;
                 self.config.lock().await.has_started = true;
-                self.write_config_to_file().await?;
+                // the server is already running, so failing to save the flag shouldn't fail the start
+                if let Err(e) = self.write_config_to_file().await {
+                    warn!("[{}] Failed to save the config: {}", config.name, e);
+                }
                 let instance_uuid = self.uuid.clone();
                 let mut rx = self.event_broadcaster.subscribe();
 
                 if block {
-                    while let Ok(event) = rx.recv().await {
-                        if let EventInner::InstanceEvent(InstanceEvent {
-                            instance_uuid: event_instance_uuid,
-                            instance_event_inner: InstanceEventInner::StateTransition { to },
-                            ..
-                        }) = event.event_inner
-                        {
-                            if instance_uuid == event_instance_uuid {
-                                if to == State::Running {
-                                    return Ok(()); // Instance started successfully
-                                } else if to == State::Stopped {
-                                    return Err(eyre!(
-                                        "Instance exited unexpectedly before starting"
-                                    )
-                                    .into());
+                    let wait_for_start = async {
+                        while let Ok(event) = rx.recv().await {
+                            if let EventInner::InstanceEvent(InstanceEvent {
+                                instance_uuid: event_instance_uuid,
+                                instance_event_inner: InstanceEventInner::StateTransition { to },
+                                ..
+                            }) = event.event_inner
+                            {
+                                if instance_uuid == event_instance_uuid {
+                                    if to == State::Running {
+                                        return Ok(()); // Instance started successfully
+                                    } else if to == State::Stopped {
+                                        return Err(eyre!(
+                                            "Instance exited unexpectedly before starting"
+                                        )
+                                        .into());
+                                    }
                                 }
                             }
                         }
+                        Err::<(), Error>(eyre!("Sender shutdown").into())
+                    };
+                    match startup_timeout {
+                        Some(startup_timeout) => {
+                            tokio::time::timeout(startup_timeout.timeout, wait_for_start)
+                                .await
+                                .unwrap_or_else(|_| {
+                                    Err(eyre!(
+                                        "Server did not finish starting within {} seconds",
+                                        startup_timeout.timeout.as_secs()
+                                    )
+                                    .into())
+                                })
+                        }
+                        None => wait_for_start.await,
                     }
-                    Err(eyre!("Sender shutdown").into())
                 } else {
                     Ok(())
                 }
             }
             Err(e) => {
                 error!("Failed to start server, {}", e);
-                self.state
-                    .lock()
-                    .await
-                    .try_transition(
-                        StateAction::InstanceStop,
-                        Some(&|state| {
-                            self.event_broadcaster.send(Event {
-                                event_inner: EventInner::InstanceEvent(InstanceEvent {
-                                    instance_name: config.name.clone(),
-                                    instance_uuid: self.uuid.clone(),
-                                    instance_event_inner: InstanceEventInner::StateTransition {
-                                        to: state,
-                                    },
-                                }),
-                                snowflake: Snowflake::default(),
-                                details: "Starting server".to_string(),
-                                caused_by: cause_by.clone(),
-                            });
-                        }),
-                    )
-                    .unwrap();
+                self.abort_start(&config.name, cause_by.clone()).await;
                 Err(e).context("Failed to start server")?;
                 unreachable!();
             }
@@ -526,6 +566,14 @@ impl TServer for MinecraftInstance {
     async fn stop(&self, cause_by: CausedBy, block: bool) -> Result<(), Error> {
         let config = self.config.lock().await.clone();
 
+        if config.backup_on_stop_mode == Some(BackupOnStopMode::BeforeStop)
+            && self.state().await == State::Running
+        {
+            if let Err(e) = self.backup_world("pre-stop").await {
+                error!("[{}] Failed to backup before stopping: {}", config.name, e);
+            }
+        }
+
         self.state.lock().await.try_transition(
             StateAction::UserStop,
             Some(&|state| {
@@ -541,24 +589,40 @@ impl TServer for MinecraftInstance {
                 });
             }),
         )?;
+        self.stop_requested
+            .store(true, std::sync::atomic::Ordering::Relaxed);
         let name = config.name.clone();
         let _uuid = self.uuid.clone();
-        self.stdin
-            .lock()
-            .await
-            .as_mut()
-            .ok_or_else(|| {
+        let stop_receiver = self.event_broadcaster.subscribe();
+        write_stdin_line(
+            self.stdin.lock().await.as_mut().ok_or_else(|| {
                 error!("[{}] Failed to stop instance: stdin not available", name);
                 eyre!("Failed to stop instance: stdin not available")
-            })?
-            .write_all(b"stop\n")
-            .await
-            .context("Failed to write to stdin")
-            .map_err(|e| {
-                error!("[{}] Failed to stop instance: {}", name, e);
-                e
-            })?;
+            })?,
+            "stop",
+            config.stdin_terminator,
+        )
+        .await
+        .context("Failed to write to stdin")
+        .map_err(|e| {
+            error!("[{}] Failed to stop instance: {}", name, e);
+            e
+        })?;
         self.rcon_conn.lock().await.take();
+        // a server that doesn't even log that it is stopping is hung and has to be killed
+        let __self = self.clone();
+        tokio::task::spawn(async move {
+            if !__self
+                .wait_for_output(stop_receiver, STDIN_RESPONSE_TIMEOUT)
+                .await
+                && __self.state().await == State::Stopping
+            {
+                warn!("[{}] Server did not respond to stop, killing it", name);
+                if let Err(e) = __self.kill(CausedBy::System).await {
+                    error!("[{}] Failed to kill unresponsive server: {}", name, e);
+                }
+            }
+        });
         let mut rx = self.event_broadcaster.subscribe();
         let instance_uuid = self.uuid.clone();
 
@@ -607,6 +671,15 @@ impl TServer for MinecraftInstance {
             warn!("[{}] Instance is already stopped", config.name.clone());
             return Err(eyre!("Instance is already stopped").into());
         }
+        if self.state().await.is_maintenance() {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("Cannot kill an instance while it is being backed up or updated"),
+            });
+        }
+        // so the process exiting isn't mistaken for a crash
+        self.stop_requested
+            .store(true, std::sync::atomic::Ordering::Relaxed);
         if let Some(process) = self.process.lock().await.as_mut() {
             process
                 .kill()
@@ -638,6 +711,25 @@ impl TServer for MinecraftInstance {
         *self.state.lock().await
     }
 
+    async fn cancel_pending_restart(&self) -> Result<(), Error> {
+        let pending_crash_restart = self
+            .pending_crash_restart
+            .lock()
+            .await
+            .take()
+            .ok_or_else(|| Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!("No restart is pending"),
+            })?;
+        pending_crash_restart.abort();
+        self.event_broadcaster.send(Event::new_system_message(
+            self.uuid.clone(),
+            self.config.lock().await.name.clone(),
+            "Pending restart cancelled".to_string(),
+        ));
+        Ok(())
+    }
+
     async fn send_command(&self, command: &str, cause_by: CausedBy) -> Result<(), Error> {
         let config = self.config.lock().await.clone();
         if self.state().await == State::Stopped {
@@ -663,8 +755,10 @@ impl TServer for MinecraftInstance {
                                 });
                             }),
                         )?;
+                        self.stop_requested
+                            .store(true, std::sync::atomic::Ordering::Relaxed);
                     }
-                    stdin.write_all(format!("{}\n", command).as_bytes()).await
+                    write_stdin_line(stdin, command, config.stdin_terminator).await
                 } {
                     Ok(_) => Ok(()),
                     Err(e) => {
@@ -713,3 +807,146 @@ impl TServer for MinecraftInstance {
         }
     }
 }
+
+impl MinecraftInstance {
+    /// Builds the command that launches the server jar for the instance's flavour
+    async fn server_start_command(
+        &self,
+        config: &RestoreConfig,
+        java_agent_args: &[String],
+    ) -> Result<Command, Error> {
+        let jre = self.path_to_java(config);
+
+        let mut command = Command::new(&jre);
+        let server_start_command = command
+            .args(java_agent_args)
+            .arg(format!("-Xmx{}M", config.max_ram))
+            .arg(format!("-Xms{}M", config.min_ram))
+            .args(config.jvm_flags_preset.flags(config.max_ram))
+            .arg("-Dfile.encoding=UTF-8")
+            .args(
+                &config
+                    .cmd_args
+                    .iter()
+                    .filter(|s| !s.is_empty())
+                    .collect::<Vec<&String>>(),
+            );
+
+        let server_start_command = match &config.flavour {
+            Flavour::Forge { build_version } => {
+                let ForgeBuildVersion(build_version) = build_version
+                    .as_ref()
+                    .ok_or_else(|| eyre!("Forge version not found"))?;
+                let version_parts: Vec<&str> = config.version.split('.').collect();
+                let major_version: i32 = version_parts[1]
+                    .parse()
+                    .context("Unable to parse major Minecraft version for Forge")?;
+
+                if 17 <= major_version {
+                    let forge_args = match std::env::consts::OS {
+                        "windows" => "win_args.txt",
+                        _ => "unix_args.txt",
+                    };
+
+                    let mut full_forge_args = std::ffi::OsString::from("@");
+                    full_forge_args.push(
+                        self.path_to_instance
+                            .join("libraries")
+                            .join("net")
+                            .join("minecraftforge")
+                            .join("forge")
+                            .join(build_version.as_str())
+                            .join(forge_args)
+                            .into_os_string()
+                            .as_os_str(),
+                    );
+
+                    server_start_command.arg(full_forge_args)
+                } else if (7..=16).contains(&major_version) {
+                    let files = list_dir(&self.path_to_instance, Some(false))
+                        .await
+                        .context("Failed to find forge.jar")?;
+                    let forge_jar_name = files
+                        .iter()
+                        .find(|p| {
+                            p.extension().unwrap_or_default() == "jar"
+                                && p.file_name()
+                                    .unwrap_or_default()
+                                    .to_str()
+                                    .unwrap_or_default()
+                                    .starts_with(format!("forge-{}-", config.version,).as_str())
+                        })
+                        .ok_or_else(|| eyre!("Failed to find forge.jar"))?;
+                    server_start_command
+                        .arg("-jar")
+                        .arg(&self.path_to_instance.join(forge_jar_name))
+                } else {
+                    // 1.5 doesn't work due to JRE issues
+                    // 1.4 doesn't work since forge doesn't provide an installer
+                    let files = list_dir(&self.path_to_instance, Some(false))
+                        .await
+                        .context("Failed to find minecraftforge.jar")?;
+                    let server_jar_name = files
+                        .iter()
+                        .find(|p| {
+                            p.extension().unwrap_or_default() == "jar"
+                                && p.file_name()
+                                    .unwrap_or_default()
+                                    .to_str()
+                                    .unwrap_or_default()
+                                    .starts_with("minecraftforge")
+                        })
+                        .ok_or_else(|| eyre!("Failed to find minecraftforge.jar"))?;
+                    server_start_command
+                        .arg("-jar")
+                        .arg(&self.path_to_instance.join(server_jar_name))
+                }
+            }
+            Flavour::NeoForge { .. } => {
+                // instances set up before the args file was recorded look for it again
+                let launch_args_file = config
+                    .launch_args_file
+                    .clone()
+                    .or_else(|| find_neoforge_args_file(&self.path_to_instance))
+                    .ok_or_else(|| {
+                        eyre!("Failed to find the arguments file NeoForge generated on install")
+                    })?;
+                let mut full_neoforge_args = std::ffi::OsString::from("@");
+                full_neoforge_args.push(self.path_to_instance.join(launch_args_file));
+                server_start_command.arg(full_neoforge_args)
+            }
+            // the launcher loads the vanilla server.jar next to it
+            Flavour::Quilt { .. } => server_start_command
+                .arg("-jar")
+                .arg(&self.path_to_instance.join("quilt-server-launch.jar")),
+            _ => server_start_command
+                .arg("-jar")
+                .arg(&self.path_to_instance.join("server.jar")),
+        };
+
+        server_start_command
+            .arg("nogui")
+            .current_dir(&self.path_to_instance);
+
+        // the server resolves the world as <universe>/<world>
+        if let Some((universe, world)) = config
+            .world_dir_override
+            .as_ref()
+            .and_then(|path| Some((path.parent()?, path.file_name()?)))
+        {
+            server_start_command
+                .arg("--universe")
+                .arg(universe)
+                .arg("--world")
+                .arg(world);
+        }
+
+        apply_process_priority(
+            server_start_command,
+            config.cpu_affinity.clone(),
+            config.nice,
+        );
+
+        Ok(command)
+    }
+}
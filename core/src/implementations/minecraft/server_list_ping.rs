@@ -0,0 +1,144 @@
+use std::time::Duration;
+
+use color_eyre::eyre::{eyre, Context};
+use serde_json::Value;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::error::{Error, ErrorKind};
+
+/// The status is a protocol string, at most 32767 characters of up to 3 bytes each
+const MAX_STATUS_LENGTH: usize = 32767 * 3;
+
+fn write_var_int(buf: &mut Vec<u8>, value: i32) {
+    let mut value = value as u32;
+    loop {
+        if value & !0x7F == 0 {
+            buf.push(value as u8);
+            return;
+        }
+        buf.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+}
+
+async fn read_var_int(reader: &mut (impl AsyncRead + Unpin)) -> Result<i32, Error> {
+    let mut value: u32 = 0;
+    for i in 0..5 {
+        let byte = reader
+            .read_u8()
+            .await
+            .context("Failed to read varint from server")?;
+        value |= ((byte & 0x7F) as u32) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok(value as i32);
+        }
+    }
+    Err(eyre!("Varint from server is too long").into())
+}
+
+/// Checks the length the server declares for its status before anything is allocated for it
+fn status_length(declared: i32) -> Result<usize, Error> {
+    match usize::try_from(declared) {
+        Ok(length) if length <= MAX_STATUS_LENGTH => Ok(length),
+        _ => Err(Error {
+            kind: ErrorKind::Internal,
+            source: eyre!(
+                "Server declared a status of {} bytes, at most {} are allowed",
+                declared,
+                MAX_STATUS_LENGTH
+            ),
+        }),
+    }
+}
+
+fn packet(id: i32, data: &[u8]) -> Vec<u8> {
+    let mut body = Vec::new();
+    write_var_int(&mut body, id);
+    body.extend_from_slice(data);
+    let mut ret = Vec::new();
+    write_var_int(&mut ret, body.len() as i32);
+    ret.extend(body);
+    ret
+}
+
+/// Queries the status of a minecraft server with the Server List Ping protocol
+///
+/// Returns the status json the server responds with, or an error if it takes longer than `timeout`
+pub async fn server_list_ping(host: &str, port: u16, timeout: Duration) -> Result<Value, Error> {
+    tokio::time::timeout(timeout, async {
+        let mut stream = TcpStream::connect((host, port))
+            .await
+            .context("Failed to connect to server")?;
+
+        let mut handshake = Vec::new();
+        // protocol version, -1 as we don't know which one the server speaks
+        write_var_int(&mut handshake, -1);
+        write_var_int(&mut handshake, host.len() as i32);
+        handshake.extend_from_slice(host.as_bytes());
+        handshake.extend_from_slice(&port.to_be_bytes());
+        // next state: status
+        write_var_int(&mut handshake, 1);
+
+        stream
+            .write_all(&packet(0x00, &handshake))
+            .await
+            .context("Failed to send handshake")?;
+        stream
+            .write_all(&packet(0x00, &[]))
+            .await
+            .context("Failed to send status request")?;
+
+        let _length = read_var_int(&mut stream).await?;
+        let packet_id = read_var_int(&mut stream).await?;
+        if packet_id != 0x00 {
+            return Err(eyre!("Unexpected packet id {} in status response", packet_id).into());
+        }
+        let json_length = status_length(read_var_int(&mut stream).await?)?;
+        let mut json = vec![0_u8; json_length];
+        stream
+            .read_exact(&mut json)
+            .await
+            .context("Failed to read status response")?;
+        Ok::<Value, Error>(
+            serde_json::from_slice(&json).context("Status response is not valid json")?,
+        )
+    })
+    .await
+    .context("Server did not respond to ping in time")?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_var_int() {
+        for (value, bytes) in [
+            (0, vec![0x00]),
+            (1, vec![0x01]),
+            (127, vec![0x7f]),
+            (128, vec![0x80, 0x01]),
+            (25565, vec![0xdd, 0xc7, 0x01]),
+            (-1, vec![0xff, 0xff, 0xff, 0xff, 0x0f]),
+        ] {
+            let mut buf = Vec::new();
+            write_var_int(&mut buf, value);
+            assert_eq!(buf, bytes);
+            assert_eq!(read_var_int(&mut bytes.as_slice()).await.unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_status_length() {
+        assert_eq!(status_length(0).unwrap(), 0);
+        assert_eq!(status_length(1024).unwrap(), 1024);
+        assert_eq!(
+            status_length(MAX_STATUS_LENGTH as i32).unwrap(),
+            MAX_STATUS_LENGTH
+        );
+        assert!(status_length(MAX_STATUS_LENGTH as i32 + 1).is_err());
+        assert!(status_length(i32::MAX).is_err());
+        assert!(status_length(-1).is_err());
+    }
+}
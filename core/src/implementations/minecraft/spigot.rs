@@ -0,0 +1,137 @@
+use std::path::Path;
+use std::process::Stdio;
+
+use color_eyre::eyre::{eyre, Context};
+use tokio::process::Command;
+
+use crate::error::{Error, ErrorKind};
+use crate::util::dont_spawn_terminal;
+
+/// BuildTools compiles Spigot locally, Spigot doesn't distribute server jars
+pub const BUILD_TOOLS_URL: &str =
+    "https://hub.spigotmc.org/jenkins/job/BuildTools/lastSuccessfulBuild/artifact/target/BuildTools.jar";
+
+pub async fn get_spigot_minecraft_versions() -> Result<Vec<String>, Error> {
+    let index = reqwest::get("https://hub.spigotmc.org/versions/")
+        .await
+        .context("Failed to get spigot versions")?
+        .text()
+        .await
+        .context("Failed to get spigot versions")?;
+    let versions = parse_spigot_versions(&index);
+    if versions.is_empty() {
+        return Err(eyre!("Failed to get spigot versions, the version index is empty").into());
+    }
+    Ok(versions)
+}
+
+/// Picks the release versions out of the BuildTools version index, newest first
+///
+/// The index also lists snapshots and build numbers, only `<major>.<minor>[.<patch>].json`
+/// entries are versions BuildTools can be asked for by name
+fn parse_spigot_versions(index: &str) -> Vec<String> {
+    let mut versions: Vec<(Vec<u32>, String)> = index
+        .split("href=\"")
+        .skip(1)
+        .filter_map(|link| link.split('"').next()?.strip_suffix(".json"))
+        .filter_map(|version| {
+            let components = version
+                .split('.')
+                .map(|component| component.parse().ok())
+                .collect::<Option<Vec<u32>>>()?;
+            (components.len() == 2 || components.len() == 3)
+                .then(|| (components, version.to_string()))
+        })
+        .collect();
+    versions.sort_by(|a, b| b.0.cmp(&a.0));
+    versions.dedup_by(|a, b| a.1 == b.1);
+    versions.into_iter().map(|(_, version)| version).collect()
+}
+
+/// Runs BuildTools in `build_dir` and moves the compiled jar to `server_jar`
+///
+/// On failure `build_dir` is left in place since it holds BuildTools.log.txt
+pub async fn build_spigot(
+    java: &Path,
+    build_tools_jar: &Path,
+    build_dir: &Path,
+    version: &str,
+    server_jar: &Path,
+) -> Result<(), Error> {
+    let git_available = dont_spawn_terminal(Command::new("git").arg("--version"))
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .stdin(Stdio::null())
+        .status()
+        .await
+        .map_or(false, |status| status.success());
+    if !git_available {
+        return Err(Error {
+            kind: ErrorKind::BadRequest,
+            source: eyre!("Building Spigot requires git, install it and make sure it is on PATH"),
+        });
+    }
+    tokio::fs::create_dir_all(build_dir)
+        .await
+        .context(format!("Failed to create {}", build_dir.display()))?;
+    let status = dont_spawn_terminal(
+        Command::new(java)
+            .arg("-jar")
+            .arg(build_tools_jar)
+            .arg("--rev")
+            .arg(version)
+            .current_dir(build_dir),
+    )
+    .stderr(Stdio::null())
+    .stdout(Stdio::null())
+    .stdin(Stdio::null())
+    .status()
+    .await
+    .context("Failed to start BuildTools.jar")?;
+    if !status.success() {
+        return Err(Error {
+            kind: ErrorKind::Internal,
+            source: eyre!(
+                "BuildTools failed to build Spigot {} ({}), see {} for details",
+                version,
+                status,
+                build_dir.join("BuildTools.log.txt").display()
+            ),
+        });
+    }
+    let built_jar = build_dir.join(format!("spigot-{}.jar", version));
+    tokio::fs::rename(&built_jar, server_jar)
+        .await
+        .context(format!(
+            "BuildTools finished but {} could not be moved to {}",
+            built_jar.display(),
+            server_jar.display()
+        ))?;
+    tokio::fs::remove_dir_all(build_dir)
+        .await
+        .context(format!("Failed to remove {}", build_dir.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_spigot_versions() {
+        let index = r#"<html><body><pre>
+<a href="../">../</a>
+<a href="1.8.json">1.8.json</a>
+<a href="1.20.4.json">1.20.4.json</a>
+<a href="1.9.json">1.9.json</a>
+<a href="1.20.json">1.20.json</a>
+<a href="3867.json">3867.json</a>
+<a href="1.20.4-pre1.json">1.20.4-pre1.json</a>
+<a href="latest.json">latest.json</a>
+</pre></body></html>"#;
+        assert_eq!(
+            parse_spigot_versions(index),
+            vec!["1.20.4", "1.20", "1.9", "1.8"]
+        );
+    }
+}
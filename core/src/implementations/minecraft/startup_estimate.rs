@@ -0,0 +1,128 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+/// Startups kept for the rolling average, older ones no longer reflect the instance
+pub const STARTUP_HISTORY_LEN: usize = 5;
+
+/// How long one start took from `Starting` to `Running`
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StartupRecord {
+    pub duration_ms: u64,
+    /// Mods installed at the time, startups scale with them more than with anything else
+    pub mod_count: usize,
+}
+
+/// How long the next start, or the one in progress, is expected to take
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct StartupEstimate {
+    /// Rolling average of the recorded startups
+    pub estimated_ms: u64,
+    pub low_ms: u64,
+    pub high_ms: u64,
+    /// Number of startups the estimate is based on
+    pub samples: usize,
+    /// Only set while the instance is starting
+    pub elapsed_ms: Option<u64>,
+    pub remaining_ms: Option<u64>,
+}
+
+/// Appends a startup, dropping the oldest ones past `STARTUP_HISTORY_LEN`
+pub fn record_startup(history: &mut Vec<StartupRecord>, record: StartupRecord) {
+    history.push(record);
+    if history.len() > STARTUP_HISTORY_LEN {
+        history.drain(..history.len() - STARTUP_HISTORY_LEN);
+    }
+}
+
+/// Averages the recorded startups, `None` until the instance has started at least once
+///
+/// The range spans the fastest and slowest recorded startup, and is widened in proportion to
+/// how much the mod count changed since the last one, since the history says little about a
+/// modpack that was just reworked
+pub fn estimate_startup(history: &[StartupRecord], mod_count: usize) -> Option<StartupEstimate> {
+    let last = history.last()?;
+    let samples = history.len();
+    let estimated_ms =
+        history.iter().map(|record| record.duration_ms).sum::<u64>() / samples as u64;
+    let mut low_ms = history.iter().map(|record| record.duration_ms).min()?;
+    let mut high_ms = history.iter().map(|record| record.duration_ms).max()?;
+    let mod_count_change = mod_count.abs_diff(last.mod_count);
+    if mod_count_change > 0 {
+        let widen_ms =
+            (estimated_ms as f64 * mod_count_change as f64 / last.mod_count.max(1) as f64) as u64;
+        low_ms = low_ms.saturating_sub(widen_ms);
+        high_ms = high_ms.saturating_add(widen_ms);
+    }
+    Some(StartupEstimate {
+        estimated_ms,
+        low_ms,
+        high_ms,
+        samples,
+        elapsed_ms: None,
+        remaining_ms: None,
+    })
+}
+
+/// Counts the jars in the mods directories, missing directories count as empty
+pub fn count_mods(mods_dirs: &[PathBuf]) -> usize {
+    mods_dirs
+        .iter()
+        .filter_map(|mods_dir| std::fs::read_dir(mods_dir).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let path = entry.path();
+            path.is_file()
+                && path
+                    .extension()
+                    .map_or(false, |extension| extension == "jar")
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(duration_ms: u64, mod_count: usize) -> StartupRecord {
+        StartupRecord {
+            duration_ms,
+            mod_count,
+        }
+    }
+
+    #[test]
+    fn test_record_startup_keeps_the_latest() {
+        let mut history = Vec::new();
+        for i in 0..STARTUP_HISTORY_LEN as u64 + 2 {
+            record_startup(&mut history, record(i, 0));
+        }
+        assert_eq!(history.len(), STARTUP_HISTORY_LEN);
+        assert_eq!(history.first(), Some(&record(2, 0)));
+    }
+
+    #[test]
+    fn test_estimate_startup() {
+        assert_eq!(estimate_startup(&[], 0), None);
+        let history = [record(10_000, 10), record(20_000, 10), record(30_000, 10)];
+        assert_eq!(
+            estimate_startup(&history, 10),
+            Some(StartupEstimate {
+                estimated_ms: 20_000,
+                low_ms: 10_000,
+                high_ms: 30_000,
+                samples: 3,
+                elapsed_ms: None,
+                remaining_ms: None,
+            })
+        );
+        // 5 more mods on top of 10 widens the range by half the average
+        let estimate = estimate_startup(&history, 15).unwrap();
+        assert_eq!(estimate.estimated_ms, 20_000);
+        assert_eq!(estimate.low_ms, 0);
+        assert_eq!(estimate.high_ms, 40_000);
+    }
+}
@@ -0,0 +1,153 @@
+use std::process::Stdio;
+
+use color_eyre::eyre::Context;
+use serde::Serialize;
+use tokio::process::Command;
+use ts_rs::TS;
+
+use crate::error::Error;
+use crate::prelude::VERSION;
+use crate::traits::t_server::State;
+use crate::util::dont_spawn_terminal;
+
+use super::{CrashCause, MinecraftInstance, RestoreConfig};
+
+/// How many lines of the latest log are included in a support bundle
+const CONSOLE_TAIL_LINES: usize = 200;
+
+const REDACTED: &str = "<redacted>";
+
+/// Diagnostic snapshot of an instance for attaching to bug reports, with secrets redacted
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct SupportBundle {
+    pub lodestone_version: String,
+    pub os: String,
+    pub flavour: String,
+    pub version: String,
+    pub state: State,
+    /// The instance's `RestoreConfig`
+    #[ts(type = "unknown")]
+    pub config: serde_json::Value,
+    /// The command the server was last launched with, `None` if it hasn't been started since
+    /// lodestone was launched
+    pub launch_command: Option<Vec<String>>,
+    /// First line of `java -version`, `None` if java couldn't be run
+    pub java_version: Option<String>,
+    pub console_tail: Vec<String>,
+    pub crash_cause: Option<CrashCause>,
+}
+
+fn is_secret_name(name: &str) -> bool {
+    let name = name.to_lowercase();
+    ["password", "passwd", "secret", "token", "key"]
+        .iter()
+        .any(|marker| name.contains(marker))
+}
+
+/// Redacts the value of a `name=value` argument if the name looks like it holds a secret
+fn redact_arg(arg: &str) -> String {
+    match arg.split_once('=') {
+        Some((name, _)) if is_secret_name(name) => format!("{name}={REDACTED}"),
+        _ => arg.to_string(),
+    }
+}
+
+fn redact_json(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if is_secret_name(key) {
+                    *value = serde_json::Value::String(REDACTED.to_string());
+                } else {
+                    redact_json(value);
+                }
+            }
+        }
+        serde_json::Value::Array(values) => values.iter_mut().for_each(redact_json),
+        serde_json::Value::String(s) => *s = redact_arg(s),
+        _ => {}
+    }
+}
+
+impl MinecraftInstance {
+    /// Gathers what maintainers usually ask for when an instance misbehaves
+    pub async fn support_bundle(&self) -> Result<SupportBundle, Error> {
+        let config = self.config.lock().await.clone();
+        let mut config_value =
+            serde_json::to_value(&config).context("Failed to serialize instance config")?;
+        redact_json(&mut config_value);
+        let launch_command = self
+            .last_launch_command
+            .lock()
+            .await
+            .as_ref()
+            .map(|command| command.iter().map(|arg| redact_arg(arg)).collect());
+        Ok(SupportBundle {
+            lodestone_version: VERSION.with(|v| v.to_string()),
+            os: std::env::consts::OS.to_string(),
+            flavour: config.flavour.to_string(),
+            version: config.version.clone(),
+            state: *self.state.lock().await,
+            config: config_value,
+            launch_command,
+            java_version: self.java_version(&config).await,
+            console_tail: self.console_tail().await,
+            crash_cause: self.crash_cause().await,
+        })
+    }
+
+    async fn java_version(&self, config: &RestoreConfig) -> Option<String> {
+        let mut command = Command::new(self.path_to_java(config));
+        command
+            .arg("-version")
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped());
+        dont_spawn_terminal(&mut command);
+        let output = command.output().await.ok()?;
+        // java prints its version to stderr
+        String::from_utf8_lossy(&output.stderr)
+            .lines()
+            .next()
+            .map(|line| line.to_string())
+    }
+
+    async fn console_tail(&self) -> Vec<String> {
+        let latest_log = self.path_to_instance.join("logs").join("latest.log");
+        let contents = match tokio::fs::read(&latest_log).await {
+            Ok(contents) => contents,
+            Err(_) => return Vec::new(),
+        };
+        let contents = String::from_utf8_lossy(&contents);
+        let lines = contents.lines().collect::<Vec<_>>();
+        lines[lines.len().saturating_sub(CONSOLE_TAIL_LINES)..]
+            .iter()
+            .map(|line| line.to_string())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_json() {
+        let mut value = serde_json::json!({
+            "name": "survival",
+            "cmd_args": ["-XX:+UseG1GC", "-Ddb.password=hunter2"],
+            "rcon_password": "hunter2",
+            "java_agents": [{ "jar": "agent.jar", "options": "token=abc" }]
+        });
+        redact_json(&mut value);
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "name": "survival",
+                "cmd_args": ["-XX:+UseG1GC", "-Ddb.password=<redacted>"],
+                "rcon_password": "<redacted>",
+                "java_agents": [{ "jar": "agent.jar", "options": "token=<redacted>" }]
+            })
+        );
+    }
+}
@@ -0,0 +1,171 @@
+use fancy_regex::Regex;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use super::FlavourKind;
+
+/// Ticks per second and milliseconds per tick as reported by the server
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export)]
+pub struct TpsReport {
+    /// Average over the last minute, or the current average if the server only reports one
+    pub tps_1m: f64,
+    pub tps_5m: Option<f64>,
+    pub tps_15m: Option<f64>,
+    /// Average time a tick takes, `None` if the server doesn't report it
+    pub mspt: Option<f64>,
+    /// Unix timestamp in seconds of when the server was asked
+    pub measured_at: i64,
+}
+
+/// The command that reports the tick rate on a flavour
+pub fn tps_command(flavour: FlavourKind) -> &'static str {
+    match flavour {
+        FlavourKind::Paper | FlavourKind::Spigot => "tps",
+        FlavourKind::Forge => "forge tps",
+        FlavourKind::NeoForge => "neoforge tps",
+        // vanilla has `tick query` since 1.20.3, fabric and quilt inherit it
+        FlavourKind::Vanilla | FlavourKind::Fabric | FlavourKind::Quilt => "tick query",
+    }
+}
+
+fn strip_formatting(text: &str) -> String {
+    lazy_static! {
+        static ref FORMATTING_RE: Regex = Regex::new(r"§.").unwrap();
+    }
+    FORMATTING_RE.replace_all(text, "").to_string()
+}
+
+/// The decimal numbers in `text`, in order
+fn numbers(text: &str) -> Vec<f64> {
+    text.split(|c: char| !c.is_ascii_digit() && c != '.')
+        .filter_map(|number| number.trim_matches('.').parse().ok())
+        .collect()
+}
+
+/// Parses the `tps` command of Paper and Spigot
+///
+/// e.g. "TPS from last 1m, 5m, 15m: 19.98, *20.0, 20.0", values above 20 are starred and capped
+pub fn parse_paper_tps(response: &str) -> Option<(f64, f64, f64)> {
+    let response = strip_formatting(response);
+    let (header, values) = response.split_once(':')?;
+    if !header.contains("TPS") {
+        return None;
+    }
+    match numbers(values)[..] {
+        [tps_1m, tps_5m, tps_15m, ..] => Some((tps_1m, tps_5m, tps_15m)),
+        _ => None,
+    }
+}
+
+/// Parses the average tick time of the last 5 seconds out of Paper's `mspt` command
+///
+/// e.g. "Server tick times (avg/min/max) from last 5s, 10s, 1m:\n◴ 1.2/0.5/3.4, ..."
+pub fn parse_paper_mspt(response: &str) -> Option<f64> {
+    let response = strip_formatting(response);
+    let (header, values) = response.split_once(':')?;
+    if !header.contains("tick times") {
+        return None;
+    }
+    numbers(values).first().copied()
+}
+
+/// Parses the overall line of `forge tps` and `neoforge tps`
+///
+/// e.g. "Overall : Mean tick time: 1.234 ms. Mean TPS: 20.000", or since 1.16
+/// "Overall: 20.000 TPS (1.234 ms/tick)"
+pub fn parse_forge_tps(response: &str) -> Option<(f64, f64)> {
+    lazy_static! {
+        static ref MSPT_RE: Regex = Regex::new(r"([\d.]+)\s*ms").unwrap();
+        static ref TPS_RE: Regex = Regex::new(r"TPS:\s*([\d.]+)|([\d.]+)\s*TPS").unwrap();
+    }
+    let response = strip_formatting(response);
+    let line = response.lines().find(|line| line.contains("Overall"))?;
+    let mspt = MSPT_RE
+        .captures(line)
+        .ok()??
+        .get(1)?
+        .as_str()
+        .parse()
+        .ok()?;
+    let tps_captures = TPS_RE.captures(line).ok()??;
+    let tps = tps_captures
+        .get(1)
+        .or_else(|| tps_captures.get(2))?
+        .as_str()
+        .parse()
+        .ok()?;
+    Some((tps, mspt))
+}
+
+/// Parses vanilla's `tick query`, the tick rate is estimated from the average tick time
+///
+/// e.g. "Target tick rate: 20.0 per second.\nAverage time per tick: 1.2ms (Target: 50.0ms)"
+pub fn parse_tick_query(response: &str) -> Option<(f64, f64)> {
+    let response = strip_formatting(response);
+    let value_of = |label: &str| {
+        response
+            .lines()
+            .find_map(|line| line.split_once(label))
+            .and_then(|(_, rest)| numbers(rest).first().copied())
+    };
+    let mspt = value_of("Average time per tick:")?;
+    let target_tps = value_of("Target tick rate:").unwrap_or(20.0);
+    let tps = if mspt > 0.0 {
+        (1000.0 / mspt).min(target_tps)
+    } else {
+        target_tps
+    };
+    Some((tps, mspt))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_paper_tps() {
+        assert_eq!(
+            parse_paper_tps("§6TPS from last 1m, 5m, 15m: §a19.98, §a*20.0, §a20.0"),
+            Some((19.98, 20.0, 20.0))
+        );
+        assert_eq!(parse_paper_tps("Unknown command"), None);
+        assert_eq!(
+            parse_paper_mspt(
+                "§6Server tick times §e(§7avg§e/§7min§e/§7max§e)§6 from last 5s§7,§6 10s§7,§6 1m§e:\n§6◴ §a1.2§7/§a0.5§7/§a3.4§7, §a1.3§7/§a0.5§7/§a3.4"
+            ),
+            Some(1.2)
+        );
+    }
+
+    #[test]
+    fn test_parse_forge_tps() {
+        assert_eq!(
+            parse_forge_tps(
+                "Dim 0 (overworld) : Mean tick time: 1.100 ms. Mean TPS: 20.000\nOverall : Mean tick time: 1.234 ms. Mean TPS: 20.000"
+            ),
+            Some((20.0, 1.234))
+        );
+        assert_eq!(
+            parse_forge_tps("minecraft:overworld: 18.500 TPS (54.054 ms/tick)\nOverall: 18.500 TPS (54.054 ms/tick)"),
+            Some((18.5, 54.054))
+        );
+        assert_eq!(parse_forge_tps("Unknown command"), None);
+    }
+
+    #[test]
+    fn test_parse_tick_query() {
+        assert_eq!(
+            parse_tick_query(
+                "The game is running normally\nTarget tick rate: 20.0 per second.\nAverage time per tick: 1.2ms (Target: 50.0ms)"
+            ),
+            Some((20.0, 1.2))
+        );
+        assert_eq!(
+            parse_tick_query("Average time per tick: 100.0ms (Target: 50.0ms)"),
+            Some((10.0, 100.0))
+        );
+        assert_eq!(parse_tick_query("Unknown or incomplete command"), None);
+    }
+}
@@ -1,13 +1,23 @@
 use color_eyre::eyre::{eyre, Context, ContextCompat};
 use indexmap::IndexMap;
+use md5::{Digest, Md5};
+use once_cell::sync::Lazy;
+use reqwest::StatusCode;
 use serde_json::{self, Value};
+use std::time::Duration;
 use std::{collections::BTreeMap, path::Path, str::FromStr};
 use tokio::io::AsyncBufReadExt;
+use tokio::sync::Mutex;
+use uuid::Uuid;
 
+use super::neoforge::{get_neoforge_builds, latest_neoforge_build, neoforge_installer_url};
+use super::quilt::{get_latest_quilt_installer_version, get_latest_quilt_loader_version};
+use super::spigot::BUILD_TOOLS_URL;
 use super::{
-    FabricInstallerVersion, FabricLoaderVersion, Flavour, ForgeBuildVersion, PaperBuildVersion,
+    FabricInstallerVersion, FabricLoaderVersion, Flavour, FlavourKind, ForgeBuildVersion,
+    PaperBuildVersion, QuiltInstallerVersion, QuiltLoaderVersion,
 };
-use crate::error::Error;
+use crate::error::{Error, ErrorKind};
 
 pub async fn read_properties_from_path(
     path_to_properties: &Path,
@@ -50,6 +60,34 @@ pub async fn read_properties_from_path(
     Ok(ret)
 }
 
+/// Escapes line breaks, which would otherwise start a new property, as `\n` like java does
+///
+/// Backslashes are escaped first, java would otherwise read them as the start of an escape
+pub fn escape_property_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace("\r\n", "\n")
+        .replace('\r', "\n")
+        .replace('\n', "\\n")
+}
+
+pub async fn write_properties_to_path(
+    path_to_properties: &Path,
+    properties: &IndexMap<String, String>,
+) -> Result<(), Error> {
+    let mut properties_str = String::new();
+    for (key, value) in properties {
+        properties_str.push_str(&format!("{}={}\n", key, value));
+    }
+    tokio::fs::write(path_to_properties, properties_str)
+        .await
+        .context(format!(
+            "Failed to write properties file at {}",
+            path_to_properties.display()
+        ))?;
+    Ok(())
+}
+
 // Returns the jar url and the updated flavour with version information
 pub async fn get_server_jar_url(version: &str, flavour: &Flavour) -> Option<(String, Flavour)> {
     match flavour {
@@ -58,9 +96,18 @@ pub async fn get_server_jar_url(version: &str, flavour: &Flavour) -> Option<(Str
             loader_version,
             installer_version,
         } => get_fabric_jar_url(version, loader_version, installer_version).await,
+        Flavour::Quilt {
+            loader_version,
+            installer_version,
+        } => get_quilt_installer_url(version, loader_version, installer_version)
+            .await
+            .ok(),
         Flavour::Paper { build_version } => get_paper_jar_url(version, build_version).await,
-        Flavour::Spigot => todo!(),
+        Flavour::Spigot => Some((BUILD_TOOLS_URL.to_string(), Flavour::Spigot)),
         Flavour::Forge { build_version } => get_forge_jar_url(version, build_version).await.ok(),
+        Flavour::NeoForge { build_version } => {
+            get_neoforge_jar_url(version, build_version).await.ok()
+        }
     }
 }
 
@@ -257,6 +304,33 @@ pub async fn get_fabric_jar_url(
     ))
 }
 
+/// Returns the url of the quilt installer, which sets up the server instead of a jar being
+/// downloaded directly, with the loader and installer versions resolved
+pub async fn get_quilt_installer_url(
+    version: &str,
+    quilt_loader_version: &Option<QuiltLoaderVersion>,
+    quilt_installer_version: &Option<QuiltInstallerVersion>,
+) -> Result<(String, Flavour), Error> {
+    let loader_version = match quilt_loader_version {
+        Some(QuiltLoaderVersion(loader_version)) => loader_version.clone(),
+        None => get_latest_quilt_loader_version(version).await?,
+    };
+    let installer_version = match quilt_installer_version {
+        Some(QuiltInstallerVersion(installer_version)) => installer_version.clone(),
+        None => get_latest_quilt_installer_version().await?,
+    };
+    Ok((
+        format!(
+            "https://maven.quiltmc.org/repository/release/org/quiltmc/quilt-installer/{}/quilt-installer-{}.jar",
+            installer_version, installer_version
+        ),
+        Flavour::Quilt {
+            loader_version: Some(QuiltLoaderVersion(loader_version)),
+            installer_version: Some(QuiltInstallerVersion(installer_version)),
+        },
+    ))
+}
+
 pub async fn get_paper_jar_url(
     version: &str,
     paper_build_version: &Option<PaperBuildVersion>,
@@ -355,6 +429,98 @@ pub async fn get_forge_jar_url(
     ))
 }
 
+pub async fn get_neoforge_jar_url(
+    version: &str,
+    neoforge_build_version: &Option<ForgeBuildVersion>,
+) -> Result<(String, Flavour), Error> {
+    let build = match neoforge_build_version {
+        Some(ForgeBuildVersion(b)) => b.clone(),
+        None => latest_neoforge_build(&get_neoforge_builds().await?, version)
+            .context("Failed to get neoforge versions, version not found")?
+            .clone(),
+    };
+
+    Ok((
+        neoforge_installer_url(&build),
+        Flavour::NeoForge {
+            build_version: Some(ForgeBuildVersion(build)),
+        },
+    ))
+}
+
+/// Fetches the changelog of a specific build of a flavour
+///
+/// Returns `None` if the flavour doesn't publish changelogs for its builds
+pub async fn get_build_changelog(
+    flavour: &FlavourKind,
+    version: &str,
+    build: &str,
+) -> Result<Option<String>, Error> {
+    let client = reqwest::Client::new();
+    match flavour {
+        FlavourKind::Paper => {
+            let response = client
+                .get(format!(
+                    "https://api.papermc.io/v2/projects/paper/versions/{}/builds/{}",
+                    version, build
+                ))
+                .send()
+                .await
+                .context("Failed to get paper build, http request failed")?;
+            if response.status() == reqwest::StatusCode::NOT_FOUND {
+                return Err(Error {
+                    kind: ErrorKind::NotFound,
+                    source: eyre!("Paper build {} for version {} not found", build, version),
+                });
+            }
+            let response: Value = response
+                .json()
+                .await
+                .context("Failed to get paper build, response is not valid json")?;
+            let changes = response
+                .get("changes")
+                .and_then(Value::as_array)
+                .context("Failed to get paper build, response does not contain changes")?
+                .iter()
+                .filter_map(|change| change.get("summary")?.as_str())
+                .map(|summary| format!("- {}", summary))
+                .collect::<Vec<_>>();
+            Ok(if changes.is_empty() {
+                None
+            } else {
+                Some(changes.join("\n"))
+            })
+        }
+        FlavourKind::Forge => {
+            // forge builds are named after the minecraft version they target, e.g. 1.18.2-40.2.0
+            let build = if build.starts_with(&format!("{}-", version)) {
+                build.to_string()
+            } else {
+                format!("{}-{}", version, build)
+            };
+            let response = client
+                .get(format!(
+                    "https://maven.minecraftforge.net/net/minecraftforge/forge/{}/forge-{}-changelog.txt",
+                    build, build
+                ))
+                .send()
+                .await
+                .context("Failed to get forge changelog, http request failed")?;
+            if !response.status().is_success() {
+                return Ok(None);
+            }
+            Ok(Some(response.text().await.context(
+                "Failed to get forge changelog, text conversion failed",
+            )?))
+        }
+        FlavourKind::Vanilla
+        | FlavourKind::Fabric
+        | FlavourKind::Quilt
+        | FlavourKind::Spigot
+        | FlavourKind::NeoForge => Ok(None),
+    }
+}
+
 pub async fn get_jre_url(version: &str) -> Option<(String, u64)> {
     let client = reqwest::Client::new();
     let os = if std::env::consts::OS == "macos" {
@@ -423,31 +589,296 @@ pub async fn get_jre_url(version: &str) -> Option<(String, u64)> {
     ))
 }
 
+/// Parses the major version out of the output of `java -version`
+///
+/// e.g. `openjdk version "17.0.2" 2022-01-18`, or `java version "1.8.0_292"` for java 8 and older
+pub fn parse_java_major_version(output: &str) -> Option<u64> {
+    let version = output
+        .lines()
+        .find(|line| line.contains("version"))?
+        .split('"')
+        .nth(1)?;
+    let mut parts = version.split(|c: char| !c.is_ascii_digit());
+    match parts.next()?.parse().ok()? {
+        1 => parts.next()?.parse().ok(),
+        major => Some(major),
+    }
+}
+
+/// Runs `java -version` on a user supplied java binary to find out which version it is
+pub async fn java_major_version(java: &Path) -> Result<u64, Error> {
+    if !java.is_absolute() {
+        return Err(Error {
+            kind: ErrorKind::BadRequest,
+            source: eyre!("{} is not an absolute path", java.display()),
+        });
+    }
+    if !java.is_file() {
+        return Err(Error {
+            kind: ErrorKind::BadRequest,
+            source: eyre!("{} is not an existing file", java.display()),
+        });
+    }
+    let output = tokio::time::timeout(
+        Duration::from_secs(10),
+        crate::util::dont_spawn_terminal(tokio::process::Command::new(java).arg("-version"))
+            .stdin(std::process::Stdio::null())
+            .output(),
+    )
+    .await
+    .map_err(|_| Error {
+        kind: ErrorKind::BadRequest,
+        source: eyre!(
+            "{} -version did not finish within 10 seconds",
+            java.display()
+        ),
+    })?
+    .map_err(|e| Error {
+        kind: ErrorKind::BadRequest,
+        source: eyre!("Failed to run {}: {}", java.display(), e),
+    })?;
+    if !output.status.success() {
+        return Err(Error {
+            kind: ErrorKind::BadRequest,
+            source: eyre!("{} -version exited with {}", java.display(), output.status),
+        });
+    }
+    // java prints its version to stderr, some builds to stdout
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    parse_java_major_version(&stderr)
+        .or_else(|| parse_java_major_version(&stdout))
+        .ok_or_else(|| Error {
+            kind: ErrorKind::BadRequest,
+            source: eyre!(
+                "Could not tell the java version of {} from {}",
+                java.display(),
+                stderr.trim()
+            ),
+        })
+}
+
 pub async fn name_to_uuid(name: impl AsRef<str>) -> Option<String> {
-    // GET https://api.mojang.com/users/profiles/minecraft/<username>
+    resolve_player_uuid(name.as_ref())
+        .await
+        .ok()
+        .map(|uuid| uuid.simple().to_string())
+}
+
+/// How many player names `resolve_player_uuid` remembers
+const PLAYER_UUID_CACHE_CAPACITY: usize = 512;
+/// How many times a lookup is tried while mojang rate limits us
+const PLAYER_UUID_MAX_ATTEMPTS: u32 = 4;
+/// Wait before the first retry of a rate limited lookup, doubled on every retry
+const PLAYER_UUID_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Player uuids keyed by lowercase name, the least recently used come first
+static PLAYER_UUID_CACHE: Lazy<Mutex<IndexMap<String, Uuid>>> =
+    Lazy::new(|| Mutex::new(IndexMap::new()));
+
+/// Looks `key` up and marks it as the most recently used
+fn lru_get<V: Copy>(cache: &mut IndexMap<String, V>, key: &str) -> Option<V> {
+    let value = cache.shift_remove(key)?;
+    cache.insert(key.to_owned(), value);
+    Some(value)
+}
+
+/// Inserts `key` as the most recently used, dropping the least recently used past `capacity`
+fn lru_insert<V>(cache: &mut IndexMap<String, V>, key: String, value: V, capacity: usize) {
+    cache.shift_remove(&key);
+    cache.insert(key, value);
+    while cache.len() > capacity {
+        cache.shift_remove_index(0);
+    }
+}
+
+/// The uuid an offline mode server gives a player, derived from `OfflinePlayer:<name>` like
+/// java's `UUID.nameUUIDFromBytes`
+pub fn offline_player_uuid(name: &str) -> Uuid {
+    let hash = Md5::digest(format!("OfflinePlayer:{}", name).as_bytes());
+    uuid::Builder::from_md5_bytes(hash.into()).into_uuid()
+}
+
+/// Looks the uuid of a Minecraft account up with mojang
+///
+/// Names are case insensitive and the results are cached, rate limited lookups are retried with
+/// backoff
+pub async fn resolve_player_uuid(name: &str) -> Result<Uuid, Error> {
+    let key = name.to_lowercase();
+    if let Some(uuid) = lru_get(&mut *PLAYER_UUID_CACHE.lock().await, &key) {
+        return Ok(uuid);
+    }
     let client = reqwest::Client::new();
-    let res: Value = client
-        .get(format!(
-            "https://api.mojang.com/users/profiles/minecraft/{}",
-            name.as_ref()
-        ))
+    let mut delay = PLAYER_UUID_RETRY_DELAY;
+    let mut attempt = 1;
+    let response = loop {
+        let response = client
+            .get(format!(
+                "https://api.mojang.com/users/profiles/minecraft/{}",
+                name
+            ))
+            .send()
+            .await
+            .context("Failed to reach mojang's API")?;
+        match response.status() {
+            // mojang used to answer 204 for names nobody has, and answers 404 now
+            StatusCode::NO_CONTENT | StatusCode::NOT_FOUND => {
+                return Err(Error {
+                    kind: ErrorKind::NotFound,
+                    source: eyre!("No Minecraft account is named {}", name),
+                })
+            }
+            StatusCode::TOO_MANY_REQUESTS if attempt < PLAYER_UUID_MAX_ATTEMPTS => {
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
+            }
+            StatusCode::TOO_MANY_REQUESTS => {
+                return Err(Error {
+                    kind: ErrorKind::Internal,
+                    source: eyre!(
+                        "Mojang's API is rate limiting us, failed to look up player {} after {} attempts",
+                        name,
+                        attempt
+                    ),
+                })
+            }
+            _ => break response,
+        }
+    };
+    let profile: Value = response
+        .error_for_status()
+        .context(format!("Failed to look up player {}", name))?
+        .json()
+        .await
+        .context("Failed to parse the response of mojang's API")?;
+    let uuid = profile["id"]
+        .as_str()
+        .and_then(|id| Uuid::parse_str(id).ok())
+        .ok_or_else(|| Error {
+            kind: ErrorKind::Internal,
+            source: eyre!("Mojang's API returned no valid uuid for player {}", name),
+        })?;
+    lru_insert(
+        &mut *PLAYER_UUID_CACHE.lock().await,
+        key,
+        uuid,
+        PLAYER_UUID_CACHE_CAPACITY,
+    );
+    Ok(uuid)
+}
+
+/// Reads the world data version a server jar targets from its bundled `version.json`
+///
+/// Only vanilla jars carry this file, launcher jars such as fabric's or paper's return `None`
+pub async fn read_jar_data_version(path_to_jar: &Path) -> Option<i32> {
+    let path_to_jar = path_to_jar.to_owned();
+    tokio::task::spawn_blocking(move || {
+        let jar = std::fs::File::open(path_to_jar).ok()?;
+        let mut archive = zip::ZipArchive::new(jar).ok()?;
+        let version_json: Value =
+            serde_json::from_reader(archive.by_name("version.json").ok()?).ok()?;
+        version_json["world_version"].as_i64().map(|v| v as i32)
+    })
+    .await
+    .ok()?
+}
+
+/// Compares the release order of two minecraft versions using mojang's version manifest
+///
+/// Returns `None` if either version is not in the manifest
+pub async fn compare_version_release(a: &str, b: &str) -> Option<std::cmp::Ordering> {
+    let response: Value = reqwest::Client::new()
+        .get("https://launchermeta.mojang.com/mc/game/version_manifest.json")
         .send()
         .await
         .ok()?
         .json()
         .await
         .ok()?;
-    Some(res["id"].as_str()?.to_owned())
+    let versions = response.get("versions")?.as_array()?;
+    let position_of = |version: &str| {
+        versions
+            .iter()
+            .position(|version_json| version_json["id"].as_str() == Some(version))
+    };
+    // the manifest lists the newest version first
+    Some(position_of(b)?.cmp(&position_of(a)?))
 }
 
 #[cfg(test)]
 mod tests {
     use crate::minecraft::{
-        util::{get_forge_jar_url, get_server_jar_url},
-        FabricInstallerVersion, FabricLoaderVersion, Flavour, ForgeBuildVersion, PaperBuildVersion,
+        util::{
+            escape_property_value, get_forge_jar_url, get_server_jar_url, lru_get, lru_insert,
+            offline_player_uuid, parse_java_major_version,
+        },
+        FabricInstallerVersion, FabricLoaderVersion, Flavour, FlavourKind, ForgeBuildVersion,
+        PaperBuildVersion,
     };
+    use indexmap::IndexMap;
     use tokio;
 
+    #[test]
+    fn test_lru_cache() {
+        let mut cache = IndexMap::new();
+        lru_insert(&mut cache, "a".to_string(), 1, 2);
+        lru_insert(&mut cache, "b".to_string(), 2, 2);
+        // reading "a" makes "b" the least recently used
+        assert_eq!(lru_get(&mut cache, "a"), Some(1));
+        lru_insert(&mut cache, "c".to_string(), 3, 2);
+        assert_eq!(lru_get(&mut cache, "b"), None);
+        assert_eq!(lru_get(&mut cache, "a"), Some(1));
+        assert_eq!(lru_get(&mut cache, "c"), Some(3));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_offline_player_uuid() {
+        assert_eq!(
+            offline_player_uuid("Notch").hyphenated().to_string(),
+            "b50ad385-829d-3141-a216-7e7d7539ba7f"
+        );
+    }
+
+    #[test]
+    fn test_parse_java_major_version() {
+        assert_eq!(
+            parse_java_major_version(
+                "openjdk version \"17.0.2\" 2022-01-18\nOpenJDK Runtime Environment (build 17.0.2+8-86)"
+            ),
+            Some(17)
+        );
+        assert_eq!(
+            parse_java_major_version("java version \"1.8.0_292\"\nJava(TM) SE Runtime Environment"),
+            Some(8)
+        );
+        assert_eq!(
+            parse_java_major_version("openjdk version \"21-ea\" 2023-09-19"),
+            Some(21)
+        );
+        assert_eq!(
+            parse_java_major_version(
+                "Picked up JAVA_TOOL_OPTIONS: -Xss1m\nopenjdk version \"11.0.20\" 2023-07-18"
+            ),
+            Some(11)
+        );
+        assert_eq!(parse_java_major_version("command not found"), None);
+    }
+
+    #[test]
+    fn test_escape_property_value() {
+        assert_eq!(
+            escape_property_value("§aA Minecraft Server"),
+            "§aA Minecraft Server"
+        );
+        assert_eq!(
+            escape_property_value("line one\r\nline two\n"),
+            "line one\\nline two\\n"
+        );
+        assert_eq!(escape_property_value("C:\\new\nline"), "C:\\\\new\\nline");
+    }
+
     #[tokio::test]
     async fn test_get_vanilla_jar_url() {
         assert_eq!(super::get_vanilla_jar_url("1.18.2").await, Some(("https://piston-data.mojang.com/v1/objects/c8f83c5655308435b3dcf03c06d9fe8740a77469/server.jar".to_string(), Flavour::Vanilla)));
@@ -512,6 +943,22 @@ mod tests {
         assert_eq!(super::get_paper_jar_url("1.19.3bruh", &None).await, None);
     }
 
+    #[tokio::test]
+    async fn test_get_build_changelog() {
+        assert!(
+            super::get_build_changelog(&FlavourKind::Paper, "1.19.3", "308")
+                .await
+                .unwrap()
+                .is_some()
+        );
+        assert_eq!(
+            super::get_build_changelog(&FlavourKind::Vanilla, "1.19.3", "")
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
     #[tokio::test]
     async fn test_get_forge_jar_url() {
         get_forge_jar_url("1.18.2", &None).await.unwrap();
@@ -5,12 +5,12 @@ use crate::event_broadcaster::EventBroadcaster;
 use crate::migration::migrate;
 use crate::prelude::{
     init_app_state, init_paths, lodestone_path, path_to_global_settings, path_to_stores,
-    path_to_tmp, path_to_users, VERSION,
+    path_to_tmp, path_to_trash, path_to_users, VERSION,
 };
 use crate::traits::t_configurable::GameType;
 use crate::traits::t_server::State;
 use crate::{
-    db::write::write_event_to_db_task,
+    db::write::{prune_events_task, write_event_to_db_task},
     global_settings::GlobalSettingsData,
     handlers::{
         checks::get_checks_routes, core_info::get_core_info_routes, events::get_events_routes,
@@ -41,10 +41,11 @@ use global_settings::GlobalSettings;
 use implementations::{generic, minecraft};
 use macro_executor::MacroExecutor;
 use playitgg::utils::is_valid_secret_key;
-use port_manager::PortManager;
+use port_manager::{detect_port_conflicts, PortManager};
 use prelude::GameInstance;
 use reqwest::{header, Method};
 use ringbuffer::{AllocRingBuffer, RingBufferWrite};
+use setup_progress::SetupProgressRegistry;
 
 use fs3::FileExt;
 use semver::Version;
@@ -89,8 +90,11 @@ mod output_types;
 pub mod playitgg;
 mod port_manager;
 pub mod prelude;
+mod resource_guardrail;
+mod setup_progress;
 pub mod tauri_export;
 mod traits;
+mod trash;
 pub mod types;
 pub mod util;
 use handlers::global_fs::DownloadableFile;
@@ -114,6 +118,7 @@ pub struct AppState {
     macro_executor: MacroExecutor,
     sqlite_pool: sqlx::SqlitePool,
     playit_keep_running: Arc<Mutex<Option<Arc<AtomicBool>>>>,
+    setup_progress: Arc<Mutex<SetupProgressRegistry>>,
 }
 
 impl AppState {
@@ -130,6 +135,49 @@ impl AppState {
     }
 }
 
+/// Restores a single instance from its directory
+pub(crate) async fn restore_instance(
+    path: &Path,
+    event_broadcaster: EventBroadcaster,
+    macro_executor: MacroExecutor,
+) -> Result<(InstanceUuid, GameInstance), Error> {
+    let dot_lodestone_config_file = std::fs::File::open(path.join(".lodestone_config"))
+        .context("Failed to read .lodestone_config file")?;
+    let dot_lodestone_config: DotLodestoneConfig =
+        serde_json::from_reader(dot_lodestone_config_file)
+            .context("Failed to parse .lodestone_config file")?;
+
+    debug!("restoring instance: {}", path.display());
+    let instance: GameInstance = match dot_lodestone_config.game_type() {
+        GameType::MinecraftJava => {
+            let instance = minecraft::MinecraftInstance::restore(
+                path.to_owned(),
+                dot_lodestone_config.clone(),
+                event_broadcaster,
+                macro_executor,
+            )
+            .await
+            .context("Failed to restore Minecraft Java instance")?;
+            debug!("Restored Minecraft Java instance successfully");
+            instance.into()
+        }
+        GameType::Generic => {
+            let instance = generic::GenericInstance::restore(
+                path.to_owned(),
+                dot_lodestone_config.clone(),
+                event_broadcaster,
+                macro_executor,
+            )
+            .await
+            .context("Failed to restore atom instance")?;
+            debug!("Restored Generic instance successfully");
+            instance.into()
+        }
+        GameType::MinecraftBedrock => todo!()
+    };
+    Ok((dot_lodestone_config.uuid().to_owned(), instance))
+}
+
 async fn restore_instances(
     instances_path: &Path,
     event_broadcaster: EventBroadcaster,
@@ -148,71 +196,15 @@ async fn restore_instances(
                 continue;
             }
         };
-        let dot_lodestone_config_file = match std::fs::File::open(path.join(".lodestone_config")) {
-            Ok(v) => v,
-            Err(e) => {
-                error!("Error while restoring instance {}, failed to read .lodestone_config file : {e}", path.display());
-                continue;
-            }
-        };
-        let dot_lodestone_config: DotLodestoneConfig = match serde_json::from_reader(
-            dot_lodestone_config_file,
-        ) {
-            Ok(v) => v,
-            Err(e) => {
-                error!("Error while restoring instance {}, failed to parse .lodestone_config file : {e}", path.display());
-                continue;
-            }
-        };
-        
-        debug!("restoring instance: {}", path.display());
-        let uuid_instance: (InstanceUuid, GameInstance) = match dot_lodestone_config.game_type() {
-            GameType::MinecraftJava => {
-                let instance = match minecraft::MinecraftInstance::restore(
-                    path.to_owned(),
-                    dot_lodestone_config.clone(),
-                    event_broadcaster.clone(),
-                    macro_executor.clone(),
-                )
-                .await
-                {
-                    Ok(v) => v,
-                    Err(e) => {
-                        error!(
-                            "Error while restoring Minecraft Java instance {} : {e}",
-                            path.display()
-                        );
-                        continue;
-                    }
-                };
-                debug!("Restored Minecraft Java instance successfully");
-                (dot_lodestone_config.uuid().to_owned(), instance.into())
-            }
-            GameType::Generic => {
-                let instance = match generic::GenericInstance::restore(
-                    path.to_owned(),
-                    dot_lodestone_config.clone(),
-                    event_broadcaster.clone(),
-                    macro_executor.clone(),
-                )
-                .await
-                {
-                    Ok(v) => v,
-                    Err(e) => {
-                        error!(
-                            "Error while restoring atom instance {} : {e}",
-                            path.display()
-                        );
-                        continue;
-                    }
-                };
-                debug!("Restored Generic instance successfully");
-                (dot_lodestone_config.uuid().to_owned(), instance.into())
-            }
-            GameType::MinecraftBedrock => todo!()
-        };
-        let uuid = uuid_instance.0;
-        let instance = uuid_instance.1;
+        let (uuid, instance) =
+            match restore_instance(&path, event_broadcaster.clone(), macro_executor.clone()).await
+            {
+                Ok(v) => v,
+                Err(e) => {
+                    error!("Error while restoring instance {} : {e}", path.display());
+                    continue;
+                }
+            };
         if ret.contains_key(&uuid) {
             warn!("UUID {} is repeated.", uuid.to_string());
         }
@@ -523,6 +515,7 @@ pub async fn run(
         system: Arc::new(Mutex::new(sysinfo::System::new_all())),
         download_urls: Arc::new(Mutex::new(HashMap::new())),
         playit_keep_running: Arc::new(Mutex::new(None)),
+        setup_progress: Arc::new(Mutex::new(SetupProgressRegistry::default())),
         global_settings: Arc::new(Mutex::new(global_settings)),
         macro_executor,
         sqlite_pool: Pool::connect_with(
@@ -545,14 +538,37 @@ pub async fn run(
 
     init_app_state(shared_state.clone());
 
-    for mut entry in shared_state.instances.iter_mut() {
-        let instance = entry.value_mut();
+    for conflict in detect_port_conflicts(&shared_state.instances).await {
+        let instance_names = conflict
+            .usages
+            .iter()
+            .map(|usage| usage.instance_name.as_str())
+            .collect::<Vec<_>>();
+        warn!(
+            "Port {} is configured for more than one server: {}",
+            conflict.port,
+            instance_names.join(", ")
+        );
+    }
+
+    // cloned so the map isn't locked while the start checks the RAM guardrail of the others
+    let instances: Vec<GameInstance> = shared_state
+        .instances
+        .iter()
+        .map(|entry| entry.value().clone())
+        .collect();
+    for instance in instances {
         if instance.auto_start().await {
+            if let GameInstance::MinecraftInstance(minecraft_instance) = &instance {
+                if !minecraft_instance.should_auto_start().await {
+                    continue;
+                }
+            }
             info!("Auto starting instance {}", instance.name().await);
             if let Err(e) = instance.start(CausedBy::System, false).await {
                 error!(
                     "Failed to start instance {}: {:?}",
-                    entry.value().name().await,
+                    instance.name().await,
                     e
                 );
             }
@@ -562,6 +578,7 @@ pub async fn run(
     let event_buffer_task = {
         let event_buffer = shared_state.events_buffer.clone();
         let console_out_buffer = shared_state.console_out_buffer.clone();
+        let setup_progress = shared_state.setup_progress.clone();
         let mut event_receiver = tx.subscribe();
         async move {
             loop {
@@ -587,13 +604,23 @@ pub async fn run(
                         .or_insert_with(|| AllocRingBuffer::with_capacity(1024))
                         .push(event.clone());
                 } else {
+                    setup_progress.lock().await.handle_event(&event);
                     event_buffer.lock().await.push(event.clone());
                 }
             }
         }
     };
 
-    let write_to_db_task = write_event_to_db_task(tx.subscribe(), shared_state.sqlite_pool.clone());
+    let write_to_db_task = write_event_to_db_task(
+        tx.subscribe(),
+        shared_state.sqlite_pool.clone(),
+        shared_state.global_settings.clone(),
+    );
+
+    let prune_events_task = prune_events_task(
+        shared_state.sqlite_pool.clone(),
+        shared_state.global_settings.clone(),
+    );
 
     let monitor_report_task = {
         let monitor_buffer = shared_state.monitor_buffer.clone();
@@ -615,6 +642,20 @@ pub async fn run(
         }
     };
 
+    let trash_purge_task = {
+        let global_settings = shared_state.global_settings.clone();
+        async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(3600));
+            loop {
+                interval.tick().await;
+                let grace_period = global_settings.lock().await.deletion_grace_period();
+                if let Err(e) = trash::purge_deleted(path_to_trash(), grace_period).await {
+                    error!("Failed to purge deleted instances : {}", e);
+                }
+            }
+        }
+    };
+
     let tls_config_result = RustlsConfig::from_pem_file(
         lodestone_path.join("tls").join("cert.pem"),
         lodestone_path.join("tls").join("key.pem"),
@@ -706,8 +747,10 @@ pub async fn run(
                 let _lock_file = lock_file;
                 select! {
                     _ = write_to_db_task => info!("Write to db task exited"),
+                    _ = prune_events_task => info!("Prune events task exited"),
                     _ = event_buffer_task => info!("Event buffer task exited"),
                     _ = monitor_report_task => info!("Monitor report task exited"),
+                    _ = trash_purge_task => info!("Trash purge task exited"),
                     _ = shutdown_rx => info!("Shutdown signal received"),
                     _ = tokio::signal::ctrl_c() => info!("Ctrl+C received"),
                 }
@@ -724,7 +767,7 @@ pub async fn run(
                 for entry in shared_state.instances.iter() {
                     let instance = entry.value().clone();
                     match instance.state().await {
-                        State::Starting => {
+                        State::Starting | State::Error => {
                             let handle = tokio::spawn({
                                 let instance = instance.clone();
                                 async move {
@@ -758,7 +801,9 @@ pub async fn run(
                             });
                             handles.push(handle);
                         }
-                        State::Error | State::Stopped | State::Stopping => continue,
+                        State::Stopped | State::Stopping | State::BackingUp | State::Updating => {
+                            continue
+                        }
                     }
                 }
                 for handle in handles {
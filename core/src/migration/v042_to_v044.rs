@@ -22,9 +22,49 @@ impl From<RestoreConfigV042> for RestoreConfig {
             auto_start: config.auto_start,
             restart_on_crash: config.restart_on_crash,
             backup_period: config.backup_period,
+            backups_enabled: true,
+            backup_include: vec![
+                "world".to_string(),
+                "world_nether".to_string(),
+                "world_the_end".to_string(),
+            ],
             jre_major_version: config.jre_major_version,
             has_started: config.has_started,
             java_cmd: None,
+            stdin_terminator: Default::default(),
+            crash_restart_cooldown: None,
+            watchdog: None,
+            crash_loop_guard: Some(Default::default()),
+            restart_schedule: None,
+            startup_timeout: None,
+            world_dir_override: None,
+            resources_dir_override: None,
+            backup_destination: None,
+            autosave_interval: None,
+            prune_backups_on_low_space: false,
+            backup_on_stop_mode: None,
+            backup_io_limit: None,
+            backup_retention: None,
+            timeout_settings: Default::default(),
+            already_running_policy: Default::default(),
+            java_agents: Vec::new(),
+            jvm_flags_preset: Default::default(),
+            server_locale: Default::default(),
+            commands_on_start: Vec::new(),
+            max_log_line_length: 32 * 1024,
+            mirror_console_output: false,
+            cpu_affinity: None,
+            nice: None,
+            runtimes_path: None,
+            java_path: None,
+            launch_args_file: None,
+            startup_history: Vec::new(),
+            prompt_responses: Vec::new(),
+            rcon_password: None,
+            manage_rcon: false,
+            rcon: Default::default(),
+            maintenance_restore: None,
+            mod_loader_mismatch_policy: Default::default(),
         }
     }
 }
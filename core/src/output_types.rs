@@ -25,6 +25,10 @@ impl From<&Event> for ClientEvent {
             EventInner::InstanceEvent(i) => match i.instance_event_inner {
                 InstanceEventInner::InstanceError { .. } => EventLevel::Error,
                 InstanceEventInner::InstanceWarning { .. } => EventLevel::Warning,
+                InstanceEventInner::ServerLagWarning { .. } => EventLevel::Warning,
+                InstanceEventInner::PaperNotice { .. } => EventLevel::Warning,
+                InstanceEventInner::OutOfDiskSpace { .. } => EventLevel::Error,
+                InstanceEventInner::BackupFailed { .. } => EventLevel::Error,
                 _ => EventLevel::Info,
             },
             EventInner::UserEvent(_) => EventLevel::Info,
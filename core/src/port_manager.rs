@@ -1,9 +1,18 @@
-use std::{collections::HashSet, net::SocketAddrV4};
+use std::{
+    collections::{BTreeMap, HashSet},
+    net::SocketAddrV4,
+};
 
 use color_eyre::eyre::{eyre, Context};
+use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
+use ts_rs::TS;
 
 use crate::error::Error;
+use crate::prelude::GameInstance;
+use crate::traits::t_configurable::TConfigurable;
+use crate::traits::t_server::{State, TServer};
+use crate::types::InstanceUuid;
 
 pub struct PortManager {
     allocated_ports: HashSet<u32>,
@@ -15,6 +24,91 @@ pub struct PortStatus {
     pub is_allocated: bool,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, TS)]
+#[ts(export)]
+pub enum PortKind {
+    Game,
+    Rcon,
+    Query,
+}
+
+impl PortKind {
+    /// Query listens on UDP, so it only collides with other query ports
+    fn is_udp(&self) -> bool {
+        matches!(self, PortKind::Query)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct PortUsage {
+    pub instance_uuid: InstanceUuid,
+    pub instance_name: String,
+    pub kind: PortKind,
+    /// Whether the instance is starting or running, maintenance states don't hold the port
+    pub is_running: bool,
+}
+
+/// A port configured for more than one server socket
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct PortConflict {
+    pub port: u32,
+    pub usages: Vec<PortUsage>,
+}
+
+impl PortConflict {
+    pub fn involves(&self, uuid: &InstanceUuid) -> bool {
+        self.usages.iter().any(|usage| &usage.instance_uuid == uuid)
+    }
+}
+
+fn find_port_conflicts(usages: Vec<(u32, PortUsage)>) -> Vec<PortConflict> {
+    let mut by_port: BTreeMap<(u32, bool), Vec<PortUsage>> = BTreeMap::new();
+    for (port, usage) in usages {
+        by_port
+            .entry((port, usage.kind.is_udp()))
+            .or_default()
+            .push(usage);
+    }
+    by_port
+        .into_iter()
+        .filter(|(_, usages)| usages.len() > 1)
+        .map(|((port, _), usages)| PortConflict { port, usages })
+        .collect()
+}
+
+/// Finds ports shared by the configured instances, so that misconfigurations can be
+/// reported before the second server fails to bind
+pub async fn detect_port_conflicts(
+    instances: &DashMap<InstanceUuid, GameInstance>,
+) -> Vec<PortConflict> {
+    let mut usages = Vec::new();
+    for entry in instances.iter() {
+        let instance = entry.value();
+        let ports = match instance {
+            GameInstance::MinecraftInstance(instance) => instance.bound_ports().await,
+            GameInstance::GenericInstance(instance) => {
+                vec![(PortKind::Game, instance.port().await)]
+            }
+        };
+        let instance_name = instance.name().await;
+        let is_running = matches!(instance.state().await, State::Running | State::Starting);
+        for (kind, port) in ports {
+            usages.push((
+                port,
+                PortUsage {
+                    instance_uuid: entry.key().clone(),
+                    instance_name: instance_name.clone(),
+                    kind,
+                    is_running,
+                },
+            ));
+        }
+    }
+    find_port_conflicts(usages)
+}
+
 impl PortManager {
     pub fn new(allocated_ports: HashSet<u32>) -> PortManager {
         PortManager { allocated_ports }
@@ -80,3 +174,44 @@ impl PortManager {
         .unwrap()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(name: &str, kind: PortKind) -> PortUsage {
+        PortUsage {
+            instance_uuid: InstanceUuid::from(name.to_string()),
+            instance_name: name.to_string(),
+            kind,
+            is_running: false,
+        }
+    }
+
+    #[test]
+    fn test_find_port_conflicts() {
+        let conflicts = find_port_conflicts(vec![
+            (25565, usage("a", PortKind::Game)),
+            (25565, usage("a", PortKind::Query)),
+            (25575, usage("a", PortKind::Rcon)),
+            (25566, usage("b", PortKind::Game)),
+            (25575, usage("b", PortKind::Rcon)),
+            (25566, usage("c", PortKind::Rcon)),
+        ]);
+        assert_eq!(
+            conflicts,
+            vec![
+                PortConflict {
+                    port: 25566,
+                    usages: vec![usage("b", PortKind::Game), usage("c", PortKind::Rcon)],
+                },
+                PortConflict {
+                    port: 25575,
+                    usages: vec![usage("a", PortKind::Rcon), usage("b", PortKind::Rcon)],
+                },
+            ]
+        );
+        assert!(conflicts[0].involves(&InstanceUuid::from("c".to_string())));
+        assert!(!conflicts[0].involves(&InstanceUuid::from("a".to_string())));
+    }
+}
@@ -45,6 +45,13 @@ pub fn path_to_tmp() -> &'static PathBuf {
     PATH_TO_TMP.get().unwrap()
 }
 
+static PATH_TO_TRASH: OnceCell<PathBuf> = OnceCell::new();
+
+/// Where deleted instances are kept until their grace period runs out
+pub fn path_to_trash() -> &'static PathBuf {
+    PATH_TO_TRASH.get().unwrap()
+}
+
 static APP_STATE: OnceCell<AppState> = OnceCell::new();
 
 pub fn init_app_state(app_state: AppState) {
@@ -55,6 +62,11 @@ pub fn app_state() -> &'static AppState {
     APP_STATE.get().unwrap()
 }
 
+/// `None` before `init_app_state` is called, e.g. in tests
+pub fn try_app_state() -> Option<&'static AppState> {
+    APP_STATE.get()
+}
+
 /// Initialize the paths for the lodestone instance.
 /// This function should only be called once.
 ///
@@ -66,11 +78,13 @@ pub fn init_paths(lodestone_path: PathBuf) {
     let path_to_global_settings = lodestone_path.join("global_settings.json");
     let path_to_users = lodestone_path.join("stores").join("users.json");
     let path_to_tmp = lodestone_path.join("tmp");
+    let path_to_trash = lodestone_path.join(".trash");
 
     std::fs::create_dir_all(&path_to_instances).unwrap();
     std::fs::create_dir_all(&path_to_binaries).unwrap();
     std::fs::create_dir_all(&path_to_stores).unwrap();
     std::fs::create_dir_all(&path_to_tmp).unwrap();
+    std::fs::create_dir_all(&path_to_trash).unwrap();
     // std::fs::File::create(&path_to_global_settings).unwrap();
     // std::fs::File::create(&path_to_users).unwrap();
     // std::fs::File::create(&path_to_tmp).unwrap();
@@ -82,6 +96,7 @@ pub fn init_paths(lodestone_path: PathBuf) {
     let _ = PATH_TO_GLOBAL_SETTINGS.set(path_to_global_settings);
     let _ = PATH_TO_USERS.set(path_to_users);
     let _ = PATH_TO_TMP.set(path_to_tmp);
+    let _ = PATH_TO_TRASH.set(path_to_trash);
 }
 
 thread_local! {
@@ -0,0 +1,177 @@
+use color_eyre::eyre::eyre;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use sysinfo::SystemExt;
+use tracing::warn;
+use ts_rs::TS;
+
+use crate::error::{Error, ErrorKind};
+use crate::prelude::{try_app_state, GameInstance};
+use crate::traits::t_server::{State, TServer};
+use crate::types::InstanceUuid;
+
+/// What creating or starting an instance does when it would commit too much of the host's RAM
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, TS)]
+#[ts(export)]
+pub enum RamGuardrailPolicy {
+    /// Go ahead and log a warning
+    #[default]
+    Warn,
+    /// Refuse to create or start the instance
+    Block,
+}
+
+/// Caps the `max_ram` of the running instances to a fraction of the host's RAM
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, TS)]
+#[ts(export)]
+pub struct RamGuardrail {
+    /// Between 0 and 1, more than 1 lets instances overcommit into swap
+    pub max_ram_fraction: f64,
+    pub policy: RamGuardrailPolicy,
+}
+
+impl Default for RamGuardrail {
+    fn default() -> Self {
+        Self {
+            max_ram_fraction: 0.9,
+            policy: RamGuardrailPolicy::default(),
+        }
+    }
+}
+
+impl RamGuardrail {
+    pub fn validate(&self) -> Result<(), Error> {
+        if !self.max_ram_fraction.is_finite() || self.max_ram_fraction <= 0.0 {
+            return Err(Error {
+                kind: ErrorKind::BadRequest,
+                source: eyre!(
+                    "Invalid RAM fraction {}, expected a positive number",
+                    self.max_ram_fraction
+                ),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Whether committing `new_mb` on top of `committed_mb` goes over the budget
+fn exceeds_ram_budget(
+    committed_mb: u64,
+    new_mb: u64,
+    total_mb: u64,
+    max_ram_fraction: f64,
+) -> bool {
+    (committed_mb + new_mb) as f64 > total_mb as f64 * max_ram_fraction
+}
+
+/// Sum of the `max_ram` in megabytes of the instances that aren't stopped, besides `exclude`
+async fn committed_ram_mb(
+    instances: &DashMap<InstanceUuid, GameInstance>,
+    exclude: Option<&InstanceUuid>,
+) -> u64 {
+    let mut ret = 0;
+    for entry in instances.iter() {
+        if Some(entry.key()) == exclude {
+            continue;
+        }
+        if let GameInstance::MinecraftInstance(instance) = entry.value() {
+            if instance.state().await != State::Stopped {
+                ret += instance.max_ram().await as u64;
+            }
+        }
+    }
+    ret
+}
+
+/// Checks that an instance with `max_ram_mb` fits next to the running instances
+///
+/// `uuid` is the instance being started, so that it isn't counted twice if it is already running
+pub async fn check_ram_guardrail(
+    instances: &DashMap<InstanceUuid, GameInstance>,
+    system: &mut sysinfo::System,
+    guardrail: Option<RamGuardrail>,
+    uuid: Option<&InstanceUuid>,
+    name: &str,
+    max_ram_mb: u32,
+) -> Result<(), Error> {
+    let guardrail = match guardrail {
+        Some(guardrail) => guardrail,
+        None => return Ok(()),
+    };
+    system.refresh_memory();
+    let total_mb = system.total_memory() / 1024 / 1024;
+    let committed_mb = committed_ram_mb(instances, uuid).await;
+    if !exceeds_ram_budget(
+        committed_mb,
+        max_ram_mb as u64,
+        total_mb,
+        guardrail.max_ram_fraction,
+    ) {
+        return Ok(());
+    }
+    let message = format!(
+        "Instance {} needs up to {} MB of RAM, running instances already use up to {} MB of the {} MB allowed ({}% of {} MB)",
+        name,
+        max_ram_mb,
+        committed_mb,
+        (total_mb as f64 * guardrail.max_ram_fraction) as u64,
+        (guardrail.max_ram_fraction * 100.0).round(),
+        total_mb
+    );
+    match guardrail.policy {
+        RamGuardrailPolicy::Warn => {
+            warn!("{}", message);
+            Ok(())
+        }
+        RamGuardrailPolicy::Block => Err(Error {
+            kind: ErrorKind::BadRequest,
+            source: eyre!(message),
+        }),
+    }
+}
+
+/// Runs `check_ram_guardrail` with the global settings for an instance that is starting
+///
+/// Called from the start itself, so that restarts after a crash or on a schedule are checked
+/// just like a start requested by a user
+pub async fn check_start_ram_guardrail(
+    uuid: &InstanceUuid,
+    name: &str,
+    max_ram_mb: u32,
+) -> Result<(), Error> {
+    let state = match try_app_state() {
+        Some(state) => state,
+        None => return Ok(()),
+    };
+    let ram_guardrail = state.global_settings.lock().await.ram_guardrail();
+    check_ram_guardrail(
+        &state.instances,
+        &mut *state.system.lock().await,
+        ram_guardrail,
+        Some(uuid),
+        name,
+        max_ram_mb,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exceeds_ram_budget() {
+        assert!(!exceeds_ram_budget(4096, 2048, 8192, 0.9));
+        assert!(exceeds_ram_budget(6144, 2048, 8192, 0.9));
+        assert!(!exceeds_ram_budget(6144, 2048, 8192, 1.0));
+        assert!(exceeds_ram_budget(0, 4096, 4096, 0.5));
+
+        assert!(RamGuardrail::default().validate().is_ok());
+        assert!(RamGuardrail {
+            max_ram_fraction: 0.0,
+            policy: RamGuardrailPolicy::Block,
+        }
+        .validate()
+        .is_err());
+    }
+}
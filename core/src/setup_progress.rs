@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::events::{Event, EventInner, ProgressionEventInner, ProgressionStartValue};
+use crate::types::{InstanceUuid, Snowflake};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, TS, PartialEq, Eq)]
+#[ts(export)]
+pub enum SetupStatus {
+    Running,
+    Failed,
+    Done,
+}
+
+/// Snapshot of an instance setup, so that clients that missed the progression events
+/// can still show where the setup is at
+#[derive(Debug, Clone, Serialize, Deserialize, TS, PartialEq)]
+#[ts(export)]
+pub struct InstanceSetupProgress {
+    pub step: String,
+    /// Between 0 and 1, `None` if the setup did not report a total
+    pub fraction: Option<f64>,
+    pub status: SetupStatus,
+    pub last_error: Option<String>,
+    #[serde(skip)]
+    progress: f64,
+    #[serde(skip)]
+    total: Option<f64>,
+}
+
+#[derive(Debug, Default)]
+pub struct SetupProgressRegistry {
+    event_ids: HashMap<Snowflake, InstanceUuid>,
+    setups: HashMap<InstanceUuid, InstanceSetupProgress>,
+}
+
+impl SetupProgressRegistry {
+    pub fn get(&self, uuid: &InstanceUuid) -> Option<InstanceSetupProgress> {
+        self.setups.get(uuid).cloned()
+    }
+
+    /// Updates the snapshot of the setup the progression event belongs to, if any
+    pub fn handle_event(&mut self, event: &Event) {
+        let progression_event = match &event.event_inner {
+            EventInner::ProgressionEvent(progression_event) => progression_event,
+            _ => return,
+        };
+        let event_id = progression_event.event_id();
+        match progression_event.progression_event_inner() {
+            ProgressionEventInner::ProgressionStart {
+                progression_name,
+                total,
+                inner: Some(ProgressionStartValue::InstanceCreation { instance_uuid }),
+            } => {
+                self.event_ids.insert(event_id, instance_uuid.clone());
+                self.setups.insert(
+                    instance_uuid.clone(),
+                    InstanceSetupProgress {
+                        step: progression_name.clone(),
+                        fraction: total.map(|_| 0.0),
+                        status: SetupStatus::Running,
+                        last_error: None,
+                        progress: 0.0,
+                        total: *total,
+                    },
+                );
+            }
+            ProgressionEventInner::ProgressionUpdate {
+                progress_message,
+                progress,
+            } => {
+                if let Some(setup) = self
+                    .event_ids
+                    .get(&event_id)
+                    .and_then(|uuid| self.setups.get_mut(uuid))
+                {
+                    // progress is reported as increments
+                    setup.progress += progress;
+                    setup.step = progress_message.clone();
+                    setup.fraction = setup
+                        .total
+                        .filter(|total| *total > 0.0)
+                        .map(|total| (setup.progress / total).clamp(0.0, 1.0));
+                }
+            }
+            ProgressionEventInner::ProgressionEnd {
+                success, message, ..
+            } => {
+                let uuid = match self.event_ids.remove(&event_id) {
+                    Some(uuid) => uuid,
+                    None => return,
+                };
+                if let Some(setup) = self.setups.get_mut(&uuid) {
+                    if *success {
+                        setup.status = SetupStatus::Done;
+                        setup.fraction = setup.total.map(|_| 1.0);
+                    } else {
+                        setup.status = SetupStatus::Failed;
+                        setup.last_error = message.clone();
+                    }
+                    if let Some(message) = message {
+                        setup.step = message.clone();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::CausedBy;
+
+    #[test]
+    fn test_setup_progress() {
+        let mut registry = SetupProgressRegistry::default();
+        let uuid = InstanceUuid::default();
+        let (start, event_id) = Event::new_progression_event_start(
+            "Setting up",
+            Some(10.0),
+            Some(ProgressionStartValue::InstanceCreation {
+                instance_uuid: uuid.clone(),
+            }),
+            CausedBy::System,
+        );
+        registry.handle_event(&start);
+        let setup = registry.get(&uuid).unwrap();
+        assert_eq!(setup.status, SetupStatus::Running);
+        assert_eq!(setup.fraction, Some(0.0));
+
+        registry.handle_event(&Event::new_progression_event_update(
+            &event_id,
+            "1/4: Creating directories",
+            1.0,
+        ));
+        registry.handle_event(&Event::new_progression_event_update(
+            &event_id,
+            "2/4: Downloading JRE",
+            4.0,
+        ));
+        let setup = registry.get(&uuid).unwrap();
+        assert_eq!(setup.step, "2/4: Downloading JRE");
+        assert_eq!(setup.fraction, Some(0.5));
+
+        registry.handle_event(&Event::new_progression_event_end(
+            event_id,
+            false,
+            Some("Instance creation failed: no space left"),
+            None,
+        ));
+        let setup = registry.get(&uuid).unwrap();
+        assert_eq!(setup.status, SetupStatus::Failed);
+        assert_eq!(
+            setup.last_error.as_deref(),
+            Some("Instance creation failed: no space left")
+        );
+        assert!(registry.get(&InstanceUuid::default()).is_none());
+    }
+}
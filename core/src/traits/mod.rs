@@ -35,7 +35,23 @@ pub struct InstanceInfo {
     pub player_count: Option<u32>,
     pub max_player_count: Option<u32>,
     pub player_list: Option<HashSet<Player>>,
+    /// Why the instance was loaded without its config, `None` if it loaded normally
+    pub needs_repair: Option<String>,
 }
+/// What a list of many instances shows of each, without anything costly to gather
+#[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq)]
+#[ts(export)]
+pub struct InstanceSummary {
+    pub uuid: InstanceUuid,
+    pub name: String,
+    pub game_type: Game,
+    pub version: String,
+    pub state: State,
+    pub player_count: Option<u32>,
+    pub port: u32,
+    pub creation_time: i64,
+}
+
 use crate::generic::GenericInstance;
 use crate::minecraft::MinecraftInstance;
 use crate::prelude::GameInstance;
@@ -45,6 +61,24 @@ use crate::types::InstanceUuid;
 pub trait TInstance:
     TConfigurable + TMacro + TPlayerManagement + TServer + Clone
 {
+    /// Set when the instance couldn't be restored properly and was loaded in a degraded state
+    async fn needs_repair(&self) -> Option<String> {
+        None
+    }
+
+    async fn get_instance_summary(&self) -> InstanceSummary {
+        InstanceSummary {
+            uuid: self.uuid().await,
+            name: self.name().await,
+            game_type: self.game_type().await,
+            version: self.version().await,
+            state: self.state().await,
+            player_count: self.get_player_count().await.ok(),
+            port: self.port().await,
+            creation_time: self.creation_time().await,
+        }
+    }
+
     async fn get_instance_info(&self) -> InstanceInfo {
         InstanceInfo {
             uuid: self.uuid().await,
@@ -61,6 +95,7 @@ pub trait TInstance:
             player_count: self.get_player_count().await.ok(),
             max_player_count: self.get_max_player_count().await.ok(),
             player_list: self.get_player_list().await.ok(),
+            needs_repair: self.needs_repair().await,
         }
     }
 }
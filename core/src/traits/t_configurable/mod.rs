@@ -25,7 +25,9 @@ use crate::types::InstanceUuid;
 pub enum MinecraftVariant {
     Vanilla,
     Forge,
+    NeoForge,
     Fabric,
+    Quilt,
     Paper,
     Spigot,
     Other { name: String },
@@ -63,6 +65,9 @@ impl From<Flavour> for Game {
             Flavour::Fabric { .. } => Self::MinecraftJava {
                 variant: MinecraftVariant::Fabric,
             },
+            Flavour::Quilt { .. } => Self::MinecraftJava {
+                variant: MinecraftVariant::Quilt,
+            },
             Flavour::Paper { .. } => Self::MinecraftJava {
                 variant: MinecraftVariant::Paper,
             },
@@ -72,6 +77,9 @@ impl From<Flavour> for Game {
             Flavour::Forge { .. } => Self::MinecraftJava {
                 variant: MinecraftVariant::Forge,
             },
+            Flavour::NeoForge { .. } => Self::MinecraftJava {
+                variant: MinecraftVariant::NeoForge,
+            },
         }
     }
 }
@@ -119,7 +127,7 @@ pub trait TConfigurable {
         })
     }
 
-    async fn change_version(&self, _version: String) -> Result<(), Error> {
+    async fn change_version(&self, _version: String, _force: bool) -> Result<(), Error> {
         Err(Error {
             kind: ErrorKind::UnsupportedOperation,
             source: eyre!("This instance does not support changing version"),
@@ -5,7 +5,7 @@ use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
 use crate::events::CausedBy;
-use crate::Error;
+use crate::error::{Error, ErrorKind};
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS, Copy)]
 #[serde(rename = "InstanceState")]
@@ -16,6 +16,10 @@ pub enum State {
     Stopping,
     Stopped,
     Error,
+    /// The instance is stopped while its world is being backed up
+    BackingUp,
+    /// The instance is stopped while its server is being changed to another version
+    Updating,
 }
 
 pub enum StateAction {
@@ -23,6 +27,9 @@ pub enum StateAction {
     UserStop,
     InstanceStart,
     InstanceStop,
+    BeginBackup,
+    BeginUpdate,
+    EndMaintenance,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
@@ -62,11 +69,18 @@ impl ToString for State {
             State::Stopping => "Stopping".to_string(),
             State::Stopped => "Stopped".to_string(),
             State::Error => "Error".to_string(),
+            State::BackingUp => "BackingUp".to_string(),
+            State::Updating => "Updating".to_string(),
         }
     }
 }
 
 impl State {
+    /// Whether the instance is in one of the transient states it can't be started or stopped in
+    pub fn is_maintenance(&self) -> bool {
+        matches!(self, State::BackingUp | State::Updating)
+    }
+
     pub fn try_new_state(
         &self,
         action: StateAction,
@@ -95,8 +109,25 @@ impl State {
             (State::Stopped, StateAction::UserStop) => {
                 Err(eyre!("Cannot stop an instance that is already stopped"))
             }
-            (State::Error, StateAction::UserStart) => todo!(),
-            (State::Error, StateAction::UserStop) => todo!(),
+            (State::Error, StateAction::UserStart) => Err(eyre!(
+                "Cannot start an instance that is in an error state, stop or kill it first"
+            )),
+            (State::Error, StateAction::UserStop) => Ok(State::Stopping),
+            (State::BackingUp, StateAction::UserStart | StateAction::UserStop) => Err(eyre!(
+                "Cannot start or stop an instance while it is being backed up"
+            )),
+            (State::Updating, StateAction::UserStart | StateAction::UserStop) => Err(eyre!(
+                "Cannot start or stop an instance while it is being updated"
+            )),
+            (State::Stopped, StateAction::BeginBackup) => Ok(State::BackingUp),
+            (State::Stopped, StateAction::BeginUpdate) => Ok(State::Updating),
+            (_, StateAction::BeginBackup | StateAction::BeginUpdate) => Err(eyre!(
+                "Instance must be stopped before it can be backed up or updated"
+            )),
+            (State::BackingUp | State::Updating, StateAction::EndMaintenance) => Ok(State::Stopped),
+            (_, StateAction::EndMaintenance) => {
+                Err(eyre!("Instance is not being backed up or updated"))
+            }
         }?;
         if let Some(on_transit) = on_transit {
             on_transit(state);
@@ -127,4 +158,11 @@ pub trait TServer {
     async fn state(&self) -> State;
     async fn send_command(&self, command: &str, caused_by: CausedBy) -> Result<(), Error>;
     async fn monitor(&self) -> MonitorReport;
+    /// Cancels a restart that is waiting out its cooldown after a crash
+    async fn cancel_pending_restart(&self) -> Result<(), Error> {
+        Err(Error {
+            kind: ErrorKind::UnsupportedOperation,
+            source: eyre!("This instance does not support cancelling pending restarts"),
+        })
+    }
 }
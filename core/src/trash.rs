@@ -0,0 +1,202 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use color_eyre::eyre::{eyre, Context};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+use ts_rs::TS;
+
+use crate::error::{Error, ErrorKind};
+use crate::prelude::{path_to_instances, path_to_trash};
+use crate::restore_instance;
+use crate::types::{DotLodestoneConfig, InstanceUuid};
+use crate::util::resolve_path_conflict;
+use crate::AppState;
+
+/// An instance that was deleted, but can still be restored until it is purged
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct DeletedInstance {
+    pub uuid: InstanceUuid,
+    /// Name of the directory the instance had before it was deleted
+    pub dir_name: String,
+    /// Unix timestamp in seconds
+    pub deletion_time: i64,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+/// Moves an instance directory into `path_to_trash`, prefixed with the time of deletion
+pub async fn move_to_trash(
+    path_to_instance: &Path,
+    path_to_trash: &Path,
+) -> Result<PathBuf, Error> {
+    let dir_name = path_to_instance.file_name().ok_or_else(|| Error {
+        kind: ErrorKind::Internal,
+        source: eyre!("Invalid instance path {}", path_to_instance.display()),
+    })?;
+    crate::util::fs::create_dir_all(path_to_trash).await?;
+    let dest = resolve_path_conflict(
+        path_to_trash.join(format!(
+            "{}_{}",
+            chrono::Utc::now().timestamp(),
+            dir_name.to_string_lossy()
+        )),
+        None,
+    );
+    tokio::fs::rename(path_to_instance, &dest)
+        .await
+        .context(format!(
+            "Failed to move {} to {}",
+            path_to_instance.display(),
+            dest.display()
+        ))?;
+    Ok(dest)
+}
+
+/// Lists the instances in `path_to_trash`, oldest deletion first
+pub async fn list_deleted(path_to_trash: &Path) -> Result<Vec<DeletedInstance>, Error> {
+    let mut ret = Vec::new();
+    if !path_to_trash.is_dir() {
+        return Ok(ret);
+    }
+    let mut read_dir = tokio::fs::read_dir(path_to_trash)
+        .await
+        .context(format!("Failed to read {}", path_to_trash.display()))?;
+    while let Some(entry) = read_dir
+        .next_entry()
+        .await
+        .context(format!("Failed to read {}", path_to_trash.display()))?
+    {
+        let path = entry.path();
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let (deletion_time, dir_name) = match file_name
+            .split_once('_')
+            .and_then(|(time, dir_name)| Some((time.parse::<i64>().ok()?, dir_name)))
+        {
+            Some(v) => v,
+            None => continue,
+        };
+        let dot_lodestone_config: DotLodestoneConfig =
+            match tokio::fs::read(path.join(".lodestone_config"))
+                .await
+                .ok()
+                .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            {
+                Some(v) => v,
+                None => {
+                    error!("{} is not an instance, skipping", path.display());
+                    continue;
+                }
+            };
+        ret.push(DeletedInstance {
+            uuid: dot_lodestone_config.uuid().to_owned(),
+            dir_name: dir_name.to_string(),
+            deletion_time,
+            path,
+        });
+    }
+    ret.sort_by_key(|deleted| deleted.deletion_time);
+    Ok(ret)
+}
+
+/// Permanently removes the instances that have been in `path_to_trash` for longer than
+/// `older_than`, returning their uuids
+pub async fn purge_deleted(
+    path_to_trash: &Path,
+    older_than: Duration,
+) -> Result<Vec<InstanceUuid>, Error> {
+    let cutoff = chrono::Utc::now().timestamp() - older_than.as_secs() as i64;
+    let mut ret = Vec::new();
+    for deleted in list_deleted(path_to_trash).await? {
+        if deleted.deletion_time > cutoff {
+            continue;
+        }
+        info!("Purging deleted instance {}", deleted.dir_name);
+        crate::util::fs::remove_dir_all(&deleted.path).await?;
+        ret.push(deleted.uuid);
+    }
+    Ok(ret)
+}
+
+/// Moves a deleted instance back into the instances directory and loads it
+pub async fn restore_deleted(state: &AppState, uuid: &InstanceUuid) -> Result<(), Error> {
+    if state.instances.contains_key(uuid) {
+        return Err(Error {
+            kind: ErrorKind::BadRequest,
+            source: eyre!("An instance with this uuid already exists"),
+        });
+    }
+    let deleted = list_deleted(path_to_trash())
+        .await?
+        .into_iter()
+        // an instance can be deleted more than once after being restored, take the latest
+        .filter(|deleted| &deleted.uuid == uuid)
+        .last()
+        .ok_or_else(|| Error {
+            kind: ErrorKind::NotFound,
+            source: eyre!("Deleted instance not found"),
+        })?;
+    let dest = resolve_path_conflict(path_to_instances().join(&deleted.dir_name), None);
+    tokio::fs::rename(&deleted.path, &dest)
+        .await
+        .context(format!(
+            "Failed to move {} to {}",
+            deleted.path.display(),
+            dest.display()
+        ))?;
+    let (uuid, instance) = restore_instance(
+        &dest,
+        state.event_broadcaster.clone(),
+        state.macro_executor.clone(),
+    )
+    .await?;
+    state
+        .port_manager
+        .lock()
+        .await
+        .add_port(instance.port().await);
+    state.instances.insert(uuid, instance);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::t_configurable::GameType;
+
+    #[tokio::test]
+    async fn test_trash() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path_to_instance = temp_dir.path().join("instances").join("world-1234");
+        let path_to_trash = temp_dir.path().join(".trash");
+        tokio::fs::create_dir_all(&path_to_instance).await.unwrap();
+        let uuid = InstanceUuid::default();
+        tokio::fs::write(
+            path_to_instance.join(".lodestone_config"),
+            serde_json::to_vec(&DotLodestoneConfig::new(uuid.clone(), GameType::MinecraftJava))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+        let trashed = move_to_trash(&path_to_instance, &path_to_trash).await.unwrap();
+        assert!(!path_to_instance.exists());
+        assert!(trashed.join(".lodestone_config").is_file());
+
+        let deleted = list_deleted(&path_to_trash).await.unwrap();
+        assert_eq!(deleted.len(), 1);
+        assert_eq!(deleted[0].uuid, uuid);
+        assert_eq!(deleted[0].dir_name, "world-1234");
+
+        assert!(purge_deleted(&path_to_trash, Duration::from_secs(3600))
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            purge_deleted(&path_to_trash, Duration::ZERO).await.unwrap(),
+            vec![uuid]
+        );
+        assert!(!trashed.exists());
+    }
+}
@@ -3,7 +3,9 @@ use std::fmt::Display;
 use crate::migration::DotLodestoneConfigV043;
 use crate::traits::t_configurable::GameType;
 use crate::{
-    implementations::minecraft::Flavour, migration::RestoreConfigV042, prelude::SNOWFLAKE_GENERATOR,
+    implementations::minecraft::Flavour,
+    migration::RestoreConfigV042,
+    prelude::{LODESTONE_EPOCH_MIL, SNOWFLAKE_GENERATOR},
 };
 use serde::{Deserialize, Serialize};
 use serde_aux::prelude::*;
@@ -54,6 +56,11 @@ impl Snowflake {
     pub fn new() -> Self {
         Self(get_snowflake())
     }
+
+    /// Unix timestamp in milliseconds of when the snowflake was generated
+    pub fn timestamp_millis(&self) -> i64 {
+        (self.0 >> 22) + LODESTONE_EPOCH_MIL.with(|p| *p)
+    }
 }
 
 impl ToString for Snowflake {
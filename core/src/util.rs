@@ -1,12 +1,14 @@
 use color_eyre::eyre::{eyre, Context, ContextCompat};
 use std::collections::HashSet;
 use std::ffi::OsStr;
-use std::io::{Read, Write};
+use std::io::Read;
 
 use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
 use std::path::{Path, PathBuf};
-use tokio::io::AsyncWriteExt;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWriteExt};
+use tokio_util::sync::CancellationToken;
 
 use futures_util::StreamExt;
 use reqwest::Client;
@@ -22,7 +24,7 @@ pub struct Authentication {
     password: String,
 }
 
-use crate::error::Error;
+use crate::error::{Error, ErrorKind};
 use crate::prelude::path_to_tmp;
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
 #[ts(export)]
@@ -196,6 +198,74 @@ pub enum UnzipOption {
     ToDir(PathBuf),
 }
 
+/// An archive format `unzip_file` can extract
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    TarGz,
+    Tar,
+    /// Only extracted when built with the `sevenz` feature
+    SevenZip,
+}
+
+/// Detects the format of an archive from its magic bytes, since archives from other tools are
+/// often misnamed
+///
+/// `None` if it isn't an archive in a known format
+pub fn detect_archive_format(file: &Path) -> Result<Option<ArchiveFormat>, Error> {
+    let mut header = Vec::with_capacity(262);
+    std::fs::File::open(file)
+        .context(format!("Failed to open file {}", file.display()))?
+        .take(262)
+        .read_to_end(&mut header)
+        .context(format!("Failed to read file {}", file.display()))?;
+    Ok(
+        if header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06") {
+            Some(ArchiveFormat::Zip)
+        } else if header.starts_with(&[0x1f, 0x8b]) {
+            Some(ArchiveFormat::TarGz)
+        } else if header.starts_with(b"7z\xbc\xaf\x27\x1c") {
+            Some(ArchiveFormat::SevenZip)
+        } else if header.get(257..262) == Some(b"ustar") {
+            Some(ArchiveFormat::Tar)
+        } else {
+            None
+        },
+    )
+}
+
+#[cfg(feature = "sevenz")]
+fn extract_7z(file: &Path, dest: &Path) -> Result<(), Error> {
+    sevenz_rust::decompress_file_with_extract_fn(file, dest, |entry, reader, path| {
+        // the same protection against entries escaping the destination as zip and tar have
+        let name = Path::new(entry.name());
+        if name.is_absolute()
+            || name
+                .components()
+                .any(|component| !matches!(component, std::path::Component::Normal(_)))
+        {
+            return Err(sevenz_rust::Error::other(format!(
+                "Entry {} is outside of the archive",
+                entry.name()
+            )));
+        }
+        sevenz_rust::default_entry_extract_fn(entry, reader, path)
+    })
+    .map_err(|e| eyre!("Failed to decompress file {}: {}", file.display(), e))?;
+    Ok(())
+}
+
+#[cfg(not(feature = "sevenz"))]
+fn extract_7z(file: &Path, _dest: &Path) -> Result<(), Error> {
+    Err(Error {
+        kind: ErrorKind::UnsupportedOperation,
+        source: eyre!(
+            "Cannot extract {}, this build of lodestone doesn't support 7z archives",
+            file.display()
+        ),
+    })
+}
+
 pub fn unzip_file(
     file: impl AsRef<Path>,
     unzip_option: UnzipOption,
@@ -206,12 +276,10 @@ pub fn unzip_file(
         return Err(eyre!("File {} does not exist", file.display()).into());
     }
 
-    let file_extension = file
-        .extension()
-        .ok_or_else(|| eyre!("Failed to get file extension for {}", file.display()))?;
-    if file_extension != "gz" && file_extension != "tgz" && file_extension != "zip" {
-        return Err(eyre!("Unsupported extension for {}", file.display()).into());
-    }
+    let archive_format = detect_archive_format(file)?.ok_or_else(|| Error {
+        kind: ErrorKind::BadRequest,
+        source: eyre!("{} is not a zip, tar, tar.gz or 7z archive", file.display()),
+    })?;
 
     let parent = file.parent().context(format!(
         "Failed to get parent directory of {}",
@@ -240,23 +308,31 @@ pub fn unzip_file(
     )?;
     let temp_dest = temp_dest_dir.path();
 
-    if file_extension == "gz" || file_extension == "tgz" {
-        let tar_gz =
-            std::fs::File::open(file).context(format!("Failed to open file {}", file.display()))?;
-        let tar = GzDecoder::new(tar_gz);
-        let mut archive = Archive::new(tar);
-        archive.set_overwrite(true);
-        archive
-            .unpack(temp_dest)
-            .context(format!("Failed to decompress file {}", file.display()))?;
-    } else if file_extension == "zip" {
-        let zip =
-            std::fs::File::open(file).context(format!("Failed to open file {}", file.display()))?;
-        let mut archive = zip::ZipArchive::new(zip)
-            .context(format!("Failed to decompress file {}", file.display()))?;
-        archive
-            .extract(temp_dest)
-            .context(format!("Failed to decompress file {}", file.display()))?;
+    // tar and zip both skip entries that would end up outside of the destination
+    match archive_format {
+        ArchiveFormat::TarGz | ArchiveFormat::Tar => {
+            let tar = std::fs::File::open(file)
+                .context(format!("Failed to open file {}", file.display()))?;
+            let mut archive = if archive_format == ArchiveFormat::TarGz {
+                Archive::new(Box::new(GzDecoder::new(tar)) as Box<dyn Read>)
+            } else {
+                Archive::new(Box::new(tar) as Box<dyn Read>)
+            };
+            archive.set_overwrite(true);
+            archive
+                .unpack(temp_dest)
+                .context(format!("Failed to decompress file {}", file.display()))?;
+        }
+        ArchiveFormat::Zip => {
+            let zip = std::fs::File::open(file)
+                .context(format!("Failed to open file {}", file.display()))?;
+            let mut archive = zip::ZipArchive::new(zip)
+                .context(format!("Failed to decompress file {}", file.display()))?;
+            archive
+                .extract(temp_dest)
+                .context(format!("Failed to decompress file {}", file.display()))?;
+        }
+        ArchiveFormat::SevenZip => extract_7z(file, temp_dest)?,
     }
 
     let mut ret: HashSet<PathBuf> = HashSet::new();
@@ -312,11 +388,88 @@ pub async fn unzip_file_async(
         ))?
 }
 
+/// Caps the throughput of blocking IO by putting the current thread to sleep
+pub struct IoRateLimiter {
+    bytes_per_sec: u64,
+    window_start: Instant,
+    bytes_in_window: u64,
+}
+
+impl IoRateLimiter {
+    pub fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            bytes_per_sec: bytes_per_sec.max(1),
+            window_start: Instant::now(),
+            bytes_in_window: 0,
+        }
+    }
+
+    /// Records `bytes` of IO, sleeping until the rate is back under the limit
+    pub fn consume(&mut self, bytes: u64) {
+        self.bytes_in_window += bytes;
+        let expected =
+            Duration::from_secs_f64(self.bytes_in_window as f64 / self.bytes_per_sec as f64);
+        let elapsed = self.window_start.elapsed();
+        if expected > elapsed {
+            std::thread::sleep(expected - elapsed);
+        }
+        // start over every second so a pause in IO doesn't allow a burst afterwards
+        if self.window_start.elapsed() >= Duration::from_secs(1) {
+            self.window_start = Instant::now();
+            self.bytes_in_window = 0;
+        }
+    }
+}
+
+struct RateLimitedReader<'a, R> {
+    inner: R,
+    limiter: Option<&'a mut IoRateLimiter>,
+    cancellation: Option<&'a CancellationToken>,
+}
+
+impl<R: Read> Read for RateLimitedReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self
+            .cancellation
+            .map_or(false, CancellationToken::is_cancelled)
+        {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other, "Cancelled"));
+        }
+        // keep reads small so the sleeps are spread out instead of stalling once per file
+        let len = buf.len().min(64 * 1024);
+        let read = self.inner.read(&mut buf[..len])?;
+        if let Some(limiter) = self.limiter.as_mut() {
+            limiter.consume(read as u64);
+        }
+        Ok(read)
+    }
+}
+
 pub fn zip_files(
     files: &[impl AsRef<Path>],
     dest: impl AsRef<Path>,
     overwrite_dest: bool,
 ) -> Result<PathBuf, Error> {
+    zip_files_rate_limited(files, dest, overwrite_dest, None, None)
+}
+
+/// Like `zip_files`, reading at most `bytes_per_sec` to go easy on the disk
+///
+/// Stops with an error soon after `cancellation` is cancelled, the partial archive is removed
+pub fn zip_files_rate_limited(
+    files: &[impl AsRef<Path>],
+    dest: impl AsRef<Path>,
+    overwrite_dest: bool,
+    bytes_per_sec: Option<u64>,
+    cancellation: Option<&CancellationToken>,
+) -> Result<PathBuf, Error> {
+    let ensure_not_cancelled = || -> Result<(), Error> {
+        if cancellation.map_or(false, CancellationToken::is_cancelled) {
+            return Err(eyre!("Zipping was cancelled").into());
+        }
+        Ok(())
+    };
+    let mut limiter = bytes_per_sec.map(IoRateLimiter::new);
     let dest = dest.as_ref();
     std::fs::create_dir_all(dest.parent().context("Failed to get destination parent")?)
         .context(format!("Failed to create directory {}", dest.display()))?;
@@ -328,10 +481,10 @@ pub fn zip_files(
     let tmp_archive = tempfile::NamedTempFile::new_in(lodestone_tmp)
         .context("Failed to create temporary file for zipping")?;
 
-    let mut buffer = Vec::new();
     let mut writer = zip::ZipWriter::new(&tmp_archive);
     let options = zip::write::FileOptions::default().unix_permissions(0o775);
     for entry_path in files.iter().map(|f| f.as_ref()) {
+        ensure_not_cancelled()?;
         if entry_path.is_dir() {
             writer
                 .add_directory(
@@ -351,6 +504,7 @@ pub fn zip_files(
                 .into_iter()
                 .filter_map(|e| e.ok())
             {
+                ensure_not_cancelled()?;
                 let child_entry_path = child_entry.path();
                 let child_entry_dest =
                     child_entry_path
@@ -382,16 +536,20 @@ pub fn zip_files(
                             child_entry_path.display()
                         ))?;
 
-                    let mut child_entry_file = std::fs::File::open(child_entry_path)
+                    let child_entry_file = std::fs::File::open(child_entry_path)
                         .context(format!("Failed to open {}", child_entry_path.display()))?;
-                    child_entry_file
-                        .read_to_end(&mut buffer)
-                        .context(format!("Failed to read {}", child_entry_path.display()))?;
-                    writer.write_all(&buffer).context(format!(
+                    std::io::copy(
+                        &mut RateLimitedReader {
+                            inner: child_entry_file,
+                            limiter: limiter.as_mut(),
+                            cancellation,
+                        },
+                        &mut writer,
+                    )
+                    .context(format!(
                         "Failed to write {} to archive",
                         child_entry_path.display()
                     ))?;
-                    buffer.clear();
                 }
             }
         }
@@ -408,16 +566,20 @@ pub fn zip_files(
                 entry_path.display()
             ))?;
 
-            let mut entry_file = std::fs::File::open(entry_path)
+            let entry_file = std::fs::File::open(entry_path)
                 .context(format!("Failed to open {}", entry_path.display()))?;
-            entry_file
-                .read_to_end(&mut buffer)
-                .context(format!("Failed to read {}", entry_path.display()))?;
-            writer.write_all(&buffer).context(format!(
+            std::io::copy(
+                &mut RateLimitedReader {
+                    inner: entry_file,
+                    limiter: limiter.as_mut(),
+                    cancellation,
+                },
+                &mut writer,
+            )
+            .context(format!(
                 "Failed to write {} to archive",
                 entry_path.display()
             ))?;
-            buffer.clear();
         }
     }
 
@@ -451,10 +613,96 @@ pub async fn zip_files_async(
         .context("Failed to spawn blocking task")?
 }
 
+pub async fn zip_files_rate_limited_async(
+    files: &[impl AsRef<Path>],
+    dest: impl AsRef<Path>,
+    overwrite_dest: bool,
+    bytes_per_sec: Option<u64>,
+    cancellation: Option<CancellationToken>,
+) -> Result<PathBuf, Error> {
+    let _files = files
+        .iter()
+        .map(|f| f.as_ref().to_owned())
+        .collect::<Vec<_>>();
+    let _dest = dest.as_ref().to_owned();
+    tokio::task::spawn_blocking(move || {
+        zip_files_rate_limited(
+            &_files,
+            &_dest,
+            overwrite_dest,
+            bytes_per_sec,
+            cancellation.as_ref(),
+        )
+    })
+    .await
+    .context("Failed to spawn blocking task")?
+}
+
 pub fn rand_alphanumeric(len: usize) -> String {
     thread_rng().sample_iter(&Alphanumeric).take(len).collect()
 }
 
+/// Whether the error, or any error it was caused by, is the disk running out of space
+pub fn is_out_of_space(error: &Error) -> bool {
+    error.source.chain().any(|cause| {
+        cause
+            .downcast_ref::<std::io::Error>()
+            .and_then(|e| e.raw_os_error())
+            .map_or(false, is_out_of_space_code)
+    })
+}
+
+/// `ENOSPC` on unix, `ERROR_HANDLE_DISK_FULL` and `ERROR_DISK_FULL` on windows
+fn is_out_of_space_code(code: i32) -> bool {
+    if cfg!(windows) {
+        matches!(code, 39 | 112)
+    } else {
+        code == 28
+    }
+}
+
+/// Appended to lines cut short by `read_line_bounded`
+pub const TRUNCATED_LINE_MARKER: &str = "…[truncated]";
+
+/// Reads up to and including the next `\n` like `read_until`, but keeps at most `max_len` bytes
+/// of the line in `buf` and discards the rest, so a single huge line can't exhaust memory
+///
+/// Returns the number of bytes consumed from the reader, 0 at EOF, and whether the line was
+/// truncated
+///
+/// Bytes consumed before the future is dropped stay in `buf`, so a cancelled read can be resumed
+/// by calling it again with the same buffer
+pub async fn read_line_bounded<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    buf: &mut Vec<u8>,
+    max_len: usize,
+) -> std::io::Result<(usize, bool)> {
+    let mut consumed = 0;
+    let mut truncated = false;
+    loop {
+        let available = reader.fill_buf().await?;
+        if available.is_empty() {
+            break;
+        }
+        let (chunk, is_line_end) = match available.iter().position(|b| *b == b'\n') {
+            Some(i) => (&available[..=i], true),
+            None => (available, false),
+        };
+        let room = max_len.saturating_sub(buf.len());
+        if chunk.len() > room {
+            truncated = true;
+        }
+        buf.extend_from_slice(&chunk[..chunk.len().min(room)]);
+        let chunk_len = chunk.len();
+        reader.consume(chunk_len);
+        consumed += chunk_len;
+        if is_line_end {
+            break;
+        }
+    }
+    Ok((consumed, truncated))
+}
+
 // safe_path only works on linux and messes up on windows
 // this is a hacky solution
 pub fn scoped_join_win_safe<R: AsRef<Path>, U: AsRef<Path>>(
@@ -554,7 +802,7 @@ pub fn dont_spawn_terminal(cmd: &mut tokio::process::Command) -> &mut tokio::pro
 
     cmd
 }
- 
+
 pub fn format_byte_download(mut bytes: u64, mut total: u64) -> String {
     let mut unit = "B";
     if bytes > 1024 {
@@ -639,13 +887,48 @@ pub fn format_byte(mut bytes: u64) -> String {
 
 #[cfg(test)]
 mod tests {
+    use crate::error::{Error, ErrorKind};
     use crate::prelude::init_paths;
-    use crate::util::{resolve_path_conflict, unzip_file, zip_files, UnzipOption};
+    use crate::util::{
+        detect_archive_format, is_out_of_space, read_line_bounded, resolve_path_conflict,
+        unzip_file, zip_files, ArchiveFormat, IoRateLimiter, UnzipOption,
+    };
     use std::collections::HashSet;
     use std::io::Read;
     use std::path::PathBuf;
     use tokio;
 
+    #[tokio::test]
+    async fn test_read_line_bounded() {
+        let mut reader =
+            tokio::io::BufReader::with_capacity(4, &b"short\nmuch longer line\nend"[..]);
+        let mut line = Vec::new();
+        assert_eq!(
+            read_line_bounded(&mut reader, &mut line, 8).await.unwrap(),
+            (6, false)
+        );
+        assert_eq!(line, b"short\n");
+
+        line.clear();
+        assert_eq!(
+            read_line_bounded(&mut reader, &mut line, 8).await.unwrap(),
+            (17, true)
+        );
+        assert_eq!(line, b"much lon");
+
+        line.clear();
+        assert_eq!(
+            read_line_bounded(&mut reader, &mut line, 8).await.unwrap(),
+            (3, false)
+        );
+        assert_eq!(line, b"end");
+        line.clear();
+        assert_eq!(
+            read_line_bounded(&mut reader, &mut line, 8).await.unwrap(),
+            (0, false)
+        );
+    }
+
     #[tokio::test]
     async fn test_unzip_file() {
         let temp_lodestone_path = tempfile::tempdir().unwrap();
@@ -708,6 +991,48 @@ mod tests {
         assert!(dest_path.join("sample_1").join("sample.obj").is_file(),);
     }
 
+    #[test]
+    fn test_detect_archive_format() {
+        let temp = tempfile::tempdir().unwrap();
+        assert_eq!(
+            detect_archive_format(&PathBuf::from("testdata/sample.zip")).unwrap(),
+            Some(ArchiveFormat::Zip)
+        );
+        assert_eq!(
+            detect_archive_format(&PathBuf::from("testdata/sample.gz")).unwrap(),
+            Some(ArchiveFormat::TarGz)
+        );
+
+        // the extension doesn't matter
+        let tar_path = temp.path().join("world.zip");
+        let mut builder = tar::Builder::new(std::fs::File::create(&tar_path).unwrap());
+        let mut header = tar::Header::new_ustar();
+        header.set_size(5);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "level.dat", &b"hello"[..])
+            .unwrap();
+        builder.finish().unwrap();
+        drop(builder);
+        assert_eq!(
+            detect_archive_format(&tar_path).unwrap(),
+            Some(ArchiveFormat::Tar)
+        );
+
+        let seven_zip_path = temp.path().join("world.7z");
+        std::fs::write(&seven_zip_path, b"7z\xbc\xaf\x27\x1c\x00\x04").unwrap();
+        assert_eq!(
+            detect_archive_format(&seven_zip_path).unwrap(),
+            Some(ArchiveFormat::SevenZip)
+        );
+
+        let garbage_path = temp.path().join("world.tar.gz");
+        std::fs::write(&garbage_path, b"not an archive").unwrap();
+        assert_eq!(detect_archive_format(&garbage_path).unwrap(), None);
+        let e = unzip_file(&garbage_path, UnzipOption::ToDir(temp.path().to_owned())).unwrap_err();
+        assert!(matches!(e.kind, ErrorKind::BadRequest));
+    }
+
     #[test]
     fn test_resolve_path_conflict() {
         let temp_lodestone_path = tempfile::tempdir().unwrap();
@@ -740,6 +1065,34 @@ mod tests {
         assert_eq!(resolve_path_conflict(dir, None), temp_path.join("test_1"));
     }
 
+    #[test]
+    fn test_is_out_of_space() {
+        let out_of_space = std::io::Error::from_raw_os_error(if cfg!(windows) { 112 } else { 28 });
+        let error = Error {
+            kind: ErrorKind::Internal,
+            source: color_eyre::Report::new(out_of_space).wrap_err("Failed to write to file"),
+        };
+        assert!(is_out_of_space(&error));
+        let error = Error {
+            kind: ErrorKind::Internal,
+            source: color_eyre::Report::new(std::io::Error::from(
+                std::io::ErrorKind::PermissionDenied,
+            ))
+            .wrap_err("Failed to write to file"),
+        };
+        assert!(!is_out_of_space(&error));
+    }
+
+    #[test]
+    fn test_io_rate_limiter() {
+        let mut limiter = IoRateLimiter::new(10_000);
+        let start = std::time::Instant::now();
+        for _ in 0..4 {
+            limiter.consume(500);
+        }
+        assert!(start.elapsed() >= std::time::Duration::from_millis(200));
+    }
+
     #[tokio::test]
     async fn test_zip_files() {
         let temp = tempdir::TempDir::new("test_unzip_file").unwrap();